@@ -57,12 +57,18 @@ pub fn subsweep_parameters(args: proc_macro::TokenStream, input: proc_macro::Tok
 
 pub(crate) fn parameter_attr_derive(args: proc_macro::TokenStream, input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let args: proc_macro2::TokenStream = args.into();
-    let name: Option<Literal> = args.into_iter().next().map(|x| match x {
-        proc_macro2::TokenTree::Literal(s) => s,
-        _ => panic!("Unexpected token in parameter_section macro"),
-    });
+    let mut name: Option<Literal> = None;
+    let mut validate_ranges = false;
+    for token in args.into_iter() {
+        match token {
+            proc_macro2::TokenTree::Literal(s) if name.is_none() => name = Some(s),
+            proc_macro2::TokenTree::Ident(ident) if ident == "validate_ranges" => validate_ranges = true,
+            proc_macro2::TokenTree::Punct(punct) if punct.as_char() == ',' => {}
+            _ => panic!("Unexpected token in parameter_section macro"),
+        }
+    }
     
-    let trait_impl: proc_macro2::TokenStream  = parameters_trait_impl(input.clone(), name).into();
+    let trait_impl: proc_macro2::TokenStream  = parameters_trait_impl(input.clone(), name, validate_ranges).into();
     let input: proc_macro2::TokenStream = input.into();
     let output = quote! {
         #[derive(Clone, serde::Serialize, serde::Deserialize, bevy_ecs::prelude::Resource)]
@@ -75,17 +81,31 @@ pub(crate) fn parameter_attr_derive(args: proc_macro::TokenStream, input: proc_m
     output.into()
 }
 
-pub(crate) fn parameters_trait_impl(input: proc_macro::TokenStream, section_name: Option<Literal>) -> proc_macro::TokenStream {
+pub(crate) fn parameters_trait_impl(input: proc_macro::TokenStream, section_name: Option<Literal>, validate_ranges: bool) -> proc_macro::TokenStream {
     let ast: DeriveInput = syn::parse(input).unwrap();
     let type_name = &ast.ident;
     let (impl_generics, type_generics, where_clause) = &ast.generics.split_for_impl();
 
+    // With `validate_ranges`, the struct is expected to implement
+    // an inherent `range_warnings` method.
+    let validate_ranges_impl = if validate_ranges {
+        quote! {
+            fn validate_ranges(&self) -> Vec<String> {
+                self.range_warnings()
+            }
+        }
+    } else {
+        quote! {}
+    };
+
     let gen = match section_name {
         Some(section_name) => quote! {
             impl #impl_generics ::derive_traits::SubsweepParameters for #type_name #type_generics #where_clause {
                 fn section_name() -> Option<&'static str> {
                     Some(#section_name)
                 }
+
+                #validate_ranges_impl
             }
         },
         None => {
@@ -94,6 +114,8 @@ pub(crate) fn parameters_trait_impl(input: proc_macro::TokenStream, section_name
                     fn section_name() -> Option<&'static str> {
                         None
                     }
+
+                    #validate_ranges_impl
                 }
             }
         }
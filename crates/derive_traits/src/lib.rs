@@ -65,4 +65,11 @@ pub trait SubsweepParameters: Serialize + for<'de> Deserialize<'de> + bevy_ecs::
         Self::section_name()
             .unwrap_or_else(|| panic!("Called unwrap_section_name on unnamed parameter struct."))
     }
+
+    /// Warnings about values which can be parsed but are outside of
+    /// physically sensible ranges. Implemented by passing
+    /// `validate_ranges` to the `subsweep_parameters` macro.
+    fn validate_ranges(&self) -> Vec<String> {
+        vec![]
+    }
 }
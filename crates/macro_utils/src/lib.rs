@@ -2,6 +2,9 @@ use std::{path::Path, env};
 
 use cargo_toml::{Manifest, Product};
 
+mod temp_dir;
+pub use temp_dir::TempDir;
+
 pub struct SubsweepManifest {
     pub manifest: Manifest,
 }
@@ -0,0 +1,45 @@
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+
+static NUM_CREATED: AtomicUsize = AtomicUsize::new(0);
+
+/// A directory with a unique name within the temporary directory of
+/// the system. The directory and its contents are removed when this
+/// is dropped, even if the test panics.
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    pub fn new() -> Self {
+        let index = NUM_CREATED.fetch_add(1, Ordering::SeqCst);
+        let path =
+            std::env::temp_dir().join(format!("subsweep_test_{}_{}", std::process::id(), index));
+        std::fs::create_dir_all(&path)
+            .unwrap_or_else(|e| panic!("Failed to create temporary directory {:?}: {}", path, e));
+        Self { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The path to the file `name` within this directory.
+    pub fn join(&self, name: impl AsRef<Path>) -> PathBuf {
+        self.path.join(name)
+    }
+}
+
+impl Default for TempDir {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
@@ -1,7 +1,6 @@
 use std::time::Instant;
 
 use rand::rngs::StdRng;
-use rand::Rng;
 use rand::SeedableRng;
 use subsweep::communication::MpiWorld;
 use subsweep::communication::SizedCommunicator;
@@ -9,18 +8,15 @@ use subsweep::communication::MPI_UNIVERSE;
 use subsweep::domain::get_decomposition_from_points_and_box;
 use subsweep::domain::DecompositionState;
 use subsweep::prelude::*;
+use subsweep::units::Length;
 use subsweep::units::Time;
 use subsweep::units::VecLength;
 
 fn setup_points_3d(num_particles: usize) -> Vec<VecLength> {
     let mut rng = StdRng::seed_from_u64(1338);
+    let box_ = SimulationBox::cube_from_side_length(Length::meters(1.0e5));
     (0..num_particles)
-        .map(|_| {
-            let x = rng.gen_range(0.0..1.0e5);
-            let y = rng.gen_range(0.0..1.0e5);
-            let z = rng.gen_range(0.0..1.0e5);
-            VecLength::meters(x, y, z)
-        })
+        .map(|_| box_.random_point(&mut rng))
         .collect()
 }
 
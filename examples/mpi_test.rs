@@ -12,6 +12,8 @@ use std::time::Duration;
 
 use mpi::traits::Communicator;
 use mpi::Tag;
+use subsweep::communication::communication_volume;
+use subsweep::communication::enable_volume_tally;
 use subsweep::communication::exchange_communicator::ExchangeCommunicator;
 use subsweep::communication::DataByRank;
 use subsweep::communication::MpiWorld;
@@ -28,6 +30,7 @@ pub fn main() {
         ("exchange_all", exchange_all),
         ("send_receive", send_receive),
         ("sweep_communicator", sweep_communicator),
+        ("tally_communication_volume", tally_communication_volume),
     ];
     for (name, f) in fns {
         f();
@@ -108,3 +111,30 @@ fn sweep_communicator() {
         }
     }
 }
+
+fn tally_communication_volume() {
+    enable_volume_tally();
+    let world = MpiWorld::<i32>::new(Tag::default());
+    let rank = world.rank();
+    let other = 1 - rank;
+    let mut exchange_comm = ExchangeCommunicator::from(world);
+    let get_volume = |other| {
+        let volume = communication_volume();
+        (
+            volume.sent.get(&other).copied().unwrap_or(0),
+            volume.received.get(&other).copied().unwrap_or(0),
+        )
+    };
+    let (sent_before, received_before) = get_volume(other);
+    let num_elements = 100;
+    let num_iterations = 10;
+    for _ in 0..num_iterations {
+        let data: Vec<i32> = (0..num_elements).collect();
+        let data = DataByRank::from_iter([(other, data)].into_iter());
+        exchange_comm.exchange_all(data);
+    }
+    let (sent, received) = get_volume(other);
+    let expected = (num_iterations * num_elements) as usize * std::mem::size_of::<i32>();
+    assert_eq!(sent - sent_before, expected);
+    assert_eq!(received - received_before, expected);
+}
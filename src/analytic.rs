@@ -0,0 +1,98 @@
+use crate::units::Dimensionless;
+use crate::units::Length;
+use crate::units::NumberDensity;
+use crate::units::SourceRate;
+use crate::units::Time;
+use crate::units::Volume3D;
+use crate::units::VolumeRate;
+
+/// The radius of the ionized sphere around a source with rate
+/// `source_rate` in a homogeneous, fully ionized hydrogen medium at
+/// which the number of recombinations within the sphere balances
+/// the source rate: R_S = (3 Q / (4 pi alpha n^2))^(1/3).
+pub fn stroemgren_radius(
+    source_rate: SourceRate,
+    number_density: NumberDensity,
+    recombination_rate: VolumeRate,
+) -> Length {
+    let volume: Volume3D = source_rate / (recombination_rate * number_density * number_density);
+    cube_root(volume * 3.0 / (4.0 * std::f64::consts::PI))
+}
+
+/// The recombination time t_rec = 1 / (alpha n) of a fully ionized
+/// hydrogen medium.
+pub fn recombination_time(number_density: NumberDensity, recombination_rate: VolumeRate) -> Time {
+    1.0 / (recombination_rate * number_density)
+}
+
+/// The radius of the ionization front of an R-type expansion around
+/// a source which is switched on at `time = 0` in a homogeneous,
+/// initially neutral hydrogen medium: R(t) = R_S (1 - exp(-t /
+/// t_rec))^(1/3). The front approaches the Strömgren radius R_S on
+/// the timescale of the recombination time t_rec.
+pub fn rtype_radius(
+    time: Time,
+    source_rate: SourceRate,
+    number_density: NumberDensity,
+    recombination_rate: VolumeRate,
+) -> Length {
+    let t_rec = recombination_time(number_density, recombination_rate);
+    let fraction: Dimensionless = time / t_rec;
+    stroemgren_radius(source_rate, number_density, recombination_rate)
+        * (1.0 - (-fraction.value()).exp()).cbrt()
+}
+
+fn cube_root(volume: Volume3D) -> Length {
+    Length::new_unchecked(volume.value_unchecked().cbrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::recombination_time;
+    use super::rtype_radius;
+    use super::stroemgren_radius;
+    use crate::units::Length;
+    use crate::units::NumberDensity;
+    use crate::units::SourceRate;
+    use crate::units::Time;
+    use crate::units::VolumeRate;
+
+    fn assert_relative_eq(x: f64, y: f64, tolerance: f64) {
+        assert!(((x - y) / y).abs() < tolerance, "{x} {y}");
+    }
+
+    /// The parameters of test 1 in Iliev et al. (2006), "Cosmological
+    /// radiative transfer codes comparison project I".
+    fn iliev_test_1() -> (SourceRate, NumberDensity, VolumeRate) {
+        (
+            SourceRate::photons_per_second(5e48),
+            NumberDensity::per_centimeters_cubed(1e-3),
+            VolumeRate::centimeters_cubed_per_s(2.59e-13),
+        )
+    }
+
+    #[test]
+    fn stroemgren_radius_and_recombination_time_match_tabulated_values() {
+        let (source_rate, number_density, alpha) = iliev_test_1();
+        // t_rec = 3.86e15 s = 122.4 Myr, r_S = 5.4 kpc
+        let t_rec = recombination_time(number_density, alpha);
+        assert_relative_eq(t_rec.in_megayears(), 122.4, 1e-3);
+        let r_s = stroemgren_radius(source_rate, number_density, alpha);
+        assert_relative_eq(r_s.in_kiloparsec(), 5.4, 1e-2);
+    }
+
+    #[test]
+    fn rtype_radius_approaches_stroemgren_radius() {
+        let (source_rate, number_density, alpha) = iliev_test_1();
+        let t_rec = recombination_time(number_density, alpha);
+        let r_s = stroemgren_radius(source_rate, number_density, alpha);
+        let radius = |time: Time| rtype_radius(time, source_rate, number_density, alpha);
+        assert_eq!(radius(Time::zero()), Length::zero());
+        assert_relative_eq(
+            (radius(t_rec) / r_s).value(),
+            (1.0 - (-1.0f64).exp()).cbrt(),
+            1e-10,
+        );
+        assert_relative_eq((radius(t_rec * 20.0) / r_s).value(), 1.0, 1e-8);
+    }
+}
@@ -13,6 +13,10 @@ pub mod unit_reader;
 #[subsweep_parameters("postprocess")]
 pub struct Parameters {
     pub initial_fraction_ionized_hydrogen: Option<Dimensionless>,
+    /// Where to take the initial ionized hydrogen fraction from, if
+    /// `initial_fraction_ionized_hydrogen` is not given.
+    #[serde(default)]
+    pub initial_ionized_fraction: InitialIonizedFraction,
     pub sources: SourceType,
     pub grid: GridParameters,
     /// Folder containing the subsweep snapshots from which to remap abundances and energies.
@@ -20,6 +24,38 @@ pub struct Parameters {
     pub remap_from: Option<PathBuf>,
 }
 
+impl Parameters {
+    /// The name of the dataset in the particle group of the initial
+    /// conditions from which the initial ionized hydrogen fraction is
+    /// read, if any.
+    pub fn initial_ionized_fraction_dataset(&self) -> Option<&str> {
+        match self.initial_ionized_fraction {
+            InitialIonizedFraction::FromElectronAbundance => None,
+            InitialIonizedFraction::FromDataset(ref name) => {
+                if self.initial_fraction_ionized_hydrogen.is_some() {
+                    panic!("Both initial_fraction_ionized_hydrogen and an initial ionized fraction dataset given.");
+                }
+                Some(name)
+            }
+        }
+    }
+
+    /// Whether the initial ionized hydrogen fraction is derived from
+    /// the electron abundance in the initial conditions.
+    pub fn initial_ionized_fraction_from_electron_abundance(&self) -> bool {
+        self.initial_fraction_ionized_hydrogen.is_none()
+            && self.initial_ionized_fraction_dataset().is_none()
+    }
+}
+
+#[derive(Default)]
+#[subsweep_parameters]
+pub enum InitialIonizedFraction {
+    #[default]
+    FromElectronAbundance,
+    FromDataset(String),
+}
+
 #[derive(Default)]
 #[subsweep_parameters]
 pub enum GridParameters {
@@ -32,6 +68,10 @@ pub enum GridParameters {
 pub enum SourceType {
     FromIcs(FromIcs),
     Explicit(Vec<Source>),
+    /// A separate source catalog, containing the `position` and
+    /// `source` datasets in the same format (and with the same unit
+    /// attributes) as subsweep snapshots.
+    FromFile(PathBuf),
 }
 
 impl SourceType {
@@ -48,3 +88,120 @@ impl SourceType {
 pub struct FromIcs {
     escape_fraction: Dimensionless,
 }
+
+#[cfg(test)]
+mod tests {
+    use macro_utils::TempDir;
+    use subsweep::components::IonizedHydrogenFraction;
+    use subsweep::cosmology::Cosmology;
+    use subsweep::io::input::Reader;
+    use subsweep::io::input::DEFAULT_PARTICLE_GROUP;
+    use subsweep::io::DatasetDescriptor;
+    use subsweep::io::DatasetShape;
+    use subsweep::io::InputDatasetDescriptor;
+    use subsweep::units::Dimensionless;
+
+    use super::unit_reader::ArepoUnitReader;
+    use super::unit_reader::A_IDENTIFIER;
+    use super::unit_reader::H_IDENTIFIER;
+    use super::unit_reader::LENGTH_IDENTIFIER;
+    use super::unit_reader::MASS_IDENTIFIER;
+    use super::unit_reader::SCALE_FACTOR_IDENTIFIER;
+    use super::unit_reader::VELOCITY_IDENTIFIER;
+    use super::GridParameters;
+    use super::InitialIonizedFraction;
+    use super::Parameters;
+    use super::SourceType;
+
+    fn make_parameters(
+        initial_fraction_ionized_hydrogen: Option<Dimensionless>,
+        initial_ionized_fraction: InitialIonizedFraction,
+    ) -> Parameters {
+        Parameters {
+            initial_fraction_ionized_hydrogen,
+            initial_ionized_fraction,
+            sources: SourceType::Explicit(vec![]),
+            grid: GridParameters::Construct,
+            remap_from: None,
+        }
+    }
+
+    #[test]
+    fn initial_ionized_fraction_is_read_from_dataset() {
+        let name = "InitialIonizedFraction";
+        let dir = TempDir::new();
+        let path = dir.join("initial_ionized_fraction.hdf5");
+        let fractions: Vec<_> = (0..10).map(|i| i as f64 / 10.0).collect();
+        let parameters = make_parameters(None, InitialIonizedFraction::FromDataset(name.into()));
+        assert!(!parameters.initial_ionized_fraction_from_electron_abundance());
+        let descriptor = InputDatasetDescriptor::<IonizedHydrogenFraction>::new(
+            DatasetDescriptor::in_particle_group(
+                parameters.initial_ionized_fraction_dataset().unwrap(),
+                Box::new(ArepoUnitReader::new(Cosmology::NonCosmological)),
+            )
+            .resolve_particle_group(DEFAULT_PARTICLE_GROUP),
+            DatasetShape::OneDimensional,
+        );
+        {
+            let file = hdf5::File::create(&path).unwrap();
+            let dataset = file
+                .new_dataset::<f64>()
+                .shape(&[fractions.len()])
+                .create(descriptor.dataset_name())
+                .unwrap();
+            dataset.write(&fractions).unwrap();
+            for identifier in [
+                LENGTH_IDENTIFIER,
+                MASS_IDENTIFIER,
+                VELOCITY_IDENTIFIER,
+                A_IDENTIFIER,
+                H_IDENTIFIER,
+            ] {
+                dataset
+                    .new_attr::<i32>()
+                    .create(identifier)
+                    .unwrap()
+                    .write_scalar(&0)
+                    .unwrap();
+            }
+            dataset
+                .new_attr::<f64>()
+                .create(SCALE_FACTOR_IDENTIFIER)
+                .unwrap()
+                .write_scalar(&0.0)
+                .unwrap();
+        }
+        let read: Vec<_> = Reader::full([&path].into_iter())
+            .read_dataset(descriptor)
+            .collect();
+        assert_eq!(read.len(), fractions.len());
+        for (xhii, fraction) in read.iter().zip(fractions.iter()) {
+            assert_eq!(xhii.value(), *fraction);
+        }
+    }
+
+    #[test]
+    fn initial_ionized_fraction_defaults_to_electron_abundance() {
+        let parameters = make_parameters(None, InitialIonizedFraction::FromElectronAbundance);
+        assert!(parameters.initial_ionized_fraction_from_electron_abundance());
+        assert!(parameters.initial_ionized_fraction_dataset().is_none());
+        let parameters = make_parameters(
+            Some(Dimensionless::dimensionless(0.5)),
+            InitialIonizedFraction::FromElectronAbundance,
+        );
+        assert!(!parameters.initial_ionized_fraction_from_electron_abundance());
+        assert!(parameters.initial_ionized_fraction_dataset().is_none());
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Both initial_fraction_ionized_hydrogen and an initial ionized fraction dataset given."
+    )]
+    fn panic_on_constant_and_dataset_initial_ionized_fraction() {
+        make_parameters(
+            Some(Dimensionless::dimensionless(0.5)),
+            InitialIonizedFraction::FromDataset("InitialIonizedFraction".into()),
+        )
+        .initial_ionized_fraction_dataset();
+    }
+}
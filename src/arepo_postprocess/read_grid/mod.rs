@@ -1,5 +1,7 @@
 mod id_cache;
 
+use std::fmt;
+
 use bevy_ecs::prelude::Commands;
 use bevy_ecs::prelude::Component;
 use bevy_ecs::prelude::Entity;
@@ -18,15 +20,19 @@ use subsweep::communication::SizedCommunicator;
 use subsweep::components::Density;
 use subsweep::cosmology::Cosmology;
 use subsweep::dimension::ActiveWrapType;
+use subsweep::dimension::WrapType;
 use subsweep::hash_map::HashMap;
 use subsweep::impl_to_dataset;
 use subsweep::io::input::DatasetInputPlugin;
 use subsweep::io::input::Reader;
+use subsweep::io::output::OutputPlugin;
 use subsweep::io::to_dataset::ToDataset;
 use subsweep::io::unit_reader::IdReader;
 use subsweep::io::DatasetDescriptor;
 use subsweep::io::DatasetShape;
 use subsweep::io::InputDatasetDescriptor;
+use subsweep::parameters::InputParameters;
+use subsweep::parameters::OutputParameters;
 use subsweep::prelude::Float;
 use subsweep::prelude::HaloParticle;
 use subsweep::prelude::ParticleId;
@@ -44,6 +50,7 @@ use subsweep::sweep::grid::RemotePeriodicNeighbour;
 use subsweep::sweep::SweepParameters;
 use subsweep::units;
 use subsweep::units::MVec;
+use subsweep::units::MassDensityVolume;
 use subsweep::units::VecDimensionless;
 use subsweep::units::Volume;
 use subsweep::units::NONE;
@@ -54,8 +61,6 @@ use super::unit_reader::ArepoUnitReader;
 use super::Parameters;
 use crate::arepo_postprocess::GridParameters;
 
-const CHUNK_SIZE: usize = 1000000;
-
 #[derive(Named)]
 pub struct ReadSweepGridPlugin;
 
@@ -102,6 +107,8 @@ pub struct Area(pub units::Area);
 pub struct FaceNormal(pub units::VecDimensionless);
 
 impl ToDataset for UniqueParticleId {
+    type Output = Self;
+
     fn dimension() -> subsweep::units::Dimension {
         NONE
     }
@@ -109,9 +116,15 @@ impl ToDataset for UniqueParticleId {
     fn convert_base_units(self, _factor: f64) -> Self {
         self
     }
+
+    fn to_output(self) -> Self::Output {
+        self
+    }
 }
 
 impl ToDataset for ConnectionTypeInt {
+    type Output = Self;
+
     fn dimension() -> subsweep::units::Dimension {
         NONE
     }
@@ -119,16 +132,26 @@ impl ToDataset for ConnectionTypeInt {
     fn convert_base_units(self, _factor: f64) -> Self {
         self
     }
+
+    fn to_output(self) -> Self::Output {
+        self
+    }
 }
 
 impl_to_dataset!(Area, units::Area, true);
 impl_to_dataset!(Mass, units::Mass, true);
 impl_to_dataset!(FaceNormal, units::Dimensionless, true);
 
+/// The number of bits at the start of the connection type which
+/// contain the periodic/boundary flags of the two particles. The
+/// remaining bits contain the image flags of the periodic particle.
+const NUM_FLAG_BITS: i32 = 4;
+
 #[derive(Debug)]
 struct ConnectionType {
     periodic1: bool,
     periodic2: bool,
+    wrap_type: ActiveWrapType,
 }
 
 fn periodic_and_boundary_flags_from_bits(bits: i32) -> (bool, bool) {
@@ -137,54 +160,118 @@ fn periodic_and_boundary_flags_from_bits(bits: i32) -> (bool, bool) {
     (periodic, boundary)
 }
 
+/// The number of periodic images (including the original cell)
+/// which can be encoded in the image flags.
+const NUM_IMAGES: u32 = 27;
+
+/// The reasons for which a connection type does not describe a face
+/// between two cells.
+#[derive(Debug, PartialEq)]
+enum ConnectionTypeError {
+    /// The connection is unused or involves a boundary particle and
+    /// is skipped.
+    NoFace,
+    /// The image flags of the periodic particle do not describe any
+    /// of the periodic images, for example in a corrupted file.
+    InvalidImageFlags(i32),
+}
+
+impl fmt::Display for ConnectionTypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConnectionTypeError::NoFace => write!(f, "Connection does not describe a face"),
+            ConnectionTypeError::InvalidImageFlags(image_flags) => write!(
+                f,
+                "Invalid periodic image flags in connection: {:b}",
+                image_flags
+            ),
+        }
+    }
+}
+
+fn wrap_type_from_image_index(index: u32) -> WrapType {
+    match index {
+        0 => WrapType::NoWrap,
+        1 => WrapType::Plus,
+        2 => WrapType::Minus,
+        _ => unreachable!(),
+    }
+}
+
+/// Decodes the image flags of the periodic particle of a
+/// connection. Arepo encodes the periodic image as a single set bit
+/// at position x + 3 y + 9 z, where each of x, y and z is 0 if
+/// the image is not shifted along that axis, 1 if it is shifted in
+/// positive direction and 2 if it is shifted in negative direction.
+fn get_periodic_wrap_type(image_flags: i32) -> Result<ActiveWrapType, ConnectionTypeError> {
+    let invalid = ConnectionTypeError::InvalidImageFlags(image_flags);
+    let index = image_flags.trailing_zeros();
+    if image_flags.count_ones() != 1 || index >= NUM_IMAGES {
+        return Err(invalid);
+    }
+    let x = wrap_type_from_image_index(index % 3);
+    let y = wrap_type_from_image_index((index / 3) % 3);
+    let z = wrap_type_from_image_index(index / 9);
+    #[cfg(feature = "2d")]
+    {
+        // A periodic image shifted along z cannot occur in 2D.
+        if z != WrapType::NoWrap {
+            return Err(invalid);
+        }
+        Ok(ActiveWrapType { x, y })
+    }
+    #[cfg(feature = "3d")]
+    Ok(ActiveWrapType { x, y, z })
+}
+
 impl TryFrom<ConnectionTypeInt> for ConnectionType {
-    type Error = ();
-    fn try_from(value: ConnectionTypeInt) -> Result<Self, ()> {
+    type Error = ConnectionTypeError;
+    fn try_from(value: ConnectionTypeInt) -> Result<Self, ConnectionTypeError> {
         if *value == -1 {
-            Err(())
+            Err(ConnectionTypeError::NoFace)
         } else {
             let (periodic1, boundary1) = periodic_and_boundary_flags_from_bits(*value & (1 + 2));
             let (periodic2, boundary2) =
                 periodic_and_boundary_flags_from_bits((*value & (4 + 8)) >> 2);
             let valid = !(boundary1 || boundary2 || (periodic1 && periodic2));
             if !valid {
-                Err(())
+                Err(ConnectionTypeError::NoFace)
             } else {
+                let wrap_type = if periodic1 || periodic2 {
+                    get_periodic_wrap_type(*value >> NUM_FLAG_BITS)?
+                } else {
+                    ActiveWrapType::no_wrap()
+                };
                 Ok(ConnectionType {
                     periodic1,
                     periodic2,
+                    wrap_type,
                 })
             }
         }
     }
 }
 
+fn unique_particle_id_descriptor() -> InputDatasetDescriptor<UniqueParticleId> {
+    InputDatasetDescriptor::<UniqueParticleId> {
+        descriptor: DatasetDescriptor::in_particle_group("ParticleIDs", Box::new(IdReader)),
+        ..Default::default()
+    }
+}
+
 impl SubsweepPlugin for ReadSweepGridPlugin {
     fn build_everywhere(&self, sim: &mut Simulation) {
         let cosmology = sim.get_parameters::<Cosmology>().clone();
         let unit_reader = Box::new(ArepoUnitReader::new(cosmology));
         sim.add_plugin(DatasetInputPlugin::<UniqueParticleId>::from_descriptor(
-            InputDatasetDescriptor::<UniqueParticleId> {
-                descriptor: DatasetDescriptor {
-                    dataset_name: "PartType0/ParticleIDs".into(),
-                    unit_reader: Box::new(IdReader),
-                },
-                ..Default::default()
-            },
+            unique_particle_id_descriptor(),
         ))
         .add_plugin(DatasetInputPlugin::<Mass>::from_descriptor(
             InputDatasetDescriptor::<Mass> {
-                descriptor: DatasetDescriptor {
-                    dataset_name: "PartType0/Masses".into(),
-                    unit_reader,
-                },
+                descriptor: DatasetDescriptor::in_particle_group("Masses", unit_reader),
                 ..Default::default()
             },
         ))
-        .add_startup_system_to_stage(
-            StartupStages::InsertComponentsAfterGrid,
-            remove_components_system::<UniqueParticleId>,
-        )
         .add_startup_system_to_stage(
             StartupStages::InsertComponentsAfterGrid,
             remove_components_system::<Mass>,
@@ -192,6 +279,28 @@ impl SubsweepPlugin for ReadSweepGridPlugin {
         .add_component_no_io::<UniqueParticleId>()
         .add_component_no_io::<Mass>()
         .add_startup_system_to_stage(StartupStages::InsertGrid, read_grid_system);
+        add_unique_particle_id_output(sim);
+    }
+}
+
+/// Keeps the original Arepo ids if they are requested as an output
+/// field, so that the snapshots can be cross-referenced with the
+/// Arepo catalog. Otherwise, the ids are removed once the grid is
+/// constructed.
+fn add_unique_particle_id_output(sim: &mut Simulation) {
+    let write_ids = sim
+        .get_resource::<OutputParameters>()
+        .map(|parameters| parameters.is_desired_field::<UniqueParticleId>())
+        .unwrap_or(false);
+    if write_ids {
+        sim.add_plugin(OutputPlugin::<UniqueParticleId>::from_descriptor(
+            DatasetDescriptor::default_for::<UniqueParticleId>(),
+        ));
+    } else {
+        sim.add_startup_system_to_stage(
+            StartupStages::InsertComponentsAfterGrid,
+            remove_components_system::<UniqueParticleId>,
+        );
     }
 }
 
@@ -213,28 +322,29 @@ fn read_normal(data: &[Float]) -> FaceNormal {
 fn read_connection_data<'a>(
     reader: &'a Reader,
     cosmology: &Cosmology,
+    chunk_size: usize,
 ) -> impl Iterator<Item = Connection> + 'a {
     let unit_reader = ArepoUnitReader::new(cosmology.clone());
     let descriptor =
         make_descriptor::<UniqueParticleId, _>(&IdReader, "Id1", DatasetShape::OneDimensional);
-    let ids1 = reader.read_dataset_chunked(descriptor, CHUNK_SIZE);
+    let ids1 = reader.read_dataset_chunked(descriptor, chunk_size);
     let descriptor =
         make_descriptor::<UniqueParticleId, _>(&IdReader, "Id2", DatasetShape::OneDimensional);
-    let ids2 = reader.read_dataset_chunked(descriptor, CHUNK_SIZE);
+    let ids2 = reader.read_dataset_chunked(descriptor, chunk_size);
     let descriptor = make_descriptor::<ConnectionTypeInt, _>(
         &IdReader,
         "ConnectionType",
         DatasetShape::OneDimensional,
     );
-    let connection_types = reader.read_dataset_chunked(descriptor, CHUNK_SIZE);
+    let connection_types = reader.read_dataset_chunked(descriptor, chunk_size);
     let descriptor = make_descriptor::<Area, _>(&unit_reader, "Area", DatasetShape::OneDimensional);
-    let areas = reader.read_dataset_chunked(descriptor, CHUNK_SIZE);
+    let areas = reader.read_dataset_chunked(descriptor, chunk_size);
     let descriptor = make_descriptor::<FaceNormal, _>(
         &unit_reader,
         "Normal",
         DatasetShape::TwoDimensional(read_normal),
     );
-    let normals = reader.read_dataset_chunked(descriptor, CHUNK_SIZE);
+    let normals = reader.read_dataset_chunked(descriptor, chunk_size);
     ids1.into_iter()
         .zip(
             ids2.into_iter().zip(
@@ -244,7 +354,11 @@ fn read_connection_data<'a>(
             ),
         )
         .filter_map(|(id1, (id2, (connection_type, (area, normal))))| {
-            let type_ = connection_type.try_into().ok()?;
+            let type_ = match ConnectionType::try_from(connection_type) {
+                Ok(type_) => type_,
+                Err(ConnectionTypeError::NoFace) => return None,
+                Err(e) => panic!("{} between {:?} and {:?}", e, id1, id2),
+            };
             Some(Connection {
                 id1,
                 id2,
@@ -308,7 +422,12 @@ impl Constructor {
         &mut self.cells[self.unique_particle_id_to_index[&id]]
     }
 
-    fn get_particle_type(&mut self, id: UniqueParticleId, is_periodic: bool) -> ParticleType {
+    fn get_particle_type(
+        &mut self,
+        id: UniqueParticleId,
+        is_periodic: bool,
+        periodic_wrap_type: ActiveWrapType,
+    ) -> ParticleType {
         let id = self.id_cache.lookup(id).unwrap();
         let is_local = id.rank == self.rank;
         match (is_local, is_periodic) {
@@ -317,7 +436,7 @@ impl Constructor {
                 if self.allow_periodic {
                     let periodic_neighbour = PeriodicNeighbour {
                         id,
-                        periodic_wrap_type: get_periodic_wrap_type(),
+                        periodic_wrap_type,
                     };
                     ParticleType::LocalPeriodic(periodic_neighbour)
                 } else {
@@ -330,7 +449,7 @@ impl Constructor {
                     let remote_periodic_neighbour = RemotePeriodicNeighbour {
                         id,
                         rank: id.rank,
-                        periodic_wrap_type: get_periodic_wrap_type(),
+                        periodic_wrap_type,
                     };
                     ParticleType::RemotePeriodic(remote_periodic_neighbour)
                 } else {
@@ -358,8 +477,9 @@ impl Constructor {
                 area: *connection.area,
                 normal: -*connection.normal,
             };
-            let ptype1 = self.get_particle_type(connection.id1, connection.type_.periodic1);
-            let ptype2 = self.get_particle_type(connection.id2, connection.type_.periodic2);
+            let type_ = &connection.type_;
+            let ptype1 = self.get_particle_type(connection.id1, type_.periodic1, type_.wrap_type);
+            let ptype2 = self.get_particle_type(connection.id2, type_.periodic2, type_.wrap_type);
             if ptype1.is_local() {
                 self.add_neighbour(connection.id1, face2, ptype2);
             }
@@ -399,19 +519,13 @@ impl Constructor {
     }
 }
 
-fn get_periodic_wrap_type() -> ActiveWrapType {
-    // Find out periodic wrap type. Probably safe to
-    // construct garbage but then again, I can let
-    // Arepo tell me by doing some bit mangling
-    todo!()
-}
-
 fn read_grid_system(
     mut commands: Commands,
     p: Particles<(Entity, &ParticleId, &UniqueParticleId, &Mass, &Density)>,
     parameters: Res<Parameters>,
     sweep_parameters: Res<SweepParameters>,
     cosmology: Res<Cosmology>,
+    input_parameters: Res<InputParameters>,
 ) {
     let grid_file = if let GridParameters::Read(ref path) = parameters.grid {
         path.clone()
@@ -421,12 +535,17 @@ fn read_grid_system(
     info!("Reading grid from {:?}", grid_file);
     let mut constructor = Constructor::new(
         p.iter()
-            .map(|(_, id1, id2, mass, density)| (*id1, *id2, **mass / **density))
+            .map(|(_, id1, id2, mass, density)| {
+                let volume = MassDensityVolume::from_any(Some(**mass), Some(**density), None)
+                    .unwrap()
+                    .volume;
+                (*id1, *id2, volume)
+            })
             .collect(),
         sweep_parameters.periodic,
     );
     let reader = Reader::full([grid_file].into_iter());
-    let connections = read_connection_data(&reader, &cosmology);
+    let connections = read_connection_data(&reader, &cosmology, input_parameters.read_chunk_size);
     constructor.add_connections(connections);
     for ((entity, _, _, _, _), cell) in p.iter().zip(constructor.cells) {
         commands.entity(entity).insert(cell);
@@ -435,3 +554,166 @@ fn read_grid_system(
         commands.spawn((HaloParticle { rank: halo_id.rank }, halo_id));
     }
 }
+
+#[cfg(test)]
+#[cfg(feature = "3d")]
+mod tests {
+    use std::path::Path;
+
+    use macro_utils::TempDir;
+    use subsweep::communication::BaseCommunicationPlugin;
+    use subsweep::components::Position;
+    use subsweep::cosmology::Cosmology;
+    use subsweep::dimension::ActiveWrapType;
+    use subsweep::dimension::WrapType;
+    use subsweep::io::input::NumParticlesTotal;
+    use subsweep::io::input::Reader;
+    use subsweep::io::input::DEFAULT_PARTICLE_GROUP;
+    use subsweep::io::output::make_output_dirs;
+    use subsweep::io::InputDatasetDescriptor;
+    use subsweep::parameters::OutputParameters;
+    use subsweep::parameters::SimulationParameters;
+    use subsweep::prelude::LocalParticle;
+    use subsweep::prelude::Named;
+    use subsweep::prelude::ParticleId;
+    use subsweep::prelude::Simulation;
+    use subsweep::prelude::SimulationBox;
+    use subsweep::simulation_plugin::SimulationPlugin;
+    use subsweep::units;
+    use subsweep::units::VecLength;
+
+    use super::add_unique_particle_id_output;
+    use super::get_periodic_wrap_type;
+    use super::unique_particle_id_descriptor;
+    use super::ConnectionType;
+    use super::ConnectionTypeError;
+    use super::ConnectionTypeInt;
+    use super::UniqueParticleId;
+    use super::NUM_FLAG_BITS;
+    use super::NUM_IMAGES;
+
+    /// Builds a simulation which writes the unique particle ids of
+    /// the given particles to the snapshots in `output_dir`.
+    fn build_id_output_sim(output_dir: &Path, ids: Vec<UniqueParticleId>) -> Simulation {
+        let mut sim = Simulation::default();
+        let output_parameters: OutputParameters = serde_yaml::from_str(&format!(
+            "output_dir: {:?}\nfields: [{}]",
+            output_dir,
+            UniqueParticleId::name()
+        ))
+        .unwrap();
+        make_output_dirs(&output_parameters);
+        sim.write_output(true)
+            .add_plugin(BaseCommunicationPlugin::new(1, 0))
+            .add_parameter_file_contents("{}".into())
+            .add_parameters_explicitly(output_parameters)
+            .add_parameters_explicitly(SimulationParameters { final_time: None })
+            .add_parameters_explicitly(SimulationBox::cube_from_side_length(units::Length::meters(
+                1.0,
+            )))
+            .add_parameters_explicitly(Cosmology::NonCosmological);
+        add_unique_particle_id_output(&mut sim);
+        sim.add_plugin(SimulationPlugin);
+        let num_particles = ids.len();
+        let world = sim.world();
+        for (index, id) in ids.into_iter().enumerate() {
+            world.spawn((
+                LocalParticle,
+                ParticleId::test(index),
+                Position(VecLength::meters(0.5, 0.5, 0.5)),
+                id,
+            ));
+        }
+        world.insert_resource(NumParticlesTotal(num_particles));
+        sim
+    }
+
+    #[test]
+    fn unique_particle_ids_survive_read_write_cycle() {
+        let dir = TempDir::new();
+        let input_path = dir.join("unique_particle_ids_input.hdf5");
+        let output_dir = dir.join("output");
+        // Ids which are not exactly representable as floats
+        let ids: Vec<u64> = (0..100).map(|i| u64::MAX - 7 * i).collect();
+        let descriptor = unique_particle_id_descriptor();
+        let descriptor = InputDatasetDescriptor::new(
+            descriptor
+                .descriptor
+                .resolve_particle_group(DEFAULT_PARTICLE_GROUP),
+            descriptor.shape,
+        );
+        {
+            let file = hdf5::File::create(&input_path).unwrap();
+            file.new_dataset::<u64>()
+                .shape(&[ids.len()])
+                .create(descriptor.dataset_name())
+                .unwrap()
+                .write(&ids)
+                .unwrap();
+        }
+        let read: Vec<UniqueParticleId> = Reader::full([&input_path].into_iter())
+            .read_dataset(descriptor)
+            .collect();
+        let mut sim = build_id_output_sim(&output_dir, read);
+        sim.update();
+        let snapshot = sim
+            .unwrap_resource::<OutputParameters>()
+            .snapshot_dir()
+            .join("000")
+            .join("0.hdf5");
+        let written: Vec<UniqueParticleId> = Reader::full([&snapshot].into_iter())
+            .read_dataset(InputDatasetDescriptor::<UniqueParticleId>::default())
+            .collect();
+        assert_eq!(written.iter().map(|id| **id).collect::<Vec<_>>(), ids);
+    }
+
+    fn connection_type(flags: i32, image_index: i32) -> ConnectionType {
+        ConnectionType::try_from(ConnectionTypeInt(
+            flags | (1 << (image_index + NUM_FLAG_BITS)),
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn periodic_wrap_type_from_connection_bits() {
+        use WrapType::*;
+        for (image_index, (x, y, z)) in [
+            (1, (Plus, NoWrap, NoWrap)),
+            (2, (Minus, NoWrap, NoWrap)),
+            (3, (NoWrap, Plus, NoWrap)),
+            (6, (NoWrap, Minus, NoWrap)),
+            (9, (NoWrap, NoWrap, Plus)),
+            (18, (NoWrap, NoWrap, Minus)),
+            (1 + 6 + 9, (Plus, Minus, Plus)),
+            (2 + 3 + 18, (Minus, Plus, Minus)),
+        ] {
+            let expected = ActiveWrapType { x, y, z };
+            let type_ = connection_type(1, image_index);
+            assert!(type_.periodic1 && !type_.periodic2);
+            assert_eq!(type_.wrap_type, expected);
+            let type_ = connection_type(4, image_index);
+            assert!(!type_.periodic1 && type_.periodic2);
+            assert_eq!(type_.wrap_type, expected);
+        }
+    }
+
+    #[test]
+    fn non_periodic_connection_has_no_wrap() {
+        let type_ = ConnectionType::try_from(ConnectionTypeInt(0)).unwrap();
+        assert_eq!(type_.wrap_type, ActiveWrapType::no_wrap());
+    }
+
+    #[test]
+    fn invalid_image_flags_are_rejected() {
+        for image_flags in [0, 0b11, 1 << NUM_IMAGES] {
+            assert_eq!(
+                get_periodic_wrap_type(image_flags),
+                Err(ConnectionTypeError::InvalidImageFlags(image_flags))
+            );
+        }
+        assert!(matches!(
+            ConnectionType::try_from(ConnectionTypeInt(1 | (0b11 << NUM_FLAG_BITS))),
+            Err(ConnectionTypeError::InvalidImageFlags(0b11))
+        ));
+    }
+}
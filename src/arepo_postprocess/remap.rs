@@ -25,6 +25,7 @@ use subsweep::domain::DecompositionState;
 use subsweep::domain::IntoKey;
 use subsweep::hash_map::HashMap;
 use subsweep::io::input::attribute::read_attribute;
+use subsweep::io::input::attribute::try_read_attribute;
 use subsweep::io::input::get_file_or_all_hdf5_files_in_path_if_dir;
 use subsweep::io::input::Reader;
 use subsweep::io::DatasetShape;
@@ -34,6 +35,7 @@ use subsweep::prelude::Extent;
 use subsweep::prelude::Float;
 use subsweep::prelude::Particles;
 use subsweep::prelude::SimulationBox;
+use subsweep::sweep::DirectionsRng;
 use subsweep::units::Dimension;
 use subsweep::units::Dimensionless;
 use subsweep::units::Length;
@@ -109,6 +111,7 @@ fn read_remap_data(
         a: *scale_factor.0,
         h: *little_h.0,
         params: None,
+        expand: false,
     };
     let factor = get_scale_factor_difference(Length::dimension(), cosmology, &remap_cosmology);
     position
@@ -321,6 +324,15 @@ fn get_files_of_last_snapshot(path: &Path) -> Vec<PathBuf> {
     get_file_or_all_hdf5_files_in_path_if_dir(&last_snapshot)
 }
 
+/// Reads the state of the rng for the direction rotations from the
+/// last snapshot, so that a restarted run continues the same sequence
+/// of rotations. Returns `None` if the snapshot was written by a run
+/// without rotating directions.
+pub fn read_directions_rng_from_last_snapshot(path: &Path) -> Option<DirectionsRng> {
+    let last_snap = get_files_of_last_snapshot(path);
+    try_read_attribute::<DirectionsRng>(&last_snap[0])
+}
+
 fn get_files_of_first_snapshot(path: &Path) -> Vec<PathBuf> {
     let first_snapshot = get_highest_or_lowest_snapshot_dir(path, false);
     get_file_or_all_hdf5_files_in_path_if_dir(&first_snapshot)
@@ -13,6 +13,7 @@ use subsweep::cosmology::Cosmology;
 use subsweep::impl_to_dataset;
 use subsweep::io::input::Reader;
 use subsweep::io::DatasetShape;
+use subsweep::io::InputDatasetDescriptor;
 use subsweep::parameters::InputParameters;
 use subsweep::source_systems::Source;
 use subsweep::source_systems::Sources;
@@ -27,6 +28,7 @@ use super::unit_reader::make_descriptor;
 use super::unit_reader::read_vec;
 use super::unit_reader::ArepoUnitReader;
 use super::Parameters;
+use super::SourceType;
 
 #[derive(H5Type, Component, Debug, Clone, Equivalence, Deref, DerefMut, From, Named)]
 #[name = "metallicity"]
@@ -54,6 +56,27 @@ pub fn read_sources_system(
     commands.insert_resource(Sources { sources });
 }
 
+pub fn read_source_catalog_system(mut commands: Commands, run_parameters: Res<Parameters>) {
+    let SourceType::FromFile(ref path) = run_parameters.sources else {
+        panic!("Sources are not read from a source catalog.")
+    };
+    let reader = Reader::split_between_ranks([path].into_iter());
+    let sources = read_source_catalog(&reader);
+    commands.insert_resource(Sources { sources });
+}
+
+fn read_source_catalog(reader: &Reader) -> Vec<Source> {
+    let position = reader.read_dataset(InputDatasetDescriptor::<Position>::default());
+    let rate = reader.read_dataset(InputDatasetDescriptor::<components::Source>::default());
+    position
+        .zip(rate)
+        .map(|(position, rate)| Source {
+            pos: *position,
+            rate: *rate,
+        })
+        .collect()
+}
+
 fn new_bpass_source(
     cosmology: &Cosmology,
     position: VecLength,
@@ -126,3 +149,55 @@ fn read_sources(
         )
         .collect()
 }
+
+#[cfg(test)]
+#[cfg(feature = "3d")]
+mod tests {
+    use hdf5::H5Type;
+    use macro_utils::TempDir;
+    use subsweep::components;
+    use subsweep::components::Position;
+    use subsweep::io::input::Reader;
+    use subsweep::io::output::add_dimension_attrs;
+    use subsweep::io::to_dataset::ToDataset;
+    use subsweep::prelude::Named;
+    use subsweep::units::SourceRate;
+    use subsweep::units::VecLength;
+
+    use super::read_source_catalog;
+
+    fn write_dataset<T: H5Type + ToDataset + Named>(file: &hdf5::File, data: &[T]) {
+        let dataset = file
+            .new_dataset::<T>()
+            .shape(&[data.len()])
+            .create(T::name())
+            .unwrap();
+        dataset.write(data).unwrap();
+        add_dimension_attrs::<T>(&dataset);
+    }
+
+    #[test]
+    fn sources_are_read_from_catalog() {
+        let dir = TempDir::new();
+        let path = dir.join("source_catalog.hdf5");
+        let positions: Vec<_> = (0..5)
+            .map(|i| VecLength::meters(i as f64, 0.5, 0.5))
+            .collect();
+        let rates: Vec<_> = (0..5)
+            .map(|i| SourceRate::photons_per_second(1e48 * (i + 1) as f64))
+            .collect();
+        {
+            let file = hdf5::File::create(&path).unwrap();
+            let pos: Vec<_> = positions.iter().map(|pos| Position(*pos)).collect();
+            let source: Vec<_> = rates.iter().map(|rate| components::Source(*rate)).collect();
+            write_dataset(&file, &pos);
+            write_dataset(&file, &source);
+        }
+        let sources = read_source_catalog(&Reader::full([&path].into_iter()));
+        assert_eq!(sources.len(), positions.len());
+        for ((source, pos), rate) in sources.iter().zip(positions.iter()).zip(rates.iter()) {
+            assert_eq!(source.pos, *pos);
+            assert_eq!(source.rate, *rate);
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use std::fmt;
+use std::num::NonZeroUsize;
+use std::time::Duration;
+use std::time::Instant;
+
+use bevy_ecs::prelude::Commands;
+use rand::rngs::StdRng;
+use rand::Rng;
+use rand::SeedableRng;
+
+use crate::communication::BaseCommunicationPlugin;
+use crate::communication::MpiWorld;
+use crate::communication::SizedCommunicator;
+use crate::components::Position;
+use crate::cosmology::Cosmology;
+use crate::domain::DomainPlugin;
+use crate::parameters::SimulationBoxParameters;
+use crate::parameters::SimulationParameters;
+use crate::parameters::SweepParameters;
+use crate::prelude::LocalParticle;
+use crate::prelude::ParallelVoronoiGridConstruction;
+use crate::prelude::ParticleId;
+use crate::prelude::Simulation;
+use crate::prelude::StartupStages;
+use crate::simulation_builder::task_pool_options;
+use crate::simulation_plugin::SimulationPlugin;
+use crate::sweep::initialize_sweep_test_components_system;
+use crate::sweep::DirectionsSpecification;
+use crate::sweep::FluxLimiter;
+use crate::sweep::RateThreshold;
+use crate::sweep::RecombinationCase;
+use crate::sweep::Reconstruction;
+use crate::sweep::SweepPlugin;
+use crate::sweep::TaskBatchSize;
+use crate::sweep::TimescaleCombination;
+use crate::units::Dimensionless;
+use crate::units::Length;
+use crate::units::PhotonRate;
+use crate::units::Time;
+use crate::units::VecLength;
+
+/// The number of directions used in the benchmark.
+pub const NUM_DIRECTIONS: usize = 84;
+const BOX_SIZE_IN_METERS: f64 = 1e5;
+
+/// A fixed sweep workload, requested with `--benchmark`.
+#[derive(Clone, Debug)]
+pub struct BenchmarkOptions {
+    pub num_particles: usize,
+    pub num_steps: NonZeroUsize,
+    /// The number of threads per rank, as given by
+    /// `--num-worker-threads`.
+    pub num_worker_threads: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct BenchmarkResult {
+    pub num_particles: usize,
+    pub num_steps: usize,
+    pub total: Duration,
+}
+
+impl BenchmarkResult {
+    pub fn time_per_step(&self) -> Duration {
+        self.total / self.num_steps as u32
+    }
+
+    /// The number of cell updates (one per particle and direction)
+    /// per second.
+    pub fn throughput(&self) -> f64 {
+        (self.num_particles * NUM_DIRECTIONS * self.num_steps) as f64 / self.total.as_secs_f64()
+    }
+}
+
+impl fmt::Display for BenchmarkResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Benchmark: {} particles, {} directions, {} steps\nTotal time: {:.3} s\nTime per step: {:.3} s\nThroughput: {:.3e} cell updates/s",
+            self.num_particles,
+            NUM_DIRECTIONS,
+            self.num_steps,
+            self.total.as_secs_f64(),
+            self.time_per_step().as_secs_f64(),
+            self.throughput(),
+        )
+    }
+}
+
+/// Sets up the sweep on `num_particles` randomly distributed
+/// particles, which are inserted on the main rank and distributed by
+/// the domain decomposition. Shared by `--benchmark` and the
+/// criterion sweep benchmark.
+pub fn setup_benchmark_sim(num_particles: usize, num_worker_threads: Option<usize>) -> Simulation {
+    let world: MpiWorld<usize> = MpiWorld::new();
+    let mut sim = Simulation::default();
+    sim.add_bevy_plugin(bevy_core::CorePlugin {
+        task_pool_options: task_pool_options(num_worker_threads),
+    });
+    let timestep_safety_factor = Dimensionless::dimensionless(0.1);
+    sim.write_output(false)
+        .add_parameter_file_contents("{}".into())
+        .add_plugin(DomainPlugin)
+        .add_plugin(BaseCommunicationPlugin::new(world.size(), world.rank()))
+        .add_parameters_explicitly(SimulationBoxParameters::Normal(Length::meters(
+            BOX_SIZE_IN_METERS,
+        )))
+        .add_parameters_explicitly(SweepParameters {
+            directions: DirectionsSpecification::Num(NUM_DIRECTIONS),
+            rotate_directions: false,
+            num_timestep_levels: 3,
+            significant_rate_threshold: RateThreshold::Absolute(PhotonRate::zero()),
+            timestep_safety_factor,
+            chemistry_timestep_safety_factor: timestep_safety_factor,
+            chemistry_max_depth: 100,
+            max_timestep: Time::seconds(1e-3),
+            check_deadlock: false,
+            check_photon_conservation: false,
+            periodic: false,
+            prevent_cooling: false,
+            temperature_floor: None,
+            temperature_ceiling: None,
+            max_optical_depth: None,
+            hydrogen_mass_fraction: 1.0.into(),
+            recombination: RecombinationCase::CaseB,
+            flux_limiter: FluxLimiter::None,
+            reconstruction: Reconstruction::Constant,
+            timescale_combination: TimescaleCombination::Min,
+            transport_only: false,
+            track_ionization_fronts: false,
+            #[cfg(feature = "2d")]
+            slab_thickness: Length::meters(1.0),
+            num_tasks_to_solve_before_send_receive: TaskBatchSize::Fixed(10000),
+        })
+        .add_parameters_explicitly(Cosmology::NonCosmological)
+        .add_parameters_explicitly(SimulationParameters { final_time: None })
+        .add_startup_system_to_stage(
+            StartupStages::InsertComponentsAfterGrid,
+            initialize_sweep_test_components_system,
+        )
+        .add_plugin(ParallelVoronoiGridConstruction)
+        .add_plugin(SimulationPlugin)
+        .add_plugin(SweepPlugin);
+    // The particles are distributed by the domain decomposition.
+    if world.rank() == 0 {
+        sim.add_startup_system_to_stage(StartupStages::ReadInput, move |commands: Commands| {
+            insert_particles_system(commands, num_particles)
+        });
+    }
+    sim
+}
+
+fn insert_particles_system(mut commands: Commands, num_particles: usize) {
+    let mut rng = StdRng::seed_from_u64(1338);
+    let mut coord = || rng.gen_range(0.0..BOX_SIZE_IN_METERS);
+    for i in 0..num_particles {
+        #[cfg(feature = "2d")]
+        let pos = VecLength::meters(coord(), coord());
+        #[cfg(not(feature = "2d"))]
+        let pos = VecLength::meters(coord(), coord(), coord());
+        commands.spawn((Position(pos), ParticleId::test(i), LocalParticle));
+    }
+}
+
+/// Runs the sweep on randomly distributed particles for the given
+/// number of steps and measures the wall time. The setup and the
+/// first update, in which the grid is constructed, are not timed.
+pub fn run_benchmark(options: &BenchmarkOptions) -> BenchmarkResult {
+    let mut sim = setup_benchmark_sim(options.num_particles, options.num_worker_threads);
+    sim.update();
+    let start = Instant::now();
+    for _ in 0..options.num_steps.get() {
+        sim.update();
+    }
+    BenchmarkResult {
+        num_particles: options.num_particles,
+        num_steps: options.num_steps.get(),
+        total: start.elapsed(),
+    }
+}
+
+/// Runs the benchmark and prints the result on the main rank.
+pub fn print_benchmark(options: &BenchmarkOptions) {
+    let result = run_benchmark(options);
+    if MpiWorld::<usize>::new().rank() == 0 {
+        println!("{}", result);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroUsize;
+    use std::time::Duration;
+
+    use super::run_benchmark;
+    use super::BenchmarkOptions;
+    use super::BenchmarkResult;
+
+    #[test]
+    fn throughput_is_computed_from_total_time() {
+        let result = BenchmarkResult {
+            num_particles: 100,
+            num_steps: 4,
+            total: Duration::from_secs(2),
+        };
+        assert_eq!(result.time_per_step(), Duration::from_millis(500));
+        assert_eq!(result.throughput(), (100 * 84 * 4) as f64 / 2.0);
+        assert!(result.to_string().contains("Throughput: 1.680e4"));
+    }
+
+    #[test]
+    #[ignore]
+    fn tiny_benchmark_runs_to_completion() {
+        let result = run_benchmark(&BenchmarkOptions {
+            num_particles: 50,
+            num_steps: NonZeroUsize::new(2).unwrap(),
+            num_worker_threads: None,
+        });
+        assert!(result.throughput() > 0.0);
+    }
+}
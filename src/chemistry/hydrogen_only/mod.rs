@@ -1,5 +1,6 @@
 use std::ops::Div;
 
+use derive_custom::subsweep_parameters;
 use diman::Quotient;
 
 use super::Chemistry;
@@ -9,6 +10,7 @@ use crate::sweep::site::Site;
 use crate::units::Density;
 use crate::units::Dimension;
 use crate::units::Dimensionless;
+use crate::units::Energy;
 use crate::units::EnergyPerTime;
 use crate::units::HeatingRate;
 use crate::units::HeatingTerm;
@@ -21,6 +23,7 @@ use crate::units::Rate;
 use crate::units::Temperature;
 use crate::units::Time;
 use crate::units::Volume;
+use crate::units::Volume3D;
 use crate::units::VolumeRate;
 use crate::units::BOLTZMANN_CONSTANT;
 use crate::units::GAMMA;
@@ -29,20 +32,87 @@ use crate::units::PHOTON_AVERAGE_ENERGY;
 use crate::units::PROTON_MASS;
 use crate::units::RYDBERG_CONSTANT;
 
-const HYDROGEN_MASS_FRACTION: f64 = 1.0;
+pub const DEFAULT_HYDROGEN_MASS_FRACTION: f64 = 1.0;
 
-const MAX_DEPTH: usize = 100;
+/// The default maximum number of times that the chemistry timestep
+/// is halved before the solver gives up.
+pub const DEFAULT_MAX_DEPTH: usize = 100;
 
 /// The ionized hydrogen fraction is always kept between this value and (1 - this value)
 /// to ensure numerical stability.
 const IONIZED_HYDROGEN_FRACTION_EPSILON: f64 = 1e-10;
 
+/// Which recombination rates to use.
+#[derive(Default, Copy, Debug, PartialEq, Eq)]
+#[subsweep_parameters]
+pub enum RecombinationCase {
+    /// Include recombinations directly into the ground state. Appropriate
+    /// for optically thin regions, in which the emitted ionizing photons
+    /// escape.
+    CaseA,
+    /// Assume that photons emitted by recombinations into the ground state
+    /// are immediately re-absorbed (on-the-spot approximation). Appropriate
+    /// for optically thick regions.
+    #[default]
+    CaseB,
+}
+
+/// Bounds between which the temperature is kept during every
+/// substep of the chemistry, independently of whether cooling is
+/// prevented.
+#[derive(Default, Clone, Copy, Debug)]
+pub struct TemperatureLimits {
+    pub floor: Option<Temperature>,
+    pub ceiling: Option<Temperature>,
+}
+
 #[derive(Debug)]
 pub struct HydrogenOnly {
     pub rate_threshold: PhotonRate,
     pub scale_factor: Dimensionless,
     pub timestep_safety_factor: Dimensionless,
-    pub prevent_cooling: bool,
+    pub recombination: RecombinationCase,
+    pub temperature_limits: TemperatureLimits,
+    pub max_depth: usize,
+    /// If given, the optical depth of a cell is capped at this value
+    /// in both the transport and the chemistry.
+    pub max_optical_depth: Option<Dimensionless>,
+    /// The fraction of the gas mass in hydrogen. The remaining mass
+    /// is assumed to be neutral helium, which does not take part in
+    /// the chemistry.
+    pub hydrogen_mass_fraction: Dimensionless,
+    /// In 2D, every cell is treated as a slab of this thickness,
+    /// so that the three-dimensional rates can be used unchanged.
+    #[cfg(feature = "2d")]
+    pub slab_thickness: Length,
+}
+
+impl HydrogenOnly {
+    /// The (three-dimensional) volume used in the chemistry for a
+    /// cell of the given volume.
+    pub fn chemistry_volume(&self, volume: Volume) -> Volume3D {
+        #[cfg(feature = "2d")]
+        {
+            volume * self.slab_thickness
+        }
+        #[cfg(not(feature = "2d"))]
+        {
+            volume
+        }
+    }
+
+    /// The optical depth of the cell with respect to its current
+    /// neutral hydrogen content.
+    fn cell_optical_depth(&self, cell: &Cell, site: &Site<Self>) -> Dimensionless {
+        let neutral_hydrogen_number_density = site.density * self.hydrogen_mass_fraction
+            / PROTON_MASS
+            * (1.0 - site.species.ionized_hydrogen_fraction);
+        optical_depth(
+            neutral_hydrogen_number_density,
+            cell.size,
+            self.max_optical_depth,
+        )
+    }
 }
 
 #[derive(Debug)]
@@ -50,19 +120,30 @@ pub struct HydrogenOnlySpecies {
     pub ionized_hydrogen_fraction: Dimensionless,
     pub temperature: Temperature,
     pub timestep: Time,
+    /// Whether the temperature and ionized fraction of this cell are
+    /// prevented from decreasing.
+    pub prevent_cooling: bool,
 }
 
 impl HydrogenOnlySpecies {
     pub(crate) fn new(
         ionized_hydrogen_fraction: Dimensionless,
         temperature: Temperature,
+        prevent_cooling: bool,
     ) -> HydrogenOnlySpecies {
         Self {
             ionized_hydrogen_fraction,
             temperature,
             timestep: Time::zero(),
+            prevent_cooling,
         }
     }
+
+    /// The values below which the temperature and ionized fraction
+    /// of the cell are not allowed to drop during the next update.
+    fn cooling_floor(&self) -> Option<(Temperature, Dimensionless)> {
+        Some((self.temperature, self.ionized_hydrogen_fraction)).filter(|_| self.prevent_cooling)
+    }
 }
 
 impl Chemistry for HydrogenOnly {
@@ -75,18 +156,24 @@ impl Chemistry for HydrogenOnly {
         site: &Site<Self>,
         incoming_rate: Self::Photons,
     ) -> PhotonRate {
-        let neutral_hydrogen_number_density =
-            site.density / PROTON_MASS * (1.0 - site.species.ionized_hydrogen_fraction);
-        let sigma = NUMBER_WEIGHTED_AVERAGE_CROSS_SECTION;
         if incoming_rate < self.rate_threshold {
             PhotonRate::zero()
         } else {
-            let non_absorbed_fraction =
-                (-neutral_hydrogen_number_density * sigma * cell.size).exp();
+            let non_absorbed_fraction = (-self.cell_optical_depth(cell, site)).exp();
             incoming_rate * non_absorbed_fraction
         }
     }
 
+    fn get_absorbed_rate(
+        &self,
+        cell: &Cell,
+        site: &Site<Self>,
+        incoming_rate: Self::Photons,
+    ) -> PhotonRate {
+        let absorbed_fraction = 1.0 - (-self.cell_optical_depth(cell, site)).exp();
+        incoming_rate * absorbed_fraction
+    }
+
     fn update_abundances(
         &self,
         site: &mut Site<Self>,
@@ -95,22 +182,26 @@ impl Chemistry for HydrogenOnly {
         volume: Volume,
         length: Length,
     ) -> Timescale {
-        let floor = Some((
-            site.species.temperature,
-            site.species.ionized_hydrogen_fraction,
-        ))
-        .filter(|_| self.prevent_cooling);
+        let floor = site.species.cooling_floor();
         let mut solver = Solver {
             ionized_hydrogen_fraction: site.species.ionized_hydrogen_fraction,
             temperature: site.species.temperature,
             density: site.density,
-            volume,
+            volume: self.chemistry_volume(volume),
             length,
             rate,
             scale_factor: self.scale_factor,
             floor,
+            recombination: self.recombination,
+            temperature_limits: self.temperature_limits,
+            max_optical_depth: self.max_optical_depth,
+            hydrogen_mass_fraction: self.hydrogen_mass_fraction,
         };
-        let timestep_used = solver.perform_timestep(timestep, self.timestep_safety_factor);
+        let timestep_used = solver.perform_timestep_with_max_depth(
+            timestep,
+            self.timestep_safety_factor,
+            self.max_depth,
+        );
         site.species.temperature = solver.temperature;
         site.species.ionized_hydrogen_fraction = solver.ionized_hydrogen_fraction;
         site.species.timestep = timestep_used.time;
@@ -119,6 +210,23 @@ impl Chemistry for HydrogenOnly {
     }
 }
 
+/// The optical depth of a cell of the given size, capped at
+/// `max_optical_depth` if given. In very optically thick cells, all
+/// photons are absorbed by the first few neutral atoms, which makes
+/// the chemistry extremely stiff. Capping the optical depth lets part
+/// of the photons through and keeps the solver stable in dense clumps.
+fn optical_depth(
+    neutral_hydrogen_number_density: NumberDensity,
+    length: Length,
+    max_optical_depth: Option<Dimensionless>,
+) -> Dimensionless {
+    let tau = neutral_hydrogen_number_density * NUMBER_WEIGHTED_AVERAGE_CROSS_SECTION * length;
+    match max_optical_depth {
+        Some(max_optical_depth) if tau > max_optical_depth => max_optical_depth,
+        _ => tau,
+    }
+}
+
 struct TimestepCriterionViolated;
 struct TimestepConvergenceFailed;
 
@@ -127,17 +235,22 @@ pub(crate) struct Solver {
     pub ionized_hydrogen_fraction: Dimensionless,
     pub temperature: Temperature,
     pub density: Density,
-    pub volume: Volume,
+    pub volume: Volume3D,
     pub length: Length,
     pub rate: PhotonRate,
     pub scale_factor: Dimensionless,
     pub floor: Option<(Temperature, Dimensionless)>,
+    pub recombination: RecombinationCase,
+    pub temperature_limits: TemperatureLimits,
+    pub max_optical_depth: Option<Dimensionless>,
+    pub hydrogen_mass_fraction: Dimensionless,
 }
 
-// All numbers taken from Rosdahl et al (2015)
+// All numbers taken from Rosdahl et al (2015), except for the case A
+// recombination fits which are taken from Hui & Gnedin (1997)
 impl Solver {
     fn hydrogen_number_density(&self) -> NumberDensity {
-        self.density / PROTON_MASS
+        self.density * self.hydrogen_mass_fraction / PROTON_MASS
     }
 
     pub fn ionized_hydrogen_number_density(&self) -> NumberDensity {
@@ -153,9 +266,17 @@ impl Solver {
         self.ionized_hydrogen_number_density()
     }
 
+    /// The number of free particles (atoms, ions and electrons) per
+    /// hydrogen atom for the given ionized hydrogen fraction, with
+    /// the helium assumed to be neutral.
+    fn particles_per_hydrogen_atom(&self, xhii: Dimensionless) -> Dimensionless {
+        let x = self.hydrogen_mass_fraction;
+        1.0 + xhii + (1.0 - x) / (4.0 * x)
+    }
+
     fn mu(&self) -> Dimensionless {
-        // Holds for hydrogen only
-        1.0 / (self.ionized_hydrogen_fraction + 1.0)
+        1.0 / (self.hydrogen_mass_fraction
+            * self.particles_per_hydrogen_atom(self.ionized_hydrogen_fraction))
     }
 
     fn collision_fit_function(&self) -> f64 {
@@ -172,7 +293,7 @@ impl Solver {
             / (t.powi(3).sqrt() * (const1 * t).sqrt() * ((const1 * t).sqrt() + 1.0).powi(2))
     }
 
-    pub fn case_b_recombination_rate(&self) -> VolumeRate {
+    fn case_b_recombination_rate(&self) -> VolumeRate {
         let lambda = Temperature::kelvins(315614.0) / self.temperature;
         VolumeRate::centimeters_cubed_per_s(
             2.753e-14 * lambda.powf(1.5) / (1.0 + (lambda / 2.74).powf(0.407)).powf(2.242),
@@ -215,6 +336,69 @@ impl Solver {
             / Temperature::kelvins(1.0)
     }
 
+    fn case_a_recombination_rate(&self) -> VolumeRate {
+        let lambda = Temperature::kelvins(315614.0) / self.temperature;
+        VolumeRate::centimeters_cubed_per_s(
+            1.269e-13 * lambda.powf(1.503) / (1.0 + (lambda / 0.522).powf(0.470)).powf(1.923),
+        )
+    }
+
+    fn case_a_recombination_rate_derivative(&self) -> Quotient<VolumeRate, Temperature> {
+        let lambda = (Temperature::kelvins(315614.0) / self.temperature).value();
+        let c1 = 1.503;
+        let c2 = 0.470;
+        let c3 = 1.923;
+        let x = (lambda / 0.522).powf(c2);
+        // d alpha / d T = -alpha / T * d ln(alpha) / d ln(lambda)
+        -self.case_a_recombination_rate() / self.temperature * (c1 - c2 * c3 * x / (1.0 + x))
+    }
+
+    fn case_a_recombination_cooling_rate(&self) -> HeatingTerm {
+        let lambda = Temperature::kelvins(315614.0) / self.temperature;
+        HeatingTerm::ergs_centimeters_cubed_per_s(
+            1.778e-29 * self.temperature.in_kelvins() * lambda.powf(1.965)
+                / (1.0 + (lambda / 0.541).powf(0.502)).powf(2.697),
+        )
+    }
+
+    fn case_a_recombination_cooling_rate_derivative(&self) -> Quotient<HeatingTerm, Temperature> {
+        let lambda = (Temperature::kelvins(315614.0) / self.temperature).value();
+        let c1 = 1.965;
+        let c2 = 0.502;
+        let c3 = 2.697;
+        let x = (lambda / 0.541).powf(c2);
+        self.case_a_recombination_cooling_rate() / self.temperature
+            * (1.0 - c1 + c2 * c3 * x / (1.0 + x))
+    }
+
+    pub fn recombination_rate(&self) -> VolumeRate {
+        match self.recombination {
+            RecombinationCase::CaseA => self.case_a_recombination_rate(),
+            RecombinationCase::CaseB => self.case_b_recombination_rate(),
+        }
+    }
+
+    fn recombination_rate_derivative(&self) -> Quotient<VolumeRate, Temperature> {
+        match self.recombination {
+            RecombinationCase::CaseA => self.case_a_recombination_rate_derivative(),
+            RecombinationCase::CaseB => self.case_b_recombination_rate_derivative(),
+        }
+    }
+
+    fn recombination_cooling_rate(&self) -> HeatingTerm {
+        match self.recombination {
+            RecombinationCase::CaseA => self.case_a_recombination_cooling_rate(),
+            RecombinationCase::CaseB => self.case_b_recombination_cooling_rate(),
+        }
+    }
+
+    fn recombination_cooling_rate_derivative(&self) -> Quotient<HeatingTerm, Temperature> {
+        match self.recombination {
+            RecombinationCase::CaseA => self.case_a_recombination_cooling_rate_derivative(),
+            RecombinationCase::CaseB => self.case_b_recombination_cooling_rate_derivative(),
+        }
+    }
+
     pub fn collisional_ionization_rate(&self) -> VolumeRate {
         VolumeRate::centimeters_cubed_per_s(5.85e-11 * self.collision_fit_function())
     }
@@ -280,7 +464,7 @@ impl Solver {
             + self.collisional_ionization_cooling_rate())
             * ne
             * nh_neutral;
-        let recombination = self.case_b_recombination_cooling_rate() * ne * nh_ionized;
+        let recombination = self.recombination_cooling_rate() * ne * nh_ionized;
         let bremsstrahlung = self.bremsstrahlung_cooling_rate() * ne * nh_ionized;
         let compton: HeatingRate = self.compton_cooling_rate() * ne;
         collisional + recombination + bremsstrahlung + compton
@@ -294,7 +478,7 @@ impl Solver {
             + self.collisional_ionization_cooling_rate_derivative())
             * ne
             * nh_neutral;
-        let recombination = self.case_b_recombination_cooling_rate_derivative() * ne * nh_ionized;
+        let recombination = self.recombination_cooling_rate_derivative() * ne * nh_ionized;
         let bremsstrahlung = self.bremsstrahlung_cooling_rate_derivative() * ne * nh_ionized;
         let compton: Quotient<HeatingRate, Temperature> =
             self.compton_cooling_rate_derivative() * ne;
@@ -310,10 +494,12 @@ impl Solver {
     }
 
     fn num_newly_ionized_hydrogen_atoms(&self, timestep: Time) -> Dimensionless {
-        let neutral_hydrogen_number_density = self.neutral_hydrogen_number_density();
-        let sigma = NUMBER_WEIGHTED_AVERAGE_CROSS_SECTION;
-        let absorbed_fraction =
-            1.0 - (-neutral_hydrogen_number_density * sigma * self.length).exp();
+        let tau = optical_depth(
+            self.neutral_hydrogen_number_density(),
+            self.length,
+            self.max_optical_depth,
+        );
+        let absorbed_fraction = 1.0 - (-tau).exp();
         let num_photons: Dimensionless = timestep * self.rate;
         num_photons * absorbed_fraction
     }
@@ -335,8 +521,8 @@ impl Solver {
         // See A23 of Rosdahl et al
         let nh = self.hydrogen_number_density();
         let ne = self.electron_number_density();
-        let alpha = self.case_b_recombination_rate();
-        let dalpha = self.case_b_recombination_rate_derivative();
+        let alpha = self.recombination_rate();
+        let dalpha = self.recombination_rate_derivative();
         let beta = self.collisional_ionization_rate();
         let dbeta = self.collisional_ionization_rate_derivative();
         let photoionization_rate = self.photoionization_rate(timestep);
@@ -345,27 +531,61 @@ impl Solver {
         let d: Rate = alpha * ne;
         let xhii = self.ionized_hydrogen_fraction;
         // Derivative
-        let rhsc: Rate = ne * self.temperature * mu * HYDROGEN_MASS_FRACTION * dbeta;
+        let rhsc: Rate = ne * self.temperature * mu * self.hydrogen_mass_fraction * dbeta;
         let dcdx: Rate = nh * beta - rhsc;
-        let rhsd: Rate = ne * self.temperature * mu * HYDROGEN_MASS_FRACTION * dalpha;
+        let rhsd: Rate = ne * self.temperature * mu * self.hydrogen_mass_fraction * dalpha;
         let dddx: Rate = nh * alpha - rhsd;
         let j = dcdx - (c + d) - xhii * (dcdx + dddx);
         timestep * (c - xhii * (c + d)) / (1.0 - j * timestep)
     }
 
+    /// The thermal energy and the energy stored in ionizations per
+    /// hydrogen atom.
+    fn energy_per_hydrogen_atom(&self) -> Energy {
+        let xhii = self.ionized_hydrogen_fraction;
+        self.particles_per_hydrogen_atom(xhii) * BOLTZMANN_CONSTANT * self.temperature
+            / (GAMMA - 1.0)
+            + xhii * RYDBERG_CONSTANT
+    }
+
+    /// Sets the ionized fraction and adjusts the temperature such
+    /// that the total energy per hydrogen atom is unchanged.
+    fn set_ionized_fraction_conserving_energy(&mut self, xhii: Dimensionless) {
+        let energy = self.energy_per_hydrogen_atom();
+        self.ionized_hydrogen_fraction = xhii;
+        self.temperature = (energy - xhii * RYDBERG_CONSTANT) * (GAMMA - 1.0)
+            / (self.particles_per_hydrogen_atom(xhii) * BOLTZMANN_CONSTANT);
+    }
+
     fn clamp(&mut self) {
-        let xhii_floor = self
-            .floor
-            .map(|(_, xhii)| *xhii)
-            .unwrap_or(IONIZED_HYDROGEN_FRACTION_EPSILON);
-        self.ionized_hydrogen_fraction = self
-            .ionized_hydrogen_fraction
-            .clamp(xhii_floor, 1.0 - IONIZED_HYDROGEN_FRACTION_EPSILON);
-        if let Some((temp_floor, _)) = self.floor {
+        let xhii = self.ionized_hydrogen_fraction.clamp(
+            IONIZED_HYDROGEN_FRACTION_EPSILON,
+            1.0 - IONIZED_HYDROGEN_FRACTION_EPSILON,
+        );
+        if xhii != self.ionized_hydrogen_fraction {
+            self.set_ionized_fraction_conserving_energy(xhii);
+        }
+        // The cooling floor is not meant to conserve energy: raising
+        // the ionized fraction to the floor at the expense of the
+        // thermal energy would undo the heating of this substep.
+        if let Some((temp_floor, xhii_floor)) = self.floor {
+            if self.ionized_hydrogen_fraction < xhii_floor {
+                self.ionized_hydrogen_fraction = xhii_floor;
+            }
+            if self.temperature < temp_floor {
+                self.temperature = temp_floor;
+            }
+        }
+        if let Some(temp_floor) = self.temperature_limits.floor {
             if self.temperature < temp_floor {
                 self.temperature = temp_floor;
             }
         }
+        if let Some(temp_ceiling) = self.temperature_limits.ceiling {
+            if self.temperature > temp_ceiling {
+                self.temperature = temp_ceiling;
+            }
+        }
     }
 
     fn try_timestep_update(
@@ -428,15 +648,27 @@ impl Solver {
         timestep: Time,
         timestep_safety_factor: Dimensionless,
     ) -> Timescale {
-        self.perform_timestep_internal(timestep, timestep_safety_factor, 0, MAX_DEPTH)
+        self.perform_timestep_with_max_depth(timestep, timestep_safety_factor, DEFAULT_MAX_DEPTH)
+    }
+
+    pub fn perform_timestep_with_max_depth(
+        &mut self,
+        timestep: Time,
+        timestep_safety_factor: Dimensionless,
+        max_depth: usize,
+    ) -> Timescale {
+        self.perform_timestep_internal(timestep, timestep_safety_factor, 0, max_depth)
             .unwrap_or_else(|_| {
-                log::error!(
+                // Failures are counted and reported once per step, so
+                // only show the state of the individual solver when
+                // debugging.
+                log::debug!(
                     "Failed to find timestep in chemistry. Solver state: {:?}",
                     self
                 );
                 // We don't panic here to make sure we can still run
                 // the process but lets return a pessimistic timescale
-                Timescale::temperature(timestep / 10.0)
+                Timescale::convergence_failure(timestep / 10.0)
             })
     }
 }
@@ -469,7 +701,14 @@ mod tests {
     use std::ops::Sub;
     use std::path::Path;
 
+    use super::optical_depth;
+    use super::HydrogenOnlySpecies;
+    use super::RecombinationCase;
     use super::Solver;
+    use super::TemperatureLimits;
+    use super::DEFAULT_MAX_DEPTH;
+    use crate::chemistry::timescale::Process;
+    use crate::chemistry::timescale::TimescaleCounter;
     use crate::units::Density;
     use crate::units::Dimension;
     use crate::units::Dimensionless;
@@ -520,6 +759,10 @@ mod tests {
                 rate: Rate::zero(),
                 scale_factor: Dimensionless::dimensionless(1.0),
                 floor: None,
+                recombination: RecombinationCase::CaseB,
+                temperature_limits: TemperatureLimits::default(),
+                max_optical_depth: None,
+                hydrogen_mass_fraction: 1.0.into(),
             };
             let analytical = derivative(&solver);
             let v1 = function(&solver);
@@ -542,6 +785,14 @@ mod tests {
         )
     }
 
+    #[test]
+    fn case_a_recombination_rate_derivative() {
+        test_numerical_derivative(
+            Solver::case_a_recombination_rate,
+            Solver::case_a_recombination_rate_derivative,
+        )
+    }
+
     #[test]
     fn collisional_ionization_rate_derivative() {
         test_numerical_derivative(
@@ -574,6 +825,14 @@ mod tests {
         )
     }
 
+    #[test]
+    fn case_a_recombination_cooling_rate_derivative() {
+        test_numerical_derivative(
+            Solver::case_a_recombination_cooling_rate,
+            Solver::case_a_recombination_cooling_rate_derivative,
+        )
+    }
+
     #[test]
     fn bremsstrahlung_cooling_rate_derivative() {
         test_numerical_derivative(
@@ -658,6 +917,10 @@ mod tests {
                 rate,
                 scale_factor: Dimensionless::dimensionless(1.0),
                 floor: None,
+                recombination: RecombinationCase::CaseB,
+                temperature_limits: TemperatureLimits::default(),
+                max_optical_depth: None,
+                hydrogen_mass_fraction: 1.0.into(),
             }
         }
 
@@ -678,7 +941,7 @@ mod tests {
             let ne = solver.electron_number_density();
             let nh_neutral = solver.neutral_hydrogen_number_density();
             let nh_ionized = solver.ionized_hydrogen_number_density();
-            let recombination = solver.case_b_recombination_cooling_rate() * ne * nh_ionized;
+            let recombination = solver.recombination_cooling_rate() * ne * nh_ionized;
             let bremsstrahlung = solver.bremsstrahlung_cooling_rate() * ne * nh_ionized;
             let compton: HeatingRate = solver.compton_cooling_rate() * ne;
             let collisional_excitation =
@@ -945,6 +1208,99 @@ mod tests {
         );
     }
 
+    fn equilibrium_ionized_fraction(recombination: RecombinationCase) -> Dimensionless {
+        let length = Length::parsec(1.0);
+        let temperature = Temperature::kelvins(1e4);
+        let mut solver = Solver {
+            ionized_hydrogen_fraction: 0.5.into(),
+            temperature,
+            density: as_density(1.0),
+            volume: length.cubed(),
+            length,
+            rate: PhotonRate::photons_per_second(2e42),
+            scale_factor: 1.0.into(),
+            floor: None,
+            recombination,
+            temperature_limits: TemperatureLimits::default(),
+            max_optical_depth: None,
+            hydrogen_mass_fraction: 1.0.into(),
+        };
+        for _ in 0..1000 {
+            solver.perform_timestep(Time::years(1e4), 0.1.into());
+            solver.temperature = temperature;
+        }
+        solver.ionized_hydrogen_fraction
+    }
+
+    #[test]
+    fn case_a_recombination_lowers_equilibrium_ionization() {
+        // The source is chosen such that the cell is only partially
+        // ionized in equilibrium, in which case the number of
+        // absorbed photons balances the number of recombinations.
+        // Since recombinations into the ground state are counted in
+        // case A, the equilibrium ionized fraction is lower.
+        let xhii_a = equilibrium_ionized_fraction(RecombinationCase::CaseA);
+        let xhii_b = equilibrium_ionized_fraction(RecombinationCase::CaseB);
+        assert!(0.1 < xhii_a.value() && xhii_b.value() < 0.9);
+        assert!(xhii_a < xhii_b);
+    }
+
+    #[test]
+    fn clamping_ionized_fraction_conserves_energy() {
+        let length = Length::parsec(1.0);
+        let mut solver = Solver {
+            ionized_hydrogen_fraction: 1.0.into(),
+            temperature: Temperature::kelvins(1e4),
+            density: as_density(1.0),
+            volume: length.cubed(),
+            length,
+            rate: PhotonRate::zero(),
+            scale_factor: 1.0.into(),
+            floor: None,
+            recombination: RecombinationCase::CaseB,
+            temperature_limits: TemperatureLimits::default(),
+            max_optical_depth: None,
+            hydrogen_mass_fraction: 1.0.into(),
+        };
+        let initial_energy = solver.energy_per_hydrogen_atom();
+        for _ in 0..1000 {
+            solver.clamp();
+            // Push the cell back to full ionization without changing
+            // its energy, so that every clamp modifies the fraction.
+            solver.set_ionized_fraction_conserving_energy(1.0.into());
+        }
+        solver.clamp();
+        assert!(solver.ionized_hydrogen_fraction < 1.0);
+        let relative_error = ((solver.energy_per_hydrogen_atom() - initial_energy)
+            / initial_energy)
+            .value()
+            .abs();
+        assert!(relative_error < 1e-10);
+    }
+
+    #[test]
+    fn cooling_floor_does_not_change_temperature() {
+        let length = Length::parsec(1.0);
+        let temperature = Temperature::kelvins(1e4);
+        let mut solver = Solver {
+            ionized_hydrogen_fraction: 0.2.into(),
+            temperature,
+            density: as_density(1.0),
+            volume: length.cubed(),
+            length,
+            rate: PhotonRate::zero(),
+            scale_factor: 1.0.into(),
+            floor: Some((Temperature::kelvins(100.0), 0.5.into())),
+            recombination: RecombinationCase::CaseB,
+            temperature_limits: TemperatureLimits::default(),
+            max_optical_depth: None,
+            hydrogen_mass_fraction: 1.0.into(),
+        };
+        solver.clamp();
+        assert_eq!(solver.ionized_hydrogen_fraction, 0.5.into());
+        assert_eq!(solver.temperature, temperature);
+    }
+
     #[test]
     fn fully_ionized_solver() {
         let mut s = Solver {
@@ -960,6 +1316,10 @@ mod tests {
             rate: PhotonRate::photons_per_second(466103097665666700000000000000000000000000000.0),
             scale_factor: 8.35028211377591.into(),
             floor: None,
+            recombination: RecombinationCase::CaseB,
+            temperature_limits: TemperatureLimits::default(),
+            max_optical_depth: None,
+            hydrogen_mass_fraction: 1.0.into(),
         };
         s.perform_timestep(Time::megayears(1.0), 0.1.into());
     }
@@ -979,7 +1339,169 @@ mod tests {
             rate: PhotonRate::photons_per_second(466103097665666700000000000000000000000000000.0),
             scale_factor: 8.35028211377591.into(),
             floor: None,
+            recombination: RecombinationCase::CaseB,
+            temperature_limits: TemperatureLimits::default(),
+            max_optical_depth: None,
+            hydrogen_mass_fraction: 1.0.into(),
         };
         s.perform_timestep(Time::megayears(1.0), 0.1.into());
     }
+
+    fn temperature_after_cooling(species: HydrogenOnlySpecies) -> Temperature {
+        let length = Length::parsec(1.0);
+        let mut solver = Solver {
+            ionized_hydrogen_fraction: species.ionized_hydrogen_fraction,
+            temperature: species.temperature,
+            density: as_density(1.0),
+            volume: length.cubed(),
+            length,
+            rate: PhotonRate::zero(),
+            scale_factor: 1.0.into(),
+            floor: species.cooling_floor(),
+            recombination: RecombinationCase::CaseB,
+            temperature_limits: TemperatureLimits::default(),
+            max_optical_depth: None,
+            hydrogen_mass_fraction: 1.0.into(),
+        };
+        solver.perform_timestep(Time::megayears(1.0), 0.1.into());
+        solver.temperature
+    }
+
+    #[test]
+    fn only_cells_with_prevented_cooling_keep_their_temperature() {
+        let initial = Temperature::kelvins(1e5);
+        let cooling = HydrogenOnlySpecies::new(0.5.into(), initial, false);
+        let prevented = HydrogenOnlySpecies::new(0.5.into(), initial, true);
+        assert!(temperature_after_cooling(cooling) < initial);
+        assert!(temperature_after_cooling(prevented) >= initial);
+    }
+
+    fn temperature_after_photoheating(temperature_limits: TemperatureLimits) -> Temperature {
+        let length = Length::parsec(1.0);
+        let mut solver = Solver {
+            ionized_hydrogen_fraction: 1e-3.into(),
+            temperature: Temperature::kelvins(1e3),
+            density: as_density(1.0),
+            volume: length.cubed(),
+            length,
+            rate: PhotonRate::photons_per_second(2e42),
+            scale_factor: 1.0.into(),
+            floor: None,
+            recombination: RecombinationCase::CaseB,
+            temperature_limits,
+            max_optical_depth: None,
+            hydrogen_mass_fraction: 1.0.into(),
+        };
+        for _ in 0..10 {
+            solver.perform_timestep(Time::years(1e4), 0.1.into());
+        }
+        solver.temperature
+    }
+
+    #[test]
+    fn temperature_ceiling_caps_photoheating() {
+        let ceiling = Temperature::kelvins(5e3);
+        assert!(temperature_after_photoheating(TemperatureLimits::default()) > ceiling);
+        let temperature = temperature_after_photoheating(TemperatureLimits {
+            floor: None,
+            ceiling: Some(ceiling),
+        });
+        assert!(temperature <= ceiling);
+    }
+
+    #[test]
+    fn convergence_failures_are_counted() {
+        let length = Length::parsec(1.0);
+        let mut solver = Solver {
+            ionized_hydrogen_fraction: 1e-3.into(),
+            temperature: Temperature::kelvins(1e3),
+            density: as_density(1.0),
+            volume: length.cubed(),
+            length,
+            rate: PhotonRate::photons_per_second(1e60),
+            scale_factor: 1.0.into(),
+            floor: None,
+            recombination: RecombinationCase::CaseB,
+            temperature_limits: TemperatureLimits::default(),
+            max_optical_depth: None,
+            hydrogen_mass_fraction: 1.0.into(),
+        };
+        let timestep = Time::megayears(1.0);
+        let timescale = solver.perform_timestep_with_max_depth(timestep, 0.1.into(), 2);
+        assert_eq!(timescale.process, Process::ConvergenceFailure);
+        let mut counter = TimescaleCounter::new(timestep);
+        counter.count(timescale);
+        assert_eq!(counter.num_convergence_failures(), 1);
+        counter.reset();
+        assert_eq!(counter.num_convergence_failures(), 0);
+    }
+
+    #[test]
+    fn hydrogen_number_density_scales_with_mass_fraction() {
+        let solver = |hydrogen_mass_fraction: f64| {
+            let length = Length::parsec(1.0);
+            Solver {
+                ionized_hydrogen_fraction: 0.3.into(),
+                temperature: Temperature::kelvins(1e4),
+                density: as_density(1.0),
+                volume: length.cubed(),
+                length,
+                rate: PhotonRate::zero(),
+                scale_factor: 1.0.into(),
+                floor: None,
+                recombination: RecombinationCase::CaseB,
+                temperature_limits: TemperatureLimits::default(),
+                max_optical_depth: None,
+                hydrogen_mass_fraction: hydrogen_mass_fraction.into(),
+            }
+        };
+        let hydrogen_only = solver(1.0);
+        let primordial = solver(0.76);
+        let ratio: Dimensionless = primordial.neutral_hydrogen_number_density()
+            / hydrogen_only.neutral_hydrogen_number_density();
+        assert!((ratio.value() - 0.76).abs() < 1e-10);
+        let ratio: Dimensionless = primordial.ionized_hydrogen_number_density()
+            / hydrogen_only.ionized_hydrogen_number_density();
+        assert!((ratio.value() - 0.76).abs() < 1e-10);
+        // The mean molecular weight is that of a gas of hydrogen
+        // (with one electron per ion) and neutral helium atoms.
+        assert!((hydrogen_only.mu().value() - 1.0 / 1.3).abs() < 1e-10);
+        assert!((primordial.mu().value() - 1.0 / (0.76 * 1.3 + 0.24 / 4.0)).abs() < 1e-10);
+    }
+
+    #[test]
+    fn max_optical_depth_lets_dense_cells_converge() {
+        // A cell with an optical depth of roughly 1e4, in which
+        // every photon would be absorbed.
+        let max_optical_depth = Dimensionless::dimensionless(20.0);
+        let mut solver = {
+            let length = Length::kiloparsec(1.0);
+            Solver {
+                ionized_hydrogen_fraction: 0.5.into(),
+                temperature: Temperature::kelvins(1e4),
+                density: as_density(1.0),
+                volume: length.cubed(),
+                length,
+                rate: PhotonRate::photons_per_second(5e56),
+                scale_factor: 1.0.into(),
+                floor: None,
+                recombination: RecombinationCase::CaseB,
+                temperature_limits: TemperatureLimits::default(),
+                max_optical_depth: Some(max_optical_depth),
+                hydrogen_mass_fraction: 1.0.into(),
+            }
+        };
+        assert_eq!(
+            optical_depth(
+                solver.neutral_hydrogen_number_density(),
+                solver.length,
+                solver.max_optical_depth
+            ),
+            max_optical_depth
+        );
+        let timestep = Time::years(1e3);
+        let timescale =
+            solver.perform_timestep_with_max_depth(timestep, 0.1.into(), DEFAULT_MAX_DEPTH);
+        assert_ne!(timescale.process, Process::ConvergenceFailure);
+    }
 }
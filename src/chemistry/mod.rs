@@ -21,9 +21,11 @@ use crate::units::PhotonRate;
 use crate::units::Time;
 use crate::units::Volume;
 
-pub trait Chemistry: Sized + 'static {
-    type Photons: Photons;
-    type Species: Debug;
+/// The chemistry is updated for many cells in parallel, so it needs
+/// to be shareable between threads.
+pub trait Chemistry: Sized + Sync + 'static {
+    type Photons: Photons + Send;
+    type Species: Debug + Send;
 
     fn get_outgoing_rate(
         &self,
@@ -32,6 +34,17 @@ pub trait Chemistry: Sized + 'static {
         incoming_rate: Self::Photons,
     ) -> Self::Photons;
 
+    /// The rate absorbed by the cell, computed from its abundances
+    /// rather than as the difference between the incoming and
+    /// outgoing rates, so that photons which are lost in the
+    /// transport show up in the photon budget.
+    fn get_absorbed_rate(
+        &self,
+        cell: &Cell,
+        site: &Site<Self>,
+        incoming_rate: Self::Photons,
+    ) -> Self::Photons;
+
     fn update_abundances(
         &self,
         site: &mut Site<Self>,
@@ -58,6 +71,7 @@ pub trait Photons:
     fn zero() -> Self;
     fn relative_change_to(&self, other: &Self) -> Dimensionless;
     fn below_threshold(&self, threshold: PhotonRate) -> bool;
+    fn ratio_to(&self, other: &Self) -> Dimensionless;
     fn make_positive(&mut self) {
         if *self < Self::zero() {
             *self = Self::zero();
@@ -79,4 +93,8 @@ impl Photons for PhotonRate {
     fn below_threshold(&self, threshold: PhotonRate) -> bool {
         self.abs() < threshold.abs()
     }
+
+    fn ratio_to(&self, other: &Self) -> Dimensionless {
+        *self / *other
+    }
 }
@@ -1,4 +1,7 @@
+use derive_custom::subsweep_parameters;
 use log::debug;
+use log::warn;
+use serde::Serialize;
 
 use crate::hash_map::HashMap;
 use crate::units::Time;
@@ -29,6 +32,13 @@ impl Timescale {
         }
     }
 
+    pub fn convergence_failure(time: Time) -> Self {
+        Self {
+            time,
+            process: Process::ConvergenceFailure,
+        }
+    }
+
     pub fn min(&self, other: Self) -> Self {
         if self.time < other.time {
             *self
@@ -38,11 +48,63 @@ impl Timescale {
     }
 }
 
+/// How the timescale at which the incoming photon rate changes and
+/// the timescale returned by the chemistry are combined into the
+/// timescale which determines the timestep of a cell.
+#[derive(Copy, Debug, Default, PartialEq)]
+#[subsweep_parameters]
+pub enum TimescaleCombination {
+    /// The shorter of the two timescales.
+    #[default]
+    Min,
+    /// The harmonic mean of the two timescales, capped at the
+    /// chemistry timescale. Gives smoother transitions between
+    /// timestep levels than `Min`.
+    HarmonicMean,
+    /// A weighted harmonic mean, with the given weight (between 0
+    /// and 1) for the photon rate timescale and the remainder for
+    /// the chemistry timescale, capped at the chemistry timescale.
+    Weighted(f64),
+}
+
+impl TimescaleCombination {
+    /// Combines the two timescales. The process of the shorter one
+    /// is reported as the limiting process. The result never exceeds
+    /// the chemistry timescale, since longer steps are not stable. If
+    /// the chemistry failed to converge, its (pessimistic) timescale
+    /// is never lengthened.
+    pub fn combine(&self, rate: Timescale, chemistry: Timescale) -> Timescale {
+        let min = rate.min(chemistry);
+        let rate_weight = match self {
+            Self::Min => return min,
+            Self::HarmonicMean => 0.5,
+            Self::Weighted(weight) => *weight,
+        };
+        if chemistry.process == Process::ConvergenceFailure {
+            return min;
+        }
+        let inverse = |time: Time| 1.0 / time.value_unchecked();
+        let inverse_mean =
+            rate_weight * inverse(rate.time) + (1.0 - rate_weight) * inverse(chemistry.time);
+        let mean = Time::new_unchecked(1.0 / inverse_mean);
+        if mean >= chemistry.time {
+            return chemistry;
+        }
+        Timescale {
+            time: mean,
+            process: min.process,
+        }
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum Process {
     Temperature,
     IonizationFraction,
     PhotonRate,
+    /// The chemistry solver failed to converge, so the timescale is
+    /// only a pessimistic guess.
+    ConvergenceFailure,
 }
 
 impl Process {
@@ -51,6 +113,7 @@ impl Process {
             Self::Temperature,
             Self::IonizationFraction,
             Self::PhotonRate,
+            Self::ConvergenceFailure,
         ]
         .into_iter()
     }
@@ -62,11 +125,43 @@ impl std::fmt::Display for Process {
             Process::Temperature => "temperature",
             Process::IonizationFraction => "ionization fraction",
             Process::PhotonRate => "photon rate",
+            Process::ConvergenceFailure => "convergence failure",
         };
         write!(f, "{}", s)
     }
 }
 
+/// The fraction of the chemistry updates with a limited timestep in
+/// which each process was the limiting one. All fractions are zero
+/// if no timestep was limited.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct LimitingProcessFractions {
+    pub temperature: f64,
+    pub ionization_fraction: f64,
+    pub photon_rate: f64,
+    pub convergence_failure: f64,
+}
+
+impl LimitingProcessFractions {
+    pub fn from_counts(counts: &[(Process, usize)]) -> Self {
+        let total: usize = counts.iter().map(|(_, count)| count).sum();
+        let mut fractions = Self::default();
+        if total == 0 {
+            return fractions;
+        }
+        for (process, count) in counts {
+            let fraction = *count as f64 / total as f64;
+            match process {
+                Process::Temperature => fractions.temperature += fraction,
+                Process::IonizationFraction => fractions.ionization_fraction += fraction,
+                Process::PhotonRate => fractions.photon_rate += fraction,
+                Process::ConvergenceFailure => fractions.convergence_failure += fraction,
+            }
+        }
+        fractions
+    }
+}
+
 pub struct TimescaleCounter {
     limiting_processes: HashMap<Process, usize>,
     max_timestep: Time,
@@ -81,7 +176,9 @@ impl TimescaleCounter {
     }
 
     pub fn count(&mut self, change_timescale: Timescale) {
-        if change_timescale.time < self.max_timestep {
+        if change_timescale.process == Process::ConvergenceFailure
+            || change_timescale.time < self.max_timestep
+        {
             *self
                 .limiting_processes
                 .get_mut(&change_timescale.process)
@@ -89,8 +186,29 @@ impl TimescaleCounter {
         }
     }
 
+    /// The number of chemistry updates since the last reset in which
+    /// the solver failed to converge.
+    pub fn num_convergence_failures(&self) -> usize {
+        self.limiting_processes[&Process::ConvergenceFailure]
+    }
+
+    /// The number of chemistry updates since the last reset which
+    /// were limited by each process.
+    pub fn counts(&self) -> Vec<(Process, usize)> {
+        Process::iter_all()
+            .map(|process| (process, self.limiting_processes[&process]))
+            .collect()
+    }
+
     pub fn show_timestep_limiting_processes(&mut self) {
         self.show_statistics();
+        let num_convergence_failures = self.num_convergence_failures();
+        if num_convergence_failures > 0 {
+            warn!(
+                "Chemistry solver failed to converge in {} cell updates",
+                num_convergence_failures
+            );
+        }
         self.reset();
     }
 
@@ -116,3 +234,68 @@ impl TimescaleCounter {
         *self = Self::new(self.max_timestep);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::LimitingProcessFractions;
+    use super::Process;
+    use super::Timescale;
+    use super::TimescaleCombination;
+    use super::TimescaleCounter;
+    use crate::test_utils::assert_float_is_close;
+    use crate::units::Time;
+
+    #[test]
+    fn limiting_process_fractions_sum_to_one() {
+        let max_timestep = Time::seconds(10.0);
+        let mut counter = TimescaleCounter::new(max_timestep);
+        let short = Time::seconds(1.0);
+        for _ in 0..5 {
+            counter.count(Timescale::temperature(short));
+        }
+        for _ in 0..3 {
+            counter.count(Timescale::photon_rate(short));
+        }
+        counter.count(Timescale::ionization_fraction(short));
+        counter.count(Timescale::convergence_failure(max_timestep * 2.0));
+        // Not limited by the chemistry, so not counted.
+        counter.count(Timescale::temperature(max_timestep * 2.0));
+        let fractions = LimitingProcessFractions::from_counts(&counter.counts());
+        assert_float_is_close(fractions.temperature, 0.5);
+        assert_float_is_close(fractions.photon_rate, 0.3);
+        assert_float_is_close(fractions.ionization_fraction, 0.1);
+        assert_float_is_close(fractions.convergence_failure, 0.1);
+        assert_float_is_close(
+            fractions.temperature
+                + fractions.photon_rate
+                + fractions.ionization_fraction
+                + fractions.convergence_failure,
+            1.0,
+        );
+        counter.reset();
+        assert_eq!(
+            LimitingProcessFractions::from_counts(&counter.counts()),
+            LimitingProcessFractions::default()
+        );
+    }
+
+    #[test]
+    fn harmonic_mean_lies_between_the_two_timescales() {
+        let rate = Timescale::photon_rate(Time::seconds(1.0));
+        let chemistry = Timescale::temperature(Time::seconds(3.0));
+        let min = TimescaleCombination::Min.combine(rate, chemistry);
+        assert_eq!(min.time, Time::seconds(1.0));
+        let mean = TimescaleCombination::HarmonicMean.combine(rate, chemistry);
+        assert!(mean.time > rate.time && mean.time < chemistry.time);
+        assert_float_is_close(mean.time.in_seconds(), 1.5);
+        assert_eq!(mean.process, Process::PhotonRate);
+        let weighted = TimescaleCombination::Weighted(1.0).combine(rate, chemistry);
+        assert_float_is_close(weighted.time.in_seconds(), 1.0);
+        // With an unchanging photon rate, the harmonic mean would be
+        // twice the chemistry timescale, but is capped at it.
+        let infinite = Timescale::photon_rate(Time::seconds(f64::INFINITY));
+        let mean = TimescaleCombination::HarmonicMean.combine(infinite, chemistry);
+        assert_float_is_close(mean.time.in_seconds(), 3.0);
+        assert_eq!(mean.process, Process::Temperature);
+    }
+}
@@ -1,5 +1,6 @@
 use std::error::Error;
 use std::fmt;
+use std::num::NonZeroUsize;
 use std::path::PathBuf;
 use std::str::FromStr;
 
@@ -47,10 +48,41 @@ impl FromStr for Override {
 #[derive(Parser, Debug, Clone)]
 #[clap(author, version, about, long_about = None)]
 pub struct CommandLineOptions {
-    pub parameter_file_path: PathBuf,
+    #[clap(required_unless_present = "benchmark")]
+    pub parameter_file_path: Option<PathBuf>,
     pub parameter_overrides: Vec<Override>,
     #[clap(short, parse(from_occurrences))]
     pub verbosity: usize,
     #[clap(long)]
     pub num_worker_threads: Option<usize>,
+    /// Instead of running a simulation, time the sweep on the given
+    /// number of randomly distributed particles.
+    #[clap(long, value_name = "NUM_PARTICLES")]
+    pub benchmark: Option<usize>,
+    /// The number of steps to time with `--benchmark`. Needs to be
+    /// at least one.
+    #[clap(long, default_value = "10")]
+    pub benchmark_steps: NonZeroUsize,
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::CommandLineOptions;
+
+    #[test]
+    fn zero_benchmark_steps_are_rejected() {
+        let parse = |steps: &str| {
+            CommandLineOptions::try_parse_from([
+                "subsweep",
+                "--benchmark",
+                "100",
+                "--benchmark-steps",
+                steps,
+            ])
+        };
+        assert_eq!(parse("3").unwrap().benchmark_steps.get(), 3);
+        assert!(parse("0").is_err());
+    }
 }
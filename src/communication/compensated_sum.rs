@@ -0,0 +1,94 @@
+use std::iter::Sum;
+
+use mpi::traits::Equivalence;
+
+use super::MpiWorld;
+
+/// A floating point sum which keeps track of the accumulated
+/// rounding error (Kahan-Babuska summation), so that summing many
+/// values of widely varying magnitude does not lose precision.
+#[derive(Clone, Copy, Debug, Default, Equivalence)]
+pub struct CompensatedSum {
+    sum: f64,
+    compensation: f64,
+}
+
+impl CompensatedSum {
+    pub fn add(&mut self, value: f64) {
+        let new_sum = self.sum + value;
+        if self.sum.abs() >= value.abs() {
+            self.compensation += (self.sum - new_sum) + value;
+        } else {
+            self.compensation += (value - new_sum) + self.sum;
+        }
+        self.sum = new_sum;
+    }
+
+    pub fn value(&self) -> f64 {
+        self.sum + self.compensation
+    }
+}
+
+impl Sum<f64> for CompensatedSum {
+    fn sum<I: Iterator<Item = f64>>(iter: I) -> Self {
+        let mut sum = Self::default();
+        for value in iter {
+            sum.add(value);
+        }
+        sum
+    }
+}
+
+/// Combines partial sums (for example from different ranks) without
+/// discarding their compensation terms.
+impl Sum<CompensatedSum> for CompensatedSum {
+    fn sum<I: Iterator<Item = CompensatedSum>>(iter: I) -> Self {
+        let mut sum = Self::default();
+        for partial in iter {
+            sum.add(partial.sum);
+            sum.add(partial.compensation);
+        }
+        sum
+    }
+}
+
+impl MpiWorld<CompensatedSum> {
+    /// Sums the values over all ranks using compensated summation,
+    /// both locally and when combining the results of the individual
+    /// ranks. The partial sums are combined in rank order, so all
+    /// ranks obtain the identical result.
+    pub fn all_reduce_sum_compensated(&mut self, values: impl Iterator<Item = f64>) -> f64 {
+        let local_sum: CompensatedSum = values.sum();
+        self.all_gather(&local_sum)
+            .into_iter()
+            .sum::<CompensatedSum>()
+            .value()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CompensatedSum;
+
+    fn get_values() -> Vec<f64> {
+        (0..10000).flat_map(|_| [1e16, 1.0, -1e16]).collect()
+    }
+
+    #[test]
+    fn compensated_sum_is_exact_where_naive_sum_is_not() {
+        let values = get_values();
+        let naive: f64 = values.iter().sum();
+        let compensated: CompensatedSum = values.into_iter().sum();
+        assert_ne!(naive, 10000.0);
+        assert_eq!(compensated.value(), 10000.0);
+    }
+
+    #[test]
+    fn combining_partial_compensated_sums_is_exact() {
+        let values = get_values();
+        let partial_sums = values
+            .chunks(1001)
+            .map(|chunk| chunk.iter().copied().sum::<CompensatedSum>());
+        assert_eq!(partial_sums.sum::<CompensatedSum>().value(), 10000.0);
+    }
+}
@@ -2,20 +2,28 @@ use derive_more::Deref;
 use derive_more::DerefMut;
 
 mod communicated_option;
+mod compensated_sum;
 mod data_by_rank;
 pub mod exchange_communicator; // public because i (currently) cannot test mpi stuff from within this module, but require an externally run example for it
 mod identified;
 mod plugin;
 mod sized_communicator;
+mod volume;
 
 use bevy_ecs::prelude::Resource;
 pub use communicated_option::CommunicatedOption;
+pub use compensated_sum::CompensatedSum;
 pub use data_by_rank::DataByRank;
 pub use exchange_communicator::ExchangeCommunicator;
 pub use identified::EntityKey;
 pub use identified::Identified;
 pub use plugin::BaseCommunicationPlugin;
 pub use sized_communicator::SizedCommunicator;
+pub use volume::communication_volume;
+pub use volume::enable_volume_tally;
+pub(crate) use volume::show_communication_volume_system;
+pub use volume::CommunicationParameters;
+pub use volume::CommunicationVolume;
 
 mod mpi_world;
 
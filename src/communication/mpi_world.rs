@@ -13,6 +13,7 @@ use derive_more::Deref;
 use derive_more::DerefMut;
 use lazy_static::lazy_static;
 use mpi::collective::SystemOperation;
+use mpi::datatype::Partition;
 use mpi::datatype::PartitionMut;
 use mpi::environment::Universe;
 use mpi::request::Request;
@@ -25,13 +26,17 @@ use mpi::traits::CommunicatorCollectives;
 use mpi::traits::Destination;
 use mpi::traits::Equivalence;
 use mpi::traits::MatchedReceiveVec;
+use mpi::traits::Root;
 use mpi::traits::Source;
 use mpi::Count;
 use mpi::Tag;
 use mpi::Threading;
 
+use super::volume::tally_received;
+use super::volume::tally_sent;
 use super::Identified;
 use super::SizedCommunicator;
+use super::WorldRank;
 
 /// A wrapper around universe which contains the universe in an
 /// Option. This allows calling .take at program completion so that
@@ -141,6 +146,7 @@ where
         let process = self.world.process_at_rank(rank);
         let result = process.matched_probe_with_tag(self.tag);
         let (data, _) = result.matched_receive_vec();
+        tally_received(rank, mem::size_of_val(&data[..]));
         data
     }
 
@@ -149,12 +155,14 @@ where
         let result = process.immediate_matched_probe_with_tag(self.tag);
         result.map(|result| {
             let (data, _) = result.matched_receive_vec();
+            tally_received(rank, mem::size_of_val(&data[..]));
             data
         })
     }
 
     pub fn blocking_send_vec(&mut self, rank: Rank, data: &[S]) {
         let process = self.world.process_at_rank(rank);
+        tally_sent(rank, mem::size_of_val(data));
         process.send_with_tag(data, self.tag);
     }
 
@@ -166,6 +174,7 @@ where
         data: &'a [S],
     ) -> Option<Request<'a, [S], Sc>> {
         let process = self.world.process_at_rank(rank);
+        tally_sent(rank, mem::size_of_val(data));
         Some(process.immediate_send_with_tag(scope, data, self.tag))
     }
 
@@ -237,14 +246,7 @@ where
         self.verify_tag();
         let mut result_buffer: Vec<S> =
             unsafe { get_buffer(counts.iter().map(|x| *x as usize).sum()) };
-        let displacements: Vec<Count> = counts
-            .iter()
-            .scan(0, |acc, &x| {
-                let tmp = *acc;
-                *acc += x;
-                Some(tmp)
-            })
-            .collect();
+        let displacements = get_displacements(counts);
         let mut partition = PartitionMut::new(&mut result_buffer, counts, &displacements[..]);
         self.world.all_gather_varcount_into(send, &mut partition);
         result_buffer
@@ -256,6 +258,71 @@ where
         let counts: Vec<_> = counts.into_iter().map(|x| x as Count).collect();
         self.all_gather_varcount_with_counts(send, &counts)
     }
+
+    /// Sends one element of `send` to every rank, in the order of
+    /// the ranks. `send` is only required on the main rank, where it
+    /// needs to contain exactly one element per rank.
+    pub fn scatter(&mut self, send: Option<&[S]>) -> S {
+        self.verify_tag();
+        let mut result_buffer: Vec<S> = unsafe { get_buffer(1) };
+        let root = self.world.process_at_rank(WorldRank::main());
+        if self.world.rank() == WorldRank::main() {
+            let send = send.expect("No data to scatter on main rank");
+            assert_eq!(
+                send.len(),
+                self.size(),
+                "Number of elements to scatter does not match the number of ranks"
+            );
+            root.scatter_into_root(send, &mut result_buffer[..]);
+        } else {
+            root.scatter_into(&mut result_buffer[..]);
+        }
+        result_buffer.remove(0)
+    }
+
+    /// Splits `send` into contiguous slices of (almost) equal size
+    /// and sends the i-th slice to the i-th rank. `send` is only
+    /// required on the main rank.
+    pub fn scatter_varcount(&mut self, send: Option<&[S]>) -> Vec<S> {
+        let on_main_rank = self.world.rank() == WorldRank::main();
+        let send = on_main_rank.then(|| send.expect("No data to scatter on main rank"));
+        let counts = send.map(|send| get_scatter_counts(send.len(), self.size()));
+        let mut counter: MpiWorld<Count> = self.unchecked_convert();
+        let count = counter.scatter(counts.as_deref());
+        self.verify_tag();
+        let mut result_buffer: Vec<S> = unsafe { get_buffer(count as usize) };
+        let root = self.world.process_at_rank(WorldRank::main());
+        if let (Some(send), Some(counts)) = (send, counts) {
+            let displacements = get_displacements(&counts);
+            let partition = Partition::new(send, &counts[..], &displacements[..]);
+            root.scatter_varcount_into_root(&partition, &mut result_buffer[..]);
+        } else {
+            root.scatter_varcount_into(&mut result_buffer[..]);
+        }
+        result_buffer
+    }
+}
+
+fn get_displacements(counts: &[Count]) -> Vec<Count> {
+    counts
+        .iter()
+        .scan(0, |acc, &x| {
+            let tmp = *acc;
+            *acc += x;
+            Some(tmp)
+        })
+        .collect()
+}
+
+/// The number of elements each rank receives in
+/// [MpiWorld::scatter_varcount]. The first ranks receive one
+/// additional element if the elements cannot be split evenly.
+fn get_scatter_counts(num_elements: usize, num_ranks: usize) -> Vec<Count> {
+    (0..num_ranks)
+        .map(|rank| {
+            (num_elements / num_ranks + usize::from(rank < num_elements % num_ranks)) as Count
+        })
+        .collect()
 }
 
 impl<T> SizedCommunicator for MpiWorld<T> {
@@ -318,6 +385,8 @@ unsafe impl<M: Equivalence> Equivalence for UninitMsg<M> {
 mod tests {
     use mpi::request::scope;
 
+    use super::get_displacements;
+    use super::get_scatter_counts;
     use super::MpiWorld;
 
     #[test]
@@ -330,4 +399,29 @@ mod tests {
         });
         assert_eq!(result, &[1, 2, 3]);
     }
+
+    #[test]
+    fn scatter_on_single_rank() {
+        let mut world = MpiWorld::<i32>::new();
+        assert_eq!(world.scatter(Some(&[5])), 5);
+        assert_eq!(world.scatter_varcount(Some(&[1, 2, 3])), &[1, 2, 3]);
+    }
+
+    #[test]
+    fn scatter_varcount_gives_each_rank_its_slice() {
+        let data: Vec<i32> = (0..10).collect();
+        let num_ranks = 4;
+        let counts = get_scatter_counts(data.len(), num_ranks);
+        let displacements = get_displacements(&counts);
+        let slices: Vec<_> = counts
+            .iter()
+            .zip(displacements.iter())
+            .map(|(count, displacement)| {
+                let start = *displacement as usize;
+                &data[start..start + *count as usize]
+            })
+            .collect();
+        assert_eq!(slices, [&[0, 1, 2][..], &[3, 4, 5], &[6, 7], &[8, 9]]);
+        assert_eq!(get_scatter_counts(2, 4), [1, 1, 0, 0]);
+    }
 }
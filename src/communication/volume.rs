@@ -0,0 +1,118 @@
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+
+use bevy_ecs::prelude::EventReader;
+use bevy_ecs::prelude::Res;
+use derive_custom::subsweep_parameters;
+use lazy_static::lazy_static;
+use log::info;
+use mpi::traits::Equivalence;
+
+use super::communicator::Communicator;
+use super::Rank;
+use super::WorldRank;
+use crate::hash_map::HashMap;
+use crate::simulation_plugin::StopSimulationEvent;
+
+/// Parameters for diagnosing the communication between ranks.
+#[subsweep_parameters("communication")]
+pub struct CommunicationParameters {
+    /// Whether to count the number of bytes sent to and received
+    /// from every other rank and show a summary at the end of the
+    /// simulation.
+    #[serde(default)]
+    pub tally_volume: bool,
+}
+
+static TALLY_ENABLED: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    static ref VOLUME: Mutex<CommunicationVolume> = Mutex::new(CommunicationVolume::default());
+}
+
+/// The number of bytes sent to and received from every other rank
+/// in point-to-point communication. Collective operations are not
+/// counted.
+#[derive(Clone, Debug, Default)]
+pub struct CommunicationVolume {
+    pub sent: HashMap<Rank, usize>,
+    pub received: HashMap<Rank, usize>,
+}
+
+/// Start counting the bytes exchanged with the other ranks. Until
+/// this is called, the tally only costs a single atomic load per
+/// message.
+pub fn enable_volume_tally() {
+    TALLY_ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// The bytes exchanged with every other rank since the tally was
+/// enabled.
+pub fn communication_volume() -> CommunicationVolume {
+    VOLUME.lock().unwrap().clone()
+}
+
+#[inline]
+pub(super) fn tally_sent(rank: Rank, num_bytes: usize) {
+    if TALLY_ENABLED.load(Ordering::Relaxed) {
+        *VOLUME.lock().unwrap().sent.entry(rank).or_default() += num_bytes;
+    }
+}
+
+#[inline]
+pub(super) fn tally_received(rank: Rank, num_bytes: usize) {
+    if TALLY_ENABLED.load(Ordering::Relaxed) {
+        *VOLUME.lock().unwrap().received.entry(rank).or_default() += num_bytes;
+    }
+}
+
+#[derive(Clone, Equivalence)]
+struct VolumeEntry {
+    rank: Rank,
+    peer: Rank,
+    sent: u64,
+    received: u64,
+}
+
+impl CommunicationVolume {
+    fn entries(&self, rank: Rank) -> Vec<VolumeEntry> {
+        let mut peers: Vec<_> = self
+            .sent
+            .keys()
+            .chain(self.received.keys())
+            .copied()
+            .collect();
+        peers.sort();
+        peers.dedup();
+        peers
+            .into_iter()
+            .map(|peer| VolumeEntry {
+                rank,
+                peer,
+                sent: self.sent.get(&peer).copied().unwrap_or(0) as u64,
+                received: self.received.get(&peer).copied().unwrap_or(0) as u64,
+            })
+            .collect()
+    }
+}
+
+pub(crate) fn show_communication_volume_system(
+    mut stop_sim: EventReader<StopSimulationEvent>,
+    rank: Res<WorldRank>,
+) {
+    if stop_sim.iter().count() == 0 {
+        return;
+    }
+    let entries = communication_volume().entries(**rank);
+    let entries = Communicator::<VolumeEntry>::new().all_gather_varcount(&entries);
+    if rank.is_main() {
+        info!("Communication volume (bytes sent / received):");
+        for entry in entries {
+            info!(
+                "{:>4} -> {:>4}: {:>14} / {:>14}",
+                entry.rank, entry.peer, entry.sent, entry.received
+            );
+        }
+    }
+}
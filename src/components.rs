@@ -36,6 +36,17 @@ pub struct IonizedHydrogenFraction(pub crate::units::Dimensionless);
 #[repr(transparent)]
 pub struct Temperature(pub crate::units::Temperature);
 
+/// Overrides the `prevent_cooling` sweep parameter for a single
+/// cell. In cells with `PreventCooling(true)`, the temperature and
+/// ionized fraction never drop below their values at the start of
+/// the current chemistry substep, so they can only increase. Cells
+/// with `PreventCooling(false)` are allowed to cool. Since this component
+/// is not exchanged between ranks, it needs to be inserted after the
+/// domain decomposition.
+#[derive(Component, Debug, Clone, Copy, Deref, DerefMut, From, Named)]
+#[name = "prevent_cooling"]
+pub struct PreventCooling(pub bool);
+
 #[derive(H5Type, Component, Debug, Clone, Equivalence, Deref, DerefMut, From, Named)]
 #[name = "source"]
 #[repr(transparent)]
@@ -71,6 +82,13 @@ pub struct HeatingRate(pub units::HeatingRate);
 #[repr(transparent)]
 pub struct Timestep(pub Time);
 
+/// The timestep of the timestep level of a cell in the last sweep,
+/// as opposed to the [Timestep] the chemistry used.
+#[derive(H5Type, Component, Debug, Clone, Equivalence, Deref, DerefMut, From, Named, Default)]
+#[name = "sweep_timestep"]
+#[repr(transparent)]
+pub struct SweepTimestep(pub Time);
+
 #[derive(H5Type, Component, Debug, Clone, Equivalence, Deref, DerefMut, From, Named)]
 #[name = "ionization_time"]
 #[repr(transparent)]
@@ -84,8 +102,33 @@ impl Default for IonizationTime {
 
 #[macro_export]
 macro_rules! impl_to_dataset {
+    (@to_output $value: ident, Self) => {
+        $value
+    };
+    (@to_output $value: ident, f32) => {
+        $value.0.value_unchecked() as f32
+    };
+    (@is_finite $value: ident, finite) => {
+        $value.0.value_unchecked().is_finite()
+    };
+    (@is_finite $value: ident, allow_infinite) => {
+        !$value.0.value_unchecked().is_nan()
+    };
     ($name: ty, $dim: ty, $is_static: expr) => {
+        $crate::impl_to_dataset!($name, $dim, $is_static, Self);
+    };
+    // Passing f32 as the output type stores the values in single
+    // precision, while still computing in f64. This only works for
+    // scalar quantities.
+    ($name: ty, $dim: ty, $is_static: expr, $output: tt) => {
+        $crate::impl_to_dataset!($name, $dim, $is_static, $output, finite);
+    };
+    // Passing allow_infinite makes the finiteness check only report
+    // NaN, for quantities which use infinity as a valid value.
+    ($name: ty, $dim: ty, $is_static: expr, $output: tt, $finite: tt) => {
         impl $crate::io::to_dataset::ToDataset for $name {
+            type Output = $output;
+
             fn dimension() -> crate::units::Dimension {
                 <$dim>::dimension()
             }
@@ -94,9 +137,17 @@ macro_rules! impl_to_dataset {
                 Self(self.0 * factor)
             }
 
+            fn to_output(self) -> Self::Output {
+                $crate::impl_to_dataset!(@to_output self, $output)
+            }
+
             fn is_static() -> bool {
                 $is_static
             }
+
+            fn is_finite(&self) -> bool {
+                $crate::impl_to_dataset!(@is_finite self, $finite)
+            }
         }
     };
 }
@@ -116,4 +167,6 @@ impl_to_dataset!(RecombinationRate, units::Rate, false);
 impl_to_dataset!(CollisionalIonizationRate, units::Rate, false);
 impl_to_dataset!(HeatingRate, units::HeatingRate, false);
 impl_to_dataset!(Timestep, units::Time, false);
-impl_to_dataset!(IonizationTime, units::Time, false);
+impl_to_dataset!(SweepTimestep, units::Time, false);
+// Cells which were never ionized have an infinite ionization time.
+impl_to_dataset!(IonizationTime, units::Time, false, f32, allow_infinite);
@@ -9,8 +9,16 @@ use crate::impl_attribute;
 use crate::io::output::ToAttribute;
 use crate::units::Dimension;
 use crate::units::Dimensionless;
+use crate::units::Rate;
 use crate::units::Time;
 
+/// The hubble constant in units of h / s.
+const HUBBLE: f64 = 3.2407789e-18;
+
+/// The number of (fourth order Runge-Kutta) steps with which the
+/// Friedmann equation is integrated.
+const NUM_FRIEDMANN_INTEGRATION_STEPS: usize = 1000;
+
 #[subsweep_parameters("cosmology")]
 #[derive(Named, Debug)]
 #[serde(untagged)]
@@ -19,6 +27,11 @@ pub enum Cosmology {
         a: f64,
         h: f64,
         params: Option<CosmologyParams>,
+        /// Whether the scale factor evolves with the simulation time.
+        /// Requires `params`. Otherwise, the scale factor stays at
+        /// `a` throughout the simulation.
+        #[serde(default)]
+        expand: bool,
     },
     NonCosmological,
 }
@@ -46,6 +59,56 @@ impl Cosmology {
         }
     }
 
+    /// The scale factor after the given time has elapsed since the
+    /// start of the simulation, obtained by integrating the
+    /// Friedmann equation. Unless `expand` is set, the initial
+    /// scale factor is returned.
+    pub fn scale_factor_at(&self, time: Time) -> Dimensionless {
+        match self {
+            Cosmology::Cosmological {
+                a,
+                h,
+                params,
+                expand,
+            } => match params {
+                Some(params) if *expand => params.integrate_scale_factor(
+                    Dimensionless::dimensionless(*a),
+                    Dimensionless::dimensionless(*h),
+                    time,
+                ),
+                None if *expand => {
+                    panic!("Tried to evolve the scale factor without cosmological parameters.")
+                }
+                _ => Dimensionless::dimensionless(*a),
+            },
+            Cosmology::NonCosmological => Dimensionless::dimensionless(1.0),
+        }
+    }
+
+    /// The factor by which physical densities change between the
+    /// two times due to the expansion of the universe. This is
+    /// exactly one unless `expand` is set.
+    pub fn density_dilution_factor(&self, t0: Time, t1: Time) -> Dimensionless {
+        (self.scale_factor_at(t0) / self.scale_factor_at(t1)).cubed()
+    }
+
+    /// The hubble parameter after the given time has elapsed since
+    /// the start of the simulation.
+    pub fn hubble_parameter_at(&self, time: Time) -> Rate {
+        match self {
+            Cosmology::Cosmological {
+                h,
+                params: Some(params),
+                ..
+            } => params
+                .hubble_parameter(self.scale_factor_at(time), Dimensionless::dimensionless(*h)),
+            Cosmology::Cosmological { params: None, .. } => {
+                panic!("Tried to compute hubble parameter without cosmological parameters.")
+            }
+            Cosmology::NonCosmological => Rate::zero(),
+        }
+    }
+
     pub fn little_h(&self) -> Dimensionless {
         match self {
             Cosmology::Cosmological { h, .. } => Dimensionless::dimensionless(*h),
@@ -77,13 +140,45 @@ impl Cosmology {
 }
 
 impl CosmologyParams {
+    /// The hubble parameter H(a) = H_0 sqrt(omega_0 a^-3 + omega_k a^-2 + omega_lambda),
+    /// where the curvature omega_k = 1 - omega_0 - omega_lambda.
+    pub fn hubble_parameter(&self, a: Dimensionless, h: Dimensionless) -> Rate {
+        let a = *a;
+        let omega_k = 1.0 - self.omega_0 - self.omega_lambda;
+        let e_squared = self.omega_0 / a.powi(3) + omega_k / a.powi(2) + self.omega_lambda;
+        Rate::per_second(HUBBLE * *h * e_squared.sqrt())
+    }
+
+    /// Get the scale factor after delta_t has elapsed, starting
+    /// from the scale factor a0, by integrating the Friedmann
+    /// equation da/dt = a H(a). Unlike
+    /// `get_scalefactor_from_scalefactor_and_time_difference`, this
+    /// does not require a flat cosmology.
+    pub fn integrate_scale_factor(
+        &self,
+        a0: Dimensionless,
+        h: Dimensionless,
+        delta_t: Time,
+    ) -> Dimensionless {
+        let dt = delta_t / NUM_FRIEDMANN_INTEGRATION_STEPS as f64;
+        let da_dt = |a: Dimensionless| a * self.hubble_parameter(a, h);
+        let mut a = a0;
+        for _ in 0..NUM_FRIEDMANN_INTEGRATION_STEPS {
+            let k1 = da_dt(a);
+            let k2 = da_dt(a + k1 * dt * 0.5);
+            let k3 = da_dt(a + k2 * dt * 0.5);
+            let k4 = da_dt(a + k3 * dt);
+            a += (k1 + k2 * 2.0 + k3 * 2.0 + k4) * dt / 6.0;
+        }
+        a
+    }
+
     pub fn time_difference_between_scalefactors(
         &self,
         a0: Dimensionless,
         a1: Dimensionless,
         h: Dimensionless,
     ) -> Time {
-        const HUBBLE: f64 = 3.2407789e-18; /* in h/sec */
         let Self {
             omega_lambda,
             omega_0,
@@ -197,6 +292,7 @@ impl_attribute!(Omega0, Dimensionless);
 
 #[cfg(test)]
 mod tests {
+    use super::Cosmology;
     use super::CosmologyParams;
     use crate::units::Dimensionless;
     use crate::units::Time;
@@ -219,6 +315,71 @@ mod tests {
         assert!((diff(0.99, 1.0) - Time::gigayears(0.14473176)).abs() < Time::years(10000.0));
     }
 
+    #[test]
+    fn matter_dominated_expansion() {
+        // In an Einstein-de Sitter universe, a = (t / t_0)^(2/3) and
+        // H = 2 / (3 t), where t_0 = 2 / (3 H_0) is the age of the
+        // universe at a = 1.
+        let cosmology = CosmologyParams {
+            omega_lambda: 0.0,
+            omega_0: 1.0,
+        };
+        let h = Dimensionless::dimensionless(0.7);
+        let a0 = Dimensionless::dimensionless(0.1);
+        let age_today = 2.0 / (3.0 * cosmology.hubble_parameter(1.0.into(), h));
+        let t0 = age_today * a0.powf(1.5);
+        for delta_t in [Time::megayears(1.0), Time::megayears(100.0), t0 * 10.0] {
+            let a = cosmology.integrate_scale_factor(a0, h, delta_t);
+            let expected = ((t0 + delta_t) / age_today).powf(2.0 / 3.0);
+            assert!(((a - expected) / expected).abs() < 1e-8);
+            let hubble = cosmology.hubble_parameter(a, h);
+            let expected = 2.0 / (3.0 * (t0 + delta_t));
+            assert!(((hubble - expected) / expected).abs() < 1e-8);
+        }
+    }
+
+    #[test]
+    fn densities_dilute_with_expansion() {
+        let params = CosmologyParams {
+            omega_lambda: 0.0,
+            omega_0: 1.0,
+        };
+        let h = Dimensionless::dimensionless(0.7);
+        let a = Dimensionless::dimensionless(0.1);
+        let cosmology = Cosmology::Cosmological {
+            a: *a,
+            h: *h,
+            params: Some(params),
+            expand: true,
+        };
+        // Since a ~ t^(2/3) in a matter dominated universe, the
+        // density dilutes as t^-2.
+        let age_today = 2.0 / (3.0 * params.hubble_parameter(1.0.into(), h));
+        let t0 = age_today * a.powf(1.5);
+        let t1 = Time::megayears(10.0);
+        let t2 = Time::megayears(30.0);
+        let factor = cosmology.density_dilution_factor(t1, t2);
+        let ratio = (t0 + t1) / (t0 + t2);
+        let expected = ratio * ratio;
+        assert!(((factor - expected) / expected).abs() < 1e-8);
+        assert!(factor < 1.0);
+        assert_eq!(
+            Cosmology::NonCosmological.density_dilution_factor(t1, t2),
+            Dimensionless::dimensionless(1.0)
+        );
+        let static_cosmology = Cosmology::Cosmological {
+            a: *a,
+            h: *h,
+            params: Some(params),
+            expand: false,
+        };
+        assert_eq!(
+            static_cosmology.density_dilution_factor(t1, t2),
+            Dimensionless::dimensionless(1.0)
+        );
+        assert_eq!(static_cosmology.scale_factor_at(t2), a);
+    }
+
     #[test]
     fn get_scalefactor_from_scalefactor_and_time_difference() {
         let (cosmology, h) = get_test_cosmology_and_h();
@@ -5,6 +5,7 @@ use crate::extent::Extent;
 use crate::prelude::Float;
 use crate::simulation_box::PeriodicWrapType2d;
 use crate::simulation_box::PeriodicWrapType3d;
+pub use crate::simulation_box::WrapType;
 use crate::units::Length;
 use crate::units::MVec2;
 use crate::units::MVec3;
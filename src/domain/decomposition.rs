@@ -200,6 +200,9 @@ impl<'a, K: Key, C: LoadCounter<K>> Decomposer<'a, K, C> {
 
 pub struct KeyCounter<K> {
     keys: Vec<K>,
+    /// The total work of all keys before the given index, i.e.
+    /// `cumulative_work[i]` is the work of `keys[..i]`.
+    cumulative_work: Vec<Work>,
 }
 
 impl<K: Key> KeyCounter<K> {
@@ -227,9 +230,25 @@ impl<K: Key> KeyCounter<K> {
         Self::new(keys)
     }
 
-    pub fn new(mut keys: Vec<K>) -> Self {
-        keys.sort();
-        Self { keys }
+    pub fn new(keys: Vec<K>) -> Self {
+        Self::new_weighted(keys.into_iter().map(|key| (key, 1)).collect())
+    }
+
+    /// Creates a counter in which every key contributes the given
+    /// work, instead of one unit of work per key.
+    pub fn new_weighted(mut keys_and_work: Vec<(K, Work)>) -> Self {
+        keys_and_work.sort_by_key(|(key, _)| *key);
+        let cumulative_work = std::iter::once(0)
+            .chain(keys_and_work.iter().scan(0, |total, (_, work)| {
+                *total += work;
+                Some(*total)
+            }))
+            .collect();
+        let keys = keys_and_work.into_iter().map(|(key, _)| key).collect();
+        Self {
+            keys,
+            cumulative_work,
+        }
     }
 }
 
@@ -241,7 +260,7 @@ impl<K: Key> LoadCounter<K> for KeyCounter<K> {
             .binary_search(&end)
             .map(|x| x + 1)
             .unwrap_or_else(|e| e);
-        end as u64 - start as u64
+        self.cumulative_work[end] - self.cumulative_work[start]
     }
 
     fn min_key(&mut self) -> K {
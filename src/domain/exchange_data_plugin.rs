@@ -20,6 +20,7 @@ use crate::communication::SizedCommunicator;
 use crate::communication::WorldRank;
 use crate::communication::WorldSize;
 use crate::named::Named;
+use crate::particle::update_particle_id_map_system;
 use crate::prelude::LocalParticle;
 use crate::prelude::Particles;
 use crate::prelude::StartupStages;
@@ -87,7 +88,13 @@ where
                 StartupStages::Exchange,
                 reset_outgoing_entities_system.after(despawn_outgoing_entities_system),
             )
-            .add_startup_system_to_stage(StartupStages::Exchange, spawn_incoming_entities_system);
+            .add_startup_system_to_stage(StartupStages::Exchange, spawn_incoming_entities_system)
+            // The entities spawned during the exchange only receive
+            // their ids once the exchange stage is finished.
+            .add_startup_system_to_stage(
+                StartupStages::TreeConstruction,
+                update_particle_id_map_system,
+            );
     }
 
     fn build_everywhere(&self, sim: &mut Simulation) {
@@ -1,18 +1,29 @@
+use std::cmp::Ordering;
+
 use bevy_ecs::prelude::*;
-use bimap::BiMap;
+use derive_more::Deref;
+use derive_more::DerefMut;
+use derive_more::From;
+use hdf5::H5Type;
+use mpi::traits::Equivalence;
+use serde::Serialize;
 
 pub mod decomposition;
 mod exchange_data_plugin;
 pub mod extent;
 mod key;
+pub mod neighbours;
+mod parameters;
 mod quadtree;
+mod work_estimate;
 
-use derive_more::Deref;
-use derive_more::DerefMut;
 pub use key::IntoKey;
 use log::debug;
 use log::error;
 use log::info;
+pub use neighbours::PeriodicNeighbourSearch;
+pub use parameters::DomainParameters;
+pub use parameters::LoadBalancing;
 pub use quadtree::LeafData;
 
 use self::decomposition::KeyCounter;
@@ -22,20 +33,31 @@ use self::exchange_data_plugin::OutgoingEntities;
 pub use self::extent::Extent;
 pub use self::quadtree::NodeData;
 pub use self::quadtree::QuadTree;
+use crate::communication::communicator::Communicator;
 use crate::communication::CommunicatedOption;
 use crate::communication::MpiWorld;
+use crate::communication::Rank;
 use crate::communication::WorldRank;
 use crate::components::Position;
+use crate::io::output::parameters::is_desired_field;
+use crate::io::output::parameters::OutputParameters;
+use crate::io::time_series::TimeSeriesPlugin;
+use crate::io::to_dataset::ToDataset;
 use crate::named::Named;
 use crate::parameters::SimulationBox;
+use crate::particle::GlobalIndexOffsets;
+use crate::particle::ParticleIdMap;
 use crate::prelude::ParticleId;
 use crate::prelude::Particles;
+use crate::prelude::Stages;
 use crate::prelude::StartupStages;
 use crate::prelude::WorldSize;
 use crate::quadtree::QuadTreeConfig;
 use crate::simulation::Simulation;
 use crate::simulation::SubsweepPlugin;
+use crate::units::Dimension;
 use crate::units::VecLength;
+use crate::units::NONE;
 
 #[cfg(feature = "2d")]
 pub type DomainKey = crate::peano_hilbert::PeanoKey2d;
@@ -45,30 +67,118 @@ pub type DecompositionState = decomposition::Decomposition<DomainKey>;
 
 pub type Work = u64;
 
-#[derive(Resource, Deref, DerefMut)]
-pub struct IdEntityMap(BiMap<ParticleId, Entity>);
+/// The rank that owns a particle after the domain decomposition.
+/// Only written to the output if requested in the output fields,
+/// which is useful for visualizing the decomposition.
+#[derive(H5Type, Component, Debug, Clone, Equivalence, Deref, DerefMut, From, Named)]
+#[name = "domain_rank"]
+#[repr(transparent)]
+pub struct DomainRank(pub Rank);
+
+impl ToDataset for DomainRank {
+    type Output = Self;
+
+    fn dimension() -> Dimension {
+        NONE
+    }
+
+    fn convert_base_units(self, _factor: f64) -> Self {
+        self
+    }
+
+    fn to_output(self) -> Self::Output {
+        self
+    }
+
+    fn is_static() -> bool {
+        true
+    }
+}
+
+/// The number of local particles on each rank, indexed by rank,
+/// which is written as a time series to monitor the load imbalance.
+#[derive(Serialize, Clone, Debug, Named)]
+#[name = "num_particles_per_rank"]
+pub struct NumParticlesPerRank {
+    pub num_particles: Vec<usize>,
+    /// The ratio of the largest number of particles on any rank to
+    /// the mean number of particles per rank.
+    pub imbalance: f64,
+}
+
+impl NumParticlesPerRank {
+    fn new(num_particles: Vec<usize>) -> Self {
+        let max = num_particles.iter().max().copied().unwrap_or(0);
+        let mean = num_particles.iter().sum::<usize>() as f64 / num_particles.len() as f64;
+        let imbalance = if mean > 0.0 { max as f64 / mean } else { 1.0 };
+        Self {
+            num_particles,
+            imbalance,
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.num_particles.iter().sum()
+    }
+}
 
 #[derive(Named)]
 pub struct DomainPlugin;
 
 impl SubsweepPlugin for DomainPlugin {
     fn build_everywhere(&self, sim: &mut Simulation) {
-        sim.add_startup_system_to_stage(
-            StartupStages::AssignParticleIds,
-            determine_particle_ids_system,
-        )
-        .add_startup_system_to_stage(StartupStages::AssignParticleIds, set_domain_extents_system)
-        .add_startup_system_to_stage(
-            StartupStages::InsertDerivedComponents,
-            check_particle_extent_system,
-        )
-        .add_startup_system_to_stage(StartupStages::Decomposition, domain_decomposition_system)
-        .add_startup_system_to_stage(
-            StartupStages::SetOutgoingEntities,
-            set_outgoing_entities_system,
-        )
-        .add_startup_system_to_stage(StartupStages::TreeConstruction, update_id_entity_map_system)
-        .add_startup_system_to_stage(StartupStages::TreeConstruction, construct_quad_tree_system);
+        sim.add_parameter_type::<DomainParameters>()
+            .add_startup_system_to_stage(
+                StartupStages::AssignParticleIds,
+                determine_particle_ids_system,
+            )
+            .add_startup_system_to_stage(
+                StartupStages::AssignParticleIds,
+                set_domain_extents_system,
+            )
+            .add_startup_system_to_stage(
+                StartupStages::InsertDerivedComponents,
+                check_particle_extent_system,
+            )
+            .add_startup_system_to_stage(StartupStages::Decomposition, domain_decomposition_system)
+            .add_startup_system_to_stage(
+                StartupStages::SetOutgoingEntities,
+                set_outgoing_entities_system,
+            )
+            .add_startup_system_to_stage(
+                StartupStages::TreeConstruction,
+                construct_quad_tree_system,
+            );
+        if sim.contains_resource::<OutputParameters>() && is_desired_field::<DomainRank>(sim) {
+            sim.add_derived_component::<DomainRank>()
+                .add_startup_system_to_stage(
+                    StartupStages::TreeConstruction,
+                    insert_domain_rank_system,
+                );
+        }
+        if sim.write_output {
+            sim.add_system_to_stage(Stages::AfterSweep, num_particles_per_rank_system)
+                .add_plugin(TimeSeriesPlugin::<NumParticlesPerRank>::default());
+        }
+    }
+}
+
+fn num_particles_per_rank_system(
+    particles: Particles<Entity>,
+    mut writer: EventWriter<NumParticlesPerRank>,
+) {
+    let num_particles_local = particles.iter().count();
+    let num_particles = Communicator::<usize>::new().all_gather(&num_particles_local);
+    writer.send(NumParticlesPerRank::new(num_particles));
+}
+
+fn insert_domain_rank_system(
+    mut commands: Commands,
+    rank: Res<WorldRank>,
+    particles: Particles<Entity>,
+) {
+    for entity in particles.iter() {
+        commands.entity(entity).insert(DomainRank(**rank));
     }
 }
 
@@ -114,13 +224,38 @@ pub(super) fn check_particle_extent_system(
     }
 }
 
+/// Compares two positions component by component.
+fn compare_positions(pos1: &VecLength, pos2: &VecLength) -> Ordering {
+    let pos1 = pos1.value_unchecked().to_array();
+    let pos2 = pos2.value_unchecked().to_array();
+    pos1.iter()
+        .zip(pos2.iter())
+        .map(|(x1, x2)| x1.total_cmp(x2))
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+/// Assigns the particle ids in the order of the domain keys of the
+/// particles, so that the ids (and thereby the order of the output)
+/// do not depend on the order in which particles were received
+/// during the exchange. Particles which are so close to each other
+/// that they share a key are ordered by their positions.
 fn determine_particle_ids_system(
     mut commands: Commands,
     rank: Res<WorldRank>,
-    particles: Particles<Entity>,
+    box_: Res<SimulationBox>,
+    particles: Particles<(Entity, &Position)>,
 ) {
-    let mut map = BiMap::default();
-    for (i, entity) in particles.iter().enumerate() {
+    let mut particles: Vec<_> = particles
+        .iter()
+        .map(|(entity, pos)| (pos.into_key(&*box_), **pos, entity))
+        .collect();
+    particles.sort_by(|(key1, pos1, _), (key2, pos2, _)| {
+        key1.cmp(key2).then_with(|| compare_positions(pos1, pos2))
+    });
+    commands.insert_resource(GlobalIndexOffsets::communicate(particles.len()));
+    let mut map = ParticleIdMap::default();
+    for (i, (_, _, entity)) in particles.into_iter().enumerate() {
         let id = ParticleId {
             index: i as u32,
             rank: **rank,
@@ -128,11 +263,7 @@ fn determine_particle_ids_system(
         commands.entity(entity).insert(id);
         map.insert(id, entity);
     }
-    commands.insert_resource(IdEntityMap(map))
-}
-
-fn update_id_entity_map_system(query: Query<(&ParticleId, Entity)>, mut map: ResMut<IdEntityMap>) {
-    map.0 = query.iter().map(|(id, entity)| (*id, entity)).collect();
+    commands.insert_resource(map)
 }
 
 pub fn get_decomposition_from_points_and_box(
@@ -147,15 +278,50 @@ pub fn get_decomposition_from_points_and_box(
     DecompositionState::new(&mut counter, world_size)
 }
 
+/// Like [get_decomposition_from_points_and_box], but weights each
+/// point by an estimate of the work of constructing its voronoi
+/// cell, which is based on the local particle density.
+pub fn get_density_weighted_decomposition_from_points_and_box(
+    points: &[VecLength],
+    box_: &SimulationBox,
+    world_size: usize,
+) -> DecompositionState {
+    debug!("Estimating work from particle density");
+    let counts = work_estimate::sum_density_grid_over_ranks(
+        work_estimate::count_particles_in_density_grid(points, box_),
+    );
+    let work = work_estimate::estimate_work_from_density(points, box_, &counts);
+    debug!("Computing keys");
+    let keys_and_work = points
+        .iter()
+        .zip(work)
+        .map(|(point, work)| (point.into_key(&**box_), work))
+        .collect();
+    let local_counter = KeyCounter::new_weighted(keys_and_work);
+    debug!("Determining cutoffs");
+    let mut counter = ParallelCounter::new(local_counter);
+    DecompositionState::new(&mut counter, world_size)
+}
+
 fn domain_decomposition_system(
     mut commands: Commands,
     box_: Res<SimulationBox>,
     particles: Particles<&Position>,
     world_size: Res<WorldSize>,
+    parameters: Res<DomainParameters>,
 ) {
     info!("Starting domain decomposition");
-    let decomp =
-        get_decomposition_from_points_and_box(particles.iter().map(|x| **x), &box_, **world_size);
+    let points = particles.iter().map(|x| **x);
+    let decomp = match parameters.load_balancing {
+        LoadBalancing::ParticleCount => {
+            get_decomposition_from_points_and_box(points, &box_, **world_size)
+        }
+        LoadBalancing::Density => get_density_weighted_decomposition_from_points_and_box(
+            &points.collect::<Vec<_>>(),
+            &box_,
+            **world_size,
+        ),
+    };
     decomp.log_imbalance();
     commands.insert_resource(decomp);
 }
@@ -184,3 +350,192 @@ fn set_domain_extents_system(
     let all_extents = communicate_extents(&particles);
     decomposition.set_extents(all_extents);
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::prelude::Commands;
+    use bevy_ecs::prelude::Events;
+    use bevy_ecs::prelude::Res;
+    use bevy_ecs::prelude::With;
+    use bevy_ecs::prelude::World;
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+
+    use super::decomposition::KeyCounter;
+    use super::determine_particle_ids_system;
+    use super::num_particles_per_rank_system;
+    use super::DecompositionState;
+    use super::DomainPlugin;
+    use super::DomainRank;
+    use super::IntoKey;
+    use super::NumParticlesPerRank;
+    use crate::communication::WorldRank;
+    use crate::components::Position;
+    use crate::io::output::parameters::OutputParameters;
+    use crate::parameters::SimulationBox;
+    use crate::parameters::SimulationParameters;
+    use crate::particle::LocalParticle;
+    use crate::prelude::ParticleId;
+    use crate::prelude::Simulation;
+    use crate::prelude::StartupStages;
+    use crate::test_utils::build_local_communication_sim_with_custom_logic;
+    use crate::test_utils::run_system_on_world;
+    use crate::units::Length;
+    use crate::units::MVec;
+    use crate::units::Time;
+    use crate::units::VecLength;
+
+    fn spawn_random_particles_system(
+        mut commands: Commands,
+        rank: Res<WorldRank>,
+        box_: Res<SimulationBox>,
+    ) {
+        let mut rng = StdRng::seed_from_u64(1337 + **rank as u64);
+        for _ in 0..250 {
+            commands.spawn((LocalParticle, Position(box_.random_point(&mut rng))));
+        }
+    }
+
+    /// Every rank spawns particles in the whole box, so most of them
+    /// are sent to another rank by the domain decomposition.
+    #[test]
+    #[ignore]
+    fn domain_rank_matches_owning_rank_on_four_ranks() {
+        build_local_communication_sim_with_custom_logic(
+            |sim: &mut Simulation| {
+                sim.add_parameter_file_contents("output:\n  fields:\n  - domain_rank".into())
+                    .add_parameter_type::<OutputParameters>()
+                    .add_required_component::<Position>()
+                    .add_plugin(DomainPlugin)
+                    .add_parameters_explicitly(SimulationBox::cube_from_side_length(
+                        Length::meters(1.0),
+                    ))
+                    .add_parameters_explicitly(SimulationParameters {
+                        final_time: Some(Time::zero()),
+                    })
+                    .add_startup_system_to_stage(
+                        StartupStages::ReadInput,
+                        spawn_random_particles_system,
+                    );
+            },
+            |sim| {
+                sim.update();
+                let rank = **sim.unwrap_resource::<WorldRank>();
+                let mut query = sim
+                    .world()
+                    .query_filtered::<&DomainRank, With<LocalParticle>>();
+                let domain_ranks: Vec<_> = query
+                    .iter(sim.world())
+                    .map(|domain_rank| **domain_rank)
+                    .collect();
+                assert!(!domain_ranks.is_empty());
+                for domain_rank in domain_ranks {
+                    assert_eq!(domain_rank, rank);
+                }
+            },
+            4,
+        );
+    }
+
+    #[test]
+    fn num_particles_per_rank_adds_up_to_total() {
+        let mut world = World::new();
+        world.init_resource::<Events<NumParticlesPerRank>>();
+        let mut entities = vec![];
+        for (step, num_new) in [10, 5, 0, 7].into_iter().enumerate() {
+            for _ in 0..num_new {
+                entities.push(world.spawn(LocalParticle).id());
+            }
+            // Simulate particles leaving this rank.
+            if step == 2 {
+                for entity in entities.drain(..3) {
+                    world.despawn(entity);
+                }
+            }
+            run_system_on_world(&mut world, num_particles_per_rank_system);
+            let mut events = world.resource_mut::<Events<NumParticlesPerRank>>();
+            let counts: Vec<_> = events.drain().collect();
+            assert_eq!(counts.len(), 1);
+            assert_eq!(counts[0].total(), entities.len());
+            assert_eq!(counts[0].imbalance, 1.0);
+        }
+    }
+
+    #[test]
+    fn imbalance_is_max_over_mean() {
+        let counts = NumParticlesPerRank::new(vec![10, 30, 20, 20]);
+        assert_eq!(counts.total(), 80);
+        assert_eq!(counts.imbalance, 1.5);
+        assert_eq!(NumParticlesPerRank::new(vec![0, 0]).imbalance, 1.0);
+    }
+
+    /// Assigns the particle ids on a single rank after spawning the
+    /// particles in the given order and returns the positions in the
+    /// order of their ids.
+    fn positions_ordered_by_id(
+        box_: &SimulationBox,
+        points: impl Iterator<Item = VecLength>,
+    ) -> Vec<VecLength> {
+        let mut world = World::new();
+        world.insert_resource(WorldRank(0));
+        world.insert_resource(box_.clone());
+        for point in points {
+            world.spawn((Position(point), LocalParticle));
+        }
+        run_system_on_world(&mut world, determine_particle_ids_system);
+        let mut query = world.query::<(&ParticleId, &Position)>();
+        let mut particles: Vec<_> = query.iter(&world).map(|(id, pos)| (*id, **pos)).collect();
+        particles.sort_by_key(|(id, _)| *id);
+        particles.into_iter().map(|(_, pos)| pos).collect()
+    }
+
+    #[test]
+    fn particle_ids_do_not_depend_on_receive_order() {
+        let num_ranks = 4;
+        let box_ = SimulationBox::cube_from_side_length(Length::meters(1.0));
+        let mut rng = StdRng::seed_from_u64(1338);
+        let points: Vec<_> = (0..1000).map(|_| box_.random_point(&mut rng)).collect();
+        let mut counter = KeyCounter::from_points_and_extent(points.iter().copied(), &*box_);
+        let decomposition = DecompositionState::new(&mut counter, num_ranks);
+        for rank in 0..num_ranks as i32 {
+            let owned: Vec<_> = points
+                .iter()
+                .copied()
+                .filter(|point| decomposition.get_owning_rank(point.into_key(&*box_)) == rank)
+                .collect();
+            let mut shuffled = owned.clone();
+            shuffled.shuffle(&mut rng);
+            let first = positions_ordered_by_id(&box_, owned.into_iter());
+            let second = positions_ordered_by_id(&box_, shuffled.into_iter());
+            assert!(!first.is_empty());
+            assert_eq!(first, second);
+        }
+    }
+
+    #[test]
+    fn particle_ids_of_particles_with_equal_keys_do_not_depend_on_receive_order() {
+        let box_ = SimulationBox::cube_from_side_length(Length::meters(1.0));
+        let mut rng = StdRng::seed_from_u64(1339);
+        let centers: Vec<_> = (0..10).map(|_| box_.random_point(&mut rng)).collect();
+        // Clusters of particles which are too close to each other to
+        // be distinguished by their keys.
+        let offset = VecLength::new_unchecked(MVec::X * 1e-15);
+        let points: Vec<_> = centers
+            .iter()
+            .flat_map(|center| (0..5).map(move |i| *center + offset * i as f64))
+            .collect();
+        for cluster in points.chunks(5) {
+            assert!(cluster
+                .iter()
+                .all(|point| point.into_key(&*box_) == cluster[0].into_key(&*box_)));
+        }
+        let first = positions_ordered_by_id(&box_, points.iter().copied());
+        for _ in 0..5 {
+            let mut shuffled = points.clone();
+            shuffled.shuffle(&mut rng);
+            let second = positions_ordered_by_id(&box_, shuffled.into_iter());
+            assert_eq!(first, second);
+        }
+    }
+}
@@ -0,0 +1,280 @@
+use bevy_ecs::prelude::Resource;
+use mpi::traits::Equivalence;
+
+use super::DecompositionState;
+use crate::communication::DataByRank;
+use crate::communication::ExchangeCommunicator;
+use crate::extent::Extent;
+use crate::parameters::SimulationBox;
+use crate::quadtree::LeafDataType;
+use crate::quadtree::NodeDataType;
+use crate::quadtree::QuadTree;
+use crate::quadtree::QuadTreeConfig;
+use crate::units::Length;
+use crate::units::MVec;
+use crate::units::VecLength;
+
+/// The data of a particle taking part in a
+/// [PeriodicNeighbourSearch].
+pub trait NeighbourData: Clone + Equivalence + 'static {
+    fn pos(&self) -> VecLength;
+}
+
+#[derive(Clone, Debug)]
+struct NeighbourLeaf {
+    index: usize,
+    pos: VecLength,
+}
+
+impl LeafDataType for NeighbourLeaf {
+    fn pos(&self) -> &VecLength {
+        &self.pos
+    }
+}
+
+#[derive(Debug, Default)]
+struct NoNodeData;
+
+impl NodeDataType<NeighbourLeaf> for NoNodeData {
+    fn update_with(&mut self, _leaf: &NeighbourLeaf) {}
+}
+
+/// Finds all pairs of particles within a cutoff radius of each
+/// other, taking into account periodic boundary conditions and
+/// particles on other ranks. On construction, every local particle
+/// which is within the cutoff radius of the domain of another rank
+/// is sent to that rank as a ghost particle.
+#[derive(Resource)]
+pub struct PeriodicNeighbourSearch<T> {
+    cutoff: Length,
+    box_: SimulationBox,
+    /// The local particles, followed by the ghost particles.
+    particles: Vec<T>,
+    num_local: usize,
+    tree: QuadTree<NoNodeData, NeighbourLeaf>,
+}
+
+impl<T: NeighbourData> PeriodicNeighbourSearch<T> {
+    /// Exchanges the ghost particles between the ranks. This needs
+    /// to be called on all ranks.
+    pub fn new(
+        local: Vec<T>,
+        cutoff: Length,
+        box_: &SimulationBox,
+        decomposition: &DecompositionState,
+    ) -> Self {
+        let mut comm = ExchangeCommunicator::<T>::new();
+        let mut outgoing: DataByRank<Vec<T>> = DataByRank::from_communicator(&comm);
+        for particle in local.iter() {
+            let extent = Extent::<MVec>::cube_around_sphere(
+                particle.pos().value_unchecked(),
+                cutoff.value_unchecked(),
+            );
+            for (rank, ghosts) in outgoing.iter_mut() {
+                if decomposition.rank_owns_part_of_search_radius(rank, &extent, box_) {
+                    ghosts.push(particle.clone());
+                }
+            }
+        }
+        let ghosts = comm
+            .exchange_all(outgoing)
+            .drain_all()
+            .flat_map(|(_, ghosts)| ghosts)
+            .collect();
+        Self::from_local_and_ghosts(local, ghosts, cutoff, box_)
+    }
+
+    pub fn from_local_and_ghosts(
+        local: Vec<T>,
+        ghosts: Vec<T>,
+        cutoff: Length,
+        box_: &SimulationBox,
+    ) -> Self {
+        let num_local = local.len();
+        let particles: Vec<_> = local.into_iter().chain(ghosts).collect();
+        let leaves = particles
+            .iter()
+            .enumerate()
+            .map(|(index, particle)| NeighbourLeaf {
+                index,
+                pos: particle.pos(),
+            })
+            .collect();
+        let tree = QuadTree::new(&QuadTreeConfig::default(), leaves, box_);
+        Self {
+            cutoff,
+            box_: box_.clone(),
+            particles,
+            num_local,
+            tree,
+        }
+    }
+
+    pub fn local(&self) -> &[T] {
+        &self.particles[..self.num_local]
+    }
+
+    pub fn ghosts(&self) -> &[T] {
+        &self.particles[self.num_local..]
+    }
+
+    /// Calls the closure for every local particle and every other
+    /// (local or ghost) particle within the cutoff radius of it. Pairs
+    /// of two local particles are visited twice, once in each order.
+    pub fn for_each_pair(&self, mut f: impl FnMut(&T, &T)) {
+        for (index, particle) in self.local().iter().enumerate() {
+            for leaf in self
+                .tree
+                .iter_particles_in_radius(&self.box_, particle.pos(), self.cutoff)
+            {
+                if leaf.index != index {
+                    f(particle, &self.particles[leaf.index]);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mpi::traits::Equivalence;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::NeighbourData;
+    use super::PeriodicNeighbourSearch;
+    use crate::communication::WorldRank;
+    use crate::domain::decomposition::KeyCounter;
+    use crate::domain::DecompositionState;
+    use crate::domain::Extent;
+    use crate::domain::IntoKey;
+    use crate::hash_map::HashSet;
+    use crate::parameters::SimulationBox;
+    use crate::prelude::Simulation;
+    use crate::test_utils::build_local_communication_sim_with_custom_logic;
+    use crate::units::Length;
+    use crate::units::VecLength;
+
+    #[derive(Clone, Debug, Equivalence)]
+    struct TestParticle {
+        id: usize,
+        pos: VecLength,
+    }
+
+    impl NeighbourData for TestParticle {
+        fn pos(&self) -> VecLength {
+            self.pos
+        }
+    }
+
+    fn random_particles(box_: &SimulationBox) -> Vec<TestParticle> {
+        let mut rng = StdRng::seed_from_u64(1337);
+        (0..300)
+            .map(|id| TestParticle {
+                id,
+                pos: box_.random_point(&mut rng),
+            })
+            .collect()
+    }
+
+    fn brute_force_pairs(
+        particles: &[TestParticle],
+        box_: &SimulationBox,
+        cutoff: Length,
+    ) -> HashSet<(usize, usize)> {
+        particles
+            .iter()
+            .flat_map(|p1| particles.iter().map(move |p2| (p1, p2)))
+            .filter(|(p1, p2)| p1.id != p2.id && box_.periodic_distance(&p1.pos, &p2.pos) < cutoff)
+            .map(|(p1, p2)| (p1.id, p2.id))
+            .collect()
+    }
+
+    #[test]
+    fn pairs_match_brute_force_periodic_search_on_two_ranks() {
+        let box_ = SimulationBox::cube_from_side_length(Length::meters(1.0));
+        let cutoff = Length::meters(0.15);
+        let particles = random_particles(&box_);
+        let brute_force = brute_force_pairs(&particles, &box_, cutoff);
+        // Make sure that the periodic boundary conditions are actually tested
+        assert!(brute_force
+            .iter()
+            .any(|(id1, id2)| { (particles[*id1].pos - particles[*id2].pos).length() > cutoff }));
+        let (rank_0, rank_1): (Vec<_>, Vec<_>) = particles
+            .iter()
+            .cloned()
+            .partition(|p| p.pos.x() < Length::meters(0.5));
+        let mut pairs = HashSet::default();
+        for (local, ghosts) in [(&rank_0, &rank_1), (&rank_1, &rank_0)] {
+            let search = PeriodicNeighbourSearch::from_local_and_ghosts(
+                local.clone(),
+                ghosts.clone(),
+                cutoff,
+                &box_,
+            );
+            search.for_each_pair(|p1, p2| {
+                pairs.insert((p1.id, p2.id));
+            });
+        }
+        assert_eq!(pairs, brute_force);
+    }
+
+    /// Every rank creates the same set of particles and keeps the
+    /// ones it owns, so that the ghosts have to be exchanged by
+    /// [PeriodicNeighbourSearch::new].
+    #[test]
+    #[ignore]
+    fn ghosts_are_exchanged_across_periodic_boundary_on_two_ranks() {
+        build_local_communication_sim_with_custom_logic(
+            |_: &mut Simulation| {},
+            |sim| {
+                let rank = **sim.unwrap_resource::<WorldRank>();
+                let num_ranks = 2;
+                let box_ = SimulationBox::cube_from_side_length(Length::meters(1.0));
+                let cutoff = Length::meters(0.15);
+                let particles = random_particles(&box_);
+                let mut counter =
+                    KeyCounter::from_points_and_extent(particles.iter().map(|p| p.pos), &*box_);
+                let mut decomposition = DecompositionState::new(&mut counter, num_ranks);
+                let owner =
+                    |p: &TestParticle| decomposition.get_owning_rank(p.pos.into_key(&*box_));
+                let extents = (0..num_ranks as i32)
+                    .map(|rank| {
+                        Extent::from_positions(
+                            particles
+                                .iter()
+                                .filter(|p| owner(p) == rank)
+                                .map(|p| &p.pos),
+                        )
+                        .unwrap()
+                    })
+                    .collect();
+                let local: Vec<_> = particles
+                    .iter()
+                    .filter(|p| owner(p) == rank)
+                    .cloned()
+                    .collect();
+                let local_ids: HashSet<_> = local.iter().map(|p| p.id).collect();
+                decomposition.set_extents(extents);
+                let search = PeriodicNeighbourSearch::new(local, cutoff, &box_, &decomposition);
+                assert!(!search.ghosts().is_empty());
+                let mut pairs = HashSet::default();
+                search.for_each_pair(|p1, p2| {
+                    pairs.insert((p1.id, p2.id));
+                });
+                let expected: HashSet<_> = brute_force_pairs(&particles, &box_, cutoff)
+                    .into_iter()
+                    .filter(|(id1, _)| local_ids.contains(id1))
+                    .collect();
+                // Make sure that some ghosts are only neighbours
+                // across the periodic boundary
+                assert!(expected.iter().any(|(id1, id2)| {
+                    !local_ids.contains(id2)
+                        && (particles[*id1].pos - particles[*id2].pos).length() > cutoff
+                }));
+                assert_eq!(pairs, expected);
+            },
+            2,
+        );
+    }
+}
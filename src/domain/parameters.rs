@@ -0,0 +1,23 @@
+use derive_custom::subsweep_parameters;
+
+/// How the work of a particle is estimated when splitting the
+/// domain between the ranks.
+#[derive(Default, Copy, Debug, PartialEq, Eq)]
+#[subsweep_parameters]
+pub enum LoadBalancing {
+    /// Every particle contributes the same amount of work, so that
+    /// all ranks end up with (roughly) the same number of particles.
+    #[default]
+    ParticleCount,
+    /// The work of a particle is estimated from the local particle
+    /// density, so that ranks containing clustered regions (which are
+    /// more expensive to triangulate) get fewer particles.
+    Density,
+}
+
+/// Parameters of the domain decomposition.
+#[subsweep_parameters("domain")]
+pub struct DomainParameters {
+    #[serde(default)]
+    pub load_balancing: LoadBalancing,
+}
@@ -0,0 +1,130 @@
+use super::Work;
+use crate::communication::MpiWorld;
+use crate::parameters::SimulationBox;
+use crate::quadtree::NUM_DIMENSIONS;
+use crate::units::VecLength;
+
+/// The number of cells per dimension of the grid on which the
+/// particle density is estimated.
+const DENSITY_GRID_CELLS_PER_DIMENSION: usize = 16;
+
+/// The work of a particle in a region of mean density. Since work
+/// is an integer, this determines the resolution of the estimate.
+const MEAN_DENSITY_WORK: Work = 100;
+
+fn get_density_grid_index(pos: &VecLength, box_: &SimulationBox) -> usize {
+    let relative = (*pos - box_.min).value_unchecked().to_array();
+    let side_lengths = box_.side_lengths().value_unchecked().to_array();
+    relative
+        .iter()
+        .zip(side_lengths.iter())
+        .fold(0, |index, (x, side_length)| {
+            let cell = ((x / side_length) * DENSITY_GRID_CELLS_PER_DIMENSION as f64) as usize;
+            index * DENSITY_GRID_CELLS_PER_DIMENSION
+                + cell.min(DENSITY_GRID_CELLS_PER_DIMENSION - 1)
+        })
+}
+
+pub(super) fn count_particles_in_density_grid(
+    positions: &[VecLength],
+    box_: &SimulationBox,
+) -> Vec<Work> {
+    let mut counts = vec![0; DENSITY_GRID_CELLS_PER_DIMENSION.pow(NUM_DIMENSIONS as u32)];
+    for pos in positions {
+        counts[get_density_grid_index(pos, box_)] += 1;
+    }
+    counts
+}
+
+pub(super) fn sum_density_grid_over_ranks(local_counts: Vec<Work>) -> Vec<Work> {
+    let all_counts = MpiWorld::<Work>::new().all_gather_varcount(&local_counts);
+    let mut counts = vec![0; local_counts.len()];
+    for rank_counts in all_counts.chunks(local_counts.len()) {
+        for (count, rank_count) in counts.iter_mut().zip(rank_counts) {
+            *count += rank_count;
+        }
+    }
+    counts
+}
+
+/// Estimates the work of constructing the voronoi cell of each
+/// particle from the (global) particle counts in the density
+/// grid. The work consists of a constant part and a part which is
+/// proportional to the local density, since the triangulation is
+/// more expensive in clustered regions.
+pub(super) fn estimate_work_from_density(
+    positions: &[VecLength],
+    box_: &SimulationBox,
+    counts: &[Work],
+) -> Vec<Work> {
+    let mean_count = counts.iter().sum::<Work>() as f64 / counts.len() as f64;
+    positions
+        .iter()
+        .map(|pos| {
+            let relative_density = counts[get_density_grid_index(pos, box_)] as f64 / mean_count;
+            MEAN_DENSITY_WORK / 2 + (relative_density * (MEAN_DENSITY_WORK / 2) as f64) as Work
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::count_particles_in_density_grid;
+    use super::estimate_work_from_density;
+    use crate::communication::Rank;
+    use crate::domain::decomposition::KeyCounter;
+    use crate::domain::DecompositionState;
+    use crate::domain::IntoKey;
+    use crate::parameters::SimulationBox;
+    use crate::units::Length;
+    use crate::units::VecLength;
+
+    fn get_num_particles_per_rank(
+        decomposition: &DecompositionState,
+        positions: &[VecLength],
+        box_: &SimulationBox,
+        num_ranks: usize,
+    ) -> Vec<usize> {
+        let mut num_particles = vec![0; num_ranks];
+        for pos in positions {
+            let rank: Rank = decomposition.get_owning_rank(pos.into_key(&**box_));
+            num_particles[rank as usize] += 1;
+        }
+        num_particles
+    }
+
+    #[test]
+    fn density_weighted_decomposition_assigns_fewer_particles_to_clustered_regions() {
+        let num_ranks = 8;
+        let box_ = SimulationBox::cube_from_side_length(Length::meters(1.0));
+        let center = box_.center();
+        let mut rng = StdRng::seed_from_u64(1337);
+        let positions: Vec<_> = (0..4000)
+            .map(|_| box_.random_point(&mut rng))
+            .chain((0..4000).map(|_| center + (box_.random_point(&mut rng) - center) * 0.05))
+            .collect();
+        let keys: Vec<_> = positions.iter().map(|pos| pos.into_key(&*box_)).collect();
+
+        let mut counter = KeyCounter::new(keys.clone());
+        let equal_count = DecompositionState::new(&mut counter, num_ranks);
+        let counts = count_particles_in_density_grid(&positions, &box_);
+        let work = estimate_work_from_density(&positions, &box_, &counts);
+        let mut counter = KeyCounter::new_weighted(keys.into_iter().zip(work).collect());
+        let density_weighted = DecompositionState::new(&mut counter, num_ranks);
+
+        let min_equal_count =
+            *get_num_particles_per_rank(&equal_count, &positions, &box_, num_ranks)
+                .iter()
+                .min()
+                .unwrap();
+        let min_density_weighted =
+            *get_num_particles_per_rank(&density_weighted, &positions, &box_, num_ranks)
+                .iter()
+                .min()
+                .unwrap();
+        assert!((min_density_weighted as f64) < 0.5 * min_equal_count as f64);
+    }
+}
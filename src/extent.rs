@@ -82,6 +82,13 @@ where
     pub fn from_points(points: impl Iterator<Item = P>) -> Option<Extent<P>> {
         get_extent_from_min_and_max_reduce(points, |p1, p2| P::min(p1, p2), |p1, p2| P::max(p1, p2))
     }
+
+    /// Returns the point within the extent which is closest to
+    /// `point`, i.e. `point` itself if it is inside the extent and
+    /// its projection onto the boundary otherwise.
+    pub fn clamp(&self, point: P) -> P {
+        P::min(P::max(point, self.min), self.max)
+    }
 }
 
 unsafe impl<P> Equivalence for Extent<P>
@@ -109,8 +116,10 @@ where
 
 #[cfg(test)]
 mod tests {
+    use super::Extent;
     use crate::test_utils::assert_float_is_close;
     use crate::voronoi::Point2d;
+    use crate::voronoi::Point3d;
 
     #[test]
     fn get_extent_from_min_and_max_reduce() {
@@ -136,4 +145,34 @@ mod tests {
         )
         .is_none());
     }
+
+    #[test]
+    fn contains_and_clamp_2d() {
+        let extent = Extent::from_min_max(Point2d::new(0.0, 0.0), Point2d::new(2.0, 1.0));
+        let interior = Point2d::new(1.0, 0.5);
+        let boundary = Point2d::new(2.0, 0.3);
+        let exterior = Point2d::new(3.0, -1.0);
+        assert!(extent.contains(&interior));
+        assert!(extent.contains(&boundary));
+        assert!(!extent.contains(&exterior));
+        assert_eq!(extent.clamp(interior), interior);
+        assert_eq!(extent.clamp(boundary), boundary);
+        assert_eq!(extent.clamp(exterior), Point2d::new(2.0, 0.0));
+        assert!(extent.contains(&extent.clamp(exterior)));
+    }
+
+    #[test]
+    fn contains_and_clamp_3d() {
+        let extent = Extent::from_min_max(Point3d::new(0.0, 0.0, 0.0), Point3d::new(1.0, 2.0, 3.0));
+        let interior = Point3d::new(0.5, 1.0, 1.5);
+        let boundary = Point3d::new(0.0, 2.0, 1.0);
+        let exterior = Point3d::new(0.5, -1.0, 4.0);
+        assert!(extent.contains(&interior));
+        assert!(extent.contains(&boundary));
+        assert!(!extent.contains(&exterior));
+        assert_eq!(extent.clamp(interior), interior);
+        assert_eq!(extent.clamp(boundary), boundary);
+        assert_eq!(extent.clamp(exterior), Point3d::new(0.5, 0.0, 3.0));
+        assert!(extent.contains(&extent.clamp(exterior)));
+    }
 }
@@ -4,3 +4,62 @@ use bevy_utils::StableHashSet;
 pub type HashMap<K, V> = StableHashMap<K, V>;
 pub type HashSet<K> = StableHashSet<K>;
 pub type BiMap<K, V> = bimap::BiMap<K, V>;
+
+/// Iteration in the order of the keys. The iteration order of the
+/// maps above depends on the order in which the entries were
+/// inserted, so this should be used wherever the order affects the
+/// result, for example in sums of floating point numbers.
+pub trait IterSorted<'a> {
+    type Item;
+
+    fn iter_sorted(&'a self) -> std::vec::IntoIter<Self::Item>;
+}
+
+impl<'a, K: Ord + 'a, V: 'a> IterSorted<'a> for HashMap<K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn iter_sorted(&'a self) -> std::vec::IntoIter<Self::Item> {
+        let mut items: Vec<_> = self.iter().collect();
+        items.sort_unstable_by(|(k1, _), (k2, _)| k1.cmp(k2));
+        items.into_iter()
+    }
+}
+
+impl<'a, K: Ord + 'a> IterSorted<'a> for HashSet<K> {
+    type Item = &'a K;
+
+    fn iter_sorted(&'a self) -> std::vec::IntoIter<Self::Item> {
+        let mut items: Vec<_> = self.iter().collect();
+        items.sort_unstable();
+        items.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HashMap;
+    use super::IterSorted;
+
+    #[test]
+    fn sorted_iteration_does_not_depend_on_insertion_order() {
+        let values: Vec<(usize, f64)> = (0..1000)
+            .map(|i| {
+                (
+                    i,
+                    1.0 / (i as f64 + 1.0) * if i % 2 == 0 { 1e10 } else { 1.0 },
+                )
+            })
+            .collect();
+        let forward: HashMap<_, _> = values.iter().copied().collect();
+        let mut backward: HashMap<_, _> = HashMap::default();
+        for (k, v) in values.iter().rev() {
+            backward.insert(*k, *v);
+        }
+        let sum = |map: &HashMap<usize, f64>| -> f64 { map.iter_sorted().map(|(_, v)| v).sum() };
+        assert_eq!(sum(&forward).to_bits(), sum(&backward).to_bits());
+        assert!(forward
+            .iter_sorted()
+            .map(|(k, _)| k)
+            .eq(values.iter().map(|(k, _)| k)));
+    }
+}
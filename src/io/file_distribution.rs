@@ -124,8 +124,11 @@ pub fn get_rank_output_assignment_for_rank(
 
 #[cfg(test)]
 mod tests {
+    use std::num::NonZeroUsize;
+
     use crate::io::file_distribution::get_input_rank_assignment;
     use crate::io::file_distribution::Region;
+    use crate::io::output::parameters::NumOutputFiles;
 
     #[test]
     fn rank_assignment() {
@@ -275,6 +278,44 @@ mod tests {
         );
     }
 
+    #[test]
+    fn auto_num_output_files_targets_particles_per_file() {
+        let num_files = NumOutputFiles::Auto {
+            particles_per_file: NonZeroUsize::new(40).unwrap(),
+        };
+        assert_eq!(num_files.resolve(0), 1);
+        assert_eq!(num_files.resolve(40), 1);
+        assert_eq!(num_files.resolve(41), 2);
+        assert_eq!(NumOutputFiles::Fixed(3).resolve(1000), 3);
+        let num_entries_per_rank = [50, 50];
+        let assignment = super::get_output_rank_assignment(
+            &num_entries_per_rank,
+            num_files.resolve(num_entries_per_rank.iter().sum()),
+        );
+        let file_indices: Vec<_> = assignment
+            .iter()
+            .flat_map(|assignment| assignment.regions.iter().map(|region| region.file_index))
+            .collect();
+        assert_eq!(file_indices, [0, 1, 1, 2]);
+        for assignment in assignment.iter() {
+            for region in assignment.regions.iter() {
+                assert!(region.size() <= 40);
+            }
+        }
+    }
+
+    #[test]
+    fn zero_particles_per_file_is_rejected() {
+        let parse = |contents| serde_yaml::from_str::<NumOutputFiles>(contents);
+        assert_eq!(
+            parse("particles_per_file: 40").unwrap(),
+            NumOutputFiles::Auto {
+                particles_per_file: NonZeroUsize::new(40).unwrap()
+            }
+        );
+        assert!(parse("particles_per_file: 0").is_err());
+    }
+
     #[test]
     fn zero_entries_doesnt_panic() {
         let assignment = super::get_input_rank_assignment(&[0], 6);
@@ -12,3 +12,11 @@ pub fn read_attribute<T: FromAttribute>(file: &Path) -> T {
     let f = File::open(file).unwrap();
     T::from_value(f.attr(T::name()).unwrap().read_scalar().unwrap())
 }
+
+/// Like [read_attribute], but returns `None` if the file does not
+/// contain the attribute.
+pub fn try_read_attribute<T: FromAttribute>(file: &Path) -> Option<T> {
+    let f = File::open(file).unwrap();
+    let attr = f.attr(T::name()).ok()?;
+    Some(T::from_value(attr.read_scalar().unwrap()))
+}
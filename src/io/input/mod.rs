@@ -19,6 +19,8 @@ use bevy_ecs::prelude::SystemLabel;
 use derive_custom::subsweep_parameters;
 use derive_more::Deref;
 use derive_more::DerefMut;
+use hdf5::filters;
+use hdf5::filters::Filter;
 use hdf5::Dataset;
 use hdf5::File;
 use hdf5::Result;
@@ -38,6 +40,8 @@ use super::InputDatasetDescriptor;
 use crate::communication::communicator::Communicator;
 use crate::communication::Rank;
 use crate::communication::SizedCommunicator;
+use crate::components::Position;
+use crate::domain::Extent;
 use crate::hash_map::HashMap;
 use crate::io::DatasetShape;
 use crate::performance::Performance;
@@ -59,11 +63,48 @@ pub enum ComponentInput<T> {
 /// Parameters describing how the initial conditions
 /// should be read. Only required if should_read_initial_conditions
 /// is set in the [SimulationBuilder](crate::prelude::SimulationBuilder)
-#[derive(Default)]
 #[subsweep_parameters("input")]
 pub struct InputParameters {
     /// The files containing the initial conditions
     paths: Vec<PathBuf>,
+    /// The number of entries read at once when reading large
+    /// datasets in chunks. Larger chunks reduce the overhead of the
+    /// hdf5 calls at the cost of memory.
+    #[serde(default = "default_read_chunk_size")]
+    pub read_chunk_size: usize,
+    /// If given, only the particles with positions inside this
+    /// region are read. This requires the positions to be part of
+    /// the initial conditions.
+    #[serde(default)]
+    pub region: Option<Extent>,
+    /// The group containing the particle datasets. Replaces the
+    /// [PARTICLE_GROUP](crate::io::PARTICLE_GROUP) placeholder in
+    /// the names of the input datasets.
+    #[serde(default = "default_particle_group")]
+    pub particle_group: String,
+}
+
+pub const DEFAULT_READ_CHUNK_SIZE: usize = 1000000;
+
+fn default_read_chunk_size() -> usize {
+    DEFAULT_READ_CHUNK_SIZE
+}
+
+pub const DEFAULT_PARTICLE_GROUP: &str = "PartType0";
+
+fn default_particle_group() -> String {
+    DEFAULT_PARTICLE_GROUP.into()
+}
+
+impl Default for InputParameters {
+    fn default() -> Self {
+        Self {
+            paths: vec![],
+            read_chunk_size: default_read_chunk_size(),
+            region: None,
+            particle_group: default_particle_group(),
+        }
+    }
 }
 
 #[derive(Resource)]
@@ -102,17 +143,45 @@ impl InputParameters {
 #[derive(Default, Deref, DerefMut, Resource)]
 struct SpawnedEntities(Vec<Entity>);
 
+/// For each entry of the datasets assigned to this rank, whether the
+/// particle lies within the region given in the [InputParameters].
+/// `None` if all particles are read.
+#[derive(Default, Deref, DerefMut, Resource)]
+struct RegionMask(Option<Vec<bool>>);
+
 #[derive(Named)]
 pub struct DatasetInputPlugin<T> {
     descriptor: InputDatasetDescriptor<T>,
+    fallback: Option<T>,
 }
 
 impl<T> DatasetInputPlugin<T> {
     pub fn from_descriptor(descriptor: InputDatasetDescriptor<T>) -> Self {
-        Self { descriptor }
+        Self {
+            descriptor,
+            fallback: None,
+        }
+    }
+
+    /// Like `from_descriptor`, but the dataset does not need to be
+    /// present in the initial conditions. If it is absent, the
+    /// component of every particle is set to `fallback` instead.
+    pub fn from_descriptor_with_fallback(
+        descriptor: InputDatasetDescriptor<T>,
+        fallback: T,
+    ) -> Self {
+        Self {
+            descriptor,
+            fallback: Some(fallback),
+        }
     }
 }
 
+/// The value used for a component if its dataset is not present in
+/// the initial conditions. If `None`, the dataset is required.
+#[derive(Deref)]
+struct DatasetFallback<T>(Option<T>);
+
 #[derive(SystemLabel)]
 struct ReadDatasetLabel;
 
@@ -122,6 +191,8 @@ pub struct RegisteredDatasets(HashMap<String, RegisteredDataset>);
 #[derive(Default, Resource)]
 pub struct RegisteredDataset {
     name: String,
+    /// Whether the dataset may be absent from the initial conditions.
+    optional: bool,
 }
 
 impl<T: Named + ToDataset + Component + Sync + Send + 'static> SubsweepPlugin
@@ -138,22 +209,35 @@ impl<T: Named + ToDataset + Component + Sync + Send + 'static> SubsweepPlugin
     fn build_once_everywhere(&self, sim: &mut Simulation) {
         sim.add_parameter_type::<InputParameters>()
             .insert_resource(SpawnedEntities::default())
+            .insert_resource(RegionMask::default())
             .add_startup_system(spawn_entities_system);
     }
 
     fn build_everywhere(&self, sim: &mut Simulation) {
+        let particle_group = sim
+            .get_parameters::<InputParameters>()
+            .particle_group
+            .clone();
+        let descriptor = InputDatasetDescriptor::new(
+            self.descriptor
+                .descriptor
+                .resolve_particle_group(&particle_group),
+            self.descriptor.shape.clone(),
+        );
         let mut registered_datasets = sim.get_resource_or_insert_with(RegisteredDatasets::default);
         registered_datasets.insert(
             T::name().into(),
             RegisteredDataset {
-                name: self.descriptor.dataset_name().into(),
+                name: descriptor.dataset_name().into(),
+                optional: self.fallback.is_some(),
             },
         );
         let input_plugin_for_type_been_added_previously = sim
             .get_non_send_resource::<InputDatasetDescriptor<T>>()
             .is_some();
         // Always use the last descriptor that has been added for a particular type.
-        sim.insert_non_send_resource(self.descriptor.clone());
+        sim.insert_non_send_resource(descriptor);
+        sim.insert_non_send_resource(DatasetFallback(self.fallback.clone()));
         // Only add read_dataset_system if it has not been added by another DatasetInputPlugin earlier.
         if !input_plugin_for_type_been_added_previously {
             sim.add_startup_system(
@@ -201,6 +285,13 @@ impl Reader {
         }
     }
 
+    /// Whether the dataset is present in any of the files.
+    pub fn has_dataset(&self, dataset_name: &str) -> bool {
+        self.files
+            .iter()
+            .any(|file| file.dataset(dataset_name).is_ok())
+    }
+
     pub fn get_num_entities(&self, dataset_name: &str) -> usize {
         self.get_assignment(dataset_name)
             .regions
@@ -288,6 +379,7 @@ fn get_dataset_and_conversion_factor_for_file<'a, T: ToDataset>(
     let set = file
         .dataset(name)
         .unwrap_or_else(|e| panic!("Failed to open dataset: {name}, {e:?}"));
+    check_filters_available(&set, name);
     let conversion_factor = descriptor.read_scale_factor(&set);
     assert_eq!(
         descriptor.read_dimension(&set),
@@ -297,6 +389,26 @@ fn get_dataset_and_conversion_factor_for_file<'a, T: ToDataset>(
     (set, conversion_factor)
 }
 
+/// The hdf5 library applies the filter pipeline of a dataset
+/// automatically while reading, but only if the filters are
+/// available. Make sure we fail with a clear error instead of an
+/// obscure one while reading the first chunk.
+fn check_filters_available(set: &Dataset, name: &str) {
+    for filter in set.filters() {
+        let available = match filter {
+            Filter::Deflate(_) => filters::deflate_available(),
+            Filter::SZip(_, _) => filters::szip_available(),
+            // User-defined filters (such as blosc) might be loaded
+            // dynamically by the hdf5 library, so we cannot check
+            // for them here.
+            _ => true,
+        };
+        if !available {
+            panic!("Dataset {name} uses the {filter:?} filter which is not available in the hdf5 library.")
+        }
+    }
+}
+
 fn convert_dataset_units<T: ToDataset>(
     data: Chunk<T>,
     factor_read: f64,
@@ -310,17 +422,25 @@ fn convert_dataset_units<T: ToDataset>(
 fn spawn_entities_system(
     mut commands: Commands,
     mut spawned_entities: ResMut<SpawnedEntities>,
+    mut region_mask: ResMut<RegionMask>,
     datasets: Res<RegisteredDatasets>,
     parameters: Res<InputParameters>,
+    position_descriptor: Option<NonSend<InputDatasetDescriptor<Position>>>,
     mut performance_data: ResMut<Performance>,
 ) {
     let reader = Reader::split_between_ranks(parameters.all_input_files());
     if datasets.len() == 0 {
         return;
     }
-    let (_, example_dataset) = &datasets.iter().next().unwrap();
+    let mut datasets = datasets
+        .values()
+        .filter(|dataset| !dataset.optional || reader.has_dataset(&dataset.name))
+        .peekable();
+    let Some(&example_dataset) = datasets.peek() else {
+        panic!("None of the input datasets are present in the initial conditions.");
+    };
     let num_entities = reader.get_num_entities(&example_dataset.name);
-    for (_, dataset) in datasets.iter() {
+    for dataset in datasets {
         let num_entities_this_dataset = reader.get_num_entities(&dataset.name);
         if num_entities_this_dataset != num_entities {
             panic!(
@@ -328,6 +448,24 @@ fn spawn_entities_system(
             );
         }
     }
+    let num_entities = match parameters.region {
+        Some(ref region) => {
+            let descriptor = position_descriptor.unwrap_or_else(|| {
+                panic!(
+                    "Reading a region of the initial conditions requires the positions to be read."
+                )
+            });
+            let mask: Vec<bool> = reader
+                .read_dataset(descriptor.clone())
+                .map(|pos| region.contains(&pos))
+                .collect();
+            assert_eq!(mask.len(), num_entities);
+            let num_inside = mask.iter().filter(|inside| **inside).count();
+            **region_mask = Some(mask);
+            num_inside
+        }
+        None => num_entities,
+    };
     let mut comm: Communicator<usize> = Communicator::new();
     let num_entities_total: usize = comm.all_gather_sum(&num_entities);
     info!("Spawned {} particles", num_entities_total);
@@ -341,15 +479,30 @@ fn spawn_entities_system(
 
 fn read_dataset_system<T: ToDataset + Component + Named>(
     descriptor: NonSend<InputDatasetDescriptor<T>>,
+    fallback: NonSend<DatasetFallback<T>>,
     mut commands: Commands,
     spawned_entities: Res<SpawnedEntities>,
+    region_mask: Res<RegionMask>,
     parameters: Res<InputParameters>,
 ) {
     let reader = Reader::split_between_ranks(parameters.all_input_files());
+    if let Some(ref fallback) = **fallback {
+        if !reader.has_dataset(descriptor.dataset_name()) {
+            info!(
+                "Dataset '{}' not present, using fallback value",
+                descriptor.dataset_name()
+            );
+            for entity in spawned_entities.iter() {
+                commands.entity(*entity).insert(fallback.clone());
+            }
+            return;
+        }
+    }
     info!("Reading dataset '{}'", descriptor.dataset_name());
     for (item, entity) in reader
         .read_dataset::<T>(descriptor.clone())
         .enumerate()
+        .filter(|(i, _)| region_mask.as_ref().map(|mask| mask[*i]).unwrap_or(true))
         .map(|(_, t)| t)
         .zip(spawned_entities.iter())
     {
@@ -418,7 +571,8 @@ fn read_chunk<T: ToDataset>(
 ) -> Chunk<T> {
     read_chunk_fallible(set, descriptor, slice).unwrap_or_else(|e| {
         let name = descriptor.dataset_name();
-        panic!("Failed to read dataset: {name}, {e:?}")
+        let filters = set.filters();
+        panic!("Failed to read dataset: {name} (filters: {filters:?}), {e:?}")
     })
 }
 
@@ -3,21 +3,39 @@ use std::path::Path;
 use bevy_ecs::prelude::Component;
 use bevy_ecs::prelude::Query;
 use bevy_ecs::prelude::World;
+use macro_utils::TempDir;
 
 use super::read_dataset_system;
+use super::spawn_entities_system;
+use super::DatasetFallback;
+use super::DatasetInputPlugin;
 use super::InputParameters;
+use super::Reader;
+use super::RegionMask;
+use super::RegisteredDataset;
+use super::RegisteredDatasets;
 use super::SpawnedEntities;
+use super::DEFAULT_READ_CHUNK_SIZE;
 use crate::components::Mass;
+use crate::components::Position;
+use crate::components::Temperature;
+use crate::domain::Extent;
+use crate::io::output::add_dimension_attrs;
 use crate::io::to_dataset::ToDataset;
 use crate::io::DatasetDescriptor;
 use crate::io::DatasetShape;
+use crate::io::DefaultUnitReader;
 use crate::io::InputDatasetDescriptor;
+use crate::performance::Performance;
 use crate::prelude::Named;
+use crate::prelude::Simulation;
 use crate::prelude::WorldRank;
 use crate::prelude::WorldSize;
 use crate::test_utils::assert_is_close;
 use crate::test_utils::run_system_on_world;
 use crate::test_utils::tests_path;
+use crate::units::MVec;
+use crate::units::VecLength;
 use crate::units::{self};
 
 #[test]
@@ -50,9 +68,242 @@ fn panic_on_dimension_mismatch() {
     run_system_on_world(&mut world, check_value_system);
 }
 
+#[test]
+fn read_gzip_compressed_dataset() {
+    let dir = TempDir::new();
+    let path = dir.join("read_gzip_compressed_dataset.hdf5");
+    let masses: Vec<_> = (0..100)
+        .map(|i| Mass(units::Mass::kilograms(i as f64)))
+        .collect();
+    {
+        let file = hdf5::File::create(&path).unwrap();
+        let dataset = file
+            .new_dataset::<Mass>()
+            .shape(&[masses.len()])
+            .chunk(10)
+            .deflate(9)
+            .create(Mass::name())
+            .unwrap();
+        add_dimension_attrs::<Mass>(&dataset);
+        dataset.write_slice(&masses[..], 0..masses.len()).unwrap();
+    }
+    let reader = Reader::full([&path].into_iter());
+    let read: Vec<_> = reader
+        .read_dataset(InputDatasetDescriptor::<Mass>::default())
+        .collect();
+    assert_eq!(read.len(), masses.len());
+    for (m1, m2) in read.iter().zip(masses.iter()) {
+        assert_is_close(**m1, **m2);
+    }
+}
+
+#[test]
+fn chunk_size_does_not_affect_read_data() {
+    let dir = TempDir::new();
+    let path = dir.join("chunk_size_does_not_affect_read_data.hdf5");
+    let masses: Vec<_> = (0..1000)
+        .map(|i| Mass(units::Mass::kilograms(i as f64)))
+        .collect();
+    {
+        let file = hdf5::File::create(&path).unwrap();
+        let dataset = file
+            .new_dataset::<Mass>()
+            .shape(&[masses.len()])
+            .create(Mass::name())
+            .unwrap();
+        add_dimension_attrs::<Mass>(&dataset);
+        dataset.write_slice(&masses[..], 0..masses.len()).unwrap();
+    }
+    let reader = Reader::full([&path].into_iter());
+    let read_with_chunk_size = |chunk_size| -> Vec<_> {
+        reader
+            .read_dataset_chunked(InputDatasetDescriptor::<Mass>::default(), chunk_size)
+            .collect()
+    };
+    let full = read_with_chunk_size(DEFAULT_READ_CHUNK_SIZE);
+    assert_eq!(full.len(), masses.len());
+    for chunk_size in [1, 7, 100, 999, 1000] {
+        let chunked = read_with_chunk_size(chunk_size);
+        assert_eq!(chunked.len(), full.len());
+        for (m1, m2) in chunked.iter().zip(full.iter()) {
+            assert_eq!(**m1, **m2);
+        }
+    }
+}
+
+fn write_masses_in_group(path: &Path, group: &str, masses: &[Mass]) {
+    let file = hdf5::File::create(path).unwrap();
+    let group = file.create_group(group).unwrap();
+    let dataset = group
+        .new_dataset::<Mass>()
+        .shape(&[masses.len()])
+        .create(Mass::name())
+        .unwrap();
+    add_dimension_attrs::<Mass>(&dataset);
+    dataset.write_slice(masses, 0..masses.len()).unwrap();
+}
+
+fn test_masses() -> Vec<Mass> {
+    (0..10)
+        .map(|i| Mass(units::Mass::kilograms(i as f64)))
+        .collect()
+}
+
+#[test]
+fn particle_group_is_resolved_from_parameter() {
+    let dir = TempDir::new();
+    let path = dir.join("particle_group_is_resolved_from_parameter.hdf5");
+    let masses = test_masses();
+    write_masses_in_group(&path, "PartType1", &masses);
+    let parameters = InputParameters {
+        particle_group: "PartType1".into(),
+        ..Default::default()
+    };
+    let descriptor =
+        DatasetDescriptor::in_particle_group(Mass::name(), Box::new(DefaultUnitReader))
+            .resolve_particle_group(&parameters.particle_group);
+    assert_eq!(descriptor.dataset_name(), "PartType1/mass");
+    let reader = Reader::full([&path].into_iter());
+    let read: Vec<_> = reader
+        .read_dataset(InputDatasetDescriptor::<Mass>::new(
+            descriptor,
+            DatasetShape::OneDimensional,
+        ))
+        .collect();
+    assert_eq!(read.len(), masses.len());
+    for (m1, m2) in read.iter().zip(masses.iter()) {
+        assert_is_close(**m1, **m2);
+    }
+}
+
+#[test]
+fn input_plugin_reads_from_particle_group_parameter() {
+    let dir = TempDir::new();
+    let path = dir.join("input_plugin_reads_from_particle_group.hdf5");
+    let masses = test_masses();
+    write_masses_in_group(&path, "PartType1", &masses);
+    let mut sim = Simulation::default();
+    sim.read_initial_conditions(true)
+        .add_parameters_explicitly(InputParameters {
+            paths: vec![path.clone()],
+            particle_group: "PartType1".into(),
+            ..Default::default()
+        })
+        .add_plugin(DatasetInputPlugin::<Mass>::from_descriptor(
+            InputDatasetDescriptor::new(
+                DatasetDescriptor::in_particle_group(Mass::name(), Box::new(DefaultUnitReader)),
+                DatasetShape::OneDimensional,
+            ),
+        ));
+    assert_eq!(
+        sim.get_resource::<RegisteredDatasets>().unwrap()[Mass::name()].name,
+        "PartType1/mass"
+    );
+    let world = sim.world();
+    let entities: Vec<_> = masses.iter().map(|_| world.spawn_empty().id()).collect();
+    world.insert_resource(SpawnedEntities(entities.clone()));
+    world.insert_resource(WorldRank(0));
+    world.insert_resource(WorldSize(1));
+    run_system_on_world(world, read_dataset_system::<Mass>);
+    for (entity, mass) in entities.into_iter().zip(masses.iter()) {
+        assert_is_close(**world.get::<Mass>(entity).unwrap(), **mass);
+    }
+}
+
+#[test]
+fn absent_optional_dataset_uses_fallback() {
+    let mut world = World::new();
+    world.insert_resource(SpawnedEntities::default());
+    world.insert_resource(RegionMask::default());
+    world.insert_resource(WorldRank(0));
+    world.insert_resource(WorldSize(1));
+    world.insert_resource(Performance::default());
+    world.insert_resource(InputParameters {
+        paths: vec![tests_path().join("input/respect_scale_factor.hdf5")],
+        ..Default::default()
+    });
+    let mut datasets = RegisteredDatasets::default();
+    for (name, optional) in [(Mass::name(), false), (Temperature::name(), true)] {
+        datasets.insert(
+            name.into(),
+            RegisteredDataset {
+                name: name.into(),
+                optional,
+            },
+        );
+    }
+    world.insert_resource(datasets);
+    run_system_on_world(&mut world, spawn_entities_system);
+    world.insert_non_send_resource(InputDatasetDescriptor::<Temperature>::default());
+    world.insert_non_send_resource(DatasetFallback(Some(Temperature(
+        units::Temperature::kelvins(100.0),
+    ))));
+    run_system_on_world(&mut world, read_dataset_system::<Temperature>);
+    run_system_on_world(&mut world, |query: Query<&Temperature>| {
+        let temperature = **query.single();
+        assert_is_close(temperature, units::Temperature::kelvins(100.0));
+    });
+}
+
+#[test]
+fn only_particles_inside_region_are_read() {
+    let dir = TempDir::new();
+    let path = dir.join("only_particles_inside_region_are_read.hdf5");
+    let positions: Vec<_> = (0..10)
+        .map(|i| Position(VecLength::new_unchecked(MVec::splat(i as f64))))
+        .collect();
+    {
+        let file = hdf5::File::create(&path).unwrap();
+        let dataset = file
+            .new_dataset::<Position>()
+            .shape(&[positions.len()])
+            .create(Position::name())
+            .unwrap();
+        add_dimension_attrs::<Position>(&dataset);
+        dataset
+            .write_slice(&positions[..], 0..positions.len())
+            .unwrap();
+    }
+    let region = Extent::from_min_max(
+        VecLength::new_unchecked(MVec::splat(2.5)),
+        VecLength::new_unchecked(MVec::splat(6.5)),
+    );
+    let mut world = World::new();
+    world.insert_resource(SpawnedEntities::default());
+    world.insert_resource(RegionMask::default());
+    world.insert_resource(WorldRank(0));
+    world.insert_resource(WorldSize(1));
+    world.insert_resource(Performance::default());
+    world.insert_resource(InputParameters {
+        paths: vec![path.clone()],
+        region: Some(region.clone()),
+        ..Default::default()
+    });
+    let mut datasets = RegisteredDatasets::default();
+    datasets.insert(
+        Position::name().into(),
+        RegisteredDataset {
+            name: Position::name().into(),
+            optional: false,
+        },
+    );
+    world.insert_resource(datasets);
+    world.insert_non_send_resource(InputDatasetDescriptor::<Position>::default());
+    world.insert_non_send_resource(DatasetFallback::<Position>(None));
+    run_system_on_world(&mut world, spawn_entities_system);
+    run_system_on_world(&mut world, read_dataset_system::<Position>);
+    run_system_on_world(&mut world, move |query: Query<&Position>| {
+        assert_eq!(query.iter().count(), 4);
+        for pos in query.iter() {
+            assert!(region.contains(pos));
+        }
+    });
+}
+
 fn read_dataset_from_file<T: ToDataset + Component + Named>(world: &mut World, file: &Path) {
     let entity = world.spawn_empty().id();
     world.insert_resource(SpawnedEntities(vec![entity]));
+    world.insert_resource(RegionMask::default());
     world.insert_resource(WorldRank(0));
     world.insert_resource(WorldSize(1));
     world.insert_resource(InputParameters {
@@ -63,5 +314,6 @@ fn read_dataset_from_file<T: ToDataset + Component + Named>(world: &mut World, f
         DatasetDescriptor::default_for::<T>(),
         DatasetShape::OneDimensional,
     ));
+    world.insert_non_send_resource(DatasetFallback::<T>(None));
     run_system_on_world(world, read_dataset_system::<T>);
 }
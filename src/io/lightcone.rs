@@ -0,0 +1,260 @@
+use std::path::PathBuf;
+
+use bevy_ecs::prelude::Res;
+use bevy_ecs::prelude::ResMut;
+use bevy_ecs::prelude::Resource;
+use derive_custom::subsweep_parameters;
+use derive_custom::Named;
+use hdf5::File;
+use hdf5::H5Type;
+use mpi::traits::Equivalence;
+
+use crate::communication::communicator::Communicator;
+use crate::components;
+use crate::components::IonizedHydrogenFraction;
+use crate::components::Position;
+use crate::parameters::OutputParameters;
+use crate::prelude::Particles;
+use crate::prelude::Stages;
+use crate::prelude::WorldRank;
+use crate::simulation::Simulation;
+use crate::simulation::SubsweepPlugin;
+use crate::simulation_plugin::SimulationTime;
+use crate::units::Dimensionless;
+use crate::units::Length;
+use crate::units::Temperature;
+use crate::units::Time;
+use crate::units::VecLength;
+use crate::units::SPEED_OF_LIGHT;
+
+const LIGHTCONE_DATASET_NAME: &str = "lightcone";
+const LIGHTCONE_CHUNK_SIZE: usize = 1024;
+
+/// Parameters for the lightcone output. Only required if the
+/// [LightconePlugin] is added.
+#[subsweep_parameters("lightcone")]
+pub struct LightconeParameters {
+    /// The position of the observer.
+    pub observer: VecLength,
+    /// The time at which the observer sees the lightcone.
+    pub observation_time: Time,
+    /// The name of the file (within the output directory) to which
+    /// the lightcone is written.
+    #[serde(default = "default_lightcone_filename")]
+    pub filename: String,
+}
+
+fn default_lightcone_filename() -> String {
+    "lightcone.hdf5".into()
+}
+
+impl LightconeParameters {
+    /// The radius of the shell around the observer from which light
+    /// emitted at `time` reaches the observer at the observation time.
+    pub fn shell_radius(&self, time: Time) -> Length {
+        SPEED_OF_LIGHT * (self.observation_time - time)
+    }
+}
+
+/// A single cell crossing the lightcone. All quantities are stored
+/// in SI units.
+#[derive(H5Type, Clone, Debug, Equivalence)]
+#[repr(C)]
+pub struct LightconeEntry {
+    pub time: Time,
+    pub distance: Length,
+    pub ionized_hydrogen_fraction: Dimensionless,
+    pub temperature: Temperature,
+}
+
+#[derive(Resource, Default)]
+struct LightconeState {
+    last_time: Option<Time>,
+}
+
+/// Records the ionized hydrogen fraction and temperature of all
+/// cells that cross the past lightcone of an observer. In every
+/// timestep, the cells within the shell that the lightcone swept
+/// over during the timestep are appended to a dataset in the output
+/// directory.
+#[derive(Named)]
+pub struct LightconePlugin;
+
+impl SubsweepPlugin for LightconePlugin {
+    fn should_build(&self, sim: &Simulation) -> bool {
+        sim.write_output
+    }
+
+    fn build_everywhere(&self, sim: &mut Simulation) {
+        sim.add_parameter_type::<LightconeParameters>()
+            .insert_resource(LightconeState::default())
+            .add_system_to_stage(Stages::Output, lightcone_system);
+    }
+
+    fn build_on_main_rank(&self, sim: &mut Simulation) {
+        sim.add_startup_system(create_lightcone_file_system);
+    }
+}
+
+fn get_lightcone_filename(
+    output_parameters: &OutputParameters,
+    parameters: &LightconeParameters,
+) -> PathBuf {
+    output_parameters.output_dir.join(&parameters.filename)
+}
+
+fn create_lightcone_file_system(
+    output_parameters: Res<OutputParameters>,
+    parameters: Res<LightconeParameters>,
+) {
+    let filename = get_lightcone_filename(&output_parameters, &parameters);
+    let file = File::create(&filename)
+        .unwrap_or_else(|e| panic!("Failed to create lightcone file {filename:?}: {e}"));
+    file.new_dataset::<LightconeEntry>()
+        .chunk(LIGHTCONE_CHUNK_SIZE)
+        .shape(0..)
+        .create(LIGHTCONE_DATASET_NAME)
+        .expect("Failed to create lightcone dataset");
+}
+
+/// Whether a cell at the given distance from the observer was swept
+/// over by the lightcone while its radius shrank from `outer` to
+/// `inner`.
+pub fn is_in_shell(distance: Length, inner: Length, outer: Length) -> bool {
+    inner < distance && distance <= outer
+}
+
+pub fn get_lightcone_entries<'a>(
+    parameters: &LightconeParameters,
+    last_time: Time,
+    time: Time,
+    cells: impl Iterator<Item = (&'a VecLength, Dimensionless, Temperature)>,
+) -> Vec<LightconeEntry> {
+    let outer = parameters.shell_radius(last_time);
+    let inner = parameters.shell_radius(time).max(Length::zero());
+    cells
+        .filter_map(|(pos, ionized_hydrogen_fraction, temperature)| {
+            let distance = (*pos - parameters.observer).length();
+            is_in_shell(distance, inner, outer).then_some(LightconeEntry {
+                time,
+                distance,
+                ionized_hydrogen_fraction,
+                temperature,
+            })
+        })
+        .collect()
+}
+
+fn lightcone_system(
+    cells: Particles<(
+        &Position,
+        &IonizedHydrogenFraction,
+        &components::Temperature,
+    )>,
+    time: Res<SimulationTime>,
+    parameters: Res<LightconeParameters>,
+    output_parameters: Res<OutputParameters>,
+    mut state: ResMut<LightconeState>,
+    rank: Res<WorldRank>,
+) {
+    let last_time = state.last_time.replace(**time);
+    let Some(last_time) = last_time else { return };
+    let entries = get_lightcone_entries(
+        &parameters,
+        last_time,
+        **time,
+        cells
+            .iter()
+            .map(|(pos, frac, temperature)| (&pos.0, **frac, **temperature)),
+    );
+    let entries = Communicator::<LightconeEntry>::new().all_gather_varcount(&entries);
+    if rank.is_main() && !entries.is_empty() {
+        append_to_lightcone_file(
+            &get_lightcone_filename(&output_parameters, &parameters),
+            &entries,
+        );
+    }
+}
+
+fn append_to_lightcone_file(filename: &PathBuf, entries: &[LightconeEntry]) {
+    let file = File::open_rw(filename)
+        .unwrap_or_else(|e| panic!("Failed to open lightcone file {filename:?}: {e}"));
+    let dataset = file
+        .dataset(LIGHTCONE_DATASET_NAME)
+        .expect("Failed to open lightcone dataset");
+    let start = dataset.size();
+    let end = start + entries.len();
+    dataset
+        .resize(end)
+        .expect("Failed to resize lightcone dataset");
+    dataset
+        .write_slice(entries, start..end)
+        .expect("Failed to write to lightcone dataset");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::get_lightcone_entries;
+    use super::LightconeParameters;
+    use crate::test_utils::get_particles;
+    use crate::units::Dimensionless;
+    use crate::units::Length;
+    use crate::units::Temperature;
+    use crate::units::Time;
+    use crate::units::VecLength;
+    use crate::units::SPEED_OF_LIGHT;
+
+    #[test]
+    fn recorded_shell_radii_match_lightcone() {
+        let observer = VecLength::zero();
+        let observation_time = Length::meters(20.0) / SPEED_OF_LIGHT;
+        let parameters = LightconeParameters {
+            observer,
+            observation_time,
+            filename: "".into(),
+        };
+        let positions: Vec<_> = get_particles(30, 30).into_iter().map(|p| p.pos).collect();
+        // A uniformly expanding ionized region around the observer.
+        let front_speed = SPEED_OF_LIGHT * 0.5;
+        let timestep = observation_time / 100.0;
+        let mut num_recorded = 0;
+        for i in 0..200 {
+            let last_time = timestep * i as f64;
+            let time = timestep * (i + 1) as f64;
+            let fraction = |pos: &VecLength| {
+                if (*pos - observer).length() < front_speed * time {
+                    Dimensionless::dimensionless(1.0)
+                } else {
+                    Dimensionless::dimensionless(0.0)
+                }
+            };
+            let entries = get_lightcone_entries(
+                &parameters,
+                last_time,
+                time,
+                positions
+                    .iter()
+                    .map(|pos| (pos, fraction(pos), Temperature::kelvins(1e4))),
+            );
+            for entry in entries.iter() {
+                assert_eq!(entry.time, time);
+                assert!(entry.distance > parameters.shell_radius(time));
+                assert!(entry.distance <= parameters.shell_radius(last_time));
+                // The cells crossing the lightcone are ionized if and
+                // only if the front has reached them.
+                let is_ionized = entry.distance < front_speed * time;
+                assert_eq!(entry.ionized_hydrogen_fraction.value() == 1.0, is_ionized);
+            }
+            if time > observation_time {
+                assert!(entries.is_empty());
+            }
+            num_recorded += entries.len();
+        }
+        // Every cell within the initial shell radius is recorded exactly once.
+        let num_inside = positions
+            .iter()
+            .filter(|pos| (**pos - observer).length() <= parameters.shell_radius(Time::zero()))
+            .count();
+        assert_eq!(num_recorded, num_inside);
+    }
+}
@@ -1,5 +1,6 @@
 mod file_distribution;
 pub mod input;
+pub mod lightcone;
 pub mod output;
 pub mod time_series;
 pub mod to_dataset;
@@ -16,6 +17,11 @@ use crate::prelude::Float;
 use crate::prelude::Named;
 use crate::units::Dimension;
 
+/// Placeholder for the group containing the particle datasets in a
+/// dataset name. It is replaced by the `particle_group` given in the
+/// [InputParameters](input::InputParameters) when the dataset is read.
+pub const PARTICLE_GROUP: &str = "{particle_group}";
+
 #[derive(Clone)]
 pub struct DatasetDescriptor {
     pub dataset_name: String,
@@ -30,6 +36,24 @@ impl DatasetDescriptor {
         }
     }
 
+    /// A descriptor for the dataset `name` within the particle group,
+    /// which is only known once the parameters have been read.
+    pub fn in_particle_group(name: &str, unit_reader: Box<dyn UnitReader>) -> Self {
+        Self {
+            dataset_name: format!("{PARTICLE_GROUP}/{name}"),
+            unit_reader,
+        }
+    }
+
+    /// Returns the descriptor with the [PARTICLE_GROUP] placeholder
+    /// in the dataset name replaced by `group`.
+    pub fn resolve_particle_group(&self, group: &str) -> Self {
+        Self {
+            dataset_name: self.dataset_name.replace(PARTICLE_GROUP, group),
+            unit_reader: self.unit_reader.clone(),
+        }
+    }
+
     pub fn dataset_name(&self) -> &str {
         &self.dataset_name
     }
@@ -48,7 +48,13 @@ impl<T: ToAttribute> IntoOutputSystem for Attribute<T> {
 }
 
 fn write_attribute<T: ToAttribute>(res: Res<T>, file: ResMut<OutputFiles>) {
-    for FileWithRegion { file, .. } in file.0.as_ref().unwrap().iter() {
+    let files = file
+        .0
+        .as_ref()
+        .unwrap()
+        .iter()
+        .flat_map(|group| group.files.iter());
+    for FileWithRegion { file, .. } in files {
         let attr = file
             .new_attr::<T::Output>()
             .shape(())
@@ -16,13 +16,18 @@ use bevy_ecs::system::Commands;
 use bevy_ecs::system::NonSend;
 use hdf5::Dataset;
 use hdf5::File;
+use hdf5::H5Type;
 use log::info;
+use log::warn;
 use mpi::traits::CommunicatorCollectives;
 use mpi::traits::Equivalence;
 
 pub use self::attribute::Attribute;
 pub use self::attribute::ToAttribute;
+use self::parameters::CheckFinite;
+use self::parameters::CompressionLevel;
 use self::parameters::OutputParameters;
+use self::parameters::OutputUnits;
 pub use self::plugin::OutputPlugin;
 use self::timer::Timer;
 use super::file_distribution::Region;
@@ -32,9 +37,12 @@ use super::DatasetDescriptor;
 use super::OutputDatasetDescriptor;
 use crate::communication::communicator::Communicator;
 use crate::communication::MPI_UNIVERSE;
+use crate::cosmology::Cosmology;
 use crate::io::file_distribution::get_rank_output_assignment_for_rank;
 use crate::io::file_distribution::RankAssignment;
+use crate::named::Named;
 use crate::parameter_plugin::ParameterFileContents;
+use crate::prelude::ParticleId;
 use crate::prelude::Particles;
 use crate::prelude::WorldRank;
 use crate::units::Dimension;
@@ -47,6 +55,9 @@ pub const TEMPERATURE_IDENTIFIER: &str = "scaling_temperature";
 pub const H_SCALING_IDENTIFIER: &str = "scaling_h";
 pub const A_SCALING_IDENTIFIER: &str = "scaling_a";
 
+/// The number of entries per chunk in compressed datasets.
+const COMPRESSION_CHUNK_SIZE: usize = 1 << 16;
+
 // Output order:
 // Output proceeds as follows
 // 1. Main rank creates files
@@ -57,8 +68,29 @@ pub const A_SCALING_IDENTIFIER: &str = "scaling_a";
 // 6. All ranks write data
 // 7. All ranks close files
 
+/// The files of the snapshots which are written in the current
+/// timestep, for every output group that is due.
 #[derive(Default, Resource)]
-pub struct OutputFiles(pub Option<Vec<FileWithRegion>>);
+pub struct OutputFiles(pub Option<Vec<GroupFiles>>);
+
+#[derive(Debug)]
+pub struct GroupFiles {
+    group: usize,
+    files: Vec<FileWithRegion>,
+}
+
+impl OutputFiles {
+    fn group_files(&self, group: usize) -> &[FileWithRegion] {
+        &self
+            .0
+            .as_ref()
+            .unwrap()
+            .iter()
+            .find(|files| files.group == group)
+            .unwrap()
+            .files
+    }
+}
 
 #[derive(Debug)]
 pub struct FileWithRegion {
@@ -101,12 +133,10 @@ pub fn make_output_dirs(parameters: &OutputParameters) {
     }
     fs::create_dir_all(&parameters.output_dir)
         .unwrap_or_else(|_| panic!("Failed to create output dir: {:?}", parameters.output_dir));
-    fs::create_dir_all(parameters.snapshot_dir()).unwrap_or_else(|_| {
-        panic!(
-            "Failed to create snapshots dir: {:?}",
-            parameters.snapshot_dir()
-        )
-    });
+    for group in parameters.snapshot_groups() {
+        fs::create_dir_all(&group.dir)
+            .unwrap_or_else(|_| panic!("Failed to create snapshots dir: {:?}", group.dir));
+    }
 }
 
 fn make_snapshot_dir(snapshot_dir: &Path) {
@@ -114,6 +144,59 @@ fn make_snapshot_dir(snapshot_dir: &Path) {
         .unwrap_or_else(|_| panic!("Failed to create snapshot dir: {snapshot_dir:?}"));
 }
 
+/// Creates and opens the hdf5 files that the snapshots are written
+/// to. The paths are the ones the snapshot files would have in the
+/// output directory. By default, the files are written to the local
+/// file system (see [`LocalFileSink`]). A custom sink can be used by
+/// inserting an [`OutputSink`] resource before adding the output
+/// plugins, for example to write the files to a staging area or to
+/// keep them in memory.
+pub trait FileSink: Send + Sync + 'static {
+    /// Creates a new, empty file. This is called once per file on
+    /// the main rank (or collectively on all ranks with parallel
+    /// hdf5), before the datasets are created.
+    fn create(&self, path: &Path) -> hdf5::Result<File>;
+
+    /// Opens a file which was previously created with
+    /// [`FileSink::create`] in order to write the data of this rank
+    /// into it.
+    fn open_rw(&self, path: &Path) -> hdf5::Result<File>;
+}
+
+/// Writes the snapshot files directly to the local file system,
+/// creating the snapshot directories as needed.
+#[derive(Default)]
+pub struct LocalFileSink;
+
+impl FileSink for LocalFileSink {
+    fn create(&self, path: &Path) -> hdf5::Result<File> {
+        if let Some(snapshot_dir) = path.parent() {
+            make_snapshot_dir(snapshot_dir);
+        }
+        create_file_rw(path)
+    }
+
+    fn open_rw(&self, path: &Path) -> hdf5::Result<File> {
+        open_file_rw(path)
+    }
+}
+
+/// The sink through which all output files are created.
+#[derive(Resource)]
+pub struct OutputSink(pub Box<dyn FileSink>);
+
+impl OutputSink {
+    pub fn new(sink: impl FileSink) -> Self {
+        Self(Box::new(sink))
+    }
+}
+
+impl Default for OutputSink {
+    fn default() -> Self {
+        Self::new(LocalFileSink)
+    }
+}
+
 pub fn compute_output_rank_assignment_system(
     mut commands: Commands,
     rank: Res<WorldRank>,
@@ -133,44 +216,52 @@ pub fn compute_output_rank_assignment_system(
     );
     let rank_assignment = get_rank_output_assignment_for_rank(
         &num_particles_per_rank,
-        parameters.num_output_files,
+        parameters.num_output_files.resolve(num_particles_total.0),
         **rank,
     );
     commands.insert_resource(rank_assignment);
 }
 
-fn get_snapshot_dir(parameters: &OutputParameters, output_timer: &Timer) -> PathBuf {
+fn get_snapshot_dir(parameters: &OutputParameters, output_timer: &Timer, group: usize) -> PathBuf {
     let snapshot_name = format!(
         "{:0snap_padding$}",
-        output_timer.snapshot_num(),
+        output_timer.snapshot_num(group),
         snap_padding = parameters.snapshot_padding
     );
-    parameters.snapshot_dir().join(&snapshot_name)
+    parameters.snapshot_group(group).dir.join(&snapshot_name)
 }
 
 fn get_output_files(
     parameters: &OutputParameters,
     output_timer: &Timer,
     assignment: &RankAssignment,
-    get_file: impl Fn(PathBuf) -> hdf5::Result<File>,
-) -> Vec<FileWithRegion> {
-    let file_index_padding = ((parameters.num_output_files as f64).log10().floor() as usize) + 1;
-    let snapshot_dir = get_snapshot_dir(parameters, output_timer);
-    make_snapshot_dir(&snapshot_dir);
-    assignment
-        .regions
-        .iter()
-        .map(|region| {
-            let filename = &format!(
-                "{:0file_index_padding$}.hdf5",
-                region.file_index,
-                file_index_padding = file_index_padding
-            );
-            let file = get_file(snapshot_dir.join(filename)).expect("Failed to open output file");
-            FileWithRegion {
-                file,
-                region: region.clone(),
-            }
+    num_particles_total: usize,
+    get_file: impl Fn(&Path) -> hdf5::Result<File>,
+) -> Vec<GroupFiles> {
+    let num_output_files = parameters.num_output_files.resolve(num_particles_total);
+    let file_index_padding = ((num_output_files as f64).log10().floor() as usize) + 1;
+    output_timer
+        .due_groups()
+        .map(|group| {
+            let snapshot_dir = get_snapshot_dir(parameters, output_timer, group);
+            let files = assignment
+                .regions
+                .iter()
+                .map(|region| {
+                    let filename = &format!(
+                        "{:0file_index_padding$}.hdf5",
+                        region.file_index,
+                        file_index_padding = file_index_padding
+                    );
+                    let file =
+                        get_file(&snapshot_dir.join(filename)).expect("Failed to open output file");
+                    FileWithRegion {
+                        file,
+                        region: region.clone(),
+                    }
+                })
+                .collect();
+            GroupFiles { group, files }
         })
         .collect()
 }
@@ -180,23 +271,31 @@ fn create_file_system(
     parameters: Res<OutputParameters>,
     output_timer: Res<Timer>,
     num_particles_total: Res<NumParticlesTotal>,
+    sink: Res<OutputSink>,
     _rank: Res<WorldRank>,
 ) {
-    info!("Writing snapshot: {}", &output_timer.snapshot_num());
+    for group in output_timer.due_groups() {
+        info!(
+            "Writing snapshot: {} to {:?}",
+            output_timer.snapshot_num(group),
+            parameters.snapshot_group(group).dir
+        );
+    }
     assert!(file.0.is_none());
 
     // In order to know how large the datasets are that we need to create:
     // Compute rank assignment for one rank.
     let assignment = get_rank_output_assignment_for_rank(
         &[num_particles_total.0],
-        parameters.num_output_files,
+        parameters.num_output_files.resolve(num_particles_total.0),
         0,
     );
     file.0 = Some(get_output_files(
         &parameters,
         &output_timer,
         &assignment,
-        create_file_rw,
+        num_particles_total.0,
+        |path| sink.0.create(path),
     ));
 }
 
@@ -219,7 +318,7 @@ fn make_mpi_file_builder() -> hdf5::FileBuilder {
 }
 
 #[cfg(feature = "parallel-hdf5")]
-fn create_file_rw(path: PathBuf) -> hdf5::Result<File> {
+fn create_file_rw(path: &Path) -> hdf5::Result<File> {
     use hdf5::plist;
 
     let mut builder = make_mpi_file_builder();
@@ -228,18 +327,18 @@ fn create_file_rw(path: PathBuf) -> hdf5::Result<File> {
 }
 
 #[cfg(feature = "parallel-hdf5")]
-fn open_file_rw(path: PathBuf) -> hdf5::Result<File> {
+fn open_file_rw(path: &Path) -> hdf5::Result<File> {
     let builder = make_mpi_file_builder();
     builder.open_rw(path)
 }
 
 #[cfg(not(feature = "parallel-hdf5"))]
-fn create_file_rw(path: PathBuf) -> hdf5::Result<File> {
+fn create_file_rw(path: &Path) -> hdf5::Result<File> {
     File::create(path)
 }
 
 #[cfg(not(feature = "parallel-hdf5"))]
-fn open_file_rw(path: PathBuf) -> hdf5::Result<File> {
+fn open_file_rw(path: &Path) -> hdf5::Result<File> {
     File::open_rw(path)
 }
 
@@ -248,13 +347,16 @@ fn open_file_system(
     parameters: Res<OutputParameters>,
     output_timer: Res<Timer>,
     assignment: Res<RankAssignment>,
+    num_particles_total: Res<NumParticlesTotal>,
+    sink: Res<OutputSink>,
 ) {
     assert!(file.0.is_none());
     file.0 = Some(get_output_files(
         &parameters,
         &output_timer,
         &assignment,
-        open_file_rw,
+        num_particles_total.0,
+        |path| sink.0.open_rw(path),
     ))
 }
 
@@ -262,41 +364,119 @@ fn close_file_system(mut file: ResMut<OutputFiles>) {
     file.0 = None;
 }
 
-pub fn create_dataset_system<T: Component + ToDataset>(
+pub fn create_dataset_system<T: Component + ToDataset + Named>(
     file: ResMut<OutputFiles>,
     descriptor: NonSend<OutputDatasetDescriptor<T>>,
+    parameters: Res<OutputParameters>,
+    output_timer: Res<Timer>,
 ) {
-    let files = file.0.as_ref().unwrap();
-    create_dataset_in_files::<T>(files, &descriptor);
+    for group in output_timer.groups_to_write::<T>(&parameters) {
+        create_dataset_in_files::<T>(
+            file.group_files(group),
+            &descriptor,
+            get_compression(&parameters),
+            parameters.units,
+        );
+    }
+}
+
+#[cfg(not(feature = "parallel-hdf5"))]
+fn get_compression(parameters: &OutputParameters) -> Option<CompressionLevel> {
+    parameters.compression
+}
+
+#[cfg(feature = "parallel-hdf5")]
+fn get_compression(_parameters: &OutputParameters) -> Option<CompressionLevel> {
+    // Writing to datasets with filters requires collective writes
+    // with MPI-IO, which we don't do, so we write uncompressed
+    // output instead.
+    None
 }
 
 pub fn create_dataset_in_files<T: ToDataset>(
     files: &[FileWithRegion],
     descriptor: &DatasetDescriptor,
+    compression: Option<CompressionLevel>,
+    units: OutputUnits,
 ) {
     for FileWithRegion { file, region } in files.iter() {
         assert!(region.start == 0);
-        let dataset = file
-            .new_dataset::<T>()
-            .shape(&[region.end - region.start])
+        let size = region.end - region.start;
+        let builder = file.new_dataset::<T::Output>().shape(&[size]);
+        // Chunked datasets cannot be empty, so write empty datasets
+        // uncompressed.
+        let builder = match compression {
+            Some(compression) if size > 0 => builder
+                .chunk(size.min(COMPRESSION_CHUNK_SIZE))
+                .deflate(compression.level()),
+            _ => builder,
+        };
+        let dataset = builder
             .create(descriptor.dataset_name())
             .expect("Failed to create dataset");
-        add_dimension_attrs::<T>(&dataset);
+        write_dimension_attrs(&dataset, units.dimension(T::dimension()));
     }
 }
 
-pub fn write_dataset_system<T: Component + ToDataset>(
-    query: Particles<&T>,
+/// Checks the data for non-finite values and warns about (or panics
+/// on) them, depending on `check_finite`. Returns the number of
+/// non-finite values.
+fn check_finite<'a, T: ToDataset>(
+    check_finite: CheckFinite,
+    dataset_name: &str,
+    data: impl Iterator<Item = (&'a ParticleId, &'a T)>,
+) -> usize {
+    if check_finite == CheckFinite::Disabled {
+        return 0;
+    }
+    let mut non_finite = data.filter(|(_, value)| !value.is_finite());
+    let Some((first_id, _)) = non_finite.next() else {
+        return 0;
+    };
+    let num_non_finite = 1 + non_finite.count();
+    let message = format!(
+        "Found {num_non_finite} non-finite values in dataset {dataset_name}, first in particle {first_id:?}"
+    );
+    match check_finite {
+        CheckFinite::Warn => warn!("{message}"),
+        CheckFinite::Panic => panic!("{message}"),
+        CheckFinite::Disabled => unreachable!(),
+    }
+    num_non_finite
+}
+
+pub fn write_dataset_system<T: Component + ToDataset + Named>(
+    query: Particles<(&ParticleId, &T)>,
     file: ResMut<OutputFiles>,
     descriptor: NonSend<OutputDatasetDescriptor<T>>,
+    parameters: Res<OutputParameters>,
+    output_timer: Res<Timer>,
+    cosmology: Option<Res<Cosmology>>,
 ) {
-    let files = file.0.as_ref().unwrap();
-    let data: Vec<T> = query.iter().cloned().collect();
-    write_dataset_to_files(data, files, &descriptor);
+    check_finite(
+        parameters.check_finite,
+        descriptor.dataset_name(),
+        query.iter(),
+    );
+    let factor = parameters
+        .units
+        .conversion_factor(T::dimension(), cosmology.as_deref());
+    // Write the particles in the order of their ids, so that the
+    // output does not depend on the order in which the entities are
+    // stored.
+    let mut particles: Vec<_> = query.iter().collect();
+    particles.sort_by_key(|(id, _)| **id);
+    let data: Vec<T::Output> = particles
+        .into_iter()
+        .map(|(_, x)| x.clone().convert_base_units(factor).to_output())
+        .collect();
+    for group in output_timer.groups_to_write::<T>(&parameters) {
+        write_dataset_to_files(&data, file.group_files(group), &descriptor);
+    }
 }
 
-pub fn write_dataset_to_files<T: ToDataset>(
-    data: Vec<T>,
+pub fn write_dataset_to_files<T: H5Type>(
+    data: &[T],
     files: &[FileWithRegion],
     descriptor: &DatasetDescriptor,
 ) {
@@ -315,12 +495,15 @@ pub fn write_dataset_to_files<T: ToDataset>(
 }
 
 pub fn add_dimension_attrs<T: ToDataset>(dataset: &Dataset) {
+    write_dimension_attrs(dataset, T::dimension());
+}
+
+fn write_dimension_attrs(dataset: &Dataset, dimension: Dimension) {
     let attr = dataset
         .new_attr::<f64>()
         .shape(())
         .create(SCALE_FACTOR_IDENTIFIER)
         .unwrap();
-    let dimension = T::dimension();
     let scale_factor = dimension.base_conversion_factor();
     attr.write_scalar(&scale_factor).unwrap();
     // Unpack this slightly awkwardly here to make sure that we
@@ -395,3 +578,299 @@ pub fn finish_wait_for_other_ranks_system(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::Path;
+    use std::path::PathBuf;
+    use std::sync::Mutex;
+
+    use hdf5::File;
+    use macro_utils::TempDir;
+
+    use super::check_finite;
+    use super::create_dataset_in_files;
+    use super::parameters::CheckFinite;
+    use super::parameters::CompressionLevel;
+    use super::parameters::OutputUnits;
+    use super::write_dataset_to_files;
+    use super::FileSink;
+    use super::FileWithRegion;
+    use super::OutputSink;
+    use super::A_SCALING_IDENTIFIER;
+    use super::H_SCALING_IDENTIFIER;
+    use crate::components::IonizationTime;
+    use crate::components::Mass;
+    use crate::components::Position;
+    use crate::components::Temperature;
+    use crate::cosmology::Cosmology;
+    use crate::io::file_distribution::Region;
+    use crate::io::input::Reader;
+    use crate::io::to_dataset::ToDataset;
+    use crate::io::DatasetDescriptor;
+    use crate::io::InputDatasetDescriptor;
+    use crate::prelude::ParticleId;
+    use crate::units;
+    use crate::units::MVec;
+    use crate::units::VecLength;
+
+    fn write_and_read(masses: &[Mass], compression: Option<CompressionLevel>) -> Vec<Mass> {
+        let dir = TempDir::new();
+        let path = dir.join(format!(
+            "compressed_output_{}.hdf5",
+            compression.map(|c| c.level() as i32).unwrap_or(-1)
+        ));
+        let files = vec![FileWithRegion {
+            file: File::create(&path).unwrap(),
+            region: Region {
+                file_index: 0,
+                start: 0,
+                end: masses.len(),
+            },
+        }];
+        let descriptor = DatasetDescriptor::default_for::<Mass>();
+        create_dataset_in_files::<Mass>(&files, &descriptor, compression, OutputUnits::Physical);
+        write_dataset_to_files(masses, &files, &descriptor);
+        drop(files);
+        let read: Vec<Mass> = Reader::full([&path].into_iter())
+            .read_dataset(InputDatasetDescriptor::<Mass>::default())
+            .collect();
+        read
+    }
+
+    #[test]
+    fn compressed_output_reads_back_identically() {
+        let masses: Vec<_> = (0..1000)
+            .map(|i| Mass(units::Mass::kilograms((i % 17) as f64)))
+            .collect();
+        let uncompressed = write_and_read(&masses, None);
+        let compressed = write_and_read(&masses, Some(CompressionLevel::new(9)));
+        assert_eq!(uncompressed.len(), masses.len());
+        for ((m1, m2), m) in compressed
+            .iter()
+            .zip(uncompressed.iter())
+            .zip(masses.iter())
+        {
+            assert_eq!(**m1, **m2);
+            assert_eq!(**m1, **m);
+        }
+    }
+
+    #[test]
+    fn f32_output_round_trips_with_single_precision() {
+        let dir = TempDir::new();
+        let path = dir.join("f32_output.hdf5");
+        let times: Vec<_> = (1..100)
+            .map(|i| IonizationTime(units::Time::seconds(1e13 / i as f64)))
+            .collect();
+        let files = vec![FileWithRegion {
+            file: File::create(&path).unwrap(),
+            region: Region {
+                file_index: 0,
+                start: 0,
+                end: times.len(),
+            },
+        }];
+        let descriptor = DatasetDescriptor::default_for::<IonizationTime>();
+        create_dataset_in_files::<IonizationTime>(&files, &descriptor, None, OutputUnits::Physical);
+        let data: Vec<f32> = times.iter().map(|time| time.clone().to_output()).collect();
+        write_dataset_to_files(&data, &files, &descriptor);
+        let dataset = files[0].file.dataset(descriptor.dataset_name()).unwrap();
+        assert_eq!(dataset.dtype().unwrap().size(), std::mem::size_of::<f32>());
+        drop(dataset);
+        drop(files);
+        let read: Vec<IonizationTime> = Reader::full([&path].into_iter())
+            .read_dataset(InputDatasetDescriptor::<IonizationTime>::default())
+            .collect();
+        assert_eq!(read.len(), times.len());
+        let mut any_inexact = false;
+        for (read, time) in read.iter().zip(times.iter()) {
+            let relative_error = ((**read - **time) / **time).value().abs();
+            assert!(relative_error <= f32::EPSILON as f64);
+            any_inexact |= relative_error > 0.0;
+        }
+        assert!(any_inexact);
+    }
+
+    fn write_positions(
+        positions: &[Position],
+        units: OutputUnits,
+        cosmology: &Cosmology,
+    ) -> (Vec<Position>, i32, i32) {
+        let dir = TempDir::new();
+        let path = dir.join(format!("output_units_{:?}.hdf5", units));
+        let files = vec![FileWithRegion {
+            file: File::create(&path).unwrap(),
+            region: Region {
+                file_index: 0,
+                start: 0,
+                end: positions.len(),
+            },
+        }];
+        let descriptor = DatasetDescriptor::default_for::<Position>();
+        create_dataset_in_files::<Position>(&files, &descriptor, None, units);
+        let factor = units.conversion_factor(Position::dimension(), Some(cosmology));
+        let data: Vec<_> = positions
+            .iter()
+            .map(|pos| pos.clone().convert_base_units(factor))
+            .collect();
+        write_dataset_to_files(&data, &files, &descriptor);
+        let dataset = files[0].file.dataset(descriptor.dataset_name()).unwrap();
+        let read_attr = |ident| dataset.attr(ident).unwrap().read_scalar().unwrap();
+        let (a, h) = (
+            read_attr(A_SCALING_IDENTIFIER),
+            read_attr(H_SCALING_IDENTIFIER),
+        );
+        let written = dataset.read_raw::<Position>().unwrap();
+        drop(dataset);
+        drop(files);
+        (written, a, h)
+    }
+
+    #[test]
+    fn comoving_output_applies_scale_factors() {
+        let (a, h) = (0.5, 0.7);
+        let cosmology = Cosmology::Cosmological {
+            a,
+            h,
+            params: None,
+            expand: false,
+        };
+        let positions: Vec<_> = (0..10)
+            .map(|i| Position(VecLength::new_unchecked(MVec::ONE * i as f64)))
+            .collect();
+        let (physical, a_physical, h_physical) =
+            write_positions(&positions, OutputUnits::Physical, &cosmology);
+        let (comoving, a_comoving, h_comoving) =
+            write_positions(&positions, OutputUnits::Comoving, &cosmology);
+        assert_eq!((a_physical, h_physical), (0, 0));
+        assert_eq!((a_comoving, h_comoving), (1, -1));
+        for ((p, c), pos) in physical.iter().zip(comoving.iter()).zip(positions.iter()) {
+            assert_eq!(**p, **pos);
+            let expected = **pos * h / a;
+            assert!((**c - expected).length().value_unchecked() < 1e-12);
+        }
+    }
+
+    fn get_temperatures_with_nan() -> Vec<(ParticleId, Temperature)> {
+        (0..10)
+            .map(|i| {
+                let value = if i == 3 || i == 7 { f64::NAN } else { 1e4 };
+                (
+                    ParticleId::test(i),
+                    Temperature(units::Temperature::kelvins(value)),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn check_finite_finds_injected_nan() {
+        let data = get_temperatures_with_nan();
+        let iter = || data.iter().map(|(id, temp)| (id, temp));
+        assert_eq!(check_finite(CheckFinite::Warn, "temperature", iter()), 2);
+        assert_eq!(
+            check_finite(CheckFinite::Disabled, "temperature", iter()),
+            0
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "first in particle")]
+    fn check_finite_panics_on_injected_nan() {
+        let data = get_temperatures_with_nan();
+        check_finite(
+            CheckFinite::Panic,
+            "temperature",
+            data.iter().map(|(id, temp)| (id, temp)),
+        );
+    }
+
+    #[test]
+    fn check_finite_accepts_infinite_ionization_time() {
+        let data = [
+            (ParticleId::test(0), IonizationTime::default()),
+            (
+                ParticleId::test(1),
+                IonizationTime(units::Time::megayears(1.0)),
+            ),
+        ];
+        let iter = || data.iter().map(|(id, time)| (id, time));
+        assert_eq!(
+            check_finite(CheckFinite::Panic, "ionization_time", iter()),
+            0
+        );
+        let nan = [(
+            ParticleId::test(0),
+            IonizationTime(units::Time::new_unchecked(f64::NAN)),
+        )];
+        assert_eq!(
+            check_finite(
+                CheckFinite::Warn,
+                "ionization_time",
+                nan.iter().map(|(id, time)| (id, time))
+            ),
+            1
+        );
+    }
+
+    /// Keeps the files in memory using the core driver of hdf5. The
+    /// sink holds on to a handle of every file it created so that
+    /// the files survive being closed by the output systems.
+    #[derive(Default)]
+    struct InMemorySink(Mutex<HashMap<PathBuf, File>>);
+
+    impl FileSink for InMemorySink {
+        fn create(&self, path: &Path) -> hdf5::Result<File> {
+            let file = File::with_options()
+                .with_fapl(|p| p.core_filebacked(false))
+                .create(path)?;
+            self.0.lock().unwrap().insert(path.to_owned(), file.clone());
+            Ok(file)
+        }
+
+        fn open_rw(&self, path: &Path) -> hdf5::Result<File> {
+            Ok(self.0.lock().unwrap()[path].clone())
+        }
+    }
+
+    #[test]
+    fn datasets_are_written_to_custom_sink() {
+        let sink = OutputSink::new(InMemorySink::default());
+        let dir = TempDir::new();
+        let path = dir.join("in_memory_sink/0.hdf5");
+        let masses: Vec<_> = (0..100)
+            .map(|i| Mass(units::Mass::kilograms(i as f64)))
+            .collect();
+        let region = Region {
+            file_index: 0,
+            start: 0,
+            end: masses.len(),
+        };
+        let descriptor = DatasetDescriptor::default_for::<Mass>();
+        let files = vec![FileWithRegion {
+            file: sink.0.create(&path).unwrap(),
+            region: region.clone(),
+        }];
+        create_dataset_in_files::<Mass>(&files, &descriptor, None, OutputUnits::Physical);
+        drop(files);
+        let files = vec![FileWithRegion {
+            file: sink.0.open_rw(&path).unwrap(),
+            region,
+        }];
+        write_dataset_to_files(&masses, &files, &descriptor);
+        drop(files);
+        let file = sink.0.open_rw(&path).unwrap();
+        let written = file
+            .dataset(descriptor.dataset_name())
+            .unwrap()
+            .read_raw::<Mass>()
+            .unwrap();
+        assert_eq!(written.len(), masses.len());
+        for (written, mass) in written.iter().zip(masses.iter()) {
+            assert_eq!(**written, **mass);
+        }
+        assert!(!path.exists());
+    }
+}
@@ -1,10 +1,13 @@
+use std::num::NonZeroUsize;
 use std::path::PathBuf;
 
 use derive_custom::subsweep_parameters;
 
 use super::plugin::IntoOutputSystem;
+use crate::cosmology::Cosmology;
 use crate::named::Named;
 use crate::simulation::Simulation;
+use crate::units::Dimension;
 use crate::units::Time;
 
 /// How to handle the case of an already existing output directory.
@@ -23,6 +26,117 @@ pub enum HandleExistingOutput {
     Delete,
 }
 
+/// The gzip compression level (between 0 and 9) used for the
+/// datasets in the snapshots.
+#[subsweep_parameters]
+#[derive(Copy, PartialEq, Eq, Debug)]
+#[serde(try_from = "u8")]
+pub struct CompressionLevel(u8);
+
+impl CompressionLevel {
+    pub fn new(level: u8) -> Self {
+        Self::try_from(level).unwrap()
+    }
+
+    pub fn level(&self) -> u8 {
+        self.0
+    }
+}
+
+impl TryFrom<u8> for CompressionLevel {
+    type Error = String;
+
+    fn try_from(level: u8) -> Result<Self, Self::Error> {
+        if level > 9 {
+            Err(format!(
+                "Invalid compression level: {level}, must be between 0 and 9."
+            ))
+        } else {
+            Ok(Self(level))
+        }
+    }
+}
+
+/// The number of output files per snapshot. Given either as a plain
+/// number or as the desired number of particles per file, in which
+/// case the number of files is chosen such that the size of the files
+/// stays roughly constant, regardless of the number of particles.
+#[derive(Copy, Debug, PartialEq, Eq)]
+#[subsweep_parameters]
+#[serde(untagged)]
+pub enum NumOutputFiles {
+    Fixed(usize),
+    Auto { particles_per_file: NonZeroUsize },
+}
+
+impl NumOutputFiles {
+    /// The number of files for the given total number of particles.
+    pub fn resolve(&self, num_particles_total: usize) -> usize {
+        match self {
+            NumOutputFiles::Fixed(num) => *num,
+            NumOutputFiles::Auto { particles_per_file } => num_particles_total
+                .div_ceil(particles_per_file.get())
+                .max(1),
+        }
+    }
+}
+
+/// What to do if non-finite values (NaN or infinity) are found in
+/// a dataset before it is written to the snapshots.
+#[derive(Default, Copy, Debug, PartialEq, Eq)]
+#[subsweep_parameters]
+pub enum CheckFinite {
+    /// Do not check the datasets.
+    #[default]
+    Disabled,
+    /// Log a warning containing the number of non-finite values and
+    /// the first particle containing one.
+    Warn,
+    /// Like `warn`, but abort the simulation afterwards.
+    Panic,
+}
+
+/// The unit convention in which the datasets in the snapshots are
+/// written.
+#[derive(Default, Copy, Debug, PartialEq, Eq)]
+#[subsweep_parameters]
+pub enum OutputUnits {
+    /// Physical quantities in SI base units.
+    #[default]
+    Physical,
+    /// Comoving quantities in SI base units, i.e. lengths are
+    /// multiplied by h / a, masses and times by h. The resulting
+    /// exponents of a and h are written as attributes of the
+    /// datasets. Requires a cosmology.
+    Comoving,
+}
+
+impl OutputUnits {
+    /// The dimension (including the exponents of a and h) with which
+    /// a quantity of the given (physical) dimension is written.
+    pub fn dimension(&self, dimension: Dimension) -> Dimension {
+        match self {
+            OutputUnits::Physical => dimension,
+            OutputUnits::Comoving => Dimension {
+                a: dimension.length,
+                h: -(dimension.length + dimension.mass + dimension.time),
+                ..dimension
+            },
+        }
+    }
+
+    /// The factor by which the (physical) values of a quantity of the
+    /// given dimension are multiplied before being written.
+    pub fn conversion_factor(&self, dimension: Dimension, cosmology: Option<&Cosmology>) -> f64 {
+        match self {
+            OutputUnits::Physical => 1.0,
+            OutputUnits::Comoving => cosmology
+                .expect("Comoving output units require a cosmology.")
+                .get_factor(&self.dimension(dimension)),
+        }
+    }
+}
+
 #[subsweep_parameters]
 #[serde(untagged)]
 pub enum Fields {
@@ -30,6 +144,46 @@ pub enum Fields {
     Some(Vec<String>),
 }
 
+/// An additional group of snapshots with its own fields and
+/// cadence, for example frequent snapshots containing only a few
+/// fields. The snapshots of the group are written to a
+/// sub-directory of the output directory with the name of the
+/// group.
+#[subsweep_parameters]
+pub struct OutputGroup {
+    /// The name of the group.
+    pub name: String,
+    /// Either 'all' or the names of all the fields that should be
+    /// written to the snapshots of this group.
+    #[serde(default = "default_fields")]
+    pub fields: Fields,
+    /// The time between two subsequent snapshots of this group.
+    #[serde(default)]
+    pub time_between_snapshots: Time,
+    /// The time at which the first snapshot of this group is
+    /// written.
+    #[serde(default)]
+    pub time_first_snapshot: Option<Time>,
+}
+
+/// A group of snapshots, either the main snapshots or one of the
+/// additional output groups.
+pub struct SnapshotGroup<'a> {
+    pub dir: PathBuf,
+    pub fields: &'a Fields,
+    pub time_between_snapshots: Time,
+    pub time_first_snapshot: Option<Time>,
+}
+
+impl SnapshotGroup<'_> {
+    pub fn is_desired_field<T: Named>(&self) -> bool {
+        match self.fields {
+            Fields::All => true,
+            Fields::Some(fields) => fields.iter().any(|field| field == T::name()),
+        }
+    }
+}
+
 /// Parameters for the output of the simulation.
 /// Only required if write_output
 /// is set in the [SimulationBuilder](crate::prelude::SimulationBuilder)
@@ -75,9 +229,30 @@ pub struct OutputParameters {
     /// The name of the file containing the performance data of
     /// the simulation.
     pub performance_data_filename: String,
+    #[serde(default = "default_performance_report_filename")]
+    /// The name of the file containing the performance report
+    /// which is written at the end of the simulation.
+    pub performance_report_filename: String,
     #[serde(default = "default_num_output_files")]
     /// The number of output files per snapshot. Default: 1
-    pub num_output_files: usize,
+    pub num_output_files: NumOutputFiles,
+    /// The gzip compression level of the datasets in the
+    /// snapshots. If None, the datasets are written uncompressed.
+    /// Not supported with parallel hdf5 output.
+    #[serde(default)]
+    pub compression: Option<CompressionLevel>,
+    /// Whether to write the datasets in physical or comoving
+    /// units.
+    #[serde(default)]
+    pub units: OutputUnits,
+    /// Whether to check the datasets for non-finite values before
+    /// writing them.
+    #[serde(default)]
+    pub check_finite: CheckFinite,
+    /// Additional groups of snapshots, each with their own fields
+    /// and cadence.
+    #[serde(default)]
+    pub groups: Vec<OutputGroup>,
 }
 
 fn default_snapshot_padding() -> usize {
@@ -108,8 +283,12 @@ fn default_performance_data_filename() -> String {
     "performance.yml".into()
 }
 
-fn default_num_output_files() -> usize {
-    1
+fn default_performance_report_filename() -> String {
+    "performance.json".into()
+}
+
+fn default_num_output_files() -> NumOutputFiles {
+    NumOutputFiles::Fixed(1)
 }
 
 pub fn is_desired_field<T: Named + IntoOutputSystem>(sim: &Simulation) -> bool {
@@ -120,11 +299,30 @@ pub fn is_desired_field<T: Named + IntoOutputSystem>(sim: &Simulation) -> bool {
 }
 
 impl OutputParameters {
+    /// Whether the field is written to the snapshots of any group.
     pub fn is_desired_field<T: Named>(&self) -> bool {
-        match &self.fields {
-            Fields::All => true,
-            Fields::Some(fields) => fields.iter().any(|field| field == T::name()),
-        }
+        self.snapshot_groups()
+            .any(|group| group.is_desired_field::<T>())
+    }
+
+    /// The main snapshots, followed by the additional output groups.
+    pub fn snapshot_groups(&self) -> impl Iterator<Item = SnapshotGroup<'_>> + '_ {
+        let main = SnapshotGroup {
+            dir: self.snapshot_dir(),
+            fields: &self.fields,
+            time_between_snapshots: self.time_between_snapshots,
+            time_first_snapshot: self.time_first_snapshot,
+        };
+        std::iter::once(main).chain(self.groups.iter().map(|group| SnapshotGroup {
+            dir: self.output_dir.join(&group.name),
+            fields: &group.fields,
+            time_between_snapshots: group.time_between_snapshots,
+            time_first_snapshot: group.time_first_snapshot,
+        }))
+    }
+
+    pub fn snapshot_group(&self, index: usize) -> SnapshotGroup<'_> {
+        self.snapshot_groups().nth(index).unwrap()
     }
 
     pub fn snapshot_dir(&self) -> PathBuf {
@@ -15,6 +15,7 @@ use super::parameters::OutputParameters;
 use super::timer::Timer;
 use super::write_used_parameters_system;
 use super::OutputFiles;
+use super::OutputSink;
 use crate::io::DatasetDescriptor;
 use crate::io::OutputDatasetDescriptor;
 use crate::named::Named;
@@ -98,6 +99,9 @@ where
     }
 
     fn build_once_everywhere(&self, sim: &mut Simulation) {
+        if !sim.contains_resource::<OutputSink>() {
+            sim.insert_resource(OutputSink::default());
+        }
         sim.add_parameter_type::<OutputParameters>()
             .insert_resource(OutputFiles::default())
             .add_startup_system_to_stage(
@@ -105,6 +109,7 @@ where
                 compute_output_rank_assignment_system,
             )
             .add_startup_system(Timer::initialize_system)
+            .add_system_to_stage(Stages::AfterSweep, Timer::determine_due_groups_system)
             .add_system_to_stage(
                 Stages::Output,
                 open_file_system.with_run_criteria(Timer::run_criterion),
@@ -160,6 +165,14 @@ where
         sim.insert_resource(RegisteredFields::default());
         sim.add_startup_system(write_used_parameters_system)
             .add_startup_system(verify_output_fields_system);
+        #[cfg(feature = "parallel-hdf5")]
+        if sim
+            .unwrap_resource::<OutputParameters>()
+            .compression
+            .is_some()
+        {
+            log::warn!("Compression is not supported with parallel hdf5 output. Writing uncompressed snapshots.");
+        }
         #[cfg(not(feature = "parallel-hdf5"))]
         add_file_creation_systems(sim);
     }
@@ -178,10 +191,12 @@ fn verify_output_fields_system(
     parameters: Res<OutputParameters>,
     registered: Res<RegisteredFields>,
 ) {
-    if let Fields::Some(ref fields) = parameters.fields {
-        for field in fields.iter() {
-            if !registered.0.contains(field) {
-                error!("Unknown field specified: {}", field);
+    for group in parameters.snapshot_groups() {
+        if let Fields::Some(fields) = group.fields {
+            for field in fields.iter() {
+                if !registered.0.contains(field) {
+                    error!("Unknown field specified: {}", field);
+                }
             }
         }
     }
@@ -7,66 +7,167 @@ use bevy_ecs::schedule::ShouldRun;
 
 use super::parameters::OutputParameters;
 use crate::io::to_dataset::ToDataset;
+use crate::named::Named;
 use crate::simulation_plugin::SimulationTime;
 use crate::simulation_plugin::StopSimulationEvent;
 use crate::units;
 
-#[derive(Resource)]
-pub struct Timer {
+struct GroupTimer {
     next_output_time: units::Time,
+    time_between_snapshots: units::Time,
     snapshot_num: usize,
 }
 
+/// Keeps track of the snapshots of every output group (see
+/// [OutputParameters::snapshot_groups]).
+#[derive(Resource)]
+pub struct Timer {
+    groups: Vec<GroupTimer>,
+    /// The indices of the groups for which a snapshot is written
+    /// in the current timestep.
+    due: Vec<usize>,
+}
+
 impl Timer {
+    fn new(parameters: &OutputParameters) -> Self {
+        let groups = parameters
+            .snapshot_groups()
+            .map(|group| GroupTimer {
+                next_output_time: group.time_first_snapshot.unwrap_or_else(units::Time::zero),
+                time_between_snapshots: group.time_between_snapshots,
+                snapshot_num: 0,
+            })
+            .collect();
+        Self {
+            groups,
+            due: vec![],
+        }
+    }
+
     pub fn initialize_system(mut commands: Commands, parameters: Res<OutputParameters>) {
-        commands.insert_resource(Timer {
-            next_output_time: parameters
-                .time_first_snapshot
-                .unwrap_or_else(units::Time::zero),
-            snapshot_num: 0,
-        });
+        commands.insert_resource(Timer::new(&parameters));
     }
 
-    pub fn run_criterion(
+    pub fn determine_due_groups_system(
         time: Res<SimulationTime>,
-        timer: Res<Self>,
-        events: EventReader<StopSimulationEvent>,
-    ) -> ShouldRun {
-        let simulation_finished = !events.is_empty();
-        if simulation_finished || time.0 >= timer.next_output_time {
-            ShouldRun::Yes
-        } else {
+        mut timer: ResMut<Self>,
+        mut events: EventReader<StopSimulationEvent>,
+    ) {
+        let simulation_finished = events.iter().count() > 0;
+        timer.determine_due_groups(time.0, simulation_finished);
+    }
+
+    fn determine_due_groups(&mut self, time: units::Time, simulation_finished: bool) {
+        self.due = self
+            .groups
+            .iter()
+            .enumerate()
+            .filter(|(_, group)| simulation_finished || time >= group.next_output_time)
+            .map(|(index, _)| index)
+            .collect();
+    }
+
+    pub fn run_criterion(timer: Res<Self>) -> ShouldRun {
+        if timer.due.is_empty() {
             ShouldRun::No
+        } else {
+            ShouldRun::Yes
         }
     }
 
-    pub fn dataset_write_run_criterion<T: ToDataset>(
-        time: Res<SimulationTime>,
+    pub fn dataset_write_run_criterion<T: ToDataset + Named>(
         timer: Res<Self>,
-        events: EventReader<StopSimulationEvent>,
+        parameters: Res<OutputParameters>,
     ) -> ShouldRun {
-        let is_first_snapshot = timer.is_first_snapshot();
-        let should_run = Self::run_criterion(time, timer, events);
-        if should_run == ShouldRun::Yes {
-            match !T::is_static() || is_first_snapshot {
-                true => ShouldRun::Yes,
-                false => ShouldRun::No,
-            }
+        if timer.groups_to_write::<T>(&parameters).next().is_some() {
+            ShouldRun::Yes
         } else {
             ShouldRun::No
         }
     }
 
-    pub fn update_system(mut output_timer: ResMut<Self>, parameters: Res<OutputParameters>) {
-        output_timer.snapshot_num += 1;
-        output_timer.next_output_time += parameters.time_between_snapshots;
+    pub fn update_system(mut timer: ResMut<Self>) {
+        timer.advance_due_groups();
+    }
+
+    fn advance_due_groups(&mut self) {
+        for index in self.due.iter() {
+            let group = &mut self.groups[*index];
+            group.snapshot_num += 1;
+            group.next_output_time += group.time_between_snapshots;
+        }
+    }
+
+    /// The groups for which a snapshot is written in the current
+    /// timestep.
+    pub fn due_groups(&self) -> impl Iterator<Item = usize> + '_ {
+        self.due.iter().copied()
+    }
+
+    /// The groups for which a snapshot is written in the current
+    /// timestep and which contain the dataset of `T`. Static
+    /// datasets are only written to the first snapshot of each
+    /// group.
+    pub fn groups_to_write<'a, T: ToDataset + Named>(
+        &'a self,
+        parameters: &'a OutputParameters,
+    ) -> impl Iterator<Item = usize> + 'a {
+        self.due_groups().filter(move |index| {
+            parameters.snapshot_group(*index).is_desired_field::<T>()
+                && (!T::is_static() || self.is_first_snapshot(*index))
+        })
+    }
+
+    pub fn snapshot_num(&self, group: usize) -> usize {
+        self.groups[group].snapshot_num
     }
 
-    pub fn snapshot_num(&self) -> usize {
-        self.snapshot_num
+    pub fn is_first_snapshot(&self, group: usize) -> bool {
+        self.groups[group].snapshot_num == 0
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Timer;
+    use crate::components::IonizedHydrogenFraction;
+    use crate::components::Temperature;
+    use crate::io::output::parameters::OutputParameters;
+    use crate::units::Time;
 
-    pub fn is_first_snapshot(&self) -> bool {
-        self.snapshot_num == 0
+    #[test]
+    fn groups_have_independent_cadences() {
+        let parameters: OutputParameters = serde_yaml::from_str(
+            "
+time_between_snapshots: 10 s
+groups:
+  - name: ionization
+    fields: [ionized_hydrogen_fraction]
+    time_between_snapshots: 2 s
+",
+        )
+        .unwrap();
+        let mut timer = Timer::new(&parameters);
+        let mut num_written = [0, 0];
+        let mut num_temperature_written = [0, 0];
+        let mut num_fraction_written = [0, 0];
+        for step in 0..=20 {
+            timer.determine_due_groups(Time::seconds(step as f64), step == 20);
+            for index in timer.due_groups() {
+                num_written[index] += 1;
+            }
+            for index in timer.groups_to_write::<Temperature>(&parameters) {
+                num_temperature_written[index] += 1;
+            }
+            for index in timer.groups_to_write::<IonizedHydrogenFraction>(&parameters) {
+                num_fraction_written[index] += 1;
+            }
+            timer.advance_due_groups();
+        }
+        assert_eq!(num_written, [3, 11]);
+        assert_eq!(timer.snapshot_num(0), 3);
+        assert_eq!(timer.snapshot_num(1), 11);
+        assert_eq!(num_temperature_written, [3, 0]);
+        assert_eq!(num_fraction_written, [3, 11]);
     }
 }
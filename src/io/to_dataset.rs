@@ -8,22 +8,37 @@ use super::output::create_dataset_system;
 use super::output::plugin::IntoOutputSystem;
 use super::output::timer::Timer;
 use super::output::write_dataset_system;
+use crate::named::Named;
 use crate::units::Dimension;
 
 #[derive(SystemLabel)]
 struct DatasetSystemAmbiguityLabel;
 
 pub trait ToDataset: Clone + H5Type + Sync + Send + 'static {
+    /// The type in which the values are stored in the output
+    /// files. This is usually the type itself, but components which
+    /// do not need double precision in the output can be narrowed
+    /// to `f32` to halve the size of the snapshots.
+    type Output: H5Type;
     fn dimension() -> Dimension;
     fn convert_base_units(self, factor: f64) -> Self;
+    fn to_output(self) -> Self::Output;
     /// A static quantity does not change over the course of the
     /// simulation and only needs to be written to output once.
     fn is_static() -> bool {
         false
     }
+    /// Whether the value is finite, i.e. neither NaN nor
+    /// infinite. Quantities without a numerical value are always
+    /// considered finite, quantities for which infinity is a valid
+    /// value (such as the [IonizationTime](crate::components::IonizationTime)
+    /// of cells that were never ionized) only report NaN.
+    fn is_finite(&self) -> bool {
+        true
+    }
 }
 
-impl<T: ToDataset + Component> IntoOutputSystem for T {
+impl<T: ToDataset + Component + Named> IntoOutputSystem for T {
     fn write_system() -> SystemDescriptor {
         write_dataset_system::<T>
             .with_run_criteria(Timer::dataset_write_run_criterion::<T>)
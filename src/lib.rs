@@ -14,6 +14,11 @@
 #![allow(clippy::unneeded_wildcard_pattern)]
 #![allow(clippy::new_without_default)]
 
+/// Analytic reference solutions for the ionization of homogeneous
+/// media, such as the Strömgren radius.
+pub mod analytic;
+/// A fixed sweep workload for quick timings from the command line.
+pub mod benchmark;
 mod chemistry;
 mod command_line_options;
 pub mod communication;
@@ -33,7 +38,8 @@ mod particle;
 mod peano_hilbert;
 mod performance;
 pub mod prelude;
-mod quadtree;
+/// A tree for spatial queries over arbitrary positioned data.
+pub mod quadtree;
 mod simulation;
 mod simulation_box;
 mod simulation_builder;
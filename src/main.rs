@@ -4,8 +4,11 @@
 mod arepo_postprocess;
 mod emit_build_information;
 
+use arepo_postprocess::read_grid::Mass as ArepoMass;
 use arepo_postprocess::read_grid::ReadSweepGridPlugin;
+use arepo_postprocess::remap::read_directions_rng_from_last_snapshot;
 use arepo_postprocess::remap::remap_abundances_and_energies_system;
+use arepo_postprocess::sources::read_source_catalog_system;
 use arepo_postprocess::sources::read_sources_system;
 use arepo_postprocess::unit_reader::read_vec;
 use arepo_postprocess::unit_reader::ArepoUnitReader;
@@ -19,6 +22,7 @@ use derive_more::From;
 use emit_build_information::emit_build_information;
 use hdf5::H5Type;
 use mpi::traits::Equivalence;
+use subsweep::benchmark::print_benchmark;
 use subsweep::components;
 use subsweep::components::Density;
 use subsweep::components::IonizedHydrogenFraction;
@@ -37,29 +41,64 @@ use subsweep::source_systems::Sources;
 use subsweep::sweep::grid::Cell;
 use subsweep::units::Dimensionless;
 use subsweep::units::Mass;
+use subsweep::units::MassDensityVolume;
 use subsweep::units::PhotonRate;
 use subsweep::units::SourceRate;
 use subsweep::units::Temperature;
 
 fn main() {
-    let mut sim = SimulationBuilder::new();
-    let mut sim = sim
+    let mut builder = SimulationBuilder::new();
+    builder
         .write_output(true)
         .read_initial_conditions(true)
         .require_parameter_file(true)
-        .update_from_command_line_options()
-        .build();
+        .update_from_command_line_options();
+    if let Some(ref options) = builder.benchmark {
+        print_benchmark(options);
+        Simulation::finalize();
+        return;
+    }
+    let mut sim = builder.build();
     emit_build_information(&sim.get_resource::<OutputParameters>().unwrap());
     let cosmology = sim.add_parameter_type_and_get_result::<Cosmology>().clone();
     let unit_reader = Box::new(ArepoUnitReader::new(cosmology));
     let parameters = sim
         .add_parameter_type_and_get_result::<Parameters>()
         .clone();
+    if let Some(name) = parameters.initial_ionized_fraction_dataset() {
+        sim.add_plugin(
+            DatasetInputPlugin::<IonizedHydrogenFraction>::from_descriptor(
+                InputDatasetDescriptor::<IonizedHydrogenFraction> {
+                    descriptor: DatasetDescriptor::in_particle_group(name, unit_reader.clone()),
+                    ..Default::default()
+                },
+            ),
+        );
+    } else {
+        sim.add_startup_system_to_stage(
+            StartupStages::ReadInput,
+            insert_initial_ionized_fraction_system,
+        );
+    }
+    if parameters.initial_ionized_fraction_from_electron_abundance() {
+        sim.add_plugin(DatasetInputPlugin::<ElectronAbundance>::from_descriptor(
+            InputDatasetDescriptor::<ElectronAbundance> {
+                descriptor: DatasetDescriptor::in_particle_group(
+                    "ElectronAbundance",
+                    unit_reader.clone(),
+                ),
+                ..Default::default()
+            },
+        ));
+    }
     let rank = sim.get_resource::<WorldRank>().unwrap();
     match parameters.sources {
         SourceType::FromIcs(_) => {
             sim.add_startup_system(read_sources_system);
         }
+        SourceType::FromFile(_) => {
+            sim.add_startup_system(read_source_catalog_system);
+        }
         SourceType::Explicit(sources) => {
             if rank.is_main() {
                 sim.insert_resource(Sources { sources });
@@ -68,73 +107,38 @@ fn main() {
             }
         }
     }
+    if let Some(ref path) = parameters.remap_from {
+        if let Some(rng) = read_directions_rng_from_last_snapshot(path) {
+            sim.insert_resource(rng);
+        }
+    }
     match parameters.grid {
         GridParameters::Construct => sim.add_plugin(ParallelVoronoiGridConstruction),
         GridParameters::Read(_) => sim.add_plugin(ReadSweepGridPlugin),
     };
-    if parameters.initial_fraction_ionized_hydrogen.is_none() {
-        sim.add_plugin(DatasetInputPlugin::<ElectronAbundance>::from_descriptor(
-            InputDatasetDescriptor::<ElectronAbundance> {
-                descriptor: DatasetDescriptor {
-                    dataset_name: "PartType0/ElectronAbundance".into(),
-                    unit_reader: unit_reader.clone(),
-                },
-                ..Default::default()
-            },
-        ));
-    }
     sim.add_plugin(SourcePlugin)
-        .add_parameter_type::<Parameters>()
-        .add_startup_system_to_stage(
-            StartupStages::ReadInput,
-            insert_initial_ionized_fraction_system,
-        )
-        .add_startup_system_to_stage(
-            StartupStages::InsertDerivedComponents,
-            set_initial_ionized_fraction_from_electron_abundance_system,
-        )
-        .add_startup_system_to_stage(
-            StartupStages::InsertDerivedComponents,
-            insert_missing_components_system
-                .after(set_initial_ionized_fraction_from_electron_abundance_system),
-        )
+        .add_parameter_type::<Parameters>();
+    add_initial_temperature_systems(&mut sim)
         .add_startup_system_to_stage(
             StartupStages::InsertComponentsAfterGrid,
             compute_cell_mass_system,
         )
         .add_startup_system_to_stage(StartupStages::Remap, remap_abundances_and_energies_system)
-        .add_startup_system_to_stage(
-            StartupStages::InsertGrid,
-            remove_components_system::<InternalEnergy>,
-        )
-        .add_startup_system_to_stage(
-            StartupStages::InsertGrid,
-            remove_components_system::<ElectronAbundance>,
-        )
         .add_plugin(DatasetInputPlugin::<Position>::from_descriptor(
             InputDatasetDescriptor::<Position>::new(
-                DatasetDescriptor {
-                    dataset_name: "PartType0/Coordinates".into(),
-                    unit_reader: unit_reader.clone(),
-                },
+                DatasetDescriptor::in_particle_group("Coordinates", unit_reader.clone()),
                 DatasetShape::TwoDimensional(read_vec),
             ),
         ))
         .add_plugin(DatasetInputPlugin::<Density>::from_descriptor(
             InputDatasetDescriptor::<Density> {
-                descriptor: DatasetDescriptor {
-                    dataset_name: "PartType0/Density".into(),
-                    unit_reader: unit_reader.clone(),
-                },
+                descriptor: DatasetDescriptor::in_particle_group("Density", unit_reader.clone()),
                 ..Default::default()
             },
         ))
         .add_plugin(DatasetInputPlugin::<InternalEnergy>::from_descriptor(
             InputDatasetDescriptor::<InternalEnergy> {
-                descriptor: DatasetDescriptor {
-                    dataset_name: "PartType0/InternalEnergy".into(),
-                    unit_reader,
-                },
+                descriptor: DatasetDescriptor::in_particle_group("InternalEnergy", unit_reader),
                 ..Default::default()
             },
         ))
@@ -155,6 +159,33 @@ pub struct ElectronAbundance(pub crate::units::Dimensionless);
 impl_to_dataset!(InternalEnergy, crate::units::EnergyPerMass, false);
 impl_to_dataset!(ElectronAbundance, crate::units::Dimensionless, false);
 
+/// Derives the initial ionized fraction and temperature from the
+/// electron abundance and internal energy and removes the latter two
+/// afterwards. The inputs are still needed during the exchange, so
+/// they can only be removed in `InsertGrid`. Since that is a later
+/// startup stage than `InsertDerivedComponents`, the derived
+/// components are always inserted before their inputs are removed,
+/// independent of the order of the systems within a stage.
+fn add_initial_temperature_systems(sim: &mut Simulation) -> &mut Simulation {
+    sim.add_startup_system_to_stage(
+        StartupStages::InsertDerivedComponents,
+        set_initial_ionized_fraction_from_electron_abundance_system,
+    )
+    .add_startup_system_to_stage(
+        StartupStages::InsertDerivedComponents,
+        insert_missing_components_system
+            .after(set_initial_ionized_fraction_from_electron_abundance_system),
+    )
+    .add_startup_system_to_stage(
+        StartupStages::InsertGrid,
+        remove_components_system::<InternalEnergy>,
+    )
+    .add_startup_system_to_stage(
+        StartupStages::InsertGrid,
+        remove_components_system::<ElectronAbundance>,
+    )
+}
+
 fn insert_missing_components_system(
     mut commands: Commands,
     particles: Particles<(Entity, &IonizedHydrogenFraction, &InternalEnergy, &Density)>,
@@ -192,12 +223,22 @@ fn insert_initial_ionized_fraction_system(
     }
 }
 
+/// Computes the mass of the cells from their density and volume. If
+/// the grid was read from an Arepo snapshot, the mass read from the
+/// snapshot is still present at this point, so the mass, density and
+/// volume are checked for consistency.
 fn compute_cell_mass_system(
     mut commands: Commands,
-    particles: Particles<(Entity, &Density, &Cell)>,
+    particles: Particles<(Entity, &Density, &Cell, Option<&ArepoMass>)>,
 ) {
-    for (entity, dens, cell) in particles.iter() {
-        let mass = **dens * cell.volume();
+    for (entity, dens, cell, arepo_mass) in particles.iter() {
+        let mass = MassDensityVolume::from_any(
+            arepo_mass.map(|mass| **mass),
+            Some(**dens),
+            Some(cell.volume()),
+        )
+        .unwrap_or_else(|e| panic!("{e}"))
+        .mass;
         commands.entity(entity).insert(components::Mass(mass));
     }
 }
@@ -206,7 +247,7 @@ fn set_initial_ionized_fraction_from_electron_abundance_system(
     mut particles: Particles<(&ElectronAbundance, &mut IonizedHydrogenFraction)>,
     parameters: Res<Parameters>,
 ) {
-    if parameters.initial_fraction_ionized_hydrogen.is_none() {
+    if parameters.initial_ionized_fraction_from_electron_abundance() {
         // Assume this everywhere, to simplify matters. The initial ionization fractions here don't need
         // to be super accurate, since we remap them anyways.
         let xh = Dimensionless::dimensionless(0.76);
@@ -215,3 +256,133 @@ fn set_initial_ionized_fraction_from_electron_abundance_system(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy_app::StartupSchedule;
+    use bevy_ecs::prelude::*;
+    use subsweep::communication::BaseCommunicationPlugin;
+    use subsweep::components;
+    use subsweep::components::Density;
+    use subsweep::components::IonizedHydrogenFraction;
+    use subsweep::components::Position;
+    use subsweep::cosmology::Cosmology;
+    use subsweep::parameters::SimulationParameters;
+    use subsweep::prelude::*;
+    use subsweep::simulation_plugin::SimulationPlugin;
+    use subsweep::sweep::grid::Cell;
+    use subsweep::units::Dimensionless;
+    use subsweep::units::Energy;
+    use subsweep::units::Length;
+    use subsweep::units::Mass;
+    use subsweep::units::Temperature;
+    use subsweep::units::VecLength;
+    use subsweep::units::Volume3D;
+
+    use super::add_initial_temperature_systems;
+    use super::compute_cell_mass_system;
+    use super::ElectronAbundance;
+    use super::InternalEnergy;
+    use crate::arepo_postprocess::read_grid::Mass as ArepoMass;
+    use crate::arepo_postprocess::GridParameters;
+    use crate::arepo_postprocess::InitialIonizedFraction;
+    use crate::arepo_postprocess::Parameters;
+    use crate::arepo_postprocess::SourceType;
+
+    /// Runs the startup schedule exactly as it is built by the
+    /// simulation, i.e. with all stages in the order in which they
+    /// are registered, including the stages which are empty here.
+    fn run_startup_schedule(sim: &mut Simulation) {
+        let app = &mut sim.app;
+        app.schedule
+            .get_stage_mut::<Schedule>(StartupSchedule)
+            .unwrap()
+            .run(&mut app.world);
+    }
+
+    #[test]
+    fn temperature_is_derived_before_inputs_are_removed() {
+        let mut sim = Simulation::default();
+        sim.write_output(false)
+            .add_plugin(BaseCommunicationPlugin::new(1, 0))
+            .add_parameter_file_contents("{}".into())
+            .add_parameters_explicitly(SimulationParameters { final_time: None })
+            .add_parameters_explicitly(SimulationBox::cube_from_side_length(Length::meters(1.0)))
+            .add_parameters_explicitly(Cosmology::NonCosmological)
+            .insert_resource(Parameters {
+                initial_fraction_ionized_hydrogen: None,
+                initial_ionized_fraction: InitialIonizedFraction::FromElectronAbundance,
+                sources: SourceType::Explicit(vec![]),
+                grid: GridParameters::Construct,
+                remap_from: None,
+            });
+        // Register the removal of the inputs before any of the systems
+        // of the simulation plugin, so that the order of registration
+        // does not accidentally enforce the correct order.
+        add_initial_temperature_systems(&mut sim);
+        sim.add_plugin(SimulationPlugin);
+        let density = Mass::kilograms(1e-24) / Volume3D::cubic_meters(1e-6);
+        let internal_energy = Energy::joules(1e6) / Mass::kilograms(1.0);
+        let electron_abundance = Dimensionless::dimensionless(0.5);
+        for _ in 0..10 {
+            sim.world().spawn((
+                LocalParticle,
+                Position(VecLength::meters(0.5, 0.5, 0.5)),
+                Density(density),
+                InternalEnergy(internal_energy),
+                ElectronAbundance(electron_abundance),
+                IonizedHydrogenFraction(Dimensionless::zero()),
+            ));
+        }
+        run_startup_schedule(&mut sim);
+        let ionized_hydrogen_fraction = Dimensionless::dimensionless(0.76) * electron_abundance;
+        let expected = Temperature::from_internal_energy_density_hydrogen_only(
+            internal_energy * density,
+            ionized_hydrogen_fraction,
+            density,
+        );
+        let world = sim.world();
+        let mut query = world.query::<&components::Temperature>();
+        assert_eq!(query.iter(world).count(), 10);
+        for temperature in query.iter(world) {
+            assert!(((**temperature - expected) / expected).value().abs() < 1e-10);
+        }
+        assert_eq!(world.query::<&InternalEnergy>().iter(world).count(), 0);
+        assert_eq!(world.query::<&ElectronAbundance>().iter(world).count(), 0);
+    }
+
+    fn compute_cell_mass(arepo_mass: Option<Mass>) -> Mass {
+        let mut sim = Simulation::default();
+        let volume = Volume3D::cubic_meters(2.0);
+        let mut entity = sim.world().spawn((
+            LocalParticle,
+            Density(Mass::kilograms(6.0) / volume),
+            Cell {
+                neighbours: vec![],
+                size: Length::meters(1.0),
+                volume,
+            },
+        ));
+        if let Some(mass) = arepo_mass {
+            entity.insert(ArepoMass(mass));
+        }
+        let entity = entity.id();
+        sim.run_system(compute_cell_mass_system);
+        **sim.world().get::<components::Mass>(entity).unwrap()
+    }
+
+    #[test]
+    fn cell_mass_is_computed_from_density_and_volume() {
+        assert_eq!(compute_cell_mass(None), Mass::kilograms(6.0));
+        assert_eq!(
+            compute_cell_mass(Some(Mass::kilograms(6.0))),
+            Mass::kilograms(6.0)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "are inconsistent")]
+    fn inconsistent_arepo_mass_is_rejected() {
+        compute_cell_mass(Some(Mass::kilograms(4.0)));
+    }
+}
@@ -1,6 +1,11 @@
+use std::backtrace::Backtrace;
+use std::panic;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering;
+use std::sync::Once;
 
+use log::error;
+use mpi::traits::Communicator;
 use mpi::traits::CommunicatorCollectives;
 
 use crate::communication::MPI_UNIVERSE;
@@ -13,6 +18,37 @@ pub(crate) fn initialize(rank: i32, size: usize) {
     SIZE.swap(size, Ordering::SeqCst);
 }
 
+static INSTALL_PANIC_HOOK: Once = Once::new();
+
+fn panic_message(rank: usize, size: usize, panic: &str, backtrace: &Backtrace) -> String {
+    format!(
+        "Rank {} (of {}) panicked: {}\nBacktrace:\n{}",
+        rank, size, panic, backtrace
+    )
+}
+
+/// Installs a panic hook which logs the rank on which the panic
+/// happened along with a backtrace. If more than one rank is running,
+/// all ranks are aborted afterwards, since the others would otherwise
+/// wait forever for the panicked rank in the next communication.
+pub fn install_panic_hook() {
+    INSTALL_PANIC_HOOK.call_once(|| {
+        let previous_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            let rank = RANK.load(Ordering::SeqCst);
+            let size = SIZE.load(Ordering::SeqCst);
+            error!(
+                "{}",
+                panic_message(rank, size, &info.to_string(), &Backtrace::force_capture())
+            );
+            previous_hook(info);
+            if size > 1 {
+                MPI_UNIVERSE.world().abort(1);
+            }
+        }));
+    });
+}
+
 /// Debug print the expression only on MPI rank 0
 #[macro_export]
 macro_rules! maindbg {
@@ -118,3 +154,16 @@ macro_rules! barrierdbg {
         $crate::mpi_log::end_barrier();
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use std::backtrace::Backtrace;
+
+    use super::panic_message;
+
+    #[test]
+    fn panic_message_identifies_rank() {
+        let message = panic_message(3, 4, "Failed to open file", &Backtrace::disabled());
+        assert!(message.starts_with("Rank 3 (of 4) panicked: Failed to open file"));
+    }
+}
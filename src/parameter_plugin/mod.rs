@@ -1,9 +1,13 @@
 pub mod parameter_file_contents;
 
+use std::collections::hash_map::DefaultHasher;
 use std::fs;
+use std::hash::Hash;
+use std::hash::Hasher;
 use std::marker::PhantomData;
 use std::path::Path;
 
+use bevy_ecs::prelude::World;
 use derive_traits::SubsweepParameters;
 use log::debug;
 
@@ -78,10 +82,45 @@ where
     }
 }
 
+/// A hash of the parameters of type `T` (or of their absence), which
+/// is the same on every rank if the parameters are.
+pub(crate) fn parameter_hash<T: SubsweepParameters>(world: &World) -> u64 {
+    let serialized = serde_yaml::to_string(&world.get_resource::<T>()).unwrap_or_else(|e| {
+        panic!(
+            "Failed to serialize parameters {}: {e}",
+            T::unwrap_section_name()
+        )
+    });
+    let mut hasher = DefaultHasher::new();
+    serialized.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Panics if the parameter hashes gathered from all ranks (in the
+/// order of the ranks) differ from those of the main rank.
+pub(crate) fn check_parameter_hashes_agree(section: &str, hashes: &[u64]) {
+    let disagreeing: Vec<_> = hashes
+        .iter()
+        .enumerate()
+        .filter(|(_, hash)| **hash != hashes[0])
+        .map(|(rank, _)| rank.to_string())
+        .collect();
+    if !disagreeing.is_empty() {
+        panic!(
+            "Parameter section {} differs between the main rank and ranks {}",
+            section,
+            disagreeing.join(", ")
+        );
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use bevy_ecs::prelude::World;
     use derive_custom::subsweep_parameters;
 
+    use super::check_parameter_hashes_agree;
+    use super::parameter_hash;
     use super::ParameterFileContents;
     use super::ParameterPlugin;
     use crate::simulation::Simulation;
@@ -178,4 +217,28 @@ parameters1:
         assert_eq!(params.x, 2.0);
         assert_eq!(params.i, 0);
     }
+
+    fn hashes_of_ranks(values: &[i32]) -> Vec<u64> {
+        values
+            .iter()
+            .map(|i| {
+                let mut world = World::default();
+                world.insert_resource(Parameters1 { i: *i });
+                parameter_hash::<Parameters1>(&world)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn ranks_with_equal_parameters_agree() {
+        check_parameter_hashes_agree("parameters1", &hashes_of_ranks(&[1, 1, 1]));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Parameter section parameters1 differs between the main rank and ranks 2"
+    )]
+    fn ranks_with_divergent_parameters_panic() {
+        check_parameter_hashes_agree("parameters1", &hashes_of_ranks(&[1, 1, 2, 1]));
+    }
 }
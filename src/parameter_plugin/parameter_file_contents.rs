@@ -141,6 +141,10 @@ impl ParameterFileContents {
         self.sections.keys()
     }
 
+    pub fn contains_section(&self, section_name: &str) -> bool {
+        self.sections.contains_key(section_name)
+    }
+
     fn get_overrides_for_section(
         &self,
         section_name: String,
@@ -1,9 +1,12 @@
+pub use crate::communication::CommunicationParameters;
 pub use crate::cosmology::Cosmology;
 pub use crate::io::input::InputParameters;
+pub use crate::io::lightcone::LightconeParameters;
 pub use crate::io::output::parameters::Fields;
 pub use crate::io::output::parameters::HandleExistingOutput;
 pub use crate::io::output::parameters::OutputParameters;
 pub use crate::prelude::SimulationBox;
 pub use crate::simulation_box::SimulationBoxParameters;
 pub use crate::simulation_plugin::SimulationParameters;
+pub use crate::sweep::DirectionOutputParameters;
 pub use crate::sweep::SweepParameters;
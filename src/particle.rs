@@ -2,20 +2,39 @@ use bevy_ecs::archetype::Archetypes;
 use bevy_ecs::component::Components;
 use bevy_ecs::prelude::Bundle;
 use bevy_ecs::prelude::Component;
+use bevy_ecs::prelude::Entity;
 use bevy_ecs::prelude::Query;
+use bevy_ecs::prelude::ResMut;
+use bevy_ecs::prelude::Resource;
 use bevy_ecs::prelude::With;
 use log::debug;
 use mpi::traits::Equivalence;
+use serde::Deserialize;
+use serde::Serialize;
 
+use crate::communication::MpiWorld;
 use crate::communication::Rank;
 use crate::components::Position;
+use crate::hash_map::BiMap;
 use crate::named::Named;
 use crate::prelude::Simulation;
 use crate::prelude::StartupStages;
 use crate::simulation::SubsweepPlugin;
 
 #[derive(
-    Component, Clone, Debug, PartialEq, Eq, Hash, Equivalence, Copy, Named, PartialOrd, Ord,
+    Component,
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    Equivalence,
+    Copy,
+    Named,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
 )]
 #[name = "id"]
 pub struct ParticleId {
@@ -30,6 +49,95 @@ impl ParticleId {
             rank: 0,
         }
     }
+
+    /// A single integer identifying the particle among the particles
+    /// of all ranks, for cross-referencing with external catalogs.
+    /// The global indices are contiguous, starting at zero. Since the
+    /// ids are assigned in the order of the domain keys and the ranks
+    /// hold consecutive ranges of keys, the global index of a
+    /// particle is its position in the domain key order, which is
+    /// the same in every run with the same input.
+    pub fn global_index(&self, offsets: &GlobalIndexOffsets) -> u64 {
+        offsets.0[self.rank as usize] + self.index as u64
+    }
+
+    /// The inverse of [ParticleId::global_index].
+    pub fn from_global_index(global_index: u64, offsets: &GlobalIndexOffsets) -> Self {
+        debug_assert!(global_index < *offsets.0.last().unwrap());
+        let rank = offsets.0.partition_point(|offset| *offset <= global_index) - 1;
+        Self {
+            index: (global_index - offsets.0[rank]) as u32,
+            rank: rank as Rank,
+        }
+    }
+}
+
+/// The global index of the first particle of each rank (the exclusive
+/// prefix sum of the number of particles per rank), followed by the
+/// total number of particles. Required to convert between
+/// [ParticleId]s and global indices. Inserted as a resource once the
+/// particle ids are assigned.
+#[derive(Resource, Clone, Debug, PartialEq, Eq)]
+pub struct GlobalIndexOffsets(Vec<u64>);
+
+impl GlobalIndexOffsets {
+    pub fn from_particles_per_rank(particles_per_rank: &[usize]) -> Self {
+        let mut offsets = vec![0];
+        let mut total = 0;
+        for num in particles_per_rank {
+            total += *num as u64;
+            offsets.push(total);
+        }
+        Self(offsets)
+    }
+
+    /// Collective: gathers the number of local particles of every rank.
+    pub fn communicate(num_local_particles: usize) -> Self {
+        let particles_per_rank = MpiWorld::<usize>::new().all_gather(&num_local_particles);
+        Self::from_particles_per_rank(&particles_per_rank)
+    }
+}
+
+/// A bidirectional map between the [ParticleId] and the [Entity] of
+/// all particles on this rank. Built once the particle ids are
+/// assigned and kept up to date whenever particles are exchanged
+/// between ranks.
+#[derive(Resource, Default, Debug)]
+pub struct ParticleIdMap(BiMap<ParticleId, Entity>);
+
+impl ParticleIdMap {
+    pub fn entity_of(&self, id: ParticleId) -> Option<Entity> {
+        self.0.get_by_left(&id).copied()
+    }
+
+    pub fn id_of(&self, entity: Entity) -> Option<ParticleId> {
+        self.0.get_by_right(&entity).copied()
+    }
+
+    pub fn insert(&mut self, id: ParticleId, entity: Entity) {
+        self.0.insert(id, entity);
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl FromIterator<(ParticleId, Entity)> for ParticleIdMap {
+    fn from_iter<I: IntoIterator<Item = (ParticleId, Entity)>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+pub(crate) fn update_particle_id_map_system(
+    query: Query<(&ParticleId, Entity)>,
+    mut map: ResMut<ParticleIdMap>,
+) {
+    *map = query.iter().map(|(id, entity)| (*id, entity)).collect();
 }
 
 #[derive(Component)]
@@ -95,7 +203,11 @@ mod tests {
     use bevy_ecs::prelude::With;
     use bevy_ecs::prelude::World;
 
+    use super::update_particle_id_map_system;
+    use super::GlobalIndexOffsets;
+    use super::ParticleIdMap;
     use crate::prelude::LocalParticle;
+    use crate::prelude::ParticleId;
     use crate::prelude::Particles;
     use crate::test_utils::run_system_on_world;
 
@@ -134,4 +246,65 @@ mod tests {
         }
         run_system_on_world(&mut world, system);
     }
+
+    #[test]
+    fn particle_id_map_round_trips() {
+        let mut world = World::default();
+        let entities: Vec<_> = (0..10)
+            .map(|i| world.spawn((ParticleId::test(i), LocalParticle)).id())
+            .collect();
+        world.insert_resource(ParticleIdMap::default());
+        run_system_on_world(&mut world, update_particle_id_map_system);
+        let map = world.resource::<ParticleIdMap>();
+        assert_eq!(map.len(), entities.len());
+        for (i, entity) in entities.iter().enumerate() {
+            let id = ParticleId::test(i);
+            assert_eq!(map.entity_of(id), Some(*entity));
+            assert_eq!(map.id_of(*entity), Some(id));
+        }
+        assert_eq!(map.entity_of(ParticleId::test(10)), None);
+    }
+
+    fn decomposition(particles_per_rank: &[usize]) -> Vec<ParticleId> {
+        particles_per_rank
+            .iter()
+            .enumerate()
+            .flat_map(|(rank, num)| {
+                (0..*num).map(move |index| ParticleId {
+                    index: index as u32,
+                    rank: rank as i32,
+                })
+            })
+            .collect()
+    }
+
+    fn check_global_indices(particles_per_rank: &[usize]) {
+        let offsets = GlobalIndexOffsets::from_particles_per_rank(particles_per_rank);
+        let ids = decomposition(particles_per_rank);
+        let indices: Vec<_> = ids.iter().map(|id| id.global_index(&offsets)).collect();
+        let num_particles: usize = particles_per_rank.iter().sum();
+        assert_eq!(indices, (0..num_particles as u64).collect::<Vec<_>>());
+        for id in ids {
+            assert_eq!(
+                ParticleId::from_global_index(id.global_index(&offsets), &offsets),
+                id
+            );
+        }
+    }
+
+    #[test]
+    fn global_indices_are_unique_and_contiguous() {
+        check_global_indices(&[25; 4]);
+    }
+
+    #[test]
+    fn global_indices_are_contiguous_for_uneven_decompositions() {
+        check_global_indices(&[10, 3, 0, 7]);
+    }
+
+    #[test]
+    fn global_index_offsets_are_communicated() {
+        let offsets = GlobalIndexOffsets::communicate(5);
+        assert_eq!(offsets, GlobalIndexOffsets::from_particles_per_rank(&[5]));
+    }
 }
@@ -1,15 +1,22 @@
+use std::collections::BTreeMap;
 use std::fs;
 use std::time::Instant;
 
+use bevy_ecs::prelude::EventReader;
+use bevy_ecs::prelude::NonSend;
 use bevy_ecs::prelude::NonSendMut;
 use bevy_ecs::prelude::Res;
 use bevy_ecs::prelude::Resource;
 use linked_hash_map::LinkedHashMap;
+use serde::Deserialize;
 use serde::Serialize;
 use serde_yaml::Value;
 
+use crate::communication::communicator::Communicator;
+use crate::communication::WorldRank;
 use crate::hash_map::HashMap;
 use crate::io::output::parameters::OutputParameters;
+use crate::simulation_plugin::StopSimulationEvent;
 use crate::units::Time;
 
 type Category = String;
@@ -76,6 +83,30 @@ impl Statistics {
     }
 }
 
+/// The timings of a single timer on a single rank.
+#[derive(Debug, Serialize, Deserialize)]
+struct TimerSummary {
+    /// The total wall time in seconds.
+    total: f64,
+    num_calls: usize,
+}
+
+type RankSummaries = BTreeMap<Category, TimerSummary>;
+
+/// The timings of a single timer across all ranks. All times are
+/// wall times in seconds. The minimum and maximum are taken over
+/// the ranks which ran the timer at least once.
+#[derive(Debug, Serialize)]
+struct TimerReport {
+    total: f64,
+    num_calls: usize,
+    min: f64,
+    max: f64,
+    /// The total time on each rank, or None if the timer never ran
+    /// on that rank.
+    ranks: Vec<Option<f64>>,
+}
+
 #[derive(Debug)]
 struct Timer(Instant);
 
@@ -134,6 +165,22 @@ impl Performance {
         };
     }
 
+    fn summaries(&self) -> RankSummaries {
+        self.results
+            .iter()
+            .filter_map(|(name, result)| match result {
+                Result::RunTimes(run_times) => Some((
+                    name.clone(),
+                    TimerSummary {
+                        total: result.total().in_seconds(),
+                        num_calls: run_times.len(),
+                    },
+                )),
+                Result::Number(_) => None,
+            })
+            .collect()
+    }
+
     pub(crate) fn time<N: Into<String> + Clone>(&mut self, name: N) -> TimerGuard<'_, N> {
         self.start(name.clone());
         TimerGuard { data: self, name }
@@ -184,3 +231,107 @@ pub fn write_performance_data_system(
         .unwrap_or_else(|e| panic!("Failed to write performance data to file. {}", e));
     }
 }
+
+fn get_performance_report(summaries: &[RankSummaries]) -> BTreeMap<Category, TimerReport> {
+    let mut report: BTreeMap<Category, TimerReport> = BTreeMap::new();
+    for (rank, rank_summaries) in summaries.iter().enumerate() {
+        for (name, summary) in rank_summaries.iter() {
+            let entry = report.entry(name.clone()).or_insert_with(|| TimerReport {
+                total: 0.0,
+                num_calls: 0,
+                min: f64::INFINITY,
+                max: 0.0,
+                ranks: vec![None; summaries.len()],
+            });
+            entry.total += summary.total;
+            entry.num_calls += summary.num_calls;
+            entry.min = entry.min.min(summary.total);
+            entry.max = entry.max.max(summary.total);
+            entry.ranks[rank] = Some(summary.total);
+        }
+    }
+    report
+}
+
+/// Gathers the timer summaries of all ranks. Since the names of
+/// the timers can differ between ranks, the summaries are
+/// exchanged in serialized form.
+fn gather_summaries(summaries: &RankSummaries) -> Vec<RankSummaries> {
+    let data = serde_json::to_vec(summaries).unwrap();
+    let lengths = Communicator::<usize>::new().all_gather(&data.len());
+    let data = Communicator::<u8>::new().all_gather_varcount(&data);
+    let mut start = 0;
+    lengths
+        .into_iter()
+        .map(|length| {
+            let summaries = serde_json::from_slice(&data[start..start + length])
+                .expect("Failed to deserialize timer summaries");
+            start += length;
+            summaries
+        })
+        .collect()
+}
+
+pub fn write_performance_report_system(
+    timers: NonSend<Performance>,
+    parameters: Option<Res<OutputParameters>>,
+    rank: Res<WorldRank>,
+    mut stop_sim: EventReader<StopSimulationEvent>,
+) {
+    if stop_sim.iter().count() == 0 {
+        return;
+    }
+    if let Some(parameters) = parameters {
+        let summaries = gather_summaries(&timers.summaries());
+        if rank.is_main() {
+            let report = get_performance_report(&summaries);
+            fs::write(
+                parameters
+                    .output_dir
+                    .join(&parameters.performance_report_filename),
+                serde_json::to_string_pretty(&report).unwrap(),
+            )
+            .unwrap_or_else(|e| panic!("Failed to write performance report to file. {}", e));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::get_performance_report;
+    use super::Performance;
+
+    #[test]
+    fn performance_report_contains_timers() {
+        let num_ranks = 2;
+        let summaries: Vec<_> = (0..num_ranks)
+            .map(|_| {
+                let mut perf = Performance::default();
+                for _ in 0..3 {
+                    let _guard = perf.time("chemistry");
+                }
+                perf.start("update levels");
+                perf.stop("update levels");
+                perf.record_number("num_ranks", num_ranks);
+                perf.summaries()
+            })
+            .collect();
+        let report = get_performance_report(&summaries);
+        let json: serde_json::Value =
+            serde_json::from_str(&serde_json::to_string(&report).unwrap()).unwrap();
+        let timers = json.as_object().unwrap();
+        assert_eq!(
+            timers.keys().collect::<Vec<_>>(),
+            ["chemistry", "update levels"]
+        );
+        assert_eq!(timers["chemistry"]["num_calls"], 3 * num_ranks);
+        assert_eq!(timers["update levels"]["num_calls"], num_ranks);
+        for timer in timers.values() {
+            let min = timer["min"].as_f64().unwrap();
+            let max = timer["max"].as_f64().unwrap();
+            assert!(timer["total"].as_f64().unwrap() >= 0.0);
+            assert!(0.0 <= min && min <= max);
+            assert_eq!(timer["ranks"].as_array().unwrap().len(), num_ranks);
+        }
+    }
+}
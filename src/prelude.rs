@@ -9,6 +9,7 @@ pub use crate::named::*;
 pub use crate::particle::HaloParticle;
 pub use crate::particle::LocalParticle;
 pub use crate::particle::ParticleId;
+pub use crate::particle::ParticleIdMap;
 pub use crate::particle::Particles;
 pub use crate::quadtree::QuadTree;
 pub use crate::simulation::Simulation;
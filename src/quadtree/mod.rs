@@ -15,14 +15,29 @@ pub const NUM_DIMENSIONS: usize = 3;
 
 pub const TWO_TO_NUM_DIMENSIONS: usize = 2i32.pow(NUM_DIMENSIONS as u32) as usize;
 
+/// The data stored in the leaves of a tree. Anything with a
+/// position can be stored.
 pub trait LeafDataType: Clone {
     fn pos(&self) -> &VecLength;
 }
 
+/// Data accumulated in every node of the tree from all the leaves
+/// below it, such as the total work in the domain decomposition.
+/// Use `()` if no node data is needed.
 pub trait NodeDataType<L>: Default {
     fn update_with(&mut self, leaf: &L);
 }
 
+impl LeafDataType for VecLength {
+    fn pos(&self) -> &VecLength {
+        self
+    }
+}
+
+impl<T> NodeDataType<T> for () {
+    fn update_with(&mut self, _: &T) {}
+}
+
 type Tree<N, L> = Box<[QuadTree<N, L>; TWO_TO_NUM_DIMENSIONS]>;
 type Leaf<L> = Vec<L>;
 
@@ -51,6 +66,43 @@ impl<N, L> Node<N, L> {
     }
 }
 
+/// A spatial index over leaves of type `L` with node data of type
+/// `N`. Each node covers an extent which is split into
+/// `TWO_TO_NUM_DIMENSIONS` equal children once it contains more than
+/// the configured number of leaves.
+/// ```
+/// # use subsweep::prelude::Extent;
+/// # use subsweep::prelude::MVec;
+/// # use subsweep::quadtree::LeafDataType;
+/// # use subsweep::quadtree::QuadTree;
+/// # use subsweep::quadtree::QuadTreeConfig;
+/// # use subsweep::units::Length;
+/// # use subsweep::units::VecLength;
+/// #[derive(Clone)]
+/// struct Star {
+///     id: usize,
+///     pos: VecLength,
+/// }
+///
+/// impl LeafDataType for Star {
+///     fn pos(&self) -> &VecLength {
+///         &self.pos
+///     }
+/// }
+///
+/// let diagonal = |x: f64| VecLength::from_vector_and_scale(MVec::ONE, Length::meters(x));
+/// let stars: Vec<_> = (0..100)
+///     .map(|id| Star { id, pos: diagonal(id as f64) })
+///     .collect();
+/// let extent = Extent::from_positions(stars.iter().map(|star| &star.pos)).unwrap();
+/// let tree: QuadTree<(), Star> = QuadTree::new(&QuadTreeConfig::default(), stars, &extent);
+/// let mut ids: Vec<_> = tree
+///     .iter_particles_in_radius_nonperiodic(diagonal(50.0), Length::meters(2.0))
+///     .map(|star| star.id)
+///     .collect();
+/// ids.sort();
+/// assert_eq!(ids, [49, 50, 51]);
+/// ```
 #[derive(Debug, Resource)]
 pub struct QuadTree<N, L> {
     pub node: Node<N, L>,
@@ -59,6 +111,8 @@ pub struct QuadTree<N, L> {
 }
 
 impl<N: NodeDataType<L>, L: LeafDataType> QuadTree<N, L> {
+    /// Builds a tree covering `extent` containing all `particles`.
+    /// The extent needs to contain the positions of all particles.
     pub fn new(config: &QuadTreeConfig, particles: Vec<L>, extent: &Extent) -> Self {
         let mut tree = Self::make_empty_leaf_from_extent(extent.clone());
         for particle in particles.iter() {
@@ -105,6 +159,7 @@ impl<N: NodeDataType<L>, L: LeafDataType> QuadTree<N, L> {
         }
     }
 
+    /// Calls `closure` on the extent and contents of every leaf.
     pub fn depth_first_map_leaf<'a>(&'a self, closure: &mut impl FnMut(&'a Extent, &'a [L])) {
         match self.node {
             Node::Tree(ref node) => {
@@ -125,16 +180,6 @@ pub mod tests {
     use crate::prelude::MVec;
     use crate::units::Length;
 
-    impl LeafDataType for VecLength {
-        fn pos(&self) -> &VecLength {
-            self
-        }
-    }
-
-    impl<T> NodeDataType<T> for () {
-        fn update_with(&mut self, _: &T) {}
-    }
-
     #[test]
     fn no_infinite_recursion_in_tree_construction_with_close_particles() {
         let positions = [
@@ -43,7 +43,13 @@ fn within_radius_periodic(
     box_.periodic_distance(pos1, pos2) < radius
 }
 
+fn within_radius(pos1: &VecLength, pos2: &VecLength, radius: Length) -> bool {
+    pos1.distance(pos2) < radius
+}
+
 impl<N, L: LeafDataType> QuadTree<N, L> {
+    /// Iterates over all leaves within `radius` of `pos`, taking
+    /// into account the periodic wrapping of the simulation box.
     pub fn iter_particles_in_radius<'a>(
         &'a self,
         box_size: &'a SimulationBox,
@@ -53,9 +59,24 @@ impl<N, L: LeafDataType> QuadTree<N, L> {
         let search = PeriodicRadiusSearch::new(box_size, pos, radius);
         TreeIter::new(self, search)
     }
+
+    /// Iterates over all leaves within `radius` of `pos`.
+    pub fn iter_particles_in_radius_nonperiodic(
+        &self,
+        pos: VecLength,
+        radius: Length,
+    ) -> impl Iterator<Item = &L> + '_ {
+        TreeIter::new(self, RadiusSearch { pos, radius })
+    }
+
+    /// Iterates over all leaves for which the criterion holds.
+    pub fn iter_with_criterion<C: SearchCriterion<N, L>>(&self, criterion: C) -> TreeIter<N, L, C> {
+        TreeIter::new(self, criterion)
+    }
 }
 
 impl<N, L> QuadTree<N, L> {
+    /// Iterates over all leaves in the tree.
     pub fn iter(&self) -> TreeIter<N, L, EntireTree> {
         TreeIter::new(self, EntireTree)
     }
@@ -169,7 +190,10 @@ impl<'a, N, L, C: SearchCriterion<N, L>> Iterator for TreeIter<'a, N, L, C> {
     }
 }
 
+/// Decides which parts of the tree are visited during a search.
 pub trait SearchCriterion<N, L> {
+    /// Whether the leaves in this node (or its children) can be
+    /// relevant. Returning `false` prunes the entire subtree.
     fn should_visit_node(&self, tree: &QuadTree<N, L>) -> bool;
     fn should_include_leaf(&self, l: &L) -> bool;
 }
@@ -186,6 +210,25 @@ impl<N, L> SearchCriterion<N, L> for EntireTree {
     }
 }
 
+#[derive(Debug)]
+struct RadiusSearch {
+    pos: VecLength,
+    radius: Length,
+}
+
+impl<N, L: LeafDataType> SearchCriterion<N, L> for RadiusSearch {
+    fn should_visit_node(&self, tree: &QuadTree<N, L>) -> bool {
+        relative_bounding_box_overlap(
+            tree.extent.center() - self.pos,
+            tree.extent.side_lengths() + VecLength::from_vector_and_scale(MVec::ONE, self.radius),
+        )
+    }
+
+    fn should_include_leaf(&self, particle: &L) -> bool {
+        within_radius(&self.pos, particle.pos(), self.radius)
+    }
+}
+
 #[derive(Debug)]
 struct PeriodicRadiusSearch<'a> {
     box_size: &'a SimulationBox,
@@ -345,6 +388,11 @@ mod tests {
                 .map(|particle| particle.id)
                 .collect();
             assert_eq!(tree_entities, direct_entities);
+            let nonperiodic_entities: HashSet<_> = tree
+                .iter_particles_in_radius_nonperiodic(particle.pos, radius)
+                .map(|particle| particle.id)
+                .collect();
+            assert_eq!(nonperiodic_entities, direct_entities);
         }
     }
 }
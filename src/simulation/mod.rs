@@ -1,3 +1,4 @@
+mod schedule_dump;
 mod subsweep_plugin;
 
 use bevy_app::prelude::App;
@@ -22,6 +23,7 @@ use mpi::traits::Equivalence;
 use mpi::traits::MatchesRaw;
 pub use subsweep_plugin::SubsweepPlugin;
 
+use crate::communication::communicator::Communicator;
 use crate::communication::WorldRank;
 use crate::domain::ExchangeDataPlugin;
 use crate::hash_map::HashMap;
@@ -34,14 +36,19 @@ use crate::io::DatasetDescriptor;
 use crate::io::DatasetShape;
 use crate::io::InputDatasetDescriptor;
 use crate::named::Named;
+use crate::parameter_plugin::check_parameter_hashes_agree;
+use crate::parameter_plugin::parameter_hash;
 use crate::parameter_plugin::ParameterFileContents;
 use crate::parameter_plugin::ParameterPlugin;
 use crate::prelude::StartupStages;
+use crate::simulation_plugin::Stages;
 
 pub struct Simulation {
     pub app: App,
     labels: HashSet<&'static str>,
     parameter_sections: HashSet<String>,
+    range_validators: Vec<fn(&World) -> Vec<String>>,
+    parameter_hashes: Vec<(&'static str, fn(&World) -> u64)>,
     ordering_labels: HashMap<&'static str, Vec<SystemLabelId>>,
     pub read_initial_conditions: bool,
     pub write_output: bool,
@@ -55,6 +62,8 @@ impl Default for Simulation {
             app,
             labels: HashSet::default(),
             parameter_sections: HashSet::default(),
+            range_validators: vec![],
+            parameter_hashes: vec![],
             ordering_labels: HashMap::default(),
             read_initial_conditions: false,
             write_output: false,
@@ -122,6 +131,15 @@ impl Simulation {
         self
     }
 
+    /// Adds the plugin unless a plugin with the same name has
+    /// already been added, in which case this does nothing.
+    pub fn add_plugin_if_absent<T: SubsweepPlugin>(&mut self, plugin: T) -> &mut Self {
+        if !self.labels.contains(T::name()) {
+            self.add_plugin(plugin);
+        }
+        self
+    }
+
     pub fn maybe_add_plugin<T: Sync + Send + 'static + SubsweepPlugin>(
         &mut self,
         plugin: Option<T>,
@@ -279,6 +297,25 @@ impl Simulation {
         self
     }
 
+    /// Runs the callback every `every` calls to `update`, at the end
+    /// of the timestep. This is meant for ad-hoc diagnostics which
+    /// need access to the world without having to care about the
+    /// stage layout.
+    pub fn add_periodic_callback(
+        &mut self,
+        every: usize,
+        mut f: impl FnMut(&mut World) + Send + Sync + 'static,
+    ) -> &mut Self {
+        assert!(every > 0, "Callback interval needs to be positive");
+        let mut num_updates = 0;
+        self.add_system_to_stage(Stages::Final, move |world: &mut World| {
+            num_updates += 1;
+            if num_updates % every == 0 {
+                f(world);
+            }
+        })
+    }
+
     pub fn add_event<T>(&mut self) -> &mut Self
     where
         T: Event,
@@ -287,6 +324,9 @@ impl Simulation {
         self
     }
 
+    /// Runs the simulation and finalizes MPI. A panic on any rank is
+    /// logged along with the rank and aborts all ranks, see
+    /// [install_panic_hook](crate::mpi_log::install_panic_hook).
     pub fn run(&mut self) {
         self.run_without_finalize();
         Simulation::finalize();
@@ -301,6 +341,10 @@ impl Simulation {
     /// simulations are run.  Make sure to call finalize() explicitly
     /// after the last run
     pub fn run_without_finalize(&mut self) {
+        crate::mpi_log::install_panic_hook();
+        if self.has_world_rank() {
+            self.check_parameters_agree_between_ranks();
+        }
         // Since this is called from tests which don't have a BaseCommunication plugin, make sure we only unwrap
         // world rank if it exists and default to validating otherwise.
         if !self.has_world_rank()
@@ -371,8 +415,14 @@ impl Simulation {
     where
         T: SubsweepParameters,
     {
-        self.parameter_sections
-            .insert(T::unwrap_section_name().into());
+        if self
+            .parameter_sections
+            .insert(T::unwrap_section_name().into())
+        {
+            self.range_validators.push(range_warnings::<T>);
+            self.parameter_hashes
+                .push((T::unwrap_section_name(), parameter_hash::<T>));
+        }
         self.add_plugin(ParameterPlugin::<T>::default());
         self
     }
@@ -405,6 +455,24 @@ impl Simulation {
         self.unwrap_resource::<T>()
     }
 
+    /// Adds the parameter type only if the parameters are already
+    /// present or if the parameter file contains their section and
+    /// returns them. Returns `None` otherwise, so that optional
+    /// subsystems can be enabled by the presence of their section.
+    pub fn add_optional_parameter_type_and_get_result<T>(&mut self) -> Option<&T>
+    where
+        T: SubsweepParameters,
+    {
+        let section_present = self
+            .get_resource::<ParameterFileContents>()
+            .map(|contents| contents.contains_section(T::unwrap_section_name()))
+            .unwrap_or(false);
+        if section_present || self.contains_resource::<T>() {
+            self.try_add_parameter_type::<T>();
+        }
+        self.try_get_parameters::<T>()
+    }
+
     pub fn add_parameters_explicitly<T: SubsweepParameters>(&mut self, parameters: T) -> &mut Self {
         self.insert_resource(parameters);
         self
@@ -414,6 +482,12 @@ impl Simulation {
         self.get_resource::<T>().unwrap()
     }
 
+    /// Returns the parameters of type `T` or `None` if the parameter
+    /// type has not been added.
+    pub fn try_get_parameters<T: SubsweepParameters>(&self) -> Option<&T> {
+        self.get_resource::<T>()
+    }
+
     pub fn add_component<T>(
         &mut self,
         input: ComponentInput<T>,
@@ -470,7 +544,30 @@ impl Simulation {
         self
     }
 
+    fn parameter_range_warnings(&self) -> Vec<String> {
+        self.range_validators
+            .iter()
+            .flat_map(|validator| validator(&self.app.world))
+            .collect()
+    }
+
+    /// Ranks can end up with different parameters, for example
+    /// because of per-rank parameter files or command line
+    /// overrides. This leads to subtle bugs, so compare the
+    /// parameters of every section between all ranks and panic if
+    /// they differ.
+    fn check_parameters_agree_between_ranks(&self) {
+        let mut comm = Communicator::<u64>::new();
+        for (section, hash) in self.parameter_hashes.iter() {
+            let hashes = comm.all_gather(&hash(&self.app.world));
+            check_parameter_hashes_agree(section, &hashes);
+        }
+    }
+
     fn validate(&self) {
+        for warning in self.parameter_range_warnings() {
+            warn!("{}", warning);
+        }
         let contents = self.unwrap_resource::<ParameterFileContents>();
         let mut unused = vec![];
         for param in contents.get_section_names() {
@@ -498,11 +595,35 @@ impl Simulation {
     }
 }
 
+fn range_warnings<T: SubsweepParameters>(world: &World) -> Vec<String> {
+    world
+        .get_resource::<T>()
+        .map(|parameters| {
+            parameters
+                .validate_ranges()
+                .into_iter()
+                .map(|warning| {
+                    format!(
+                        "Parameter section {}: {}",
+                        T::unwrap_section_name(),
+                        warning
+                    )
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
 #[cfg(test)]
 mod tests {
+    use bevy_ecs::prelude::Resource;
+    use derive_custom::subsweep_parameters;
+
     use crate::named::Named;
+    use crate::performance::Performance;
     use crate::simulation::Simulation;
     use crate::simulation::SubsweepPlugin;
+    use crate::sweep::SweepParameters;
 
     #[test]
     #[should_panic]
@@ -516,6 +637,126 @@ mod tests {
         sim.add_plugin(MyPlugin);
     }
 
+    #[test]
+    fn add_plugin_if_absent_twice() {
+        #[derive(Resource, Default)]
+        struct NumBuilds(usize);
+
+        #[derive(Named)]
+        #[name = "my_plugin"]
+        struct MyPlugin;
+        impl SubsweepPlugin for MyPlugin {
+            fn build_everywhere(&self, sim: &mut Simulation) {
+                sim.get_resource_or_insert_with(NumBuilds::default).0 += 1;
+            }
+        }
+        let mut sim = Simulation::default();
+        sim.add_plugin_if_absent(MyPlugin);
+        sim.add_plugin_if_absent(MyPlugin);
+        assert_eq!(sim.unwrap_resource::<NumBuilds>().0, 1);
+    }
+
+    #[test]
+    fn periodic_callback_fires_every_n_updates() {
+        #[derive(Resource, Default)]
+        struct NumCalls(usize);
+
+        let mut sim = Simulation::default();
+        sim.insert_resource(Performance::default())
+            .insert_resource(NumCalls::default())
+            .add_periodic_callback(3, |world| world.resource_mut::<NumCalls>().0 += 1);
+        for _ in 0..10 {
+            sim.update();
+        }
+        assert_eq!(sim.unwrap_resource::<NumCalls>().0, 3);
+    }
+
+    #[subsweep_parameters("optional")]
+    struct OptionalParameters {
+        #[serde(default)]
+        i: i32,
+    }
+
+    #[test]
+    fn try_get_parameters() {
+        let mut sim = Simulation::default();
+        sim.add_parameter_file_contents("{}".into());
+        assert!(sim.try_get_parameters::<OptionalParameters>().is_none());
+        assert!(sim
+            .add_optional_parameter_type_and_get_result::<OptionalParameters>()
+            .is_none());
+        sim.add_parameter_type::<OptionalParameters>();
+        assert_eq!(sim.try_get_parameters::<OptionalParameters>().unwrap().i, 0);
+    }
+
+    #[test]
+    fn add_optional_parameter_type_if_section_is_present() {
+        let mut sim = Simulation::default();
+        sim.add_parameter_file_contents("optional:\n  i: 3".into());
+        assert_eq!(
+            sim.add_optional_parameter_type_and_get_result::<OptionalParameters>()
+                .unwrap()
+                .i,
+            3
+        );
+        assert_eq!(sim.try_get_parameters::<OptionalParameters>().unwrap().i, 3);
+    }
+
+    fn sweep_parameter_range_warnings(extra_parameters: &str) -> Vec<String> {
+        let mut sim = Simulation::default();
+        let contents = format!(
+            "
+sweep:
+  directions: 4
+  num_timestep_levels: 1
+  periodic: false
+  max_timestep: 1 Myr
+{}
+{}",
+            extra_parameters,
+            if cfg!(feature = "2d") {
+                "  slab_thickness: 1 m"
+            } else {
+                ""
+            }
+        );
+        sim.add_parameter_file_contents(contents);
+        sim.add_parameter_type::<SweepParameters>();
+        sim.parameter_range_warnings()
+    }
+
+    #[test]
+    fn extreme_parameter_values_produce_warnings() {
+        let warnings = sweep_parameter_range_warnings("  timestep_safety_factor: 50.0");
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("timestep_safety_factor"));
+    }
+
+    #[test]
+    fn hydrogen_mass_fraction_outside_unit_interval_produces_warning() {
+        for value in ["0.76", "1.0"] {
+            let warnings =
+                sweep_parameter_range_warnings(&format!("  hydrogen_mass_fraction: {value}"));
+            assert!(warnings.is_empty());
+        }
+        for value in ["0.0", "-0.5", "1.5"] {
+            let warnings =
+                sweep_parameter_range_warnings(&format!("  hydrogen_mass_fraction: {value}"));
+            assert_eq!(warnings.len(), 1);
+            assert!(warnings[0].contains("hydrogen_mass_fraction"));
+        }
+    }
+
+    #[test]
+    fn non_positive_max_optical_depth_produces_warning() {
+        assert!(sweep_parameter_range_warnings("  max_optical_depth: 20.0").is_empty());
+        for value in ["0.0", "-1.0"] {
+            let warnings = sweep_parameter_range_warnings(&format!("  max_optical_depth: {value}"));
+            assert_eq!(warnings.len(), 1);
+            assert!(warnings[0].contains("max_optical_depth"));
+        }
+    }
+
     #[test]
     #[should_panic(expected = "Unused parameter sections")]
     fn panic_on_unused_parameter_section() {
@@ -0,0 +1,144 @@
+use std::fmt::Write;
+
+use bevy_ecs::schedule::GraphNode;
+use bevy_ecs::schedule::Schedule;
+use bevy_ecs::schedule::SystemContainer;
+use bevy_ecs::schedule::SystemLabelId;
+use bevy_ecs::schedule::SystemStage;
+use log::info;
+
+use super::Simulation;
+
+const INDENT: &str = "    ";
+
+impl Simulation {
+    /// Logs the systems of every stage (including the startup
+    /// stages) in an execution order that satisfies their ordering
+    /// constraints, along with their labels and constraints. Useful
+    /// for debugging the ordering of systems added by different
+    /// plugins. Only logs on the main rank, since the schedule is
+    /// the same on every rank.
+    pub fn dump_schedule(&self) {
+        if self.on_main_rank() {
+            info!("Schedule:\n{}", self.format_schedule());
+        }
+    }
+
+    pub fn format_schedule(&self) -> String {
+        let mut out = String::new();
+        format_schedule(&mut out, &self.app.schedule, 0);
+        out
+    }
+}
+
+fn format_schedule(out: &mut String, schedule: &Schedule, depth: usize) {
+    for (label, stage) in schedule.iter_stages() {
+        writeln!(out, "{}{:?}:", INDENT.repeat(depth), label).unwrap();
+        if let Some(schedule) = stage.downcast_ref::<Schedule>() {
+            format_schedule(out, schedule, depth + 1);
+        } else if let Some(stage) = stage.downcast_ref::<SystemStage>() {
+            format_stage(out, stage, depth + 1);
+        }
+    }
+}
+
+/// The systems are listed in the same groups in which the stage
+/// executes them. The order is only resolved by bevy once the stage
+/// runs for the first time, so it is computed here from the
+/// constraints instead, which gives the same result if the
+/// constraints determine the order uniquely.
+fn format_stage(out: &mut String, stage: &SystemStage, depth: usize) {
+    let groups = [
+        stage.exclusive_at_start_systems(),
+        stage.parallel_systems(),
+        stage.exclusive_before_commands_systems(),
+        stage.exclusive_at_end_systems(),
+    ];
+    for systems in groups {
+        for index in resolve_order(systems) {
+            let system = &systems[index];
+            write!(out, "{}{}", INDENT.repeat(depth), system.name()).unwrap();
+            write_labels(out, "labels", system.labels());
+            write_labels(out, "after", system.after());
+            write_labels(out, "before", system.before());
+            writeln!(out).unwrap();
+        }
+    }
+}
+
+fn write_labels(out: &mut String, kind: &str, labels: &[SystemLabelId]) {
+    if !labels.is_empty() {
+        let labels: Vec<_> = labels.iter().map(|label| format!("{label:?}")).collect();
+        write!(out, " [{kind}: {}]", labels.join(", ")).unwrap();
+    }
+}
+
+/// Sorts the systems topologically with respect to their
+/// `before`/`after` constraints. Among the systems that are free to
+/// run, the one that was added first comes first. Systems that are
+/// part of a dependency cycle are appended in the order in which they
+/// were added.
+fn resolve_order(systems: &[SystemContainer]) -> Vec<usize> {
+    let has_label = |index: usize, label: &SystemLabelId| systems[index].labels().contains(label);
+    let must_run_before = |first: usize, second: usize| {
+        first != second
+            && (systems[second]
+                .after()
+                .iter()
+                .any(|label| has_label(first, label))
+                || systems[first]
+                    .before()
+                    .iter()
+                    .any(|label| has_label(second, label)))
+    };
+    let mut remaining: Vec<usize> = (0..systems.len()).collect();
+    let mut order = vec![];
+    while !remaining.is_empty() {
+        let next = remaining
+            .iter()
+            .position(|second| {
+                !remaining
+                    .iter()
+                    .any(|first| must_run_before(*first, *second))
+            })
+            .unwrap_or(0);
+        order.push(remaining.remove(next));
+    }
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::schedule::IntoSystemDescriptor;
+
+    use crate::simulation::Simulation;
+    use crate::simulation_plugin::Stages;
+    use crate::simulation_plugin::StartupStages;
+
+    fn startup_system() {}
+    fn first_system() {}
+    fn second_system() {}
+    fn third_system() {}
+
+    fn position_of(dump: &str, name: &str) -> usize {
+        dump.lines()
+            .position(|line| line.split_whitespace().next().unwrap().ends_with(name))
+            .unwrap_or_else(|| panic!("{name} not found in\n{dump}"))
+    }
+
+    #[test]
+    fn dump_lists_systems_after_their_dependencies() {
+        let mut sim = Simulation::default();
+        sim.add_system_to_stage(Stages::Initial, third_system.after(second_system))
+            .add_system_to_stage(Stages::Initial, second_system.after(first_system))
+            .add_system_to_stage(Stages::Initial, first_system)
+            .add_startup_system_to_stage(StartupStages::ReadInput, startup_system);
+        let dump = sim.format_schedule();
+        let first = position_of(&dump, "first_system");
+        let second = position_of(&dump, "second_system");
+        let third = position_of(&dump, "third_system");
+        assert!(first < second && second < third, "{dump}");
+        // The startup schedule runs before all other stages.
+        assert!(position_of(&dump, "startup_system") < first, "{dump}");
+    }
+}
@@ -4,16 +4,26 @@ use derive_more::Deref;
 use derive_more::DerefMut;
 use derive_more::From;
 use derive_more::Into;
+use kiddo::distance::squared_euclidean;
+use kiddo::KdTree;
+use rand::Rng;
 
 use crate::domain::Extent;
 use crate::parameters::Cosmology;
 use crate::prelude::Float;
 use crate::prelude::Simulation;
 use crate::prelude::SubsweepPlugin;
+use crate::quadtree::NUM_DIMENSIONS;
 use crate::units::ComovingLengthTimesH;
 use crate::units::Length;
+use crate::units::MVec;
 use crate::units::VecLength;
 
+/// The number of rejected candidates after which
+/// [SimulationBox::poisson_disk_points] gives up on finding
+/// further points.
+const MAX_NUM_POISSON_DISK_REJECTIONS: usize = 1000;
+
 #[derive(From, Into, Deref, DerefMut, Debug)]
 #[subsweep_parameters]
 pub struct SimulationBox(pub Extent);
@@ -130,6 +140,69 @@ impl SimulationBox {
         self.periodic_distance_vec(p1, p2).length()
     }
 
+    /// A point drawn uniformly from within the box.
+    pub fn random_point(&self, rng: &mut impl Rng) -> VecLength {
+        #[cfg(feature = "2d")]
+        let fraction = MVec::new(rng.gen(), rng.gen());
+        #[cfg(not(feature = "2d"))]
+        let fraction = MVec::new(rng.gen(), rng.gen(), rng.gen());
+        self.min + VecLength::new_unchecked(self.side_lengths().value_unchecked() * fraction)
+    }
+
+    /// Generates up to `n` random points within the box whose
+    /// periodic distance to each other is at least `min_dist`. Points
+    /// are drawn uniformly and rejected if they are too close to a
+    /// previous point. Fewer than `n` points are returned if too many
+    /// subsequent candidates are rejected, i.e. if the box is
+    /// (almost) full.
+    pub fn poisson_disk_points(
+        &self,
+        rng: &mut impl Rng,
+        min_dist: Length,
+        n: usize,
+    ) -> Vec<VecLength> {
+        let min_dist_squared = min_dist.value_unchecked().powi(2);
+        let mut tree: KdTree<Float, NUM_DIMENSIONS> = KdTree::new();
+        let mut points = vec![];
+        let mut num_rejections = 0;
+        while points.len() < n && num_rejections < MAX_NUM_POISSON_DISK_REJECTIONS {
+            let candidate = self.random_point(rng);
+            let too_close = !points.is_empty()
+                && self.iter_periodic_images(candidate).any(|(_, image)| {
+                    let (dist_squared, _) =
+                        tree.nearest_one(&image.value_unchecked().to_array(), &squared_euclidean);
+                    dist_squared < min_dist_squared
+                });
+            if too_close {
+                num_rejections += 1;
+            } else {
+                tree.add(&candidate.value_unchecked().to_array(), points.len());
+                points.push(candidate);
+                num_rejections = 0;
+            }
+        }
+        points
+    }
+
+    /// Iterates over the position itself, followed by its copies
+    /// translated into the neighbouring periodic images of the box
+    /// (27 positions in total in 3D, 9 in 2D).
+    pub fn periodic_images(&self, pos: VecLength) -> impl Iterator<Item = VecLength> + '_ {
+        self.iter_periodic_images(pos).map(|(_, image)| image)
+    }
+
+    /// Like [SimulationBox::periodic_images], but only yields the
+    /// images which are closer than `radius` to `center`.
+    pub fn periodic_images_within_radius(
+        &self,
+        pos: VecLength,
+        center: VecLength,
+        radius: Length,
+    ) -> impl Iterator<Item = VecLength> + '_ {
+        self.periodic_images(pos)
+            .filter(move |image| image.distance(&center) < radius)
+    }
+
     #[cfg(feature = "3d")]
     pub(crate) fn iter_periodic_images(
         &self,
@@ -264,6 +337,9 @@ impl std::fmt::Debug for PeriodicWrapType2d {
 #[cfg(feature = "3d")]
 pub(crate) mod tests {
 
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
     use crate::domain::Extent;
     use crate::parameters::SimulationBox;
     use crate::test_utils::assert_is_close;
@@ -358,4 +434,58 @@ pub(crate) mod tests {
             }
         }
     }
+
+    #[test]
+    fn periodic_images_map_back_to_original() {
+        let box_: SimulationBox = Extent::from_min_max(
+            VecLength::meters(0.0, 0.0, 0.0),
+            VecLength::meters(1.0, 2.0, 3.0),
+        )
+        .into();
+        let pos = VecLength::meters(0.1, 1.0, 1.5);
+        let images: Vec<_> = box_.periodic_images(pos).collect();
+        assert_eq!(images.len(), 27);
+        assert_eq!(images[0], pos);
+        for image in images.iter() {
+            assert_vec_is_close(box_.periodic_wrap(*image), pos);
+        }
+        let center = VecLength::meters(0.9, 1.0, 1.5);
+        let close: Vec<_> = box_
+            .periodic_images_within_radius(pos, center, Length::meters(0.3))
+            .collect();
+        assert_eq!(close.len(), 1);
+        assert_vec_is_close(close[0], VecLength::meters(1.1, 1.0, 1.5));
+    }
+
+    #[test]
+    fn random_points_are_within_box() {
+        let mut rng = StdRng::seed_from_u64(1337);
+        let box_: SimulationBox = Extent::from_min_max(
+            VecLength::meters(-1.0, -1.0, -1.0),
+            VecLength::meters(1.0, 2.0, 3.0),
+        )
+        .into();
+        for _ in 0..1000 {
+            assert!(box_.contains(&box_.random_point(&mut rng)));
+        }
+    }
+
+    #[test]
+    fn poisson_disk_points_respect_minimum_periodic_distance() {
+        let mut rng = StdRng::seed_from_u64(1338);
+        let box_: SimulationBox = Extent::from_min_max(
+            VecLength::meters(-1.0, -1.0, -1.0),
+            VecLength::meters(1.0, 2.0, 3.0),
+        )
+        .into();
+        let min_dist = Length::meters(0.3);
+        let points = box_.poisson_disk_points(&mut rng, min_dist, 200);
+        assert_eq!(points.len(), 200);
+        for (i, p1) in points.iter().enumerate() {
+            assert!(box_.contains(p1));
+            for p2 in points[i + 1..].iter() {
+                assert!(box_.periodic_distance(p1, p2) >= min_dist);
+            }
+        }
+    }
 }
@@ -20,6 +20,7 @@ use time::UtcOffset;
 use super::command_line_options::CommandLineOptions;
 use super::domain::DomainPlugin;
 use super::simulation_plugin::SimulationPlugin;
+use crate::benchmark::BenchmarkOptions;
 use crate::communication::BaseCommunicationPlugin;
 use crate::communication::MPI_UNIVERSE;
 use crate::io::output::make_output_dirs;
@@ -37,6 +38,7 @@ pub struct SimulationBuilder {
     pub write_output: bool,
     pub log: bool,
     pub parameter_overrides: Vec<Override>,
+    pub benchmark: Option<BenchmarkOptions>,
     base_communication: Option<BaseCommunicationPlugin>,
     require_parameter_file: bool,
 }
@@ -59,6 +61,7 @@ impl Default for SimulationBuilder {
             log: true,
             base_communication: None,
             parameter_overrides: vec![],
+            benchmark: None,
             require_parameter_file: false,
         }
     }
@@ -107,9 +110,16 @@ impl SimulationBuilder {
         if let Some(num_worker_threads) = opts.num_worker_threads {
             self.num_worker_threads(Some(num_worker_threads));
         }
-        self.parameter_file_path(&opts.parameter_file_path);
+        if let Some(ref parameter_file_path) = opts.parameter_file_path {
+            self.parameter_file_path(parameter_file_path);
+        }
         self.verbosity(opts.verbosity);
         self.parameter_overrides = opts.parameter_overrides.clone();
+        self.benchmark = opts.benchmark.map(|num_particles| BenchmarkOptions {
+            num_particles,
+            num_steps: opts.benchmark_steps,
+            num_worker_threads: opts.num_worker_threads,
+        });
         self
     }
 
@@ -183,19 +193,11 @@ impl SimulationBuilder {
 
     fn add_default_bevy_plugins(&self, sim: &mut Simulation) {
         sim.add_bevy_plugin(bevy_core::CorePlugin {
-            task_pool_options: self.task_pool_opts(),
+            task_pool_options: task_pool_options(self.num_worker_threads),
         })
         .add_bevy_plugin(bevy_app::ScheduleRunnerPlugin);
     }
 
-    fn task_pool_opts(&self) -> TaskPoolOptions {
-        if let Some(num_worker_threads) = self.num_worker_threads {
-            TaskPoolOptions::with_num_threads(num_worker_threads)
-        } else {
-            TaskPoolOptions::default()
-        }
-    }
-
     fn log_setup(
         &self,
         sim: &mut Simulation,
@@ -269,3 +271,11 @@ impl SimulationBuilder {
         MPI_UNIVERSE.barrier();
     }
 }
+
+pub(crate) fn task_pool_options(num_worker_threads: Option<usize>) -> TaskPoolOptions {
+    if let Some(num_worker_threads) = num_worker_threads {
+        TaskPoolOptions::with_num_threads(num_worker_threads)
+    } else {
+        TaskPoolOptions::default()
+    }
+}
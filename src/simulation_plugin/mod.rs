@@ -8,6 +8,8 @@ use mpi::traits::Equivalence;
 
 pub use self::parameters::SimulationParameters;
 pub use self::time::SimulationTime;
+use crate::communication::enable_volume_tally;
+use crate::communication::show_communication_volume_system;
 use crate::components::Position;
 use crate::cosmology::set_initial_cosmology_attributes_system;
 use crate::cosmology::LittleH;
@@ -16,10 +18,12 @@ use crate::cosmology::ScaleFactor;
 use crate::io::output::Attribute;
 use crate::io::output::OutputPlugin;
 use crate::named::Named;
+use crate::parameters::CommunicationParameters;
 use crate::parameters::Cosmology;
 use crate::parameters::SimulationBox;
 use crate::particle::ParticlePlugin;
 use crate::performance::write_performance_data_system;
+use crate::performance::write_performance_report_system;
 use crate::performance::Performance;
 use crate::performance::TOTAL_RUNTIME_IDENTIFIER;
 use crate::prelude::Particles;
@@ -86,7 +90,13 @@ impl SubsweepPlugin for SimulationPlugin {
             .add_system_to_stage(Stages::Initial, show_time_system)
             .add_system_to_stage(Stages::AfterSweep, write_simulated_time_system)
             .add_system_to_stage(Stages::Final, exit_system)
+            .add_system_to_stage(Stages::Final, write_performance_report_system)
             .add_system_to_stage(Stages::Initial, stop_simulation_system);
+        let communication = sim.add_parameter_type_and_get_result::<CommunicationParameters>();
+        if communication.tally_volume {
+            enable_volume_tally();
+            sim.add_system_to_stage(Stages::Final, show_communication_volume_system);
+        }
         let cosmology = sim.get_parameters::<Cosmology>();
         if let Cosmology::Cosmological { .. } = cosmology {
             sim.add_startup_system_to_stage(
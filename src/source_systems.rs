@@ -1,10 +1,13 @@
 use bevy_ecs::event::EventWriter;
 use bevy_ecs::prelude::Res;
+use bevy_ecs::prelude::ResMut;
 use derive_custom::subsweep_parameters;
 use derive_custom::Named;
 use kiddo::distance::squared_euclidean;
 use kiddo::KdTree;
 use log::debug;
+use log::info;
+use log::warn;
 use mpi::traits::Equivalence;
 use serde::Serialize;
 
@@ -14,13 +17,17 @@ use crate::components::Position;
 use crate::domain::DecompositionState;
 use crate::domain::IntoKey;
 use crate::io::time_series::TimeSeriesPlugin;
+use crate::performance::Performance;
 use crate::prelude::Float;
+use crate::prelude::ParticleId;
 use crate::prelude::Particles;
 use crate::prelude::SimulationBox;
 use crate::prelude::StartupStages;
 use crate::prelude::WorldRank;
+use crate::quadtree::NUM_DIMENSIONS;
 use crate::simulation::Simulation;
 use crate::simulation::SubsweepPlugin;
+use crate::sweep::grid::Cell;
 use crate::units::Length;
 use crate::units::SourceRate;
 use crate::units::VecLength;
@@ -46,30 +53,42 @@ pub struct Sources {
 }
 
 fn set_source_terms_system(
-    mut particles: Particles<(&Position, &mut components::Source)>,
+    mut particles: Particles<(
+        &ParticleId,
+        &Position,
+        &mut components::Source,
+        Option<&Cell>,
+    )>,
     sources: Res<Sources>,
     decomposition: Res<DecompositionState>,
     box_: Res<SimulationBox>,
     world_rank: Res<WorldRank>,
     mut writer: EventWriter<TotalLuminosity>,
+    mut performance_data: ResMut<Performance>,
 ) {
     let mut source_comm = MpiWorld::<Source>::new();
     let all_sources = source_comm.all_gather_varcount(&sources.sources);
     let mut particles: Vec<_> = particles.iter_mut().collect();
-    let tree: KdTree<Float, 3> = (&particles
-        .iter()
-        .map(|(pos, _)| pos_to_tree_coord(pos))
-        .collect::<Vec<_>>())
-        .into();
-    for s in all_sources.iter() {
+    let positions: Vec<_> = particles.iter().map(|(_, pos, _, _)| pos.0).collect();
+    let local_sources = all_sources.iter().filter(|s| {
         let key = s.pos.into_key(&*box_);
-        let rank = decomposition.get_owning_rank(key);
-        if rank == **world_rank {
-            let (_, index) = tree.nearest_one(&pos_to_tree_coord(&s.pos), &squared_euclidean);
-            let (_, ref mut source_term) = &mut particles[index];
-            ***source_term += s.rate;
-        }
+        decomposition.get_owning_rank(key) == **world_rank
+    });
+    let assignment = assign_sources_to_closest_cells(&positions, local_sources);
+    let warnings = source_placement_warnings(
+        particles
+            .iter()
+            .zip(assignment.rates.iter())
+            .map(|((id, pos, _, cell), rate)| (**id, pos.0, *cell, *rate)),
+    );
+    for warning in warnings {
+        warn!("{}", warning);
+    }
+    for ((_, _, source_term, _), rate) in particles.iter_mut().zip(assignment.rates.into_iter()) {
+        ***source_term += rate;
     }
+    let num_merged: usize = MpiWorld::<usize>::new().all_gather_sum(&assignment.num_merged);
+    performance_data.record_number("num_merged_sources", num_merged);
     let total: SourceRate = all_sources.iter().map(|source| source.rate).sum();
     writer.send(TotalLuminosity(total));
     debug!(
@@ -77,9 +96,67 @@ fn set_source_terms_system(
         all_sources.len(),
         total.in_photons_per_second()
     );
+    if num_merged > 0 {
+        info!(
+            "{} sources were merged into cells already containing a source",
+            num_merged
+        );
+    }
+}
+
+struct SourceAssignment {
+    /// The total rate of all sources assigned to each cell.
+    rates: Vec<SourceRate>,
+    /// The number of sources that were assigned to a cell which
+    /// already contained another source.
+    num_merged: usize,
+}
+
+/// Assigns every source to the cell closest to it. If multiple sources
+/// fall into the same cell, their rates are added up.
+fn assign_sources_to_closest_cells<'a>(
+    positions: &[VecLength],
+    sources: impl Iterator<Item = &'a Source>,
+) -> SourceAssignment {
+    let mut rates = vec![SourceRate::zero(); positions.len()];
+    let mut num_sources = vec![0usize; positions.len()];
+    let tree: KdTree<Float, NUM_DIMENSIONS> =
+        (&positions.iter().map(pos_to_tree_coord).collect::<Vec<_>>()).into();
+    for s in sources {
+        let (_, index) = tree.nearest_one(&pos_to_tree_coord(&s.pos), &squared_euclidean);
+        rates[index] += s.rate;
+        num_sources[index] += 1;
+    }
+    let num_merged = num_sources.iter().map(|num| num.saturating_sub(1)).sum();
+    SourceAssignment { rates, num_merged }
+}
+
+/// Warnings for every cell which was assigned a source but lies at
+/// the boundary of the box, where the emitted photons can leave the
+/// box immediately.
+fn source_placement_warnings<'a>(
+    cells: impl Iterator<Item = (ParticleId, VecLength, Option<&'a Cell>, SourceRate)>,
+) -> Vec<String> {
+    cells
+        .filter(|(_, _, cell, rate)| {
+            *rate != SourceRate::zero() && cell.map(|cell| cell.is_at_boundary()).unwrap_or(false)
+        })
+        .map(|(id, pos, _, _)| {
+            format!(
+                "Source assigned to cell {:?} at {:?}, which is at the boundary of the box",
+                id, pos
+            )
+        })
+        .collect()
+}
+
+#[cfg(feature = "2d")]
+fn pos_to_tree_coord(pos: &VecLength) -> [f64; NUM_DIMENSIONS] {
+    [pos.x().value_unchecked(), pos.y().value_unchecked()]
 }
 
-fn pos_to_tree_coord(pos: &VecLength) -> [f64; 3] {
+#[cfg(not(feature = "2d"))]
+fn pos_to_tree_coord(pos: &VecLength) -> [f64; NUM_DIMENSIONS] {
     [
         pos.x().value_unchecked(),
         pos.y().value_unchecked(),
@@ -99,3 +176,100 @@ impl SubsweepPlugin for SourcePlugin {
         .add_plugin(TimeSeriesPlugin::<TotalLuminosity>::default());
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::assign_sources_to_closest_cells;
+    use super::source_placement_warnings;
+    use super::Source;
+    use crate::dimension::ActiveDimension;
+    use crate::hash_map::HashMap;
+    use crate::prelude::Constructor;
+    use crate::prelude::MVec;
+    use crate::prelude::ParticleId;
+    use crate::quadtree::NUM_DIMENSIONS;
+    use crate::sweep::grid::ParticleType;
+    use crate::test_utils::get_particles;
+    use crate::units::SourceRate;
+    use crate::units::VecLength;
+
+    #[test]
+    fn sources_in_the_same_cell_are_merged() {
+        let positions: Vec<_> = get_particles(3, 3).into_iter().map(|p| p.pos).collect();
+        let rate1 = SourceRate::photons_per_second(1e49);
+        let rate2 = SourceRate::photons_per_second(3e49);
+        let rate3 = SourceRate::photons_per_second(5e49);
+        let sources = [
+            Source {
+                pos: positions[4],
+                rate: rate1,
+            },
+            Source {
+                pos: positions[4],
+                rate: rate2,
+            },
+            Source {
+                pos: positions[0],
+                rate: rate3,
+            },
+        ];
+        let assignment = assign_sources_to_closest_cells(&positions, sources.iter());
+        assert_eq!(assignment.rates[4], rate1 + rate2);
+        assert_eq!(assignment.rates[0], rate3);
+        assert_eq!(assignment.num_merged, 1);
+        let total: SourceRate = assignment.rates.iter().copied().sum();
+        assert_eq!(total, rate1 + rate2 + rate3);
+    }
+
+    #[cfg(feature = "2d")]
+    fn lattice_point(i: usize, n: usize) -> MVec {
+        MVec::new((i % n) as f64, (i / n) as f64)
+    }
+
+    #[cfg(not(feature = "2d"))]
+    fn lattice_point(i: usize, n: usize) -> MVec {
+        MVec::new((i % n) as f64, ((i / n) % n) as f64, (i / (n * n)) as f64)
+    }
+
+    #[test]
+    fn sources_at_the_box_boundary_produce_warnings() {
+        let n = 5;
+        let points: Vec<_> = (0..n.pow(NUM_DIMENSIONS as u32))
+            .map(|i| (ParticleId::test(i), lattice_point(i, n)))
+            .collect();
+        let cons = Constructor::<ActiveDimension>::new(points.iter().cloned());
+        let cells: HashMap<_, _> = cons
+            .sweep_grid(false)
+            .into_iter()
+            .filter_map(|(particle_type, cell)| match particle_type {
+                ParticleType::Local(id) => Some((id, cell)),
+                _ => None,
+            })
+            .collect();
+        let positions: Vec<_> = points
+            .iter()
+            .map(|(_, p)| VecLength::new_unchecked(*p))
+            .collect();
+        let rate = SourceRate::photons_per_second(1e49);
+        let sources = [
+            Source {
+                pos: VecLength::new_unchecked(MVec::splat(-0.1)),
+                rate,
+            },
+            Source {
+                pos: VecLength::new_unchecked(MVec::splat(2.1)),
+                rate,
+            },
+        ];
+        let assignment = assign_sources_to_closest_cells(&positions, sources.iter());
+        let warnings = source_placement_warnings(
+            points
+                .iter()
+                .zip(positions.iter())
+                .zip(assignment.rates.iter())
+                .map(|(((id, _), pos), rate)| (*id, *pos, cells.get(id), *rate)),
+        );
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains(&format!("{:?}", ParticleId::test(0))));
+    }
+}
@@ -1,8 +1,15 @@
+use bevy_tasks::ComputeTaskPool;
+use bevy_tasks::TaskPool;
+
 use super::timestep_level::TimestepLevel;
 use crate::communication::Rank;
 use crate::hash_map::HashMap;
 use crate::particle::ParticleId;
 
+/// The number of chunks per thread into which the items are split in
+/// [ActiveList::par_map_active_mut].
+const CHUNKS_PER_THREAD: usize = 4;
+
 pub struct ActiveList<T> {
     items: Vec<T>,
     levels: Vec<TimestepLevel>,
@@ -15,10 +22,11 @@ pub struct ActiveList<T> {
 impl<T> ActiveList<T> {
     pub fn new(
         mut map: HashMap<ParticleId, T>,
+        rank: Rank,
         max_num_levels: usize,
         initial_level: TimestepLevel,
     ) -> Self {
-        let rank = map.iter().next().unwrap().0.rank;
+        // The map can be empty if this rank does not own any cells.
         assert!(map.keys().all(|id| id.rank == rank));
         let mut items = Vec::with_capacity(map.len());
         let mut levels = Vec::with_capacity(map.len());
@@ -45,6 +53,36 @@ impl<T> ActiveList<T> {
         list
     }
 
+    /// The index of the item belonging to the id. Panics with a
+    /// message naming the id if it does not belong to this list.
+    fn index(&self, id: ParticleId) -> usize {
+        if cfg!(debug_assertions) && id.rank != self.rank {
+            self.missing(id);
+        }
+        let index = id.index as usize;
+        if index >= self.items.len() {
+            self.missing(id);
+        }
+        index
+    }
+
+    #[cold]
+    #[inline(never)]
+    fn missing(&self, id: ParticleId) -> ! {
+        if id.rank != self.rank {
+            panic!(
+                "Expected a local particle on rank {} but {:?} is a halo particle of rank {}",
+                self.rank, id, id.rank
+            );
+        }
+        panic!(
+            "Local particle {:?} not found: rank {} only has {} local particles",
+            id,
+            self.rank,
+            self.items.len()
+        );
+    }
+
     fn get_id_from_index(&self, index: usize) -> ParticleId {
         ParticleId {
             rank: self.rank,
@@ -65,6 +103,55 @@ impl<T> ActiveList<T> {
             })
     }
 
+    /// Calls `f` on all active items in parallel and returns the
+    /// results in the order of the items. Every call only has mutable
+    /// access to its own item, so the result does not depend on the
+    /// number of threads. The work is distributed on the compute
+    /// task pool, whose size is set by the `num_worker_threads`
+    /// option.
+    pub fn par_map_active_mut<R: Send + 'static>(
+        &mut self,
+        current_level: TimestepLevel,
+        f: impl Fn(ParticleId, TimestepLevel, &mut T) -> R + Sync,
+    ) -> Vec<R>
+    where
+        T: Send,
+    {
+        assert!(self.valid);
+        let rank = self.rank;
+        let pool = ComputeTaskPool::init(TaskPool::default);
+        // Use a few chunks per thread, so that threads which finish
+        // early can pick up remaining work.
+        let chunk_size = (self.items.len() / (pool.thread_num() * CHUNKS_PER_THREAD)).max(1);
+        let levels = &self.levels;
+        let f = &f;
+        pool.scope(|scope| {
+            for (chunk_index, chunk) in self.items.chunks_mut(chunk_size).enumerate() {
+                scope.spawn(async move {
+                    let offset = chunk_index * chunk_size;
+                    chunk
+                        .iter_mut()
+                        .enumerate()
+                        .filter_map(|(i, item)| {
+                            let index = offset + i;
+                            let level = levels[index];
+                            level.is_active(current_level).then(|| {
+                                let id = ParticleId {
+                                    index: index as u32,
+                                    rank,
+                                };
+                                f(id, level, item)
+                            })
+                        })
+                        .collect::<Vec<_>>()
+                });
+            }
+        })
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
     pub fn enumerate_with_levels(&self) -> impl Iterator<Item = (ParticleId, TimestepLevel, &T)> {
         self.levels
             .iter()
@@ -106,38 +193,39 @@ impl<T> ActiveList<T> {
         id: ParticleId,
         current_level: TimestepLevel,
     ) -> (&mut T, bool) {
-        debug_assert!(id.rank == self.rank);
-        let item = &mut self.items[id.index as usize];
-        let level = &mut self.levels[id.index as usize];
+        let index = self.index(id);
+        let item = &mut self.items[index];
+        let level = &mut self.levels[index];
         (item, level.is_active(current_level))
     }
 
     pub fn get_mut(&mut self, id: ParticleId) -> &mut T {
-        debug_assert!(id.rank == self.rank);
-        &mut self.items[id.index as usize]
+        let index = self.index(id);
+        &mut self.items[index]
     }
 
-    pub fn get_mut_with_level(&mut self, id: ParticleId) -> (TimestepLevel, &mut T) {
-        debug_assert!(id.rank == self.rank);
-        let item = &mut self.items[id.index as usize];
-        let level = self.levels[id.index as usize];
-        (level, item)
+    pub fn contains(&self, id: ParticleId) -> bool {
+        id.rank == self.rank && (id.index as usize) < self.items.len()
     }
 
     pub fn get(&self, id: ParticleId) -> &T {
-        debug_assert!(id.rank == self.rank);
-        &self.items[id.index as usize]
+        &self.items[self.index(id)]
     }
 
     pub fn get_level(&self, id: ParticleId) -> TimestepLevel {
-        debug_assert!(id.rank == self.rank);
-        self.levels[id.index as usize]
+        self.levels[self.index(id)]
     }
 
     pub fn set_level(&mut self, id: ParticleId, level: TimestepLevel) {
-        debug_assert!(id.rank == self.rank);
+        let index = self.index(id);
         self.valid = false;
-        self.levels[id.index as usize] = level;
+        self.levels[index] = level;
+    }
+
+    /// The number of items at each timestep level.
+    pub fn num_at_each_level(&self) -> Vec<usize> {
+        assert!(self.valid);
+        self.bins.iter().map(|bin| bin.len()).collect()
     }
 
     pub(crate) fn update_bins(&mut self) {
@@ -152,3 +240,78 @@ impl<T> ActiveList<T> {
         self.valid = true;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::ActiveList;
+    use crate::hash_map::HashMap;
+    use crate::particle::ParticleId;
+    use crate::sweep::timestep_level::TimestepLevel;
+
+    fn list_with_items(num: usize) -> ActiveList<usize> {
+        let map: HashMap<_, _> = (0..num).map(|i| (ParticleId::test(i), i)).collect();
+        ActiveList::new(map, 0, 1, TimestepLevel(0))
+    }
+
+    #[test]
+    fn level_histogram_sums_to_num_items() {
+        let num = 100;
+        let map: HashMap<_, _> = (0..num).map(|i| (ParticleId::test(i), i)).collect();
+        let mut list = ActiveList::new(map, 0, 4, TimestepLevel(3));
+        for i in 0..num {
+            list.set_level(ParticleId::test(i), TimestepLevel(i % 3));
+        }
+        list.update_bins();
+        let histogram = list.num_at_each_level();
+        assert_eq!(histogram, vec![34, 33, 33, 0]);
+        assert_eq!(histogram.iter().sum::<usize>(), num);
+    }
+
+    #[test]
+    #[should_panic(expected = "Local particle ParticleId { index: 5, rank: 0 } not found")]
+    fn missing_local_id_panics_with_id() {
+        let list = list_with_items(3);
+        list.get(ParticleId::test(5));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "is a halo particle of rank 1")]
+    fn halo_id_panics_with_rank() {
+        let mut list = list_with_items(3);
+        list.get_mut(ParticleId { index: 0, rank: 1 });
+    }
+
+    #[test]
+    fn parallel_map_matches_serial_map() {
+        let num = 1000;
+        let map: HashMap<_, _> = (0..num).map(|i| (ParticleId::test(i), i as f64)).collect();
+        let mut list = ActiveList::new(map, 0, 3, TimestepLevel(0));
+        for i in 0..num {
+            list.set_level(ParticleId::test(i), TimestepLevel(i % 3));
+        }
+        list.update_bins();
+        let update = |x: &mut f64| {
+            *x = (*x + 1.0).sqrt().ln();
+            *x * 2.0
+        };
+        let current_level = TimestepLevel(1);
+        let mut serial: Vec<_> = list
+            .enumerate_active(current_level)
+            .map(|(id, x)| {
+                let mut x = *x;
+                (id, update(&mut x), x)
+            })
+            .collect();
+        serial.sort_by_key(|(id, _, _)| *id);
+        let results = list.par_map_active_mut(current_level, |id, _, x| (id, update(x)));
+        assert_eq!(results.len(), serial.len());
+        for ((id, result), (serial_id, serial_result, serial_value)) in
+            results.into_iter().zip(serial.into_iter())
+        {
+            assert_eq!(id, serial_id);
+            assert_eq!(result, serial_result);
+            assert_eq!(*list.get(id), serial_value);
+        }
+    }
+}
@@ -10,6 +10,7 @@ use crate::components::RecombinationRate;
 use crate::parameters::Cosmology;
 use crate::prelude::ParticleId;
 use crate::prelude::Particles;
+use crate::simulation_plugin::SimulationTime;
 use crate::units::Time;
 
 const TIMESTEP_YRS: f64 = 1.0;
@@ -37,7 +38,7 @@ impl ChemistryOutputType for HeatingRate {
 impl ChemistryOutputType for RecombinationRate {
     fn from_solver(solver: &Solver) -> Self {
         RecombinationRate(
-            solver.case_b_recombination_rate()
+            solver.recombination_rate()
                 * solver.electron_number_density()
                 * solver.ionized_hydrogen_fraction,
         )
@@ -58,10 +59,12 @@ pub fn sweep_optional_output_system<C: ChemistryOutputType + Component>(
     mut solver: NonSendMut<Option<Sweep<HydrogenOnly>>>,
     mut items: Particles<(&ParticleId, &mut C)>,
     cosmology: Res<Cosmology>,
+    time: Res<SimulationTime>,
 ) {
     let solver = (*solver).as_mut().unwrap();
+    let scale_factor = cosmology.scale_factor_at(**time);
     for (id, mut item) in items.iter_mut() {
-        let solver = solver.get_solver(*id, cosmology.scale_factor());
+        let solver = solver.get_solver(*id, scale_factor);
         *item = C::from_solver(&solver);
     }
 }
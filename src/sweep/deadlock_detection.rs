@@ -84,12 +84,15 @@ impl<C: Chemistry> Sweep<C> {
     }
 
     fn check_some_initial_task_exists(&self) {
+        // Every rank needs to take part in the reductions, even if
+        // it has no active cells itself.
         let num_to_solve = self.cells.enumerate_active(self.current_level).count();
-        if num_to_solve == 0 {
+        let mut ex = MpiWorld::new_custom_tag(DEADLOCK_DETECTION_TAG);
+        let total_to_solve: usize = ex.all_gather_sum(&num_to_solve);
+        if total_to_solve == 0 {
             return;
         }
         let num_initial_tasks = self.to_solve.len();
-        let mut ex = MpiWorld::new_custom_tag(DEADLOCK_DETECTION_TAG);
         let total: usize = ex.all_gather_sum(&num_initial_tasks);
         assert!(
             total > 0,
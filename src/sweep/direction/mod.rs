@@ -2,10 +2,12 @@
 mod healpix;
 
 use std::f64::consts::PI;
+use std::ops::Mul;
 
 use bevy_ecs::prelude::NonSendMut;
 use bevy_ecs::prelude::ResMut;
 use bevy_ecs::prelude::Resource;
+use derive_custom::Named;
 use derive_more::Deref;
 use derive_more::DerefMut;
 use mpi::traits::Equivalence;
@@ -19,7 +21,8 @@ use serde::Serialize;
 use super::parameters::DirectionsSpecification;
 use super::Sweep;
 use crate::chemistry::hydrogen_only::HydrogenOnly;
-use crate::chemistry::Chemistry;
+use crate::io::input::attribute::FromAttribute;
+use crate::io::output::ToAttribute;
 use crate::prelude::Simulation;
 use crate::units::Dimensionless;
 use crate::units::MVec;
@@ -33,18 +36,44 @@ pub struct DirectionIndex(pub usize);
 #[derive(Deref, DerefMut, Deserialize, Serialize, Clone, Debug)]
 pub struct Direction(pub VecDimensionless);
 
+/// The directions of the sweep, each of which represents a bin of
+/// solid angle. The weights are the fractions of the full solid angle
+/// covered by each bin and sum to one.
 #[derive(Resource, Clone)]
 pub struct Directions {
     directions: Vec<Direction>,
+    weights: Vec<Dimensionless>,
 }
 
 impl Directions {
+    fn uniform(directions: Vec<Direction>) -> Self {
+        let weights = vec![1.0; directions.len()];
+        Self::weighted(directions, &weights)
+    }
+
+    /// Normalizes the weights such that they sum to one.
+    pub fn weighted(directions: Vec<Direction>, weights: &[f64]) -> Self {
+        assert_eq!(directions.len(), weights.len());
+        let total: f64 = weights.iter().sum();
+        assert!(
+            total > 0.0 && weights.iter().all(|weight| *weight >= 0.0),
+            "Direction weights need to be non-negative and not all zero"
+        );
+        Self {
+            directions,
+            weights: weights
+                .iter()
+                .map(|weight| Dimensionless::dimensionless(weight / total))
+                .collect(),
+        }
+    }
+
     #[cfg(feature = "2d")]
     fn from_num(num: usize) -> Self {
         use std::f64::consts::PI;
 
-        Self {
-            directions: (0..num)
+        Self::uniform(
+            (0..num)
                 .map(|i| {
                     let fraction = 0.125 + (i as f64) / (num as f64);
                     let x = (fraction * 2.0 * PI).cos();
@@ -52,7 +81,7 @@ impl Directions {
                     Direction(MVec::new(x, y) * Dimensionless::dimensionless(1.0))
                 })
                 .collect(),
-        }
+        )
     }
 
     #[cfg(not(feature = "2d"))]
@@ -66,12 +95,11 @@ impl Directions {
             84 => &healpix::DIRECTION_BINS_84,
             _ => unimplemented!(),
         };
-        Self {
-            directions: bins
-                .iter()
+        Self::uniform(
+            bins.iter()
                 .map(|&[x, y, z]| Direction(MVec::new(x, y, z) * Dimensionless::dimensionless(1.0)))
                 .collect(),
-        }
+        )
     }
 
     pub fn enumerate(&self) -> impl Iterator<Item = (DirectionIndex, &Direction)> {
@@ -84,6 +112,12 @@ impl Directions {
     pub fn len(&self) -> usize {
         self.directions.len()
     }
+
+    /// The fraction of the full solid angle covered by the bin of
+    /// the given direction.
+    pub fn weight(&self, index: DirectionIndex) -> Dimensionless {
+        self.weights[index.0]
+    }
 }
 
 impl std::ops::Index<DirectionIndex> for Directions {
@@ -98,43 +132,100 @@ impl From<&DirectionsSpecification> for Directions {
     fn from(value: &DirectionsSpecification) -> Self {
         match value {
             DirectionsSpecification::Num(num) => Self::from_num(*num),
-            DirectionsSpecification::Explicit(ref directions) => Self {
-                directions: directions
+            DirectionsSpecification::Explicit(ref directions) => Self::uniform(
+                directions
                     .iter()
                     .map(|dir| Direction(dir.clone().normalize()))
                     .collect(),
-            },
+            ),
+            DirectionsSpecification::Weighted(ref directions) => Self::weighted(
+                directions
+                    .iter()
+                    .map(|dir| Direction(dir.direction.normalize()))
+                    .collect(),
+                &directions.iter().map(|dir| dir.weight).collect::<Vec<_>>(),
+            ),
+        }
+    }
+}
+
+const DIRECTIONS_RNG_SEED: u64 = 1337;
+
+/// The random number generator used for rotating the
+/// directions. Since the state of the generator itself cannot be
+/// written to the snapshots, it keeps track of the number of
+/// rotations drawn so far instead. This is enough to restore the
+/// exact state on a restart by replaying the rotations from the
+/// seed.
+#[derive(Resource, Clone, Named)]
+#[name = "directions_rng_num_rotations"]
+pub struct DirectionsRng {
+    rng: StdRng,
+    num_rotations: u64,
+}
+
+impl DirectionsRng {
+    pub fn new(num_rotations: u64) -> Self {
+        let mut rng = Self {
+            rng: StdRng::seed_from_u64(DIRECTIONS_RNG_SEED),
+            num_rotations: 0,
+        };
+        for _ in 0..num_rotations {
+            rng.get_random_rotation_matrix();
         }
+        rng
+    }
+
+    fn get_random_rotation_matrix(&mut self) -> RotationMatrix {
+        self.num_rotations += 1;
+        get_random_rotation_matrix(&mut self.rng)
+    }
+}
+
+impl ToAttribute for DirectionsRng {
+    type Output = u64;
+
+    fn to_value(&self) -> Self::Output {
+        self.num_rotations
+    }
+}
+
+impl FromAttribute for DirectionsRng {
+    fn from_value(val: Self::Output) -> Self {
+        Self::new(val)
     }
 }
 
-#[derive(Resource, Clone, Deref, DerefMut)]
-pub struct DirectionsRng(StdRng);
+#[cfg(feature = "2d")]
+type RotationMatrix = [[f64; 2]; 2];
+#[cfg(not(feature = "2d"))]
+type RotationMatrix = [[f64; 3]; 3];
+
+#[cfg(not(feature = "2d"))]
+fn get_rotation_matrix(axis: MVec, angle: f64) -> RotationMatrix {
+    let rotate = |v: MVec| {
+        VecDimensionless::new_unchecked(v)
+            .rotate_around(
+                VecDimensionless::new_unchecked(axis),
+                Dimensionless::dimensionless(angle),
+            )
+            .value_unchecked()
+    };
+    // The columns of the matrix are the rotated basis vectors.
+    let (x, y, z) = (rotate(MVec::X), rotate(MVec::Y), rotate(MVec::Z));
+    [[x.x, y.x, z.x], [x.y, y.y, z.y], [x.z, y.z, z.z]]
+}
 
-fn get_rotation_matrix(axis: MVec, angle: f64) -> [[f64; 3]; 3] {
-    let (x, y, z) = (axis.x, axis.y, axis.z);
+#[cfg(feature = "2d")]
+fn get_random_rotation_matrix(rng: &mut StdRng) -> RotationMatrix {
+    let angle: f64 = rng.gen_range(0.0..(2.0 * PI));
     let cos = angle.cos();
     let sin = angle.sin();
-    [
-        [
-            cos + x * x * (1.0 - cos),
-            x * y * (1.0 - cos) - z * sin,
-            x * z * (1.0 - cos) + y * sin,
-        ],
-        [
-            y * x * (1.0 - cos) + z * sin,
-            cos + y * y * (1.0 - cos),
-            y * z * (1.0 - cos) - x * sin,
-        ],
-        [
-            z * x * (1.0 - cos) - y * sin,
-            z * y * (1.0 - cos) + x * sin,
-            cos + z * z * (1.0 - cos),
-        ],
-    ]
-}
-
-fn get_random_rotation_matrix(rng: &mut StdRng) -> [[f64; 3]; 3] {
+    [[cos, -sin], [sin, cos]]
+}
+
+#[cfg(not(feature = "2d"))]
+fn get_random_rotation_matrix(rng: &mut StdRng) -> RotationMatrix {
     let phi = rng.gen_range(0.0..(2.0 * PI));
     let rand: f64 = rng.gen_range(0.0..1.0);
     let theta = (2.0 * rand - 1.0).acos();
@@ -147,7 +238,15 @@ fn get_random_rotation_matrix(rng: &mut StdRng) -> [[f64; 3]; 3] {
     get_rotation_matrix(axis, psi)
 }
 
-fn multiply_by_matrix(vec: &mut MVec, matrix: &[[f64; 3]; 3]) {
+#[cfg(feature = "2d")]
+fn multiply_by_matrix(vec: &mut MVec, matrix: &RotationMatrix) {
+    let (x, y) = (vec.x, vec.y);
+    vec.x = x * matrix[0][0] + y * matrix[0][1];
+    vec.y = x * matrix[1][0] + y * matrix[1][1];
+}
+
+#[cfg(not(feature = "2d"))]
+fn multiply_by_matrix(vec: &mut MVec, matrix: &RotationMatrix) {
     let (x, y, z) = (vec.x, vec.y, vec.z);
     vec.x = x * matrix[0][0] + y * matrix[0][1] + z * matrix[0][2];
     vec.y = x * matrix[1][0] + y * matrix[1][1] + z * matrix[1][2];
@@ -160,7 +259,7 @@ pub(super) fn rotate_directions_system(
     mut rng: ResMut<DirectionsRng>,
 ) {
     let solver = (*solver).as_mut().unwrap();
-    let matrix = get_random_rotation_matrix(&mut rng);
+    let matrix = rng.get_random_rotation_matrix();
     let old_dirs = solver.directions.directions.clone();
     for dir in solver.directions.directions.iter_mut() {
         multiply_by_matrix(&mut dir.0 .0, &matrix)
@@ -170,6 +269,12 @@ pub(super) fn rotate_directions_system(
         remap(&mut site.incoming_total_rate, &old_dirs, &new_dirs);
         remap(&mut site.outgoing_total_rate, &old_dirs, &new_dirs);
         remap(&mut site.periodic_source, &old_dirs, &new_dirs);
+        // The slopes determine how the outgoing rates were
+        // distributed among the faces, so they need to stay with
+        // the rates.
+        if !site.slope.is_empty() {
+            remap(&mut site.slope, &old_dirs, &new_dirs);
+        }
     }
 }
 
@@ -187,8 +292,8 @@ fn kernel_f(d1: &Direction, dirs: &[Direction]) -> Vec<f64> {
         .collect()
 }
 
-fn remap(
-    values: &mut [<HydrogenOnly as Chemistry>::Photons],
+fn remap<T: Copy + Mul<f64, Output = T>>(
+    values: &mut [T],
     old_dirs: &[Direction],
     new_dirs: &[Direction],
 ) {
@@ -204,9 +309,12 @@ fn remap(
     }
 }
 
+/// Inserts a freshly seeded rng, unless the state has already been
+/// restored (for example from a previous snapshot).
 pub(super) fn init_directions_rng(sim: &mut Simulation) {
-    const DIRECTIONS_RNG_SEED: u64 = 1337;
-    sim.insert_resource(DirectionsRng(StdRng::seed_from_u64(DIRECTIONS_RNG_SEED)));
+    if !sim.contains_resource::<DirectionsRng>() {
+        sim.insert_resource(DirectionsRng::new(0));
+    }
 }
 
 #[cfg(test)]
@@ -216,12 +324,26 @@ mod tests {
 
     use super::get_random_rotation_matrix;
     use super::multiply_by_matrix;
+    use super::Direction;
+    use super::Directions;
+    use super::DirectionsRng;
+    use crate::chemistry::hydrogen_only::HydrogenOnly;
+    use crate::chemistry::hydrogen_only::HydrogenOnlySpecies;
+    use crate::io::input::attribute::FromAttribute;
+    use crate::io::output::ToAttribute;
+    use crate::sweep::site::Site;
     use crate::test_utils::assert_float_is_close;
+    use crate::units::Density;
+    use crate::units::Dimensionless;
     use crate::units::MVec;
-    use crate::voronoi::math::utils::determinant3x3;
+    use crate::units::PhotonRate;
+    use crate::units::SourceRate;
+    use crate::units::Temperature;
 
+    #[cfg(not(feature = "2d"))]
     #[test]
     fn rotation_matrix_has_determinant_1() {
+        use crate::voronoi::math::utils::determinant3x3;
         let mut rng = StdRng::seed_from_u64(1337);
         for _ in 0..100 {
             let m = get_random_rotation_matrix(&mut rng);
@@ -229,6 +351,16 @@ mod tests {
         }
     }
 
+    #[cfg(feature = "2d")]
+    #[test]
+    fn rotation_matrix_has_determinant_1() {
+        let mut rng = StdRng::seed_from_u64(1337);
+        for _ in 0..100 {
+            let m = get_random_rotation_matrix(&mut rng);
+            assert_float_is_close(m[0][0] * m[1][1] - m[0][1] * m[1][0], 1.0);
+        }
+    }
+
     // This is technically covered by the above test but oh well.
     #[test]
     fn rotation_matrix_preserves_vector_norm() {
@@ -240,4 +372,54 @@ mod tests {
             assert_float_is_close(v.length(), 1.0);
         }
     }
+
+    #[test]
+    fn restored_rng_continues_the_sequence_of_rotations() {
+        let num_rotations = 20;
+        let mut uninterrupted = DirectionsRng::new(0);
+        let expected: Vec<_> = (0..2 * num_rotations)
+            .map(|_| uninterrupted.get_random_rotation_matrix())
+            .collect();
+        let mut before_restart = DirectionsRng::new(0);
+        let mut rotations: Vec<_> = (0..num_rotations)
+            .map(|_| before_restart.get_random_rotation_matrix())
+            .collect();
+        let mut after_restart = DirectionsRng::from_value(before_restart.to_value());
+        rotations.extend((0..num_rotations).map(|_| after_restart.get_random_rotation_matrix()));
+        assert_eq!(rotations, expected);
+    }
+
+    #[test]
+    fn source_is_split_according_to_direction_weights() {
+        let dirs = [MVec::X, MVec::Y, -MVec::X]
+            .into_iter()
+            .map(|dir| Direction(dir * Dimensionless::dimensionless(1.0)))
+            .collect();
+        let directions = Directions::weighted(dirs, &[1.0, 2.0, 3.0]);
+        let source = SourceRate::photons_per_second(6.0);
+        let site = Site::<HydrogenOnly>::new(
+            &directions,
+            HydrogenOnlySpecies::new(
+                Dimensionless::dimensionless(0.0),
+                Temperature::kelvins(1e4),
+                false,
+            ),
+            Density::zero(),
+            source,
+        );
+        let rates: Vec<PhotonRate> = directions
+            .enumerate()
+            .map(|(dir, _)| site.get_rate(&directions, dir))
+            .collect();
+        for (rate, weight) in rates.iter().zip([1.0, 2.0, 3.0]) {
+            assert_float_is_close(rate.in_photons_per_second(), weight);
+        }
+        assert_float_is_close(
+            rates
+                .into_iter()
+                .sum::<PhotonRate>()
+                .in_photons_per_second(),
+            source.in_photons_per_second(),
+        );
+    }
 }
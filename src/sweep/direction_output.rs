@@ -0,0 +1,164 @@
+use std::path::PathBuf;
+
+use bevy_ecs::prelude::NonSend;
+use bevy_ecs::prelude::Res;
+use derive_custom::subsweep_parameters;
+use derive_custom::Named;
+use hdf5::File;
+use hdf5::H5Type;
+use mpi::traits::Equivalence;
+
+use super::Sweep;
+use crate::chemistry::hydrogen_only::HydrogenOnly;
+use crate::communication::communicator::Communicator;
+use crate::parameters::OutputParameters;
+use crate::prelude::ParticleId;
+use crate::prelude::Stages;
+use crate::prelude::WorldRank;
+use crate::simulation::Simulation;
+use crate::simulation::SubsweepPlugin;
+use crate::simulation_plugin::SimulationTime;
+use crate::units::PhotonRate;
+use crate::units::Time;
+use crate::units::VecDimensionless;
+
+const DIRECTION_OUTPUT_DATASET_NAME: &str = "incoming_rates";
+const DIRECTION_OUTPUT_CHUNK_SIZE: usize = 1024;
+
+/// Parameters for writing the incoming photon rate in every
+/// direction for a few selected cells, which is useful for debugging
+/// shadowing and other anisotropies. Nothing is written unless `ids`
+/// is given.
+#[subsweep_parameters("direction_output")]
+pub struct DirectionOutputParameters {
+    /// The ids of the cells for which the rates are written.
+    #[serde(default)]
+    pub ids: Vec<ParticleId>,
+    /// The name of the file (within the output directory) to which
+    /// the rates are written.
+    #[serde(default = "default_direction_output_filename")]
+    pub filename: String,
+}
+
+fn default_direction_output_filename() -> String {
+    "incoming_rates.hdf5".into()
+}
+
+/// The incoming rate of a single cell in a single direction. All
+/// quantities are stored in SI units.
+#[derive(H5Type, Clone, Debug, Equivalence)]
+#[repr(C)]
+pub struct DirectionalRateEntry {
+    pub time: Time,
+    pub index: u32,
+    pub rank: i32,
+    pub direction: VecDimensionless,
+    pub rate: PhotonRate,
+}
+
+#[derive(Named)]
+pub struct DirectionOutputPlugin;
+
+impl SubsweepPlugin for DirectionOutputPlugin {
+    fn should_build(&self, sim: &Simulation) -> bool {
+        sim.write_output
+    }
+
+    fn build_everywhere(&self, sim: &mut Simulation) {
+        let parameters = sim.add_parameter_type_and_get_result::<DirectionOutputParameters>();
+        if !parameters.ids.is_empty() {
+            sim.add_system_to_stage(Stages::Output, direction_output_system);
+        }
+    }
+
+    fn build_on_main_rank(&self, sim: &mut Simulation) {
+        if !sim
+            .unwrap_resource::<DirectionOutputParameters>()
+            .ids
+            .is_empty()
+        {
+            sim.add_startup_system(create_direction_output_file_system);
+        }
+    }
+}
+
+fn get_direction_output_filename(
+    output_parameters: &OutputParameters,
+    parameters: &DirectionOutputParameters,
+) -> PathBuf {
+    output_parameters.output_dir.join(&parameters.filename)
+}
+
+fn create_direction_output_file_system(
+    output_parameters: Res<OutputParameters>,
+    parameters: Res<DirectionOutputParameters>,
+) {
+    let filename = get_direction_output_filename(&output_parameters, &parameters);
+    let file = File::create(&filename)
+        .unwrap_or_else(|e| panic!("Failed to create direction output file {filename:?}: {e}"));
+    file.new_dataset::<DirectionalRateEntry>()
+        .chunk(DIRECTION_OUTPUT_CHUNK_SIZE)
+        .shape(0..)
+        .create(DIRECTION_OUTPUT_DATASET_NAME)
+        .expect("Failed to create direction output dataset");
+}
+
+impl Sweep<HydrogenOnly> {
+    /// The incoming rate in every direction for those of the given
+    /// cells that are local to this rank.
+    pub(super) fn get_directional_rate_entries(
+        &self,
+        ids: &[ParticleId],
+        time: Time,
+    ) -> Vec<DirectionalRateEntry> {
+        ids.iter()
+            .filter(|id| self.sites.contains(**id))
+            .flat_map(|id| {
+                let site = self.sites.get(*id);
+                self.directions
+                    .enumerate()
+                    .map(move |(dir, direction)| DirectionalRateEntry {
+                        time,
+                        index: id.index,
+                        rank: id.rank,
+                        direction: direction.0,
+                        rate: site.incoming_total_rate[dir.0],
+                    })
+            })
+            .collect()
+    }
+}
+
+fn direction_output_system(
+    solver: NonSend<Option<Sweep<HydrogenOnly>>>,
+    parameters: Res<DirectionOutputParameters>,
+    output_parameters: Res<OutputParameters>,
+    time: Res<SimulationTime>,
+    rank: Res<WorldRank>,
+) {
+    let solver = (*solver).as_ref().unwrap();
+    let entries = solver.get_directional_rate_entries(&parameters.ids, **time);
+    let entries = Communicator::<DirectionalRateEntry>::new().all_gather_varcount(&entries);
+    if rank.is_main() {
+        append_to_direction_output_file(
+            &get_direction_output_filename(&output_parameters, &parameters),
+            &entries,
+        );
+    }
+}
+
+fn append_to_direction_output_file(filename: &PathBuf, entries: &[DirectionalRateEntry]) {
+    let file = File::open_rw(filename)
+        .unwrap_or_else(|e| panic!("Failed to open direction output file {filename:?}: {e}"));
+    let dataset = file
+        .dataset(DIRECTION_OUTPUT_DATASET_NAME)
+        .expect("Failed to open direction output dataset");
+    let start = dataset.size();
+    let end = start + entries.len();
+    dataset
+        .resize(end)
+        .expect("Failed to resize direction output dataset");
+    dataset
+        .write_slice(entries, start..end)
+        .expect("Failed to write to direction output dataset");
+}
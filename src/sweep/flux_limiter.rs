@@ -0,0 +1,106 @@
+use derive_custom::subsweep_parameters;
+
+use super::grid::FaceArea;
+use crate::units::Dimensionless;
+
+/// How the outgoing rate of a cell is distributed among its
+/// downwind faces.
+#[derive(Copy, Debug, Default, PartialEq, Eq)]
+#[subsweep_parameters]
+pub enum FluxLimiter {
+    /// Proportional to the effective area (the area projected onto
+    /// the direction) of each face.
+    #[default]
+    None,
+    /// Like `None`, but the effective area of every face is capped
+    /// at the mean effective area of all downwind faces first. This
+    /// prevents a single face from receiving almost all of the
+    /// outgoing rate, which can artificially channel the radiation
+    /// along thin sequences of cells.
+    MeanCap,
+}
+
+impl FluxLimiter {
+    fn limiting_area(&self, total: FaceArea, num_faces: usize) -> Option<FaceArea> {
+        match self {
+            FluxLimiter::None => None,
+            FluxLimiter::MeanCap => Some(total / num_faces as f64),
+        }
+    }
+}
+
+/// The fractions of the outgoing rate of a cell which are passed
+/// through each of its downwind faces.
+pub(super) struct DownwindWeights {
+    limit: Option<FaceArea>,
+    total: FaceArea,
+}
+
+impl DownwindWeights {
+    pub fn new<I: Iterator<Item = FaceArea>>(
+        limiter: FluxLimiter,
+        effective_areas: impl Fn() -> I,
+    ) -> Self {
+        let (total, num_faces) = effective_areas()
+            .fold((FaceArea::zero(), 0), |(total, num), area| {
+                (total + area, num + 1)
+            });
+        let limit = limiter.limiting_area(total, num_faces);
+        let total = match limit {
+            Some(limit) => effective_areas().map(|area| limited(area, limit)).sum(),
+            None => total,
+        };
+        Self { limit, total }
+    }
+
+    pub fn fraction(&self, effective_area: FaceArea) -> Dimensionless {
+        match self.limit {
+            Some(limit) => limited(effective_area, limit) / self.total,
+            None => effective_area / self.total,
+        }
+    }
+}
+
+fn limited(area: FaceArea, limit: FaceArea) -> FaceArea {
+    if area > limit {
+        limit
+    } else {
+        area
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DownwindWeights;
+    use super::FluxLimiter;
+    use crate::sweep::grid::FaceArea;
+
+    fn fractions(limiter: FluxLimiter, areas: &[f64]) -> Vec<f64> {
+        let areas: Vec<_> = areas.iter().map(|a| FaceArea::new_unchecked(*a)).collect();
+        let weights = DownwindWeights::new(limiter, || areas.iter().copied());
+        areas
+            .iter()
+            .map(|area| weights.fraction(*area).value())
+            .collect()
+    }
+
+    #[test]
+    fn mean_cap_limiter_reduces_flux_through_dominant_face() {
+        // One face almost aligned with the direction, as in a thin
+        // channel of cells.
+        let areas = [9.0, 0.5, 0.5];
+        let unlimited = fractions(FluxLimiter::None, &areas);
+        let limited = fractions(FluxLimiter::MeanCap, &areas);
+        assert!((unlimited[0] - 0.9).abs() < 1e-10);
+        assert!(limited[0] < unlimited[0]);
+        assert!((limited[0] - 10.0 / 13.0).abs() < 1e-10);
+        for f in [unlimited, limited] {
+            assert!((f.iter().sum::<f64>() - 1.0).abs() < 1e-10);
+        }
+        // Equal faces are not affected by the limiter.
+        assert_eq!(
+            fractions(FluxLimiter::MeanCap, &[1.0, 1.0]),
+            fractions(FluxLimiter::None, &[1.0, 1.0])
+        );
+    }
+}
@@ -13,6 +13,7 @@ use crate::communication::Rank;
 use crate::components::Position;
 use crate::dimension::ActiveWrapType;
 use crate::hash_map::HashMap;
+use crate::hash_map::IterSorted;
 use crate::parameters::SimulationBox;
 use crate::particle::HaloParticle;
 use crate::particle::ParticleId;
@@ -75,7 +76,7 @@ impl NumCellsSpec {
     }
 }
 
-#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
 pub struct IntegerPosition {
     x: i32,
     y: i32,
@@ -360,15 +361,17 @@ impl GridConstructor {
         (self.rank_function)(pos)
     }
 
-    fn spawn_local_cells(&mut self, mut commands: Commands) {
-        let mut drained_cells: Vec<_> = self.cells.drain().collect();
-        drained_cells.sort_by_key(|(integer_pos, _)| integer_pos.x);
-        for (integer_pos, cell) in drained_cells {
-            let particle_id = self.ids[&integer_pos];
-            let pos = self.to_pos(integer_pos);
-            let rank = self.get_rank(integer_pos);
+    /// Spawns the cells in the order of their positions, so that the
+    /// order of the entities (and thereby the order in which the maps
+    /// of the sweep are filled) does not depend on the iteration
+    /// order of the map of cells.
+    fn spawn_local_cells(&self, mut commands: Commands) {
+        for (integer_pos, cell) in self.cells.iter_sorted() {
+            let particle_id = self.ids[integer_pos];
+            let pos = self.to_pos(*integer_pos);
+            let rank = self.get_rank(*integer_pos);
             if rank == self.rank {
-                commands.spawn((LocalParticle, Position(pos), cell, particle_id));
+                commands.spawn((LocalParticle, Position(pos), cell.clone(), particle_id));
             } else if cell.neighbours.iter().any(|(_, neighbour)| {
                 if let ParticleType::Remote(neighbour) = neighbour {
                     neighbour.rank == self.rank
@@ -378,7 +381,12 @@ impl GridConstructor {
                     false
                 }
             }) {
-                commands.spawn((HaloParticle { rank }, Position(pos), cell, particle_id));
+                commands.spawn((
+                    HaloParticle { rank },
+                    Position(pos),
+                    cell.clone(),
+                    particle_id,
+                ));
             }
         }
     }
@@ -105,6 +105,14 @@ impl Cell {
         self.volume
     }
 
+    /// Whether any of the faces of this cell lie on the
+    /// (non-periodic) boundary of the simulation box.
+    pub fn is_at_boundary(&self) -> bool {
+        self.neighbours
+            .iter()
+            .any(|(_, neighbour)| neighbour.is_boundary())
+    }
+
     pub fn iter_downwind_faces<'a>(
         &'a self,
         direction: &'a VecDimensionless,
@@ -0,0 +1,198 @@
+use bevy_ecs::prelude::*;
+use derive_custom::Named;
+use serde::Serialize;
+
+use super::grid::Cell;
+use super::time_series::compute_global_sum;
+use crate::communication::MpiWorld;
+use crate::components::IonizedHydrogenFraction;
+use crate::components::Position;
+use crate::prelude::Particles;
+use crate::simulation_plugin::SimulationTime;
+use crate::source_systems::Source;
+use crate::source_systems::Sources;
+use crate::units::Dimensionless;
+use crate::units::Length;
+use crate::units::Time;
+use crate::units::VecLength;
+use crate::units::Velocity;
+use crate::units::Volume;
+
+/// The ionization fronts around all sources, in the order in which
+/// the sources were read.
+#[derive(Serialize, Clone, Named)]
+#[name = "ionization_fronts"]
+pub struct IonizationFronts(Vec<IonizationFront>);
+
+#[derive(Serialize, Clone, Debug)]
+pub struct IonizationFront {
+    pub source: VecLength,
+    /// The radius of a sphere (a circle in 2D) with the same volume
+    /// as the ionized volume around the source.
+    pub radius: Length,
+    /// The change of the radius since the last time the fronts were
+    /// computed. Not available in the first step.
+    pub velocity: Option<Velocity>,
+}
+
+/// The radii of the fronts the last time they were computed.
+#[derive(Resource, Default)]
+pub(super) struct PreviousIonizationFronts(Option<(Time, Vec<Length>)>);
+
+/// The ionized volume around each source. Every cell is attributed
+/// to the closest source, so that the fronts of multiple sources can
+/// be told apart as long as they do not overlap.
+fn ionized_volume_per_source(
+    cells: impl Iterator<Item = (VecLength, Volume, Dimensionless)>,
+    sources: &[VecLength],
+) -> Vec<Volume> {
+    let mut volumes = vec![Volume::zero(); sources.len()];
+    for (pos, volume, ionized_fraction) in cells {
+        let closest = sources
+            .iter()
+            .enumerate()
+            .min_by(|(_, s1), (_, s2)| {
+                (**s1 - pos)
+                    .length()
+                    .partial_cmp(&(**s2 - pos).length())
+                    .unwrap()
+            })
+            .map(|(i, _)| i);
+        if let Some(closest) = closest {
+            volumes[closest] += volume * ionized_fraction;
+        }
+    }
+    volumes
+}
+
+#[cfg(feature = "2d")]
+fn effective_radius(volume: Volume) -> Length {
+    Length::new_unchecked((volume.value_unchecked() / std::f64::consts::PI).sqrt())
+}
+
+#[cfg(not(feature = "2d"))]
+fn effective_radius(volume: Volume) -> Length {
+    Length::new_unchecked((volume.value_unchecked() * 3.0 / (4.0 * std::f64::consts::PI)).cbrt())
+}
+
+fn front_velocities(
+    previous: &PreviousIonizationFronts,
+    time: Time,
+    radii: &[Length],
+) -> Vec<Option<Velocity>> {
+    radii
+        .iter()
+        .enumerate()
+        .map(|(i, radius)| {
+            let (previous_time, previous_radii) = previous.0.as_ref()?;
+            if previous_radii.len() != radii.len() || time <= *previous_time {
+                return None;
+            }
+            Some((*radius - previous_radii[i]) / (time - *previous_time))
+        })
+        .collect()
+}
+
+pub(super) fn ionization_front_system(
+    particles: Particles<(&Position, &Cell, &IonizedHydrogenFraction)>,
+    sources: Res<Sources>,
+    time: Res<SimulationTime>,
+    mut previous: ResMut<PreviousIonizationFronts>,
+    mut writer: EventWriter<IonizationFronts>,
+) {
+    let all_sources = MpiWorld::<Source>::new().all_gather_varcount(&sources.sources);
+    let source_positions: Vec<_> = all_sources.iter().map(|source| source.pos).collect();
+    let local_volumes = ionized_volume_per_source(
+        particles
+            .iter()
+            .map(|(pos, cell, frac)| (**pos, cell.volume(), **frac)),
+        &source_positions,
+    );
+    let radii: Vec<_> = local_volumes
+        .into_iter()
+        .map(|volume| effective_radius(compute_global_sum(std::iter::once(volume))))
+        .collect();
+    let velocities = front_velocities(&previous, **time, &radii);
+    writer.send(IonizationFronts(
+        source_positions
+            .into_iter()
+            .zip(radii.iter())
+            .zip(velocities)
+            .map(|((source, radius), velocity)| IonizationFront {
+                source,
+                radius: *radius,
+                velocity,
+            })
+            .collect(),
+    ));
+    previous.0 = Some((**time, radii));
+}
+
+#[cfg(not(feature = "2d"))]
+#[cfg(test)]
+mod tests {
+    use super::effective_radius;
+    use super::front_velocities;
+    use super::ionized_volume_per_source;
+    use super::PreviousIonizationFronts;
+    use crate::units::Dimensionless;
+    use crate::units::Length;
+    use crate::units::Time;
+    use crate::units::VecLength;
+    use crate::units::Velocity;
+
+    #[test]
+    fn linearly_expanding_fronts_have_constant_velocity() {
+        let sources = [
+            VecLength::meters(0.0, 0.0, 0.0),
+            VecLength::meters(3e18, 0.0, 0.0),
+        ];
+        let velocities = [
+            Velocity::meters_per_second(1e4),
+            Velocity::meters_per_second(3e4),
+        ];
+        let mut previous = PreviousIonizationFronts::default();
+        for step in 1..5 {
+            let time = Time::megayears(step as f64);
+            // One fully ionized cell next to each source, with the
+            // volume of the ionized sphere, and one neutral cell.
+            let cells = sources
+                .iter()
+                .zip(velocities.iter())
+                .flat_map(|(source, velocity)| {
+                    let radius = *velocity * time;
+                    let volume = radius.cubed() * (4.0 / 3.0 * std::f64::consts::PI);
+                    [
+                        (
+                            *source + VecLength::meters(3e16, 0.0, 0.0),
+                            volume,
+                            Dimensionless::dimensionless(1.0),
+                        ),
+                        (
+                            *source - VecLength::meters(3e16, 0.0, 0.0),
+                            volume,
+                            Dimensionless::dimensionless(0.0),
+                        ),
+                    ]
+                });
+            let radii: Vec<Length> = ionized_volume_per_source(cells, &sources)
+                .into_iter()
+                .map(effective_radius)
+                .collect();
+            for (radius, velocity) in radii.iter().zip(velocities.iter()) {
+                let expected = *velocity * time;
+                assert!(((*radius - expected) / expected).value().abs() < 1e-10);
+            }
+            let measured = front_velocities(&previous, time, &radii);
+            if step == 1 {
+                assert!(measured.iter().all(|velocity| velocity.is_none()));
+            } else {
+                for (measured, velocity) in measured.iter().zip(velocities.iter()) {
+                    let relative_error: Dimensionless = (measured.unwrap() - *velocity) / *velocity;
+                    assert!(relative_error.value().abs() < 1e-10);
+                }
+            }
+            previous.0 = Some((time, radii));
+        }
+    }
+}
@@ -4,25 +4,41 @@ mod communicator;
 mod count_by_dir;
 mod deadlock_detection;
 mod direction;
+mod direction_output;
+mod flux_limiter;
 pub mod grid;
+mod ionization_front;
 mod parameters;
+mod photon_budget;
+mod reconstruction;
 pub(crate) mod site;
 mod task;
+mod task_batch_size;
 #[cfg(test)]
 mod tests;
 mod time_series;
 pub mod timestep_level;
 mod timestep_state;
 
+use std::time::Instant;
+
 use bevy_ecs::prelude::*;
 use derive_more::Into;
+pub use flux_limiter::FluxLimiter;
 use hdf5::H5Type;
 use log::info;
 use log::trace;
 use mpi::traits::Equivalence;
 use mpi::traits::MatchesRaw;
 pub use parameters::DirectionsSpecification;
+pub use parameters::RateThreshold;
 pub use parameters::SweepParameters;
+pub use parameters::TaskBatchSize;
+pub use parameters::WeightedDirection;
+pub use reconstruction::Reconstruction;
+
+pub use crate::chemistry::hydrogen_only::RecombinationCase;
+pub use crate::chemistry::timescale::TimescaleCombination;
 
 use self::active_list::ActiveList;
 use self::chemistry_output::sweep_optional_output_system;
@@ -32,28 +48,47 @@ use self::direction::init_directions_rng;
 use self::direction::rotate_directions_system;
 pub use self::direction::DirectionIndex;
 use self::direction::Directions;
+pub use self::direction::DirectionsRng;
+pub use self::direction_output::DirectionOutputParameters;
+use self::direction_output::DirectionOutputPlugin;
 use self::grid::Cell;
 use self::grid::FaceArea;
 use self::grid::ParticleType;
 use self::grid::RemoteNeighbour;
 use self::grid::RemotePeriodicNeighbour;
+use self::ionization_front::ionization_front_system;
+use self::ionization_front::IonizationFronts;
+use self::ionization_front::PreviousIonizationFronts;
+use self::photon_budget::check_photon_conservation_system;
+use self::photon_budget::escape_fraction_system;
+use self::reconstruction::FaceShares;
+use self::reconstruction::UpwindNeighbour;
 use self::site::Site;
 pub use self::task::RateData;
 use self::task::Task;
+use self::task_batch_size::TaskBatchSizeTuner;
 use self::time_series::compute_time_series_system;
 use self::time_series::num_particles_at_timestep_levels_system;
+use self::time_series::timestep_level_histogram_system;
+use self::time_series::timestep_limiting_processes_system;
+use self::time_series::EscapeFraction;
 use self::time_series::HydrogenIonizationMassAverage;
 use self::time_series::HydrogenIonizationVolumeAverage;
 use self::time_series::NumParticlesAtTimestepLevels;
 use self::time_series::PhotoionizationRateVolumeAverage;
 use self::time_series::TemperatureMassAverage;
 use self::time_series::TemperatureVolumeAverage;
+use self::time_series::TimestepLevelHistogram;
+use self::time_series::TimestepLimitingProcesses;
 use self::time_series::WeightedPhotoionizationRateVolumeAverage;
 use self::timestep_level::TimestepLevel;
 use self::timestep_state::TimestepState;
 use crate::chemistry::hydrogen_only::HydrogenOnly;
 use crate::chemistry::hydrogen_only::HydrogenOnlySpecies;
 use crate::chemistry::hydrogen_only::Solver;
+use crate::chemistry::hydrogen_only::TemperatureLimits;
+use crate::chemistry::timescale::LimitingProcessFractions;
+use crate::chemistry::timescale::Process;
 use crate::chemistry::timescale::Timescale;
 use crate::chemistry::timescale::TimescaleCounter;
 use crate::chemistry::Chemistry;
@@ -71,13 +106,17 @@ use crate::components::IonizationTime;
 use crate::components::IonizedHydrogenFraction;
 use crate::components::PhotoionizationRate;
 use crate::components::PhotonRate;
+use crate::components::PreventCooling;
 use crate::components::RecombinationRate;
 use crate::components::Source;
+use crate::components::SweepTimestep;
 use crate::components::Timestep;
 use crate::cosmology::Cosmology;
 use crate::hash_map::HashMap;
 use crate::io::output::parameters::is_desired_field;
 use crate::io::output::parameters::OutputParameters;
+use crate::io::output::Attribute;
+use crate::io::output::OutputPlugin;
 use crate::io::time_series::TimeSeriesPlugin;
 use crate::io::to_dataset::ToDataset;
 use crate::particle::HaloParticles;
@@ -91,7 +130,8 @@ use crate::units::Mass;
 use crate::units::SourceRate;
 use crate::units::Temperature;
 use crate::units::Time;
-use crate::units::Volume;
+use crate::units::VecDimensionless;
+use crate::units::Volume3D;
 
 pub type Rate<C> = <C as Chemistry>::Photons;
 pub type Species<C> = <C as Chemistry>::Species;
@@ -135,17 +175,32 @@ impl SubsweepPlugin for SweepPlugin {
             .add_plugin(TimeSeriesPlugin::<PhotoionizationRateVolumeAverage>::default())
             .add_plugin(TimeSeriesPlugin::<WeightedPhotoionizationRateVolumeAverage>::default())
             .add_plugin(TimeSeriesPlugin::<NumParticlesAtTimestepLevels>::default())
+            .add_plugin(TimeSeriesPlugin::<TimestepLevelHistogram>::default())
+            .add_plugin(TimeSeriesPlugin::<TimestepLimitingProcesses>::default())
+            .add_plugin(TimeSeriesPlugin::<EscapeFraction>::default())
+            .add_plugin(DirectionOutputPlugin)
             .insert_resource(IsFirstTime(true))
             .insert_non_send_resource(Option::<Sweep<HydrogenOnly>>::None)
             .add_startup_system_to_stage(StartupStages::InitSweep, init_sweep_system)
             .add_system_to_stage(Stages::Sweep, run_sweep_system)
             .add_parameter_type_and_get_result::<SweepParameters>();
+        let check_photon_conservation = parameters.check_photon_conservation;
+        let track_ionization_fronts = parameters.track_ionization_fronts;
         if parameters.rotate_directions {
             init_directions_rng(sim);
             sim.add_system_to_stage(
                 Stages::Sweep,
                 rotate_directions_system.after(run_sweep_system),
-            );
+            )
+            .add_plugin(OutputPlugin::<Attribute<DirectionsRng>>::default());
+        }
+        if check_photon_conservation {
+            sim.add_system_to_stage(Stages::AfterSweep, check_photon_conservation_system);
+        }
+        if track_ionization_fronts && sim.write_output {
+            sim.insert_resource(PreviousIonizationFronts::default())
+                .add_plugin(TimeSeriesPlugin::<IonizationFronts>::default())
+                .add_system_to_stage(Stages::AfterSweep, ionization_front_system);
         }
         if sim.write_output {
             sim.add_system_to_stage(
@@ -155,7 +210,21 @@ impl SubsweepPlugin for SweepPlugin {
             )
             .add_system_to_stage(
                 Stages::AfterSweep,
-                num_particles_at_timestep_levels_system::<HydrogenOnly>,
+                num_particles_at_timestep_levels_system::<HydrogenOnly>
+                    .before(timestep_level_histogram_system::<HydrogenOnly>),
+            )
+            .add_system_to_stage(
+                Stages::AfterSweep,
+                timestep_level_histogram_system::<HydrogenOnly>
+                    .before(timestep_limiting_processes_system::<HydrogenOnly>),
+            )
+            .add_system_to_stage(
+                Stages::AfterSweep,
+                timestep_limiting_processes_system::<HydrogenOnly>,
+            )
+            .add_system_to_stage(
+                Stages::AfterSweep,
+                escape_fraction_system.after(timestep_limiting_processes_system::<HydrogenOnly>),
             )
             .add_startup_system_to_stage(StartupStages::InitSweep, show_num_directions_system);
         }
@@ -164,6 +233,7 @@ impl SubsweepPlugin for SweepPlugin {
         init_optional_chemistry_component::<CollisionalIonizationRate>(sim);
         init_optional_chemistry_component::<PhotoionizationRate>(sim);
         init_optional_component::<Timestep>(sim);
+        init_optional_component::<SweepTimestep>(sim);
         init_optional_component::<IonizationTime>(sim);
     }
 }
@@ -173,6 +243,11 @@ struct Sweep<C: Chemistry> {
     directions: Directions,
     cells: Cells,
     sites: Sites<C>,
+    /// The timestep levels of the halo cells. This map is only used
+    /// for lookups and never iterated over, so that its iteration
+    /// order cannot affect the result. Use
+    /// [IterSorted](crate::hash_map::IterSorted) if it ever needs
+    /// to be.
     halo_levels: HashMap<ParticleId, TimestepLevel>,
     to_solve: PriorityQueue<Task>,
     to_send: DataByRank<Queue<RateData<C>>>,
@@ -187,17 +262,25 @@ struct Sweep<C: Chemistry> {
     chemistry: C,
     rank: Rank,
     timescale_counter: TimescaleCounter,
-    num_tasks_to_solve_before_send_receive: usize,
+    flux_limiter: FluxLimiter,
+    reconstruction: Reconstruction,
+    timescale_combination: TimescaleCombination,
+    transport_only: bool,
+    /// The counts of the timestep limiting processes during the
+    /// last step, kept for the time series after the counter is reset.
+    last_limiting_process_counts: Vec<(Process, usize)>,
+    task_batch_size: TaskBatchSizeTuner,
 }
 
 impl<C: Chemistry> Sweep<C> {
     fn new(
         directions: Directions,
         cells: HashMap<ParticleId, Cell>,
-        sites: HashMap<ParticleId, Site<C>>,
+        mut sites: HashMap<ParticleId, Site<C>>,
         halo_ids: Vec<ParticleId>,
         max_timestep: Time,
         timestep_safety_factor: Dimensionless,
+        significant_rate_threshold: units::PhotonRate,
         parameters: &SweepParameters,
         world_size: usize,
         world_rank: Rank,
@@ -208,9 +291,17 @@ impl<C: Chemistry> Sweep<C> {
         let timestep_state = TimestepState::new(max_timestep, parameters.num_timestep_levels);
         let halo_levels = halo_ids.into_iter().map(|id| (id, initial_level)).collect();
         let rank = communicator.rank();
+        if parameters.reconstruction == Reconstruction::Linear {
+            for site in sites.values_mut() {
+                site.slope = directions
+                    .enumerate()
+                    .map(|_| VecDimensionless::zero())
+                    .collect();
+            }
+        }
         Sweep {
-            cells: Cells::new(cells, parameters.num_timestep_levels, initial_level),
-            sites: Sites::<C>::new(sites, parameters.num_timestep_levels, initial_level),
+            cells: Cells::new(cells, rank, parameters.num_timestep_levels, initial_level),
+            sites: Sites::<C>::new(sites, rank, parameters.num_timestep_levels, initial_level),
             halo_levels,
             to_solve: PriorityQueue::new(),
             to_send: DataByRank::from_size_and_rank(world_size, world_rank),
@@ -224,10 +315,16 @@ impl<C: Chemistry> Sweep<C> {
             check_deadlock: parameters.check_deadlock,
             chemistry,
             rank,
-            significant_rate_threshold: parameters.significant_rate_threshold,
+            significant_rate_threshold,
             timescale_counter: TimescaleCounter::new(parameters.max_timestep),
-            num_tasks_to_solve_before_send_receive: parameters
-                .num_tasks_to_solve_before_send_receive,
+            flux_limiter: parameters.flux_limiter,
+            reconstruction: parameters.reconstruction,
+            timescale_combination: parameters.timescale_combination,
+            transport_only: parameters.transport_only,
+            last_limiting_process_counts: vec![],
+            task_batch_size: TaskBatchSizeTuner::new(
+                parameters.num_tasks_to_solve_before_send_receive,
+            ),
         }
     }
 
@@ -237,6 +334,34 @@ impl<C: Chemistry> Sweep<C> {
         count_communicator.all_gather_sum(&CellCount(local_count))
     }
 
+    /// The number of cells at exactly each timestep level, summed
+    /// over all ranks.
+    fn get_level_histogram_global(&mut self) -> Vec<usize> {
+        let mut count_communicator = MpiWorld::new_custom_tag(91101);
+        self.cells
+            .num_at_each_level()
+            .into_iter()
+            .map(|count| count_communicator.all_gather_sum(&CellCount(count)))
+            .collect()
+    }
+
+    /// The fractions of timesteps limited by each process during the
+    /// last step, over all ranks.
+    fn get_limiting_process_fractions_global(&mut self) -> LimitingProcessFractions {
+        let mut count_communicator = MpiWorld::new_custom_tag(91102);
+        let counts: Vec<_> = self
+            .last_limiting_process_counts
+            .iter()
+            .map(|(process, count)| {
+                (
+                    *process,
+                    count_communicator.all_gather_sum(&CellCount(*count)),
+                )
+            })
+            .collect();
+        LimitingProcessFractions::from_counts(&counts)
+    }
+
     fn get_cell_counts_per_level(&mut self) -> Vec<usize> {
         self.timestep_state
             .iter_all_levels()
@@ -264,6 +389,7 @@ impl<C: Chemistry> Sweep<C> {
                 self.single_sweep(timers);
             }
         }
+        self.last_limiting_process_counts = self.timescale_counter.counts();
         self.timescale_counter.show_timestep_limiting_processes();
         let time_elapsed = self.timestep_state.current_max_timestep();
         self.timestep_state.advance_allowed_levels();
@@ -298,18 +424,27 @@ impl<C: Chemistry> Sweep<C> {
                 .sum::<usize>()
                 > 0
         {
+            let start = Instant::now();
             if self.to_solve.is_empty() {
                 self.receive_all_messages();
             }
+            let receive_done = Instant::now();
             let mut num_solved = 0;
+            let batch_size = self.task_batch_size.batch_size();
             while let Some(task) = self.to_solve.pop() {
                 self.solve_task(task);
                 num_solved += 1;
-                if num_solved > self.num_tasks_to_solve_before_send_receive {
+                if num_solved > batch_size {
                     break;
                 }
             }
+            let solve_done = Instant::now();
             self.send_all_messages();
+            if self.task_batch_size.is_adaptive() {
+                let communication = (receive_done - start) + solve_done.elapsed();
+                self.task_batch_size
+                    .update(num_solved, solve_done - receive_done, communication);
+            }
         }
     }
 
@@ -416,16 +551,62 @@ impl<C: Chemistry> Sweep<C> {
         // be fine, but I could also see this causing numerical
         // instability problems, so I'd rather prevent it.
         site.incoming_total_rate[task.dir.0].make_positive();
-        let incoming_rate = site.get_rate(self.directions.len(), task.dir);
+        let incoming_rate = site.get_rate(&self.directions, task.dir);
         self.chemistry.get_outgoing_rate(cell, site, incoming_rate)
     }
 
+    /// The slope of the radiation field within the cell, estimated
+    /// from the flux densities (the outgoing rate per effective
+    /// downwind area) of its local upwind neighbours.
+    fn get_slope(&self, task: &Task, outgoing_rate: &Rate<C>) -> VecDimensionless {
+        if self.reconstruction == Reconstruction::Constant || *outgoing_rate <= Rate::<C>::zero() {
+            return VecDimensionless::zero();
+        }
+        let dir = &self.directions[task.dir];
+        let downwind_area = |cell: &Cell| -> FaceArea {
+            cell.iter_downwind_faces(dir)
+                .map(|face| face.area * face.normal.dot(**dir))
+                .sum()
+        };
+        let cell = self.cells.get(task.id);
+        let area = downwind_area(cell);
+        let upwind: Vec<_> = cell
+            .neighbours
+            .iter()
+            .filter(|(face, _)| face.points_upwind(dir))
+            .filter_map(|(face, neighbour)| match neighbour {
+                ParticleType::Local(id) => {
+                    let neighbour_cell = self.cells.get(*id);
+                    let neighbour_rate = &self.sites.get(*id).outgoing_total_rate[task.dir.0];
+                    let ratio = neighbour_rate.ratio_to(outgoing_rate)
+                        * (area / downwind_area(neighbour_cell));
+                    Some(UpwindNeighbour {
+                        relative_difference: ratio - Dimensionless::dimensionless(1.0),
+                        normal: face.normal,
+                        area: face.area,
+                        distance: (cell.size + neighbour_cell.size) / cell.size,
+                    })
+                }
+                _ => None,
+            })
+            .collect();
+        self.reconstruction.slope(
+            &upwind,
+            cell.iter_downwind_faces(dir).map(|face| face.normal),
+        )
+    }
+
     fn solve_task(&mut self, task: Task) {
         let outgoing_rate = self.get_outgoing_rate(&task);
+        let slope = self.get_slope(&task, &outgoing_rate);
         let site = self.sites.get_mut(task.id);
-        let outgoing_rate_correction =
-            outgoing_rate.clone() - site.outgoing_total_rate[task.dir.0].clone();
-        site.outgoing_total_rate[task.dir.0] = outgoing_rate;
+        let previous_rate = site.outgoing_total_rate[task.dir.0].clone();
+        let previous_slope = site.get_slope(task.dir);
+        let outgoing_rate_correction = outgoing_rate.clone() - previous_rate.clone();
+        site.outgoing_total_rate[task.dir.0] = outgoing_rate.clone();
+        if let Some(site_slope) = site.slope.get_mut(task.dir.0) {
+            *site_slope = slope;
+        }
         self.to_solve_count.reduce(task.dir);
         // I'd like to apologize. The reason for this unsafe garbage
         // is that the borrow checker cannot see that both
@@ -450,15 +631,21 @@ impl<C: Chemistry> Sweep<C> {
         };
         let dir = &self.directions[task.dir];
         let cell = &self.cells.get(task.id);
-        let total_effective_area: FaceArea = cell
-            .iter_downwind_faces(dir)
-            .map(|face| face.area * face.normal.dot(**dir))
-            .sum();
+        let shares = FaceShares::new(self.flux_limiter, cell, dir, slope);
+        // If the slope changed, the rates which were previously
+        // passed on were distributed differently among the faces, so
+        // the correction differs from face to face.
+        let previous_shares = (previous_slope != slope)
+            .then(|| FaceShares::new(self.flux_limiter, cell, dir, previous_slope));
         for (face, neighbour) in cell.neighbours.iter() {
             if face.points_downwind(dir) {
-                let effective_area = face.area * face.normal.dot(**dir);
-                let rate_correction_this_cell =
-                    outgoing_rate_correction.clone() * (effective_area / total_effective_area);
+                let rate_correction_this_cell = match &previous_shares {
+                    None => outgoing_rate_correction.clone() * shares.fraction(face),
+                    Some(previous_shares) => {
+                        outgoing_rate.clone() * shares.fraction(face)
+                            - previous_rate.clone() * previous_shares.fraction(face)
+                    }
+                };
                 match neighbour {
                     ParticleType::Local(neighbour_id) => this.handle_local_neighbour(
                         rate_correction_this_cell,
@@ -546,30 +733,52 @@ impl<C: Chemistry> Sweep<C> {
         }
     }
 
+    /// Updates the chemistry of all active cells. The cells are
+    /// independent of each other, so this is done in parallel. With
+    /// `transport_only`, the abundances are left untouched and only
+    /// the photon rate timescale is computed.
     fn update_chemistry(&mut self, timers: &mut Performance) {
         let _timer = timers.time("chemistry");
-        for (id, cell) in self.cells.enumerate_active(self.current_level) {
-            let (level, site) = self.sites.get_mut_with_level(id);
-            let timestep = self.timestep_state.timestep_at_level(level);
-            let rate: Rate<C> = self
-                .directions
-                .enumerate()
-                .map(|(dir, _)| site.get_rate(self.directions.len(), dir))
-                .sum();
-            let relative_change = if rate.below_threshold(self.significant_rate_threshold) {
-                0.0.into()
-            } else {
-                rate.relative_change_to(&site.previous_incoming_total_rate)
-                    .abs()
-            };
-            site.previous_incoming_total_rate = rate.clone();
-            let rate_timescale = Timescale::photon_rate(timestep / relative_change);
-            let chemistry_timescale =
-                self.chemistry
-                    .update_abundances(site, rate, timestep, cell.volume, cell.size);
-            let change_timescale = rate_timescale.min(chemistry_timescale);
-            site.change_timescale = change_timescale.time;
-            self.timescale_counter.count(change_timescale);
+        let cells = &self.cells;
+        let directions = &self.directions;
+        let chemistry = &self.chemistry;
+        let timestep_state = &self.timestep_state;
+        let significant_rate_threshold = self.significant_rate_threshold;
+        let timescale_combination = self.timescale_combination;
+        let transport_only = self.transport_only;
+        let timescales = self
+            .sites
+            .par_map_active_mut(self.current_level, |id, level, site| {
+                let cell = cells.get(id);
+                let timestep = timestep_state.timestep_at_level(level);
+                let rate: Rate<C> = directions
+                    .enumerate()
+                    .map(|(dir, _)| site.get_rate(directions, dir))
+                    .sum();
+                let relative_change = if rate.below_threshold(significant_rate_threshold) {
+                    0.0.into()
+                } else {
+                    rate.relative_change_to(&site.previous_incoming_total_rate)
+                        .abs()
+                };
+                site.previous_incoming_total_rate = rate.clone();
+                // The abundances are still the ones with which the
+                // outgoing rates were computed during the sweep.
+                site.absorbed_rate = chemistry.get_absorbed_rate(cell, site, rate.clone());
+                let rate_timescale = Timescale::photon_rate(timestep / relative_change);
+                if transport_only {
+                    site.change_timescale = rate_timescale.time;
+                    return rate_timescale;
+                }
+                let chemistry_timescale =
+                    chemistry.update_abundances(site, rate, timestep, cell.volume, cell.size);
+                let change_timescale =
+                    timescale_combination.combine(rate_timescale, chemistry_timescale);
+                site.change_timescale = change_timescale.time;
+                change_timescale
+            });
+        for timescale in timescales {
+            self.timescale_counter.count(timescale);
         }
     }
 
@@ -616,17 +825,31 @@ impl Sweep<HydrogenOnly> {
         let rate: Rate<HydrogenOnly> = self
             .directions
             .enumerate()
-            .map(|(dir, _)| site.get_rate(self.directions.len(), dir))
+            .map(|(dir, _)| site.get_rate(&self.directions, dir))
             .sum();
         Solver {
             ionized_hydrogen_fraction: site.species.ionized_hydrogen_fraction,
             temperature: site.species.temperature,
             density: site.density,
-            volume: cell.volume,
+            volume: self.chemistry.chemistry_volume(cell.volume),
             length: cell.size,
             rate,
             scale_factor: scale_factor,
             floor: None,
+            recombination: self.chemistry.recombination,
+            temperature_limits: self.chemistry.temperature_limits,
+            max_optical_depth: self.chemistry.max_optical_depth,
+            hydrogen_mass_fraction: self.chemistry.hydrogen_mass_fraction,
+        }
+    }
+}
+
+impl<C: Chemistry> Sweep<C> {
+    /// Rescales the densities of all sites, for example to account
+    /// for the expansion of the universe.
+    fn dilute_densities(&mut self, factor: Dimensionless) {
+        for site in self.sites.iter_mut() {
+            site.density = site.density * factor;
         }
     }
 }
@@ -642,6 +865,7 @@ fn init_sweep_system(
         &components::Temperature,
         &Source,
     )>,
+    prevent_cooling_query: Particles<&PreventCooling>,
     haloes: HaloParticles<&ParticleId>,
     sweep_parameters: Res<SweepParameters>,
     world_rank: Res<WorldRank>,
@@ -656,12 +880,20 @@ fn init_sweep_system(
     let sites: HashMap<_, _> = sites_query
         .iter()
         .map(
-            |(_, id, density, ionized_hydrogen_fraction, temperature, source)| {
+            |(entity, id, density, ionized_hydrogen_fraction, temperature, source)| {
+                let prevent_cooling = prevent_cooling_query
+                    .get(entity)
+                    .map(|prevent_cooling| **prevent_cooling)
+                    .unwrap_or(sweep_parameters.prevent_cooling);
                 (
                     *id,
                     Site::<HydrogenOnly>::new(
                         &directions,
-                        HydrogenOnlySpecies::new(**ionized_hydrogen_fraction, **temperature),
+                        HydrogenOnlySpecies::new(
+                            **ionized_hydrogen_fraction,
+                            **temperature,
+                            prevent_cooling,
+                        ),
                         **density,
                         **source,
                     ),
@@ -670,8 +902,18 @@ fn init_sweep_system(
         )
         .collect();
     let halo_ids: Vec<_> = haloes.iter().copied().collect();
-    #[cfg(test)]
-    assert!(!cells.is_empty() && !sites.is_empty());
+    let significant_rate_threshold = match sweep_parameters.significant_rate_threshold {
+        RateThreshold::Absolute(rate) => rate,
+        threshold @ RateThreshold::Relative(_) => {
+            let local_source_rate: SourceRate = sites_query
+                .iter()
+                .map(|(_, _, _, _, _, source)| **source)
+                .sum();
+            let total_source_rate: SourceRate =
+                MpiWorld::<SourceRate>::new().all_gather_sum(&local_source_rate);
+            threshold.resolve(total_source_rate)
+        }
+    };
     *solver = Some(Sweep::new(
         directions,
         cells,
@@ -679,14 +921,24 @@ fn init_sweep_system(
         halo_ids,
         sweep_parameters.max_timestep,
         sweep_parameters.timestep_safety_factor,
+        significant_rate_threshold,
         &sweep_parameters,
         **world_size,
         **world_rank,
         HydrogenOnly {
-            rate_threshold: sweep_parameters.significant_rate_threshold,
+            rate_threshold: significant_rate_threshold,
             scale_factor: cosmology.scale_factor(),
             timestep_safety_factor: sweep_parameters.chemistry_timestep_safety_factor,
-            prevent_cooling: sweep_parameters.prevent_cooling,
+            max_depth: sweep_parameters.chemistry_max_depth,
+            max_optical_depth: sweep_parameters.max_optical_depth,
+            hydrogen_mass_fraction: sweep_parameters.hydrogen_mass_fraction,
+            recombination: sweep_parameters.recombination,
+            temperature_limits: TemperatureLimits {
+                floor: sweep_parameters.temperature_floor,
+                ceiling: sweep_parameters.temperature_ceiling,
+            },
+            #[cfg(feature = "2d")]
+            slab_thickness: sweep_parameters.slab_thickness,
         },
     ));
 }
@@ -699,11 +951,14 @@ fn run_sweep_system(
         &mut components::Temperature,
     )>,
     mut timesteps: Particles<(&ParticleId, &mut Timestep)>,
+    mut sweep_timesteps: Particles<(&ParticleId, &mut SweepTimestep)>,
     mut ionization_times: Particles<(&ParticleId, &mut IonizationTime)>,
     mut rates: Particles<(&ParticleId, &mut components::PhotonRate)>,
     mut time: ResMut<SimulationTime>,
     mut timers: NonSendMut<Performance>,
     mut is_first: ResMut<IsFirstTime>,
+    mut densities: Particles<(&ParticleId, &mut Density)>,
+    cosmology: Res<Cosmology>,
 ) {
     // This is a slightly hacky way of making sure that we can output
     // the ICS. The first time this system would run, it doesn't run so that
@@ -714,7 +969,15 @@ fn run_sweep_system(
     }
     let solver = (*solver).as_mut().unwrap();
     let time_elapsed = solver.run_sweeps(&mut timers);
+    let dilution_factor = cosmology.density_dilution_factor(**time, **time + time_elapsed);
     **time += time_elapsed;
+    solver.chemistry.scale_factor = cosmology.scale_factor_at(**time);
+    if *dilution_factor != 1.0 {
+        solver.dilute_densities(dilution_factor);
+        for (id, mut density) in densities.iter_mut() {
+            **density = solver.sites.get(*id).density;
+        }
+    }
     for (id, mut fraction, mut temperature) in sites.iter_mut() {
         let site = solver.sites.get_mut(*id);
         **fraction = site.species.ionized_hydrogen_fraction;
@@ -724,6 +987,10 @@ fn run_sweep_system(
         let site = solver.sites.get(*id);
         **timestep = site.species.timestep;
     }
+    for (id, mut timestep) in sweep_timesteps.iter_mut() {
+        let level = solver.cells.get_level(*id);
+        **timestep = solver.timestep_state.timestep_at_level(level);
+    }
     for (id, mut rate) in rates.iter_mut() {
         let site = solver.sites.get(*id);
         **rate = site.incoming_total_rate.iter().copied().sum();
@@ -787,7 +1054,7 @@ pub fn initialize_sweep_test_components_system(
     for entity in local_particles.iter() {
         commands.entity(entity).insert((
             // It really doesnt matter as long as nothing crashes
-            Density(Mass::kilograms(1.0e-10) / Volume::cubic_centimeters(1.0)),
+            Density(Mass::kilograms(1.0e-10) / Volume3D::cubic_centimeters(1.0)),
             components::IonizedHydrogenFraction(Dimensionless::dimensionless(1e-10)),
             components::Temperature(Temperature::kelvins(1000.0)),
             Source(SourceRate::zero()),
@@ -1,11 +1,21 @@
 use derive_custom::subsweep_parameters;
 
+use super::flux_limiter::FluxLimiter;
+use super::reconstruction::Reconstruction;
+use crate::chemistry::hydrogen_only::RecombinationCase;
+use crate::chemistry::hydrogen_only::DEFAULT_HYDROGEN_MASS_FRACTION;
+use crate::chemistry::hydrogen_only::DEFAULT_MAX_DEPTH;
+use crate::chemistry::timescale::TimescaleCombination;
 use crate::units::Dimensionless;
+#[cfg(feature = "2d")]
+use crate::units::Length;
 use crate::units::PhotonRate;
+use crate::units::SourceRate;
+use crate::units::Temperature;
 use crate::units::Time;
 use crate::units::VecDimensionless;
 
-#[subsweep_parameters("sweep")]
+#[subsweep_parameters("sweep", validate_ranges)]
 pub struct SweepParameters {
     /// The number (or concrete list) of directions to use in the
     /// sweep.
@@ -20,29 +30,208 @@ pub struct SweepParameters {
     /// Whether to rotate the direction bins after every (full) sweep step.
     #[serde(default = "default_rotate_directions")]
     pub rotate_directions: bool,
+    /// Rates below this threshold are treated as zero.
     #[serde(default)]
-    pub significant_rate_threshold: PhotonRate,
+    pub significant_rate_threshold: RateThreshold,
     #[serde(default = "default_timestep_factor")]
     pub timestep_safety_factor: Dimensionless,
     #[serde(default = "default_timestep_factor")]
     pub chemistry_timestep_safety_factor: Dimensionless,
+    /// The maximum number of times the chemistry solver halves its
+    /// timestep before giving up on a cell.
+    #[serde(default = "default_chemistry_max_depth")]
+    pub chemistry_max_depth: usize,
     /// Whether to run a deadlock check before each sweep. Potentially
     /// heavy impact on performance, should only be used during
     /// debugging.
     #[serde(default)]
     pub check_deadlock: bool,
-    /// If true, temperatures and ionization fractions will always be kept above the
-    /// values in the ICS (which makes sense for overdense regions which would be kept
-    /// ionized and heated by feedback processes which are not modelled in subsweep).
+    /// Whether to log the photon budget of the whole box after
+    /// every step, i.e. the fraction of photons emitted by sources
+    /// that is neither absorbed nor leaves the box. Useful to
+    /// validate the sweep.
+    #[serde(default)]
+    pub check_photon_conservation: bool,
+    /// If true, the temperature and ionized fraction of a cell are
+    /// not allowed to drop below their values at the start of each
+    /// chemistry substep, so they can only ever increase. This makes
+    /// sense for overdense regions which would be kept ionized and
+    /// heated by feedback processes which are not modelled in
+    /// subsweep.
     #[serde(default = "default_prevent_cooling")]
     pub prevent_cooling: bool,
+    /// If given, the temperature is never allowed to drop below this
+    /// value during the chemistry update, regardless of
+    /// `prevent_cooling`.
+    #[serde(default)]
+    pub temperature_floor: Option<Temperature>,
+    /// If given, the temperature is never allowed to rise above this
+    /// value during the chemistry update.
+    #[serde(default)]
+    pub temperature_ceiling: Option<Temperature>,
+    /// If given, the optical depth of every cell is capped at this
+    /// value in both the transport and the chemistry. This keeps the
+    /// chemistry solver stable in extremely dense cells, at the cost
+    /// of letting some radiation leak through them.
+    #[serde(default)]
+    pub max_optical_depth: Option<Dimensionless>,
+    /// The fraction of the gas mass in hydrogen, which determines
+    /// the hydrogen number density of a cell with a given density.
+    /// The rest of the mass is treated as neutral helium.
+    #[serde(default = "default_hydrogen_mass_fraction")]
+    pub hydrogen_mass_fraction: Dimensionless,
+    /// How the outgoing rate of a cell is distributed among its
+    /// downwind faces.
+    #[serde(default)]
+    pub flux_limiter: FluxLimiter,
+    /// How the radiation field within a cell is reconstructed when
+    /// the outgoing rate is distributed among its downwind faces.
+    #[serde(default)]
+    pub reconstruction: Reconstruction,
+    /// How the photon rate timescale and the chemistry timescale are
+    /// combined to determine the timestep of a cell.
+    #[serde(default)]
+    pub timescale_combination: TimescaleCombination,
+    /// Whether to use case A or case B recombination rates in the
+    /// chemistry.
+    #[serde(default)]
+    pub recombination: RecombinationCase,
+    /// If true, the abundances and temperatures are never updated
+    /// and only the photon rates are transported, which is useful
+    /// for testing the transport in isolation. The timesteps are
+    /// then determined by the photon rate timescale alone.
+    #[serde(default)]
+    pub transport_only: bool,
+    /// If true, the radius and velocity of the ionization front
+    /// around every source are written to the time series.
+    #[serde(default)]
+    pub track_ionization_fronts: bool,
+    /// The thickness of the slab that every cell represents in 2D
+    /// runs. Only used to convert cell areas into volumes in the
+    /// chemistry.
+    #[cfg(feature = "2d")]
+    pub slab_thickness: Length,
     /// The number of tasks to solve before sending/receiving
     /// outgoing/incoming fluxes.  Low numbers reduce serial
     /// performance, high numbers can reduce parallel performance
     /// because downstream cores (or central cores) will need to wait
-    /// for incoming tasks for too long.
+    /// for incoming tasks for too long. Either a fixed number or
+    /// `auto`, in which case the number is adapted during the run.
     #[serde(default = "default_num_tasks_to_solve_before_send_receive")]
-    pub num_tasks_to_solve_before_send_receive: usize,
+    pub num_tasks_to_solve_before_send_receive: TaskBatchSize,
+}
+
+impl SweepParameters {
+    fn range_warnings(&self) -> Vec<String> {
+        let mut warnings = vec![];
+        if self.max_timestep <= Time::zero() {
+            warnings.push(format!(
+                "max_timestep should be positive, but is {:?}",
+                self.max_timestep
+            ));
+        }
+        if self.num_timestep_levels == 0 {
+            warnings.push("num_timestep_levels should be at least 1".into());
+        }
+        for (name, factor) in [
+            ("timestep_safety_factor", self.timestep_safety_factor),
+            (
+                "chemistry_timestep_safety_factor",
+                self.chemistry_timestep_safety_factor,
+            ),
+        ] {
+            if factor.value() <= 0.0 || factor.value() > 1.0 {
+                warnings.push(format!(
+                    "{} should be between 0 and 1, but is {}",
+                    name,
+                    factor.value()
+                ));
+            }
+        }
+        if let (Some(floor), Some(ceiling)) = (self.temperature_floor, self.temperature_ceiling) {
+            if floor > ceiling {
+                warnings.push(format!(
+                    "temperature_floor ({:?}) is above temperature_ceiling ({:?})",
+                    floor, ceiling
+                ));
+            }
+        }
+        let hydrogen_mass_fraction = self.hydrogen_mass_fraction.value();
+        if hydrogen_mass_fraction <= 0.0 || hydrogen_mass_fraction > 1.0 {
+            warnings.push(format!(
+                "hydrogen_mass_fraction should be above 0 and at most 1, but is {}",
+                hydrogen_mass_fraction
+            ));
+        }
+        if let Some(max_optical_depth) = self.max_optical_depth {
+            if max_optical_depth.value() <= 0.0 {
+                warnings.push(format!(
+                    "max_optical_depth should be positive, but is {}",
+                    max_optical_depth.value()
+                ));
+            }
+        }
+        if let TimescaleCombination::Weighted(weight) = self.timescale_combination {
+            if !(0.0..=1.0).contains(&weight) {
+                warnings.push(format!(
+                    "The weight of timescale_combination should be between 0 and 1, but is {}",
+                    weight
+                ));
+            }
+        }
+        warnings
+    }
+}
+
+/// A threshold for the photon rates, given either as an absolute
+/// rate (with units) or as a fraction (a plain number) of the total
+/// luminosity of all sources.
+#[derive(Copy, Debug)]
+#[subsweep_parameters]
+#[serde(untagged)]
+pub enum RateThreshold {
+    Absolute(PhotonRate),
+    Relative(Dimensionless),
+}
+
+impl Default for RateThreshold {
+    fn default() -> Self {
+        Self::Absolute(PhotonRate::zero())
+    }
+}
+
+impl RateThreshold {
+    /// The absolute threshold, given the total luminosity of all
+    /// sources.
+    pub fn resolve(&self, total_source_rate: SourceRate) -> PhotonRate {
+        match self {
+            RateThreshold::Absolute(rate) => *rate,
+            RateThreshold::Relative(fraction) => total_source_rate * *fraction,
+        }
+    }
+}
+
+/// The number of tasks to solve before sending/receiving. Given
+/// either as a plain number or as `auto`.
+#[derive(Copy, Debug, PartialEq, Eq)]
+#[subsweep_parameters]
+#[serde(untagged)]
+pub enum TaskBatchSize {
+    Fixed(usize),
+    Auto(AutoTaskBatchSize),
+}
+
+/// Only exists so that `auto` can be given as a plain string.
+#[derive(Copy, Debug, PartialEq, Eq)]
+#[subsweep_parameters]
+pub enum AutoTaskBatchSize {
+    Auto,
+}
+
+impl TaskBatchSize {
+    pub fn auto() -> Self {
+        Self::Auto(AutoTaskBatchSize::Auto)
+    }
 }
 
 #[subsweep_parameters]
@@ -50,6 +239,15 @@ pub struct SweepParameters {
 pub enum DirectionsSpecification {
     Num(usize),
     Explicit(Vec<VecDimensionless>),
+    /// Directions which cover unequal solid angles. The weights are
+    /// normalized to sum to one.
+    Weighted(Vec<WeightedDirection>),
+}
+
+#[subsweep_parameters]
+pub struct WeightedDirection {
+    pub direction: VecDimensionless,
+    pub weight: f64,
 }
 
 impl DirectionsSpecification {
@@ -57,6 +255,7 @@ impl DirectionsSpecification {
         match self {
             DirectionsSpecification::Num(num) => *num,
             DirectionsSpecification::Explicit(directions) => directions.len(),
+            DirectionsSpecification::Weighted(directions) => directions.len(),
         }
     }
 }
@@ -69,10 +268,20 @@ fn default_timestep_factor() -> Dimensionless {
     Dimensionless::percent(10.0)
 }
 
+fn default_chemistry_max_depth() -> usize {
+    DEFAULT_MAX_DEPTH
+}
+
+fn default_hydrogen_mass_fraction() -> Dimensionless {
+    DEFAULT_HYDROGEN_MASS_FRACTION.into()
+}
+
 fn default_prevent_cooling() -> bool {
     true
 }
 
-pub fn default_num_tasks_to_solve_before_send_receive() -> usize {
-    10000
+pub const DEFAULT_NUM_TASKS_TO_SOLVE_BEFORE_SEND_RECEIVE: usize = 10000;
+
+pub fn default_num_tasks_to_solve_before_send_receive() -> TaskBatchSize {
+    TaskBatchSize::Fixed(DEFAULT_NUM_TASKS_TO_SOLVE_BEFORE_SEND_RECEIVE)
 }
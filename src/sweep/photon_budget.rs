@@ -0,0 +1,131 @@
+use bevy_ecs::prelude::*;
+use log::debug;
+use log::info;
+
+use super::grid::ParticleType;
+use super::reconstruction::FaceShares;
+use super::time_series::compute_global_sum;
+use super::time_series::EscapeFraction;
+use super::Sweep;
+use crate::chemistry::hydrogen_only::HydrogenOnly;
+use crate::units::Dimensionless;
+use crate::units::PhotonRate;
+
+/// The total photon rates entering and leaving the box, summed over
+/// all cells and directions. In a box without errors in the flux
+/// bookkeeping, everything that is emitted by sources or re-enters
+/// through periodic boundaries is either absorbed or leaves the box.
+#[derive(Debug, Clone)]
+pub(super) struct PhotonBudget {
+    pub source: PhotonRate,
+    pub absorbed: PhotonRate,
+    pub leaving_through_boundary: PhotonRate,
+    pub leaving_through_periodic_boundary: PhotonRate,
+    pub entering_through_periodic_boundary: PhotonRate,
+}
+
+impl PhotonBudget {
+    pub fn global_sum(&self) -> Self {
+        let sum = |rate: PhotonRate| compute_global_sum(std::iter::once(rate));
+        Self {
+            source: sum(self.source),
+            absorbed: sum(self.absorbed),
+            leaving_through_boundary: sum(self.leaving_through_boundary),
+            leaving_through_periodic_boundary: sum(self.leaving_through_periodic_boundary),
+            entering_through_periodic_boundary: sum(self.entering_through_periodic_boundary),
+        }
+    }
+
+    /// The photons which are unaccounted for, as a fraction of the
+    /// total source rate.
+    pub fn residual(&self) -> Dimensionless {
+        let incoming = self.source + self.entering_through_periodic_boundary;
+        let outgoing =
+            self.absorbed + self.leaving_through_boundary + self.leaving_through_periodic_boundary;
+        (incoming - outgoing) / self.source
+    }
+}
+
+impl Sweep<HydrogenOnly> {
+    /// The photon budget of the cells on this rank. The absorbed
+    /// rate of a cell is the one the chemistry used to ionize
+    /// hydrogen, which is independent of the outgoing rates.
+    pub(super) fn local_photon_budget(&self) -> PhotonBudget {
+        let mut budget = PhotonBudget {
+            source: PhotonRate::zero(),
+            absorbed: PhotonRate::zero(),
+            leaving_through_boundary: PhotonRate::zero(),
+            leaving_through_periodic_boundary: PhotonRate::zero(),
+            entering_through_periodic_boundary: PhotonRate::zero(),
+        };
+        for (id, _, cell) in self.cells.enumerate_with_levels() {
+            let site = self.sites.get(id);
+            budget.absorbed += site.absorbed_rate;
+            for (dir_index, dir) in self.directions.enumerate() {
+                let outgoing = site.outgoing_total_rate[dir_index.0];
+                budget.source += site.source_per_direction_bin(&self.directions, dir_index);
+                budget.entering_through_periodic_boundary += site.periodic_source[dir_index.0];
+                let shares =
+                    FaceShares::new(self.flux_limiter, cell, dir, site.get_slope(dir_index));
+                for (face, neighbour) in cell.neighbours.iter() {
+                    if !face.points_downwind(dir) {
+                        continue;
+                    }
+                    let fraction = shares.fraction(face);
+                    match neighbour {
+                        ParticleType::Boundary => {
+                            budget.leaving_through_boundary += outgoing * fraction
+                        }
+                        ParticleType::LocalPeriodic(_) | ParticleType::RemotePeriodic(_) => {
+                            budget.leaving_through_periodic_boundary += outgoing * fraction
+                        }
+                        ParticleType::Local(_) | ParticleType::Remote(_) => {}
+                    }
+                }
+            }
+        }
+        budget
+    }
+
+    /// The rate leaving the box through non-periodic boundaries as a
+    /// fraction of the total source rate, over all ranks. Zero if
+    /// there are no sources.
+    pub(super) fn escape_fraction_global(&self) -> Dimensionless {
+        let budget = self.local_photon_budget().global_sum();
+        if budget.source == PhotonRate::zero() {
+            return Dimensionless::zero();
+        }
+        budget.leaving_through_boundary / budget.source
+    }
+}
+
+pub(super) fn check_photon_conservation_system(solver: NonSend<Option<Sweep<HydrogenOnly>>>) {
+    let solver = (*solver).as_ref().unwrap();
+    let budget = solver.local_photon_budget().global_sum();
+    if budget.source == PhotonRate::zero() {
+        return;
+    }
+    info!(
+        "Photon conservation: residual {:.3e} of source rate ({:.2}% absorbed, {:.2}% leaving the box)",
+        budget.residual().value(),
+        (budget.absorbed / budget.source).in_percent(),
+        ((budget.leaving_through_boundary + budget.leaving_through_periodic_boundary
+            - budget.entering_through_periodic_boundary)
+            / budget.source)
+            .in_percent(),
+    );
+}
+
+pub(super) fn escape_fraction_system(
+    solver: NonSend<Option<Sweep<HydrogenOnly>>>,
+    mut writer: EventWriter<EscapeFraction>,
+) {
+    let solver = (*solver).as_ref().unwrap();
+    let escape_fraction = solver.escape_fraction_global();
+    debug!(
+        "{:<41}: {:.2}%",
+        "Escape fraction",
+        escape_fraction.in_percent()
+    );
+    writer.send(EscapeFraction(escape_fraction));
+}
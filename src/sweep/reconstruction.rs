@@ -0,0 +1,252 @@
+use derive_custom::subsweep_parameters;
+
+use super::flux_limiter::DownwindWeights;
+use super::flux_limiter::FluxLimiter;
+use super::grid::Cell;
+use super::grid::Face;
+use super::grid::FaceArea;
+use crate::units::Dimensionless;
+use crate::units::MVec;
+use crate::units::VecDimensionless;
+
+#[cfg(feature = "2d")]
+type Matrix = glam::DMat2;
+#[cfg(feature = "3d")]
+type Matrix = glam::DMat3;
+
+/// Regularizes the least squares problem for the slope when the
+/// normals of the upwind faces do not span all dimensions (as for
+/// directions along the axes of a cartesian grid), relative to the
+/// trace of the matrix.
+const REGULARIZATION: f64 = 1e-8;
+
+/// How the radiation field within a cell is reconstructed when the
+/// outgoing rate is distributed among its downwind faces.
+#[derive(Copy, Debug, Default, PartialEq, Eq)]
+#[subsweep_parameters]
+pub enum Reconstruction {
+    /// The radiation field is constant within each cell, which is
+    /// first order accurate.
+    #[default]
+    Constant,
+    /// The radiation field varies linearly within each cell. The
+    /// slope is estimated from the flux densities of the local
+    /// upwind neighbours. It is limited such that the reconstructed
+    /// values at the downwind faces remain positive and do not
+    /// deviate further from the value of the cell than any of the
+    /// upwind neighbours does. This keeps ionization fronts and
+    /// shadows sharper.
+    Linear,
+}
+
+/// An upwind neighbour of a cell, as seen by the slope estimate.
+pub(super) struct UpwindNeighbour {
+    /// The flux density of the neighbour relative to that of the
+    /// cell, minus one.
+    pub relative_difference: Dimensionless,
+    /// The normal of the face shared with the neighbour, pointing
+    /// towards the neighbour.
+    pub normal: VecDimensionless,
+    pub area: FaceArea,
+    /// The distance to the neighbour, in units of half the size of
+    /// the cell.
+    pub distance: Dimensionless,
+}
+
+impl Reconstruction {
+    /// The slope of the relative flux density within the cell, in
+    /// units of half the size of the cell, such that the flux
+    /// density at a face with normal `n` is `1 + slope * n` times
+    /// the flux density of the cell.
+    pub(super) fn slope(
+        &self,
+        upwind: &[UpwindNeighbour],
+        downwind_normals: impl Iterator<Item = VecDimensionless>,
+    ) -> VecDimensionless {
+        match self {
+            Reconstruction::Constant => VecDimensionless::zero(),
+            Reconstruction::Linear => {
+                let slope = least_squares_slope(upwind);
+                let max_difference = upwind
+                    .iter()
+                    .map(|neighbour| neighbour.relative_difference.value().abs())
+                    .fold(0.0, f64::max);
+                slope * limiter(slope, max_difference, downwind_normals)
+            }
+        }
+    }
+}
+
+/// Finds the slope which best reproduces the differences to the
+/// upwind neighbours.
+fn least_squares_slope(upwind: &[UpwindNeighbour]) -> VecDimensionless {
+    let mut matrix = Matrix::ZERO;
+    let mut rhs = MVec::ZERO;
+    let mut trace = 0.0;
+    for neighbour in upwind.iter() {
+        let normal = neighbour.normal.value_unchecked();
+        let distance = neighbour.distance.value();
+        let weight = neighbour.area.value_unchecked();
+        matrix += outer(normal) * (weight * distance * distance);
+        rhs += normal * (weight * distance * neighbour.relative_difference.value());
+        trace += weight * distance * distance * normal.length_squared();
+    }
+    if trace == 0.0 {
+        return VecDimensionless::zero();
+    }
+    let matrix = matrix + Matrix::IDENTITY * (trace * REGULARIZATION);
+    VecDimensionless::new_unchecked(matrix.inverse() * rhs)
+}
+
+#[cfg(feature = "2d")]
+fn outer(v: MVec) -> Matrix {
+    Matrix::from_cols(v * v.x, v * v.y)
+}
+
+#[cfg(feature = "3d")]
+fn outer(v: MVec) -> Matrix {
+    Matrix::from_cols(v * v.x, v * v.y, v * v.z)
+}
+
+/// The factor (between 0 and 1) by which the slope is reduced. This
+/// is a variant of the Barth-Jespersen limiter. The flux densities of
+/// the downwind neighbours are not known when a cell is solved, so
+/// the bounds are mirrored from the upwind neighbours.
+fn limiter(
+    slope: VecDimensionless,
+    max_difference: f64,
+    downwind_normals: impl Iterator<Item = VecDimensionless>,
+) -> f64 {
+    downwind_normals
+        .map(|normal| {
+            let change = slope.dot(normal).value();
+            if change > 0.0 {
+                (max_difference / change).min(1.0)
+            } else if change < 0.0 {
+                (max_difference.min(1.0) / -change).min(1.0)
+            } else {
+                1.0
+            }
+        })
+        .fold(1.0, f64::min)
+}
+
+fn effective_area(face: &Face, dir: &VecDimensionless, slope: VecDimensionless) -> FaceArea {
+    face.area * face.normal.dot(*dir) * (1.0 + slope.dot(face.normal).value())
+}
+
+/// The fractions of the outgoing rate of a cell which are passed
+/// through each of its downwind faces, taking the reconstructed
+/// radiation field into account.
+pub(super) struct FaceShares {
+    weights: DownwindWeights,
+    dir: VecDimensionless,
+    slope: VecDimensionless,
+}
+
+impl FaceShares {
+    pub fn new(
+        limiter: FluxLimiter,
+        cell: &Cell,
+        dir: &VecDimensionless,
+        slope: VecDimensionless,
+    ) -> Self {
+        let weights = DownwindWeights::new(limiter, move || {
+            cell.iter_downwind_faces(dir)
+                .map(move |face| effective_area(face, dir, slope))
+        });
+        Self {
+            weights,
+            dir: *dir,
+            slope,
+        }
+    }
+
+    pub fn fraction(&self, face: &Face) -> Dimensionless {
+        self.weights
+            .fraction(effective_area(face, &self.dir, self.slope))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Reconstruction;
+    use super::UpwindNeighbour;
+    use crate::sweep::grid::FaceArea;
+    use crate::units::Dimensionless;
+    use crate::units::MVec;
+    use crate::units::VecDimensionless;
+
+    fn normal(v: MVec) -> VecDimensionless {
+        VecDimensionless::new_unchecked(v)
+    }
+
+    /// The relative error of the flux densities reconstructed at the
+    /// two downwind faces of a cell on a cartesian grid with cell
+    /// size `h`, for a diagonal direction through a smooth field.
+    fn face_error(reconstruction: Reconstruction, h: f64) -> f64 {
+        let field = |pos: MVec| (pos.x + 0.5 * pos.y).exp();
+        let center = MVec::ZERO;
+        let value = field(center);
+        let axes = [MVec::X, MVec::Y];
+        let upwind: Vec<_> = axes
+            .iter()
+            .map(|axis| UpwindNeighbour {
+                relative_difference: Dimensionless::dimensionless(
+                    field(center - *axis * h) / value - 1.0,
+                ),
+                normal: normal(-*axis),
+                area: FaceArea::new_unchecked(1.0),
+                distance: Dimensionless::dimensionless(2.0),
+            })
+            .collect();
+        let slope = reconstruction.slope(&upwind, axes.iter().map(|axis| normal(*axis)));
+        axes.iter()
+            .map(|axis| {
+                let reconstructed = value * (1.0 + slope.dot(normal(*axis)).value());
+                let exact = field(center + *axis * h * 0.5);
+                ((reconstructed - exact) / exact).abs()
+            })
+            .fold(0.0, f64::max)
+    }
+
+    fn convergence_order(reconstruction: Reconstruction) -> f64 {
+        let errors: Vec<_> = [0.1, 0.05, 0.025]
+            .into_iter()
+            .map(|h| face_error(reconstruction, h))
+            .collect();
+        (errors[1] / errors[2]).log2()
+    }
+
+    #[test]
+    fn linear_reconstruction_converges_at_second_order() {
+        let constant = convergence_order(Reconstruction::Constant);
+        let linear = convergence_order(Reconstruction::Linear);
+        assert!((constant - 1.0).abs() < 0.1, "{constant}");
+        assert!((linear - 2.0).abs() < 0.1, "{linear}");
+    }
+
+    #[test]
+    fn slope_is_limited_at_discontinuities() {
+        // A cell at the edge of a shadow, next to a much brighter
+        // upwind neighbour.
+        let upwind = [UpwindNeighbour {
+            relative_difference: Dimensionless::dimensionless(9.0),
+            normal: normal(-MVec::X),
+            area: FaceArea::new_unchecked(1.0),
+            distance: Dimensionless::dimensionless(2.0),
+        }];
+        let downwind = [normal(MVec::X), normal(MVec::Y)];
+        let factor = |reconstruction: Reconstruction, face: VecDimensionless| {
+            let slope = reconstruction.slope(&upwind, downwind.iter().copied());
+            1.0 + slope.dot(face).value()
+        };
+        // Unlimited, the slope would give a negative flux density
+        // at the face opposite to the neighbour.
+        assert!(factor(Reconstruction::Linear, normal(MVec::X)).abs() < 1e-10);
+        assert!((factor(Reconstruction::Linear, normal(MVec::Y)) - 1.0).abs() < 1e-10);
+        for face in downwind {
+            assert_eq!(factor(Reconstruction::Constant, face), 1.0);
+        }
+    }
+}
@@ -5,9 +5,9 @@ use super::Rate;
 use super::Species;
 use crate::chemistry::Chemistry;
 use crate::chemistry::Photons;
-use crate::units::helpers::Float;
 use crate::units::Density;
 use crate::units::Time;
+use crate::units::VecDimensionless;
 
 #[derive(Debug)]
 pub struct Site<C: Chemistry> {
@@ -19,6 +19,12 @@ pub struct Site<C: Chemistry> {
     pub species: Species<C>,
     pub density: Density,
     pub change_timescale: Time,
+    /// The slope of the reconstructed radiation field for every
+    /// direction. Empty unless the reconstruction is linear.
+    pub slope: Vec<VecDimensionless>,
+    /// The total rate absorbed by the cell the last time its
+    /// chemistry was updated, summed over all directions.
+    pub absorbed_rate: C::Photons,
     source: C::Photons,
 }
 
@@ -39,6 +45,8 @@ impl<C: Chemistry> Site<C> {
             periodic_source: directions.enumerate().map(|_| C::Photons::zero()).collect(),
             previous_incoming_total_rate: C::Photons::zero(),
             change_timescale: Time::zero(),
+            slope: vec![],
+            absorbed_rate: C::Photons::zero(),
         }
     }
 
@@ -46,12 +54,23 @@ impl<C: Chemistry> Site<C> {
         self.incoming_total_rate.iter().cloned().sum()
     }
 
-    pub fn source_per_direction_bin(&self, num_directions: usize) -> C::Photons {
-        self.source.clone() / num_directions as Float
+    pub fn source_per_direction_bin(
+        &self,
+        directions: &Directions,
+        dir: DirectionIndex,
+    ) -> C::Photons {
+        self.source.clone() * directions.weight(dir)
+    }
+
+    pub fn get_slope(&self, dir: DirectionIndex) -> VecDimensionless {
+        self.slope
+            .get(dir.0)
+            .copied()
+            .unwrap_or(VecDimensionless::zero())
     }
 
-    pub fn get_rate(&self, num_directions: usize, dir: DirectionIndex) -> Rate<C> {
-        let source = self.source_per_direction_bin(num_directions);
+    pub fn get_rate(&self, directions: &Directions, dir: DirectionIndex) -> Rate<C> {
+        let source = self.source_per_direction_bin(directions, dir);
         self.incoming_total_rate[dir.0].clone() + source + self.periodic_source[dir.0].clone()
     }
 }
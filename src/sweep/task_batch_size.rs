@@ -0,0 +1,130 @@
+use std::time::Duration;
+
+use super::parameters::TaskBatchSize;
+use super::parameters::DEFAULT_NUM_TASKS_TO_SOLVE_BEFORE_SEND_RECEIVE;
+
+/// The ratio of time spent solving a batch of tasks to the time
+/// spent sending and receiving afterwards that the adaptive batch
+/// size aims for. Large values mean that the communication overhead
+/// is small, small values mean that downstream ranks receive their
+/// fluxes earlier.
+const TARGET_COMPUTATION_TO_COMMUNICATION_RATIO: f64 = 10.0;
+/// The maximum factor by which the batch size changes in a single
+/// update, to damp the effect of noisy timings.
+const MAX_CHANGE_FACTOR: f64 = 2.0;
+const MIN_BATCH_SIZE: usize = 10;
+const MAX_BATCH_SIZE: usize = 1_000_000;
+
+/// Keeps track of the number of tasks that are solved before
+/// sending/receiving. If the batch size is set to `auto`, it is
+/// adapted after every batch such that the time spent solving is
+/// `TARGET_COMPUTATION_TO_COMMUNICATION_RATIO` times the time spent
+/// communicating.
+pub(super) struct TaskBatchSizeTuner {
+    adaptive: bool,
+    batch_size: usize,
+}
+
+impl TaskBatchSizeTuner {
+    pub fn new(batch_size: TaskBatchSize) -> Self {
+        match batch_size {
+            TaskBatchSize::Fixed(batch_size) => Self {
+                adaptive: false,
+                batch_size,
+            },
+            TaskBatchSize::Auto(_) => Self {
+                adaptive: true,
+                batch_size: DEFAULT_NUM_TASKS_TO_SOLVE_BEFORE_SEND_RECEIVE,
+            },
+        }
+    }
+
+    pub fn batch_size(&self) -> usize {
+        self.batch_size
+    }
+
+    pub fn is_adaptive(&self) -> bool {
+        self.adaptive
+    }
+
+    /// Update the batch size from the timings of the last batch.
+    /// Batches which were cut short because there were no more tasks
+    /// to solve say nothing about the batch size and are ignored.
+    pub fn update(&mut self, num_solved: usize, computation: Duration, communication: Duration) {
+        if !self.adaptive || num_solved < self.batch_size {
+            return;
+        }
+        let computation = computation.as_secs_f64();
+        let communication = communication.as_secs_f64();
+        if computation == 0.0 || communication == 0.0 {
+            return;
+        }
+        let ratio = computation / communication;
+        let factor = (TARGET_COMPUTATION_TO_COMMUNICATION_RATIO / ratio)
+            .sqrt()
+            .clamp(1.0 / MAX_CHANGE_FACTOR, MAX_CHANGE_FACTOR);
+        self.batch_size = ((self.batch_size as f64 * factor).round() as usize)
+            .clamp(MIN_BATCH_SIZE, MAX_BATCH_SIZE);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::TaskBatchSizeTuner;
+    use super::TARGET_COMPUTATION_TO_COMMUNICATION_RATIO;
+    use crate::sweep::parameters::TaskBatchSize;
+
+    /// A synthetic workload in which solving a task takes a fixed
+    /// amount of time and every send/receive has a fixed latency in
+    /// addition to a cost per task.
+    fn synthetic_timings(batch_size: usize) -> (Duration, Duration) {
+        let computation = Duration::from_nanos(1000) * batch_size as u32;
+        let communication =
+            Duration::from_micros(200) + Duration::from_nanos(10) * batch_size as u32;
+        (computation, communication)
+    }
+
+    #[test]
+    fn auto_batch_size_converges() {
+        let mut tuner = TaskBatchSizeTuner::new(TaskBatchSize::auto());
+        let mut batch_sizes = vec![];
+        for _ in 0..100 {
+            let batch_size = tuner.batch_size();
+            let (computation, communication) = synthetic_timings(batch_size);
+            tuner.update(batch_size, computation, communication);
+            batch_sizes.push(tuner.batch_size());
+        }
+        let last = *batch_sizes.last().unwrap();
+        for batch_size in batch_sizes[90..].iter() {
+            assert!((*batch_size as f64 - last as f64).abs() <= 1.0);
+        }
+        // Fixed point of 1000 ns * b = 10 * (200 us + 10 ns * b)
+        let expected = 2000.0 / (1.0 - 0.1);
+        assert!((last as f64 - expected).abs() / expected < 0.01);
+        let (computation, communication) = synthetic_timings(last);
+        let ratio = computation.as_secs_f64() / communication.as_secs_f64();
+        assert!((ratio - TARGET_COMPUTATION_TO_COMMUNICATION_RATIO).abs() < 0.1);
+    }
+
+    #[test]
+    fn incomplete_batches_are_ignored() {
+        let mut tuner = TaskBatchSizeTuner::new(TaskBatchSize::auto());
+        let batch_size = tuner.batch_size();
+        let (computation, communication) = synthetic_timings(batch_size / 2);
+        tuner.update(batch_size / 2, computation, communication);
+        assert_eq!(tuner.batch_size(), batch_size);
+    }
+
+    #[test]
+    fn fixed_batch_size_is_unchanged() {
+        let mut tuner = TaskBatchSizeTuner::new(TaskBatchSize::Fixed(1234));
+        for _ in 0..10 {
+            let (computation, communication) = synthetic_timings(1234);
+            tuner.update(1234, computation, communication);
+        }
+        assert!(!tuner.is_adaptive());
+        assert_eq!(tuner.batch_size(), 1234);
+    }
+}
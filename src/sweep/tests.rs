@@ -1,23 +1,48 @@
 use bevy_ecs::prelude::Commands;
+use bevy_ecs::prelude::Entity;
+use bevy_ecs::prelude::IntoSystemDescriptor;
+use bevy_ecs::prelude::Query;
 use bevy_ecs::prelude::Res;
+use bevy_ecs::prelude::With;
+use ordered_float::OrderedFloat;
 
 use super::grid::init_cartesian_grid_system;
+use super::grid::Cell;
 use super::grid::NumCellsSpec;
+use super::init_sweep_system;
+use super::Sweep;
+use crate::chemistry::hydrogen_only::HydrogenOnly;
+use crate::chemistry::hydrogen_only::DEFAULT_MAX_DEPTH;
+use crate::communication::BaseCommunicationPlugin;
+use crate::components;
+use crate::components::Position;
+use crate::cosmology::Cosmology;
 use crate::parameters::SimulationBox;
 use crate::parameters::SimulationParameters;
 use crate::parameters::SweepParameters;
+use crate::prelude::LocalParticle;
+use crate::prelude::ParticleId;
 use crate::prelude::StartupStages;
 use crate::prelude::WorldRank;
 use crate::prelude::WorldSize;
 use crate::simulation::Simulation;
+use crate::simulation_plugin::SimulationPlugin;
 use crate::sweep::initialize_sweep_test_components_system;
 use crate::sweep::parameters::DirectionsSpecification;
+use crate::sweep::FluxLimiter;
+use crate::sweep::RateThreshold;
+use crate::sweep::RecombinationCase;
+use crate::sweep::Reconstruction;
 use crate::sweep::SweepPlugin;
+use crate::sweep::TaskBatchSize;
+use crate::sweep::TimescaleCombination;
+use crate::test_utils::assert_is_close;
 use crate::test_utils::build_local_communication_sim_with_custom_logic;
 use crate::units::Dimensionless;
 use crate::units::Length;
 use crate::units::MVec;
 use crate::units::PhotonRate;
+use crate::units::SourceRate;
 use crate::units::Time;
 use crate::units::VecDimensionless;
 
@@ -26,6 +51,8 @@ struct SweepSetup {
     num_timestep_levels: usize,
     timestep_safety_factor: Dimensionless,
     box_: SimulationBox,
+    #[cfg(feature = "2d")]
+    slab_thickness: Length,
 }
 
 fn setup_sweep_sim(sim: &mut Simulation, setup: SweepSetup) -> &mut Simulation {
@@ -35,14 +62,28 @@ fn setup_sweep_sim(sim: &mut Simulation, setup: SweepSetup) -> &mut Simulation {
             directions: DirectionsSpecification::Explicit(setup.dirs.clone()),
             rotate_directions: false,
             num_timestep_levels: setup.num_timestep_levels,
-            significant_rate_threshold: PhotonRate::zero(),
+            significant_rate_threshold: RateThreshold::Absolute(PhotonRate::zero()),
             timestep_safety_factor: setup.timestep_safety_factor,
             chemistry_timestep_safety_factor: setup.timestep_safety_factor,
+            chemistry_max_depth: DEFAULT_MAX_DEPTH,
             check_deadlock: false,
+            check_photon_conservation: false,
             periodic: false,
             max_timestep: Time::seconds(1e-3),
             prevent_cooling: false,
-            num_tasks_to_solve_before_send_receive: 10000,
+            temperature_floor: None,
+            temperature_ceiling: None,
+            max_optical_depth: None,
+            hydrogen_mass_fraction: 1.0.into(),
+            recombination: RecombinationCase::CaseB,
+            flux_limiter: FluxLimiter::None,
+            reconstruction: Reconstruction::Constant,
+            timescale_combination: TimescaleCombination::Min,
+            transport_only: false,
+            track_ionization_fronts: false,
+            #[cfg(feature = "2d")]
+            slab_thickness: setup.slab_thickness,
+            num_tasks_to_solve_before_send_receive: TaskBatchSize::Fixed(10000),
         })
         .add_parameters_explicitly(SimulationParameters { final_time: None })
         .add_startup_system_to_stage(
@@ -59,7 +100,24 @@ fn build_cartesian_sweep_sim(
     num_timestep_levels: usize,
     periodic: bool,
 ) {
-    let cell_size = Length::meters(0.1);
+    build_cartesian_sweep_sim_with_cell_size(
+        sim,
+        dirs,
+        num_cells,
+        Length::meters(0.1),
+        num_timestep_levels,
+        periodic,
+    )
+}
+
+fn build_cartesian_sweep_sim_with_cell_size(
+    sim: &mut Simulation,
+    dirs: Vec<VecDimensionless>,
+    num_cells: usize,
+    cell_size: Length,
+    num_timestep_levels: usize,
+    periodic: bool,
+) {
     let simulation_box = SimulationBox::cube_from_side_length(cell_size * num_cells as f64);
     let grid_setup = move |commands: Commands,
                            box_size: Res<SimulationBox>,
@@ -81,11 +139,75 @@ fn build_cartesian_sweep_sim(
             num_timestep_levels,
             timestep_safety_factor: Dimensionless::zero(),
             box_: simulation_box,
+            #[cfg(feature = "2d")]
+            slab_thickness: cell_size,
         },
     );
     sim.add_startup_system(grid_setup);
 }
 
+/// Builds a simulation on a single rank, which, unlike
+/// [build_local_communication_sim_with_custom_logic], can be run
+/// directly within the test.
+fn build_single_rank_sim(build: impl FnOnce(&mut Simulation)) -> Simulation {
+    let mut sim = Simulation::default();
+    sim.write_output(false)
+        .add_plugin(BaseCommunicationPlugin::new(1, 0));
+    build(&mut sim);
+    sim.add_parameters_explicitly(Cosmology::NonCosmological)
+        .add_plugin(SimulationPlugin);
+    sim
+}
+
+fn axis_directions() -> Vec<VecDimensionless> {
+    vec![MVec::X, -MVec::X, MVec::Y, -MVec::Y]
+        .into_iter()
+        .map(|dir| dir * Dimensionless::dimensionless(1.0))
+        .collect()
+}
+
+fn get_solver(sim: &mut Simulation) -> &Sweep<HydrogenOnly> {
+    sim.world()
+        .non_send_resource::<Option<Sweep<HydrogenOnly>>>()
+        .as_ref()
+        .unwrap()
+}
+
+#[test]
+fn relative_rate_threshold_resolves_to_fraction_of_total_luminosity() {
+    let sources = [
+        SourceRate::photons_per_second(1e49),
+        SourceRate::photons_per_second(3e49),
+        SourceRate::photons_per_second(6e49),
+    ];
+    let total: SourceRate = sources.iter().copied().sum();
+    let relative = RateThreshold::Relative(Dimensionless::dimensionless(1e-3));
+    assert_is_close(
+        relative.resolve(total),
+        PhotonRate::photons_per_second(1e47),
+    );
+    let absolute = RateThreshold::Absolute(PhotonRate::photons_per_second(5.0));
+    assert_eq!(absolute.resolve(total), PhotonRate::photons_per_second(5.0));
+}
+
+#[test]
+fn plain_rate_threshold_is_read_as_relative() {
+    let threshold: RateThreshold = serde_yaml::from_str("1.0e-3").unwrap();
+    match threshold {
+        RateThreshold::Relative(fraction) => {
+            assert_eq!(fraction, Dimensionless::dimensionless(1e-3))
+        }
+        RateThreshold::Absolute(_) => panic!("Expected relative threshold, got {threshold:?}"),
+    }
+    let threshold: RateThreshold = serde_yaml::from_str("1.0e-5 s^-1").unwrap();
+    match threshold {
+        RateThreshold::Absolute(rate) => {
+            assert_eq!(rate, PhotonRate::photons_per_second(1e-5))
+        }
+        RateThreshold::Relative(_) => panic!("Expected absolute threshold, got {threshold:?}"),
+    }
+}
+
 #[test]
 #[ignore]
 fn simple_sweep() {
@@ -132,3 +254,636 @@ fn sweep_along_grid_axes_does_not_deadlock_or_crash() {
         2,
     );
 }
+
+#[test]
+#[ignore]
+fn sweep_with_empty_ranks_does_not_deadlock_or_crash() {
+    // The grid is decomposed into slabs along the x axis, so with two
+    // cells along each axis and four ranks, ranks 0 and 2 do not own
+    // any cells.
+    build_local_communication_sim_with_custom_logic(
+        |sim: &mut Simulation| {
+            build_cartesian_sweep_sim(
+                sim,
+                vec![MVec::ONE * Dimensionless::dimensionless(1.0)],
+                2,
+                2,
+                false,
+            )
+        },
+        |sim| {
+            sim.update();
+            sim.update();
+        },
+        4,
+    );
+}
+
+/// A single source in the center of a homogeneous 2D box. In
+/// equilibrium, the number of recombinations within the ionized
+/// region balances the source rate, so its area is given by the 2D
+/// analogue of the Strömgren radius, A = Q / (alpha_B n^2 d), where
+/// d is the slab thickness.
+#[cfg(feature = "2d")]
+#[test]
+#[ignore]
+fn central_source_produces_stromgren_area_2d() {
+    use bevy_ecs::prelude::Entity;
+
+    use crate::sweep::grid::Cell;
+    use crate::units::NumberDensity;
+    use crate::units::Temperature;
+    use crate::units::Volume;
+    use crate::units::VolumeRate;
+    use crate::units::PROTON_MASS;
+
+    let num_cells = 33;
+    let cell_size = Length::kiloparsec(0.1);
+    let slab_thickness = cell_size;
+    let number_density = NumberDensity::per_centimeters_cubed(1.0);
+    let source_rate = SourceRate::photons_per_second(2.4e51);
+    // Case B recombination rate at 10^4 K
+    let alpha_b = VolumeRate::centimeters_cubed_per_s(2.59e-13);
+    let expected_area = source_rate / (alpha_b * number_density * number_density * slab_thickness);
+    let num_dirs = 8;
+    let dirs = (0..num_dirs)
+        .map(|i| {
+            let angle = 2.0 * std::f64::consts::PI * (i as f64 + 0.5) / num_dirs as f64;
+            MVec::new(angle.cos(), angle.sin()) * Dimensionless::dimensionless(1.0)
+        })
+        .collect();
+    let box_ = SimulationBox::cube_from_side_length_centered(cell_size * num_cells as f64);
+    let initialize_components =
+        move |mut commands: Commands,
+              particles: Query<(Entity, &Position), With<LocalParticle>>| {
+            for (entity, pos) in particles.iter() {
+                // Put the source into the cell at the center of the box.
+                let source = if pos.length() < cell_size * 0.5 {
+                    source_rate
+                } else {
+                    SourceRate::zero()
+                };
+                commands.entity(entity).insert((
+                    components::Density(number_density * PROTON_MASS),
+                    components::IonizedHydrogenFraction(Dimensionless::dimensionless(1e-10)),
+                    components::Temperature(Temperature::kelvins(1e4)),
+                    components::Source(source),
+                    components::PhotonRate(PhotonRate::zero()),
+                ));
+            }
+        };
+    build_local_communication_sim_with_custom_logic(
+        move |sim: &mut Simulation| {
+            let grid_setup = move |commands: Commands,
+                                   box_size: Res<SimulationBox>,
+                                   world_size: Res<WorldSize>,
+                                   world_rank: Res<WorldRank>| {
+                init_cartesian_grid_system(
+                    commands,
+                    box_size,
+                    NumCellsSpec::CellSize(cell_size),
+                    world_size,
+                    world_rank,
+                    false,
+                )
+            };
+            sim.add_parameter_file_contents("{}".into())
+                .add_parameters_explicitly(box_.clone())
+                .add_parameters_explicitly(SweepParameters {
+                    directions: DirectionsSpecification::Explicit(dirs),
+                    rotate_directions: false,
+                    num_timestep_levels: 1,
+                    significant_rate_threshold: RateThreshold::Absolute(PhotonRate::zero()),
+                    timestep_safety_factor: Dimensionless::dimensionless(0.1),
+                    chemistry_timestep_safety_factor: Dimensionless::dimensionless(0.1),
+                    chemistry_max_depth: DEFAULT_MAX_DEPTH,
+                    check_deadlock: false,
+                    check_photon_conservation: false,
+                    periodic: false,
+                    max_timestep: Time::kiloyears(100.0),
+                    prevent_cooling: false,
+                    temperature_floor: None,
+                    temperature_ceiling: None,
+                    max_optical_depth: None,
+                    hydrogen_mass_fraction: 1.0.into(),
+                    recombination: RecombinationCase::CaseB,
+                    flux_limiter: FluxLimiter::None,
+                    reconstruction: Reconstruction::Constant,
+                    timescale_combination: TimescaleCombination::Min,
+                    transport_only: false,
+                    track_ionization_fronts: false,
+                    slab_thickness,
+                    num_tasks_to_solve_before_send_receive: TaskBatchSize::Fixed(10000),
+                })
+                .add_parameters_explicitly(SimulationParameters { final_time: None })
+                .add_startup_system(grid_setup)
+                .add_startup_system_to_stage(
+                    StartupStages::InsertComponentsAfterGrid,
+                    initialize_components,
+                )
+                .add_plugin(SweepPlugin);
+        },
+        move |sim| {
+            // Run for many recombination times to reach equilibrium.
+            for _ in 0..100 {
+                sim.update();
+            }
+            let mut query = sim
+                .world()
+                .query::<(&Cell, &components::IonizedHydrogenFraction)>();
+            let ionized_area: Volume = query
+                .iter(sim.world())
+                .map(|(cell, frac)| cell.volume() * **frac)
+                .sum();
+            let relative_error = ((ionized_area - expected_area) / expected_area).value();
+            assert!(
+                relative_error.abs() < 0.2,
+                "ionized area {:?} differs from expected {:?}",
+                ionized_area,
+                expected_area,
+            );
+        },
+        1,
+    );
+}
+
+fn place_source_in_corner_system(
+    mut particles: Query<(&Position, &mut components::Source), With<LocalParticle>>,
+) {
+    let corner = particles
+        .iter_mut()
+        .min_by_key(|(pos, _)| OrderedFloat(pos.length().value_unchecked()));
+    if let Some((_, mut source)) = corner {
+        **source = SourceRate::photons_per_second(1e50);
+    }
+}
+
+#[test]
+#[ignore]
+fn incoming_rate_is_largest_in_direction_away_from_source() {
+    let dirs = vec![MVec::X, -MVec::X, MVec::Y, -MVec::Y]
+        .into_iter()
+        .map(|dir| dir * Dimensionless::dimensionless(1.0))
+        .collect::<Vec<_>>();
+    build_local_communication_sim_with_custom_logic(
+        move |sim: &mut Simulation| {
+            build_cartesian_sweep_sim(sim, dirs.clone(), 5, 1, false);
+            sim.add_startup_system_to_stage(
+                StartupStages::InitSweep,
+                place_source_in_corner_system.before(init_sweep_system),
+            );
+        },
+        |sim| {
+            sim.update();
+            sim.update();
+            let mut query = sim
+                .world()
+                .query::<(&ParticleId, &Position, &components::Source)>();
+            let cells: Vec<_> = query
+                .iter(sim.world())
+                .map(|(id, pos, source)| (*id, **pos, **source))
+                .collect();
+            let (_, source_pos, _) = cells
+                .iter()
+                .find(|(_, _, source)| *source != SourceRate::zero())
+                .unwrap();
+            // All cells downstream of the source along the x axis.
+            let ids: Vec<_> = cells
+                .iter()
+                .filter(|(_, pos, _)| {
+                    let offset = (*pos - *source_pos).value_unchecked();
+                    offset.x > 0.0 && offset.normalize().dot(MVec::X) > 1.0 - 1e-10
+                })
+                .map(|(id, _, _)| *id)
+                .collect();
+            assert!(!ids.is_empty());
+            let solver = sim
+                .world()
+                .non_send_resource::<Option<Sweep<HydrogenOnly>>>()
+                .as_ref()
+                .unwrap();
+            let entries = solver.get_directional_rate_entries(&ids, Time::zero());
+            for id in ids.iter() {
+                let brightest = entries
+                    .iter()
+                    .filter(|entry| entry.index == id.index && entry.rank == id.rank)
+                    .max_by_key(|entry| OrderedFloat(entry.rate.value_unchecked()))
+                    .unwrap();
+                assert_eq!(
+                    brightest.direction,
+                    MVec::X * Dimensionless::dimensionless(1.0)
+                );
+                assert!(brightest.rate > PhotonRate::zero());
+            }
+        },
+        1,
+    );
+}
+
+/// A source in a periodic box, in which all photons are either
+/// absorbed or re-enter the box on the other side. Since re-entering
+/// photons are only passed on in the next step, the budget only
+/// closes once the radiation field has converged, which requires
+/// the abundances to be frozen.
+#[test]
+fn photon_number_is_conserved_in_periodic_box() {
+    let mut sim = build_single_rank_sim(|sim| {
+        build_cartesian_sweep_sim(sim, axis_directions(), 5, 1, true);
+        sim.unwrap_resource_mut::<SweepParameters>().transport_only = true;
+        sim.add_startup_system_to_stage(
+            StartupStages::InitSweep,
+            place_source_in_corner_system.before(init_sweep_system),
+        );
+    });
+    let num_steps_to_converge = 10;
+    for step in 0..num_steps_to_converge + 10 {
+        sim.update();
+        let budget = get_solver(&mut sim).local_photon_budget().global_sum();
+        assert!(budget.source > PhotonRate::zero());
+        assert_eq!(budget.leaving_through_boundary, PhotonRate::zero());
+        if step >= num_steps_to_converge {
+            assert!(budget.entering_through_periodic_boundary > PhotonRate::zero());
+            assert!(budget.residual().value().abs() < 1e-8);
+        }
+    }
+}
+
+/// Photons can only escape from a box without periodic boundaries.
+#[test]
+fn escape_fraction_is_zero_in_closed_box() {
+    for periodic in [true, false] {
+        let mut sim = build_single_rank_sim(|sim| {
+            build_cartesian_sweep_sim(sim, axis_directions(), 5, 1, periodic);
+            sim.add_startup_system_to_stage(
+                StartupStages::InitSweep,
+                place_source_in_corner_system.before(init_sweep_system),
+            );
+        });
+        for _ in 0..5 {
+            sim.update();
+        }
+        let solver = get_solver(&mut sim);
+        let escape_fraction = solver.escape_fraction_global();
+        let budget = solver.local_photon_budget().global_sum();
+        if periodic {
+            assert_eq!(budget.leaving_through_boundary, PhotonRate::zero());
+            assert_eq!(escape_fraction, Dimensionless::zero());
+        } else {
+            assert!(budget.leaving_through_boundary > PhotonRate::zero());
+            assert_is_close(
+                escape_fraction,
+                budget.leaving_through_boundary / budget.source,
+            );
+            assert!(escape_fraction > Dimensionless::zero());
+            assert!(escape_fraction.value() < 1.0 + 1e-10);
+        }
+    }
+}
+
+/// Every emitted photon is either absorbed or leaves the box, unless
+/// photons are dropped in the transport, which happens here because
+/// the rate threshold lies above the rate the source emits into each
+/// direction.
+#[test]
+fn photon_budget_detects_lost_photons() {
+    for (threshold, photons_are_lost) in [(0.0, false), (3e49, true)] {
+        let mut sim = build_single_rank_sim(|sim| {
+            build_cartesian_sweep_sim(sim, axis_directions(), 5, 1, false);
+            sim.unwrap_resource_mut::<SweepParameters>()
+                .significant_rate_threshold =
+                RateThreshold::Absolute(PhotonRate::photons_per_second(threshold));
+            sim.add_startup_system_to_stage(
+                StartupStages::InitSweep,
+                place_source_in_corner_system.before(init_sweep_system),
+            );
+        });
+        for _ in 0..5 {
+            sim.update();
+        }
+        let budget = get_solver(&mut sim).local_photon_budget().global_sum();
+        assert!(budget.source > PhotonRate::zero());
+        let residual = budget.residual().value();
+        if photons_are_lost {
+            assert!(residual > 0.5, "{residual}");
+        } else {
+            assert!(budget.leaving_through_boundary > PhotonRate::zero());
+            assert!(residual.abs() < 1e-8, "{residual}");
+        }
+    }
+}
+
+/// The source and the cells downstream of it along the x axis,
+/// ordered by their distance to the source.
+fn source_and_cells_along_x_axis(sim: &mut Simulation) -> (ParticleId, Vec<ParticleId>) {
+    let mut query = sim
+        .world()
+        .query::<(&ParticleId, &Position, &components::Source)>();
+    let cells: Vec<_> = query
+        .iter(sim.world())
+        .map(|(id, pos, source)| (*id, **pos, **source))
+        .collect();
+    let (source_id, source_pos, _) = *cells
+        .iter()
+        .find(|(_, _, source)| *source != SourceRate::zero())
+        .unwrap();
+    let mut line: Vec<_> = cells
+        .iter()
+        .filter(|(_, pos, _)| {
+            let offset = (*pos - source_pos).value_unchecked();
+            offset.x > 0.0 && offset.normalize().dot(MVec::X) > 1.0 - 1e-10
+        })
+        .map(|(id, pos, _)| (*id, (*pos - source_pos).length()))
+        .collect();
+    line.sort_by_key(|(_, distance)| OrderedFloat(distance.value_unchecked()));
+    (source_id, line.into_iter().map(|(id, _)| id).collect())
+}
+
+/// The incoming rates of the cells downstream of the source along
+/// the x axis, for a single direction which is slightly inclined
+/// against the x axis.
+fn rates_along_x_axis(flux_limiter: FluxLimiter) -> Vec<PhotonRate> {
+    use crate::sweep::direction::DirectionIndex;
+
+    let dir = (MVec::X + MVec::Y * 0.2).normalize() * Dimensionless::dimensionless(1.0);
+    let mut sim = build_single_rank_sim(|sim| {
+        build_cartesian_sweep_sim(sim, vec![dir], 5, 1, false);
+        {
+            let mut parameters = sim.unwrap_resource_mut::<SweepParameters>();
+            parameters.transport_only = true;
+            parameters.flux_limiter = flux_limiter;
+        }
+        sim.add_startup_system_to_stage(
+            StartupStages::InitSweep,
+            place_source_in_corner_system.before(init_sweep_system),
+        );
+    });
+    sim.update();
+    sim.update();
+    let (_, line) = source_and_cells_along_x_axis(&mut sim);
+    let solver = get_solver(&mut sim);
+    line.into_iter()
+        .map(|id| {
+            solver
+                .sites
+                .get(id)
+                .get_rate(&solver.directions, DirectionIndex(0))
+        })
+        .collect()
+}
+
+/// A direction which is almost aligned with the x axis passes most
+/// of the rate of a cell through its +x face, which channels the
+/// radiation along the row of cells containing the source. Capping
+/// the effective areas spreads more of the rate into the
+/// neighbouring rows.
+#[test]
+fn mean_cap_flux_limiter_reduces_channeling() {
+    let unlimited = rates_along_x_axis(FluxLimiter::None);
+    let limited = rates_along_x_axis(FluxLimiter::MeanCap);
+    assert_eq!(unlimited.len(), 4);
+    assert_eq!(limited.len(), unlimited.len());
+    for (limited, unlimited) in limited.iter().zip(unlimited.iter()) {
+        assert!(*unlimited > PhotonRate::zero());
+        assert!(limited < unlimited, "{limited:?} {unlimited:?}");
+    }
+}
+
+/// Spawns the local cells again in reverse order, so that the maps
+/// from which the sweep is constructed are filled in a different
+/// order.
+fn reverse_cell_order_system(
+    mut commands: Commands,
+    cells: Query<(Entity, &Position, &Cell, &ParticleId), With<LocalParticle>>,
+) {
+    let cells: Vec<_> = cells
+        .iter()
+        .map(|(entity, pos, cell, id)| (entity, pos.clone(), cell.clone(), *id))
+        .collect();
+    for (entity, pos, cell, id) in cells.into_iter().rev() {
+        commands.entity(entity).despawn();
+        commands.spawn((LocalParticle, pos, cell, id));
+    }
+}
+
+/// The outgoing rates of all cells in every direction after a few
+/// timesteps, in the order of the cell ids.
+fn outgoing_rates_after_a_few_steps(reverse_cell_order: bool) -> Vec<Vec<PhotonRate>> {
+    let dirs: Vec<VecDimensionless> = vec![MVec::X + MVec::Y * 0.3, MVec::Y - MVec::X * 0.6]
+        .into_iter()
+        .map(|dir| dir.normalize() * Dimensionless::dimensionless(1.0))
+        .collect();
+    let mut sim = build_single_rank_sim(|sim| {
+        build_cartesian_sweep_sim(sim, dirs, 6, 2, true);
+        sim.add_startup_system_to_stage(
+            StartupStages::InitSweep,
+            place_source_in_corner_system.before(init_sweep_system),
+        );
+        if reverse_cell_order {
+            sim.add_startup_system_to_stage(StartupStages::InsertGrid, reverse_cell_order_system);
+        }
+    });
+    for _ in 0..3 {
+        sim.update();
+    }
+    let mut query = sim
+        .world()
+        .query_filtered::<&ParticleId, With<LocalParticle>>();
+    let mut ids: Vec<_> = query.iter(sim.world()).copied().collect();
+    ids.sort();
+    let solver = get_solver(&mut sim);
+    ids.into_iter()
+        .map(|id| solver.sites.get(id).outgoing_total_rate.clone())
+        .collect()
+}
+
+/// The fluxes are accumulated in floating point sums, whose result
+/// depends on the order of the summands. Filling the maps of the
+/// sweep in a different order must not change that order.
+#[test]
+fn two_runs_give_identical_fluxes_regardless_of_cell_order() {
+    let first = outgoing_rates_after_a_few_steps(false);
+    let second = outgoing_rates_after_a_few_steps(true);
+    assert!(first
+        .iter()
+        .flatten()
+        .any(|rate| *rate > PhotonRate::zero()));
+    let bits = |rates: &[Vec<PhotonRate>]| -> Vec<u64> {
+        rates
+            .iter()
+            .flatten()
+            .map(|rate| rate.value_unchecked().to_bits())
+            .collect()
+    };
+    assert_eq!(bits(&first), bits(&second));
+}
+
+/// With frozen abundances, every cell along the direction of the
+/// source attenuates the rate by exp(-tau), where tau is the optical
+/// depth of the cell.
+#[test]
+fn transport_only_attenuates_by_optical_depth() {
+    use crate::sweep::direction::DirectionIndex;
+    use crate::units::NUMBER_WEIGHTED_AVERAGE_CROSS_SECTION;
+    use crate::units::PROTON_MASS;
+
+    let mut sim = build_single_rank_sim(|sim| {
+        build_cartesian_sweep_sim(sim, axis_directions(), 5, 1, false);
+        sim.unwrap_resource_mut::<SweepParameters>().transport_only = true;
+        sim.add_startup_system_to_stage(
+            StartupStages::InitSweep,
+            place_source_in_corner_system.before(init_sweep_system),
+        );
+    });
+    sim.update();
+    sim.update();
+    let (source_id, line) = source_and_cells_along_x_axis(&mut sim);
+    assert_eq!(line.len(), 4);
+    let solver = get_solver(&mut sim);
+    let dir = DirectionIndex(0);
+    let mut expected = solver.sites.get(source_id).outgoing_total_rate[dir.0];
+    assert!(expected > PhotonRate::zero());
+    for id in line {
+        let site = solver.sites.get(id);
+        let cell = solver.cells.get(id);
+        // The abundances are frozen at their initial values.
+        assert_eq!(
+            site.species.ionized_hydrogen_fraction,
+            Dimensionless::dimensionless(1e-10)
+        );
+        let incoming = site.get_rate(&solver.directions, dir);
+        assert_is_close(incoming, expected);
+        let tau = site.density / PROTON_MASS
+            * (1.0 - site.species.ionized_hydrogen_fraction)
+            * NUMBER_WEIGHTED_AVERAGE_CROSS_SECTION
+            * cell.size;
+        assert!(tau.value() > 0.0);
+        expected = incoming * (-tau).exp();
+        assert_is_close(site.outgoing_total_rate[dir.0], expected);
+    }
+}
+
+/// With linear reconstruction, the outgoing rate of a cell is
+/// distributed among its downwind faces according to the slope of
+/// the radiation field, which must not create or destroy photons.
+#[test]
+fn linear_reconstruction_conserves_photons() {
+    let inclined = (MVec::X + MVec::Y * 0.5).normalize() * Dimensionless::dimensionless(1.0);
+    let mut dirs = axis_directions();
+    dirs.push(inclined);
+    let mut sim = build_single_rank_sim(|sim| {
+        build_cartesian_sweep_sim(sim, dirs, 5, 1, false);
+        sim.unwrap_resource_mut::<SweepParameters>().reconstruction = Reconstruction::Linear;
+        sim.add_startup_system_to_stage(
+            StartupStages::InitSweep,
+            place_source_in_corner_system.before(init_sweep_system),
+        );
+    });
+    for _ in 0..5 {
+        sim.update();
+        let budget = get_solver(&mut sim).local_photon_budget().global_sum();
+        assert!(budget.source > PhotonRate::zero());
+        assert!(budget.leaving_through_boundary > PhotonRate::zero());
+        let residual = budget.residual().value();
+        assert!(residual.abs() < 1e-8, "{residual}");
+    }
+    let ids: Vec<_> = sim
+        .world()
+        .query::<&ParticleId>()
+        .iter(sim.world())
+        .copied()
+        .collect();
+    let solver = get_solver(&mut sim);
+    // Make sure that the linear reconstruction is actually used
+    assert!(ids.iter().any(|id| solver
+        .sites
+        .get(*id)
+        .slope
+        .iter()
+        .any(|slope| *slope != VecDimensionless::zero())));
+}
+
+/// Illuminates a transparent box from the cells at its left edge
+/// along a direction which is inclined against the grid axes, and
+/// returns the mean deviation of the normalized rate from the exact
+/// solution, in which the region below the ray starting at the
+/// origin is in shadow. This is the error in the position of the
+/// front of the radiation field, which is smeared out by the
+/// diffusion of the sweep.
+fn front_error(num_cells: usize, reconstruction: Reconstruction) -> f64 {
+    use crate::sweep::direction::DirectionIndex;
+    use crate::units::NumberDensity;
+    use crate::units::PROTON_MASS;
+
+    let inclination = 0.5;
+    let dir = (MVec::X + MVec::Y * inclination).normalize() * Dimensionless::dimensionless(1.0);
+    let cell_size = Length::meters(1.0) / num_cells as f64;
+    let illuminate_left_edge = move |mut particles: Query<
+        (&Position, &mut components::Source, &mut components::Density),
+        With<LocalParticle>,
+    >| {
+        for (pos, mut source, mut density) in particles.iter_mut() {
+            **density = NumberDensity::per_centimeters_cubed(1e-20) * PROTON_MASS;
+            if pos.x() < cell_size {
+                **source = SourceRate::photons_per_second(1e50);
+            }
+        }
+    };
+    let mut sim = build_single_rank_sim(|sim| {
+        build_cartesian_sweep_sim_with_cell_size(sim, vec![dir], num_cells, cell_size, 1, false);
+        {
+            let mut parameters = sim.unwrap_resource_mut::<SweepParameters>();
+            parameters.transport_only = true;
+            parameters.reconstruction = reconstruction;
+        }
+        sim.add_startup_system_to_stage(
+            StartupStages::InitSweep,
+            illuminate_left_edge.before(init_sweep_system),
+        );
+    });
+    sim.update();
+    sim.update();
+    let cells: Vec<_> = sim
+        .world()
+        .query::<(&ParticleId, &Position)>()
+        .iter(sim.world())
+        .map(|(id, pos)| (*id, **pos))
+        .collect();
+    let solver = get_solver(&mut sim);
+    let rate = |id: ParticleId| {
+        solver
+            .sites
+            .get(id)
+            .get_rate(&solver.directions, DirectionIndex(0))
+    };
+    // Far above the front, the rate is the same in every cell.
+    let reference = cells
+        .iter()
+        .filter(|(_, pos)| pos.x() < cell_size)
+        .map(|(id, _)| rate(*id))
+        .max_by_key(|rate| OrderedFloat(rate.value_unchecked()))
+        .unwrap();
+    assert!(reference > PhotonRate::zero());
+    let total_error: f64 = cells
+        .iter()
+        .map(|(id, pos)| {
+            let exact = if pos.y() > pos.x() * inclination {
+                1.0
+            } else {
+                0.0
+            };
+            ((rate(*id) / reference).value() - exact).abs()
+        })
+        .sum();
+    total_error / cells.len() as f64
+}
+
+#[test]
+fn front_error_with_linear_reconstruction_decreases_under_refinement() {
+    let errors: Vec<_> = [6, 12, 24]
+        .into_iter()
+        .map(|num_cells| front_error(num_cells, Reconstruction::Linear))
+        .collect();
+    for pair in errors.windows(2) {
+        assert!(pair[1] < pair[0], "{errors:?}");
+    }
+}
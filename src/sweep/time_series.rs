@@ -1,5 +1,3 @@
-use std::iter;
-
 use bevy_ecs::prelude::*;
 use derive_custom::Named;
 use derive_more::Deref;
@@ -12,14 +10,18 @@ use serde::Serialize;
 use super::grid::Cell;
 use super::Sweep;
 use super::SweepParameters;
+use crate::chemistry::timescale::LimitingProcessFractions;
 use crate::chemistry::Chemistry;
 use crate::communication::communicator::Communicator;
+use crate::communication::CompensatedSum;
 use crate::components;
 use crate::components::IonizedHydrogenFraction;
 use crate::components::Mass;
 use crate::prelude::Particles;
+use crate::units::Dimension;
 use crate::units::Dimensionless;
 use crate::units::PhotonRate;
+use crate::units::Quantity;
 use crate::units::Temperature;
 use crate::units::Time;
 
@@ -47,10 +49,24 @@ pub struct PhotoionizationRateVolumeAverage(pub PhotonRate);
 #[name = "weighted_photoionization_rate_volume_average"]
 pub struct WeightedPhotoionizationRateVolumeAverage(pub PhotonRate);
 
+/// The fraction of the photons emitted by all sources which leave
+/// the box through its non-periodic boundaries.
+#[derive(Component, Debug, Clone, Equivalence, Deref, DerefMut, From, Named, Serialize)]
+#[name = "escape_fraction"]
+pub struct EscapeFraction(pub Dimensionless);
+
+/// The number of cells which are active at each timestep level,
+/// i.e. the cells at that level or any of the levels above it.
 #[derive(Serialize, Clone, Named)]
 #[name = "num_particles_at_timestep_levels"]
 pub struct NumParticlesAtTimestepLevels(Vec<NumAtLevel>);
 
+/// A histogram of the number of cells at exactly each timestep
+/// level.
+#[derive(Serialize, Clone, Named)]
+#[name = "timestep_level_histogram"]
+pub struct TimestepLevelHistogram(Vec<NumAtLevel>);
+
 #[derive(Serialize, Clone)]
 struct NumAtLevel {
     level: usize,
@@ -58,6 +74,12 @@ struct NumAtLevel {
     timestep: Time,
 }
 
+/// The fractions of timesteps limited by each process in the last
+/// step.
+#[derive(Serialize, Clone, Named, From)]
+#[name = "timestep_limiting_processes"]
+pub struct TimestepLimitingProcesses(LimitingProcessFractions);
+
 pub fn compute_time_series_system(
     mass_av_frac: Particles<(&components::Mass, &IonizedHydrogenFraction)>,
     volume_av_frac: Particles<(&Cell, &IonizedHydrogenFraction)>,
@@ -154,14 +176,11 @@ pub fn compute_time_series_system(
     weighted_photoionization_rate_writer.send(WeightedPhotoionizationRateVolumeAverage(average));
 }
 
-fn compute_global_sum<T>(i: impl Iterator<Item = T>) -> T
-where
-    T: iter::Sum<T> + Clone + Equivalence + 'static,
-{
-    let mut comm = Communicator::new();
-    let local_value: T = i.sum();
-    let value: T = comm.all_gather_sum(&local_value);
-    value
+pub(super) fn compute_global_sum<const D: Dimension>(
+    i: impl Iterator<Item = Quantity<f64, D>>,
+) -> Quantity<f64, D> {
+    let mut comm = Communicator::<CompensatedSum>::new();
+    Quantity::new_unchecked(comm.all_reduce_sum_compensated(i.map(|x| x.value_unchecked())))
 }
 
 pub(super) fn num_particles_at_timestep_levels_system<C: Chemistry>(
@@ -186,3 +205,35 @@ pub(super) fn num_particles_at_timestep_levels_system<C: Chemistry>(
             .collect(),
     ));
 }
+
+pub(super) fn timestep_level_histogram_system<C: Chemistry>(
+    mut solver: NonSendMut<Option<Sweep<C>>>,
+    mut writer: EventWriter<TimestepLevelHistogram>,
+    parameters: Res<SweepParameters>,
+) {
+    let solver = (*solver).as_mut().unwrap();
+    let max_timestep = parameters.max_timestep;
+    let histogram = solver.get_level_histogram_global();
+    writer.send(TimestepLevelHistogram(
+        solver
+            .timestep_state
+            .iter_all_levels()
+            .zip(histogram)
+            .map(|(level, num)| NumAtLevel {
+                level: level.0,
+                num,
+                timestep: level.to_timestep(max_timestep),
+            })
+            .collect(),
+    ));
+}
+
+pub(super) fn timestep_limiting_processes_system<C: Chemistry>(
+    mut solver: NonSendMut<Option<Sweep<C>>>,
+    mut writer: EventWriter<TimestepLimitingProcesses>,
+) {
+    let solver = (*solver).as_mut().unwrap();
+    writer.send(TimestepLimitingProcesses(
+        solver.get_limiting_process_fractions_global(),
+    ));
+}
@@ -16,7 +16,7 @@ pub enum TimeSpec {
 impl TimeSpec {
     pub fn new(time: Time, cosmology: &Cosmology) -> Self {
         match cosmology {
-            Cosmology::Cosmological { a, h, params } => {
+            Cosmology::Cosmological { a, h, params, .. } => {
                 if let Some(params) = params {
                     TimeSpec::Cosmological(CosmologicalTime::new(
                         time,
@@ -0,0 +1,124 @@
+use std::fmt;
+
+use super::Dimension;
+use super::Dimensionless;
+use super::Energy;
+use super::Length;
+use super::Mass;
+use super::Quantity;
+use super::Temperature;
+use super::Time;
+use super::Velocity;
+use crate::prelude::Float;
+
+/// The number of digits after the decimal point if
+/// no precision is given in the format string.
+const DEFAULT_PRECISION: usize = 2;
+
+type UnitTable = Vec<(&'static str, Float)>;
+
+/// The units which a quantity of a given dimension can be displayed
+/// in, in ascending order of size.
+fn get_unit_table(dimension: Dimension) -> Option<UnitTable> {
+    let table = if dimension == Dimensionless::dimension() {
+        vec![("", 1.0)]
+    } else if dimension == Length::dimension() {
+        vec![
+            ("m", Length::meters(1.0).value_unchecked()),
+            ("km", Length::kilometers(1.0).value_unchecked()),
+            ("pc", Length::parsec(1.0).value_unchecked()),
+            ("kpc", Length::kiloparsec(1.0).value_unchecked()),
+            ("Mpc", Length::megaparsec(1.0).value_unchecked()),
+            ("Gpc", Length::gigaparsec(1.0).value_unchecked()),
+        ]
+    } else if dimension == Time::dimension() {
+        vec![
+            ("ns", Time::nanoseconds(1.0).value_unchecked()),
+            ("µs", Time::microseconds(1.0).value_unchecked()),
+            ("ms", Time::milliseconds(1.0).value_unchecked()),
+            ("s", Time::seconds(1.0).value_unchecked()),
+            ("yr", Time::years(1.0).value_unchecked()),
+            ("kyr", Time::kiloyears(1.0).value_unchecked()),
+            ("Myr", Time::megayears(1.0).value_unchecked()),
+            ("Gyr", Time::gigayears(1.0).value_unchecked()),
+        ]
+    } else if dimension == Mass::dimension() {
+        vec![
+            ("g", Mass::grams(1.0).value_unchecked()),
+            ("kg", Mass::kilograms(1.0).value_unchecked()),
+            ("Msol", Mass::solar(1.0).value_unchecked()),
+        ]
+    } else if dimension == Temperature::dimension() {
+        vec![("K", Temperature::kelvins(1.0).value_unchecked())]
+    } else if dimension == Velocity::dimension() {
+        vec![
+            ("m/s", Velocity::meters_per_second(1.0).value_unchecked()),
+            (
+                "km/s",
+                Velocity::meters_per_second(1000.0).value_unchecked(),
+            ),
+        ]
+    } else if dimension == Energy::dimension() {
+        vec![
+            ("eV", Energy::electron_volts(1.0).value_unchecked()),
+            ("J", Energy::joules(1.0).value_unchecked()),
+        ]
+    } else {
+        return None;
+    };
+    Some(table)
+}
+
+/// Returns the largest unit in the table which is still smaller
+/// than the value, or the smallest unit if there is none.
+fn choose_unit(value: Float, table: &UnitTable) -> (&'static str, Float) {
+    *table
+        .iter()
+        .rev()
+        .find(|(_, factor)| value.abs() >= *factor)
+        .unwrap_or(&table[0])
+}
+
+/// Displays the quantity in a human-readable unit, for example
+/// parsecs for large lengths or Myr for long times. Quantities of
+/// dimensions without a unit table are shown in base units.
+impl<const D: Dimension> fmt::Display for Quantity<Float, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self.value_unchecked();
+        let Some(table) = get_unit_table(D) else {
+            return write!(f, "{:?}", self);
+        };
+        let (name, factor) = choose_unit(value, &table);
+        let scaled = value / factor;
+        let precision = f.precision().unwrap_or(DEFAULT_PRECISION);
+        let separator = if name.is_empty() { "" } else { " " };
+        if scaled != 0.0 && (scaled.abs() < 1e-2 || scaled.abs() >= 1e5) {
+            write!(f, "{:.*e}{}{}", precision, scaled, separator, name)
+        } else {
+            write!(f, "{:.*}{}{}", precision, scaled, separator, name)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::units::Dimensionless;
+    use crate::units::Length;
+    use crate::units::Time;
+
+    #[test]
+    fn display_chooses_readable_unit() {
+        assert_eq!(format!("{}", Length::meters(3e19)), "972.23 pc");
+        assert_eq!(format!("{}", Length::kiloparsec(6.79)), "6.79 kpc");
+        assert_eq!(format!("{}", Length::meters(5.0)), "5.00 m");
+        assert_eq!(format!("{:.1}", Time::megayears(10.0)), "10.0 Myr");
+        assert_eq!(format!("{}", Dimensionless::dimensionless(0.5)), "0.50");
+    }
+
+    #[test]
+    fn display_uses_scientific_notation_outside_of_unit_table() {
+        assert_eq!(format!("{}", Length::meters(1e-5)), "1.00e-5 m");
+        assert_eq!(format!("{}", Length::gigaparsec(1e6)), "1.00e6 Gpc");
+        assert_eq!(format!("{}", Length::zero()), "0.00 m");
+    }
+}
@@ -0,0 +1,71 @@
+use std::fmt;
+
+use super::Dimension;
+use super::Quantity;
+use crate::prelude::Float;
+
+/// An error while converting a quantity into a unit given as a
+/// string, either because the unit is unknown or because it has a
+/// different dimension than the quantity.
+#[derive(Debug, PartialEq)]
+pub struct UnitConversionError(String);
+
+impl fmt::Display for UnitConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid unit: {}", self.0)
+    }
+}
+
+impl std::error::Error for UnitConversionError {}
+
+impl<const D: Dimension> Quantity<Float, D> {
+    /// The value of the quantity in the given unit, for example
+    /// `g/cm^3` for a density. The unit is written as in the
+    /// parameter files, i.e. as a sequence of unit symbols with
+    /// optional exponents, such as `kg m^-3`. Returns an error if the
+    /// unit is unknown or has a different dimension than the
+    /// quantity.
+    pub fn in_units(&self, unit: &str) -> Result<Float, UnitConversionError> {
+        let unit: Self = serde_yaml::from_str(&format!("1.0 {unit}"))
+            .map_err(|e| UnitConversionError(format!("'{unit}': {e}")))?;
+        Ok(self.value_unchecked() / unit.value_unchecked())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::assert_float_is_close;
+    use crate::units::Density;
+    use crate::units::Length;
+    use crate::units::Mass;
+    use crate::units::PhotonRate;
+    use crate::units::Time;
+    use crate::units::Velocity;
+    use crate::units::Volume3D;
+
+    #[test]
+    fn in_units_converts_compound_units() {
+        let density: Density = Mass::kilograms(1.0) / Volume3D::cubic_meters(1.0);
+        assert_float_is_close(density.in_units("g/cm^3").unwrap(), 1e-3);
+        assert_float_is_close(density.in_units("kg m^-3").unwrap(), 1.0);
+        assert_float_is_close(
+            Velocity::meters_per_second(1000.0)
+                .in_units("km s^-1")
+                .unwrap(),
+            1.0,
+        );
+        assert_float_is_close(
+            PhotonRate::photons_per_second(5.0)
+                .in_units("s^-1")
+                .unwrap(),
+            5.0,
+        );
+        assert!((Time::megayears(1.0).in_units("kyr").unwrap() - 1000.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn in_units_checks_dimension() {
+        assert!(Length::meters(1.0).in_units("s").is_err());
+        assert!(Length::meters(1.0).in_units("furlong").is_err());
+    }
+}
@@ -0,0 +1,116 @@
+use std::fmt;
+
+use super::Density;
+use super::Mass;
+use super::Volume3D;
+use crate::prelude::Float;
+
+/// The relative deviation between `mass` and `density * volume` up
+/// to which the three quantities are considered consistent.
+const RELATIVE_TOLERANCE: Float = 1e-6;
+
+/// An error while deriving the mass, density and volume of a cell.
+#[derive(Debug, PartialEq)]
+pub enum MassDensityVolumeError {
+    /// Fewer than two of the three quantities were given.
+    Underdetermined,
+    /// All three quantities were given but `mass != density * volume`.
+    Inconsistent { relative_error: Float },
+}
+
+impl fmt::Display for MassDensityVolumeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MassDensityVolumeError::Underdetermined => write!(
+                f,
+                "At least two of mass, density and volume are required to derive the third"
+            ),
+            MassDensityVolumeError::Inconsistent { relative_error } => write!(
+                f,
+                "Mass, density and volume are inconsistent: mass and density * volume differ by a relative error of {:.3e}",
+                relative_error
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MassDensityVolumeError {}
+
+/// The mass, density and volume of a cell, which are guaranteed to
+/// satisfy `mass = density * volume`.
+#[derive(Clone, Copy, Debug)]
+pub struct MassDensityVolume {
+    pub mass: Mass,
+    pub density: Density,
+    pub volume: Volume3D,
+}
+
+impl MassDensityVolume {
+    /// Derives the missing quantity from the two given ones. If all
+    /// three are given, checks that they are consistent with each
+    /// other.
+    pub fn from_any(
+        mass: Option<Mass>,
+        density: Option<Density>,
+        volume: Option<Volume3D>,
+    ) -> Result<Self, MassDensityVolumeError> {
+        let (mass, density, volume) = match (mass, density, volume) {
+            (Some(mass), Some(density), Some(volume)) => {
+                let error = (mass - density * volume).abs();
+                if error > mass.abs() * RELATIVE_TOLERANCE {
+                    let relative_error = (error / mass).value().abs();
+                    return Err(MassDensityVolumeError::Inconsistent { relative_error });
+                }
+                (mass, density, volume)
+            }
+            (Some(mass), Some(density), None) => (mass, density, mass / density),
+            (Some(mass), None, Some(volume)) => (mass, mass / volume, volume),
+            (None, Some(density), Some(volume)) => (density * volume, density, volume),
+            _ => return Err(MassDensityVolumeError::Underdetermined),
+        };
+        Ok(Self {
+            mass,
+            density,
+            volume,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MassDensityVolume;
+    use super::MassDensityVolumeError;
+    use crate::test_utils::assert_is_close;
+    use crate::units::Density;
+    use crate::units::Mass;
+    use crate::units::Volume3D;
+
+    #[test]
+    fn missing_quantity_is_derived() {
+        let mass = Mass::kilograms(6.0);
+        let volume = Volume3D::cubic_meters(2.0);
+        let density: Density = mass / volume;
+        let derived = MassDensityVolume::from_any(None, Some(density), Some(volume)).unwrap();
+        assert_is_close(derived.mass, mass);
+        let derived = MassDensityVolume::from_any(Some(mass), Some(density), None).unwrap();
+        assert_is_close(derived.volume, volume);
+        let derived = MassDensityVolume::from_any(Some(mass), None, Some(volume)).unwrap();
+        assert_is_close(derived.density, density);
+        assert!(MassDensityVolume::from_any(Some(mass), Some(density), Some(volume)).is_ok());
+    }
+
+    #[test]
+    fn inconsistent_quantities_are_rejected() {
+        let mass = Mass::kilograms(6.0);
+        let volume = Volume3D::cubic_meters(2.0);
+        let density: Density = Mass::kilograms(4.0) / volume;
+        assert!(matches!(
+            MassDensityVolume::from_any(Some(mass), Some(density), Some(volume)),
+            Err(MassDensityVolumeError::Inconsistent { .. })
+        ));
+        assert_eq!(
+            MassDensityVolume::from_any(Some(mass), None, None).unwrap_err(),
+            MassDensityVolumeError::Underdetermined
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use std::ops::Add;
+use std::ops::Mul;
+use std::ops::Sub;
+
+use super::Dimension;
+use super::MVec3;
+use super::Quantity;
+use crate::prelude::Float;
+
+pub type MMat3 = glam::DMat3;
+
+/// A dimensioned 3x3 matrix, such as a stress or tidal tensor. This
+/// mirrors the vector quantities: multiplying by a scalar, a vector
+/// or another matrix multiplies the dimensions.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MatQuantity<const D: Dimension>(MMat3);
+
+impl<const D: Dimension> MatQuantity<D> {
+    pub fn new_unchecked(m: MMat3) -> Self {
+        Self(m)
+    }
+
+    pub fn value_unchecked(&self) -> MMat3 {
+        self.0
+    }
+
+    pub fn zero() -> Self {
+        Self(MMat3::ZERO)
+    }
+
+    pub fn from_cols(x: Quantity<MVec3, D>, y: Quantity<MVec3, D>, z: Quantity<MVec3, D>) -> Self {
+        Self(MMat3::from_cols(
+            x.value_unchecked(),
+            y.value_unchecked(),
+            z.value_unchecked(),
+        ))
+    }
+
+    pub fn from_diagonal(diagonal: Quantity<MVec3, D>) -> Self {
+        Self(MMat3::from_diagonal(diagonal.value_unchecked()))
+    }
+
+    pub fn transpose(&self) -> Self {
+        Self(self.0.transpose())
+    }
+
+    pub fn trace(&self) -> Quantity<Float, D> {
+        Quantity::new_unchecked(self.0.x_axis.x + self.0.y_axis.y + self.0.z_axis.z)
+    }
+}
+
+impl<const D: Dimension> Quantity<MVec3, D> {
+    /// The outer product `self other^T` of two vectors.
+    pub fn outer<const E: Dimension>(
+        &self,
+        other: Quantity<MVec3, E>,
+    ) -> MatQuantity<{ D.dimension_mul(E) }>
+    where
+        MatQuantity<{ D.dimension_mul(E) }>:,
+    {
+        let (a, b) = (self.0, other.0);
+        MatQuantity(MMat3::from_cols(a * b.x, a * b.y, a * b.z))
+    }
+}
+
+impl<const D: Dimension> Add for MatQuantity<D> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self(self.0 + rhs.0)
+    }
+}
+
+impl<const D: Dimension> Sub for MatQuantity<D> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self(self.0 - rhs.0)
+    }
+}
+
+impl<const D: Dimension, const E: Dimension> Mul<Quantity<Float, E>> for MatQuantity<D>
+where
+    MatQuantity<{ D.dimension_mul(E) }>:,
+{
+    type Output = MatQuantity<{ D.dimension_mul(E) }>;
+
+    fn mul(self, rhs: Quantity<Float, E>) -> Self::Output {
+        MatQuantity(self.0 * rhs.value_unchecked())
+    }
+}
+
+impl<const D: Dimension, const E: Dimension> Mul<Quantity<MVec3, E>> for MatQuantity<D>
+where
+    Quantity<MVec3, { D.dimension_mul(E) }>:,
+{
+    type Output = Quantity<MVec3, { D.dimension_mul(E) }>;
+
+    fn mul(self, rhs: Quantity<MVec3, E>) -> Self::Output {
+        Quantity::new_unchecked(self.0 * rhs.value_unchecked())
+    }
+}
+
+impl<const D: Dimension, const E: Dimension> Mul<MatQuantity<E>> for MatQuantity<D>
+where
+    MatQuantity<{ D.dimension_mul(E) }>:,
+{
+    type Output = MatQuantity<{ D.dimension_mul(E) }>;
+
+    fn mul(self, rhs: MatQuantity<E>) -> Self::Output {
+        MatQuantity(self.0 * rhs.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MMat3;
+    use super::MatQuantity;
+    use crate::units::dvec3;
+    use crate::units::Length;
+    use crate::units::MVec3;
+
+    #[test]
+    fn matrix_times_vector_multiplies_dimensions() {
+        let stress = MatQuantity::from_diagonal(dvec3::EnergyDensity::new_unchecked(MVec3::new(
+            1.0, 2.0, 3.0,
+        )));
+        let area = dvec3::Area::new_unchecked(MVec3::new(1.0, 1.0, 2.0));
+        let force: dvec3::Force = stress * area;
+        assert_eq!(force.value_unchecked(), MVec3::new(1.0, 2.0, 6.0));
+        let doubled: dvec3::Force = (stress * Length::meters(2.0)) * (area / Length::meters(1.0));
+        assert_eq!(doubled.value_unchecked(), MVec3::new(2.0, 4.0, 12.0));
+    }
+
+    #[test]
+    fn outer_product_and_trace() {
+        let a = dvec3::Length::new_unchecked(MVec3::new(1.0, 2.0, 3.0));
+        let b = dvec3::Length::new_unchecked(MVec3::new(4.0, 5.0, 6.0));
+        let m = a.outer(b);
+        assert_eq!(m.trace().value_unchecked(), 32.0);
+        assert_eq!(
+            m.transpose().value_unchecked(),
+            b.outer(a).value_unchecked()
+        );
+        assert_eq!(
+            m.value_unchecked(),
+            MMat3::from_cols_array(&[4.0, 8.0, 12.0, 5.0, 10.0, 15.0, 6.0, 12.0, 18.0])
+        );
+    }
+}
@@ -1,10 +1,19 @@
 mod dimension;
+mod display;
 pub(crate) mod helpers;
+mod in_units;
+mod mass_density_volume;
+mod matrix;
 mod specific_impls;
 
 use diman::unit_system;
 pub use dimension::Dimension;
 pub use dimension::NONE;
+pub use in_units::UnitConversionError;
+pub use mass_density_volume::MassDensityVolume;
+pub use mass_density_volume::MassDensityVolumeError;
+pub use matrix::MMat3;
+pub use matrix::MatQuantity;
 
 #[rustfmt::skip]
 unit_system!(
@@ -113,12 +122,12 @@ pub use self::f64::*;
 
 #[cfg(feature = "2d")]
 mod reexport {
-    use super::Dimension;
-
-    pub type Density = super::Density2D;
-    pub type NumberDensity = super::NumberDensity2D;
+    // Densities, cross sections and rates stay three-dimensional
+    // in 2D, since the chemistry treats each cell as a slab of
+    // finite thickness.
     pub type Volume = super::Volume2D;
-    pub type CrossSection = super::CrossSection2D;
+    pub type VecLength = super::dvec2::Length;
+    pub type VecDimensionless = super::dvec2::Dimensionless;
     pub type MVec = super::MVec2;
 }
 
@@ -134,5 +143,6 @@ pub type MVec2 = glam::DVec2;
 pub type MVec3 = glam::DVec3;
 pub type Vec2Length = self::dvec2::Length;
 pub type Vec3Length = self::dvec3::Length;
+pub type Vec3Dimensionless = self::dvec3::Dimensionless;
 
 pub use reexport::*;
@@ -4,8 +4,10 @@ use super::Dimensionless;
 use super::EnergyDensity;
 use super::EnergyPerMass;
 use super::Length;
+use super::MVec3;
 use super::Quantity;
 use super::Temperature;
+use super::Vec3Dimensionless;
 use super::BOLTZMANN_CONSTANT;
 use super::GAMMA;
 use super::PROTON_MASS;
@@ -16,6 +18,23 @@ impl<const D: Dimension> Quantity<Float, D> {
     pub fn one_unchecked() -> Self {
         Self(1.0)
     }
+
+    pub fn is_finite(&self) -> bool {
+        self.0.is_finite()
+    }
+
+    /// Panics if the quantity is NaN or infinite, but only in debug
+    /// builds. Meant to catch numerical blowups where they happen
+    /// instead of when the result is written.
+    #[track_caller]
+    pub fn debug_assert_finite(&self) {
+        debug_assert!(
+            self.is_finite(),
+            "Non-finite quantity: {} (dimension {:?})",
+            self.0,
+            D
+        );
+    }
 }
 
 impl<const D: Dimension, S> Quantity<S, D> {
@@ -24,6 +43,34 @@ impl<const D: Dimension, S> Quantity<S, D> {
     }
 }
 
+impl<const D: Dimension> Quantity<MVec3, D> {
+    /// The cross product of two vectors. The dimension of the result
+    /// is the product of the dimensions of both vectors.
+    pub fn cross<const E: Dimension>(
+        &self,
+        other: Quantity<MVec3, E>,
+    ) -> Quantity<MVec3, { D.dimension_mul(E) }>
+    where
+        Quantity<MVec3, { D.dimension_mul(E) }>:,
+    {
+        Quantity::new_unchecked(self.0.cross(other.0))
+    }
+
+    /// Rotate the vector around the given (normalized) axis by the
+    /// given angle in radians, counterclockwise when looking
+    /// against the direction of the axis.
+    pub fn rotate_around(&self, axis: Vec3Dimensionless, angle: Dimensionless) -> Self {
+        let (axis, angle) = (axis.0, angle.value());
+        let v = self.0;
+        // Rodrigues' rotation formula
+        Self::new_unchecked(
+            v * angle.cos()
+                + axis.cross(v) * angle.sin()
+                + axis * axis.dot(v) * (1.0 - angle.cos()),
+        )
+    }
+}
+
 #[cfg(feature = "3d")]
 impl super::Vec3Length {
     pub fn from_vector_and_scale(m: super::MVec3, l: Length) -> super::Vec3Length {
@@ -83,3 +130,54 @@ where
         Quantity::new_unchecked(self.0 * cosmology.get_factor(&D))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use crate::test_utils::assert_float_is_close;
+    use crate::units::Dimensionless;
+    use crate::units::Length;
+    use crate::units::MVec3;
+    use crate::units::Vec3Dimensionless;
+    use crate::units::Vec3Length;
+
+    #[test]
+    fn division_by_zero_is_not_finite() {
+        let ratio = Length::meters(1.0) / Length::meters(0.0);
+        assert!(!ratio.is_finite());
+        assert!(Length::meters(1.0).is_finite());
+        Length::meters(1.0).debug_assert_finite();
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "Non-finite quantity")]
+    fn debug_assert_finite_catches_division_by_zero() {
+        (Length::meters(0.0) / Length::meters(0.0)).debug_assert_finite();
+    }
+
+    #[test]
+    fn cross_product_of_basis_vectors() {
+        let x = Vec3Length::new_unchecked(MVec3::X);
+        let y = Vec3Length::new_unchecked(MVec3::Y);
+        let area = x.cross(y);
+        assert_eq!(area.value_unchecked(), MVec3::Z);
+        let z = Vec3Dimensionless::new_unchecked(MVec3::Z);
+        let x = Vec3Dimensionless::new_unchecked(MVec3::X);
+        assert_eq!(z.cross(x).value_unchecked(), MVec3::Y);
+    }
+
+    #[test]
+    fn quarter_rotation_maps_x_to_y() {
+        let x = Vec3Length::new_unchecked(MVec3::X);
+        let rotated = x.rotate_around(
+            Vec3Dimensionless::new_unchecked(MVec3::Z),
+            Dimensionless::dimensionless(PI / 2.0),
+        );
+        let rotated = rotated.value_unchecked();
+        assert_float_is_close(rotated.x, 0.0);
+        assert_float_is_close(rotated.y, 1.0);
+        assert_float_is_close(rotated.z, 0.0);
+    }
+}
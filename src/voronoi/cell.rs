@@ -15,6 +15,7 @@ use crate::dimension::Point;
 use crate::dimension::ThreeD;
 use crate::dimension::TwoD;
 use crate::hash_map::HashSet;
+use crate::hash_map::IterSorted;
 use crate::prelude::Float;
 use crate::sweep::grid::ParticleType;
 use crate::voronoi::delaunay::TetraIndex;
@@ -24,6 +25,11 @@ pub trait DCell: Sized {
     type Dimension: DDimension;
     fn size(&self) -> Float;
     fn volume(&self) -> Float;
+    /// The geometric center of the cell, which in general differs
+    /// from the generating point.
+    fn centroid(&self) -> Point<Self::Dimension>;
+    /// The sum of the areas of all faces of the cell.
+    fn surface_area(&self) -> Float;
     fn contains(&self, point: Point<Self::Dimension>) -> bool;
     fn new(data: &TriangulationData<Self::Dimension>, point: PointIndex) -> Self;
 }
@@ -134,6 +140,20 @@ impl DCell for Cell<TwoD> {
             .abs()
     }
 
+    fn centroid(&self) -> Point2d {
+        let (signed_area, weighted) =
+            self.point_windows()
+                .fold((0.0, Point2d::ZERO), |(area, weighted), (p1, p2)| {
+                    let cross = p1.x * p2.y - p2.x * p1.y;
+                    (area + 0.5 * cross, weighted + (*p1 + *p2) * cross)
+                });
+        weighted / (6.0 * signed_area)
+    }
+
+    fn surface_area(&self) -> Float {
+        self.faces.iter().map(|face| face.area).sum()
+    }
+
     fn new(data: &TriangulationData<TwoD>, p: PointIndex) -> Self {
         let tetras = &data.point_to_tetras_map[&p];
         let points = arrange_cyclic_by(tetras, |t1, t2| Self::tetras_are_neighbours(data, t1, t2))
@@ -167,8 +187,10 @@ impl Cell<ThreeD> {
                     .filter(|p2| *p2 != p1)
             })
             .collect();
+        // Sort the faces so that sums over them do not depend on
+        // the iteration order of the set.
         connected_points
-            .iter()
+            .iter_sorted()
             .map(|p2| get_face_polygon_perpendicular_to_line(data, p1, *p2))
             .collect()
     }
@@ -196,6 +218,27 @@ impl DCell for Cell<ThreeD> {
             .sum()
     }
 
+    fn centroid(&self) -> Point3d {
+        // Decompose the cell into tetrahedra spanned by the
+        // generating point and a triangle of one of the faces.
+        let mut volume = 0.0;
+        let mut weighted = Point3d::ZERO;
+        for face in self.faces.iter() {
+            let r = face.data.points[0];
+            for (p1, p2) in periodic_windows_2(&face.data.points) {
+                let (a, b, c) = (r - self.center, *p1 - self.center, *p2 - self.center);
+                let tetra_volume = a.dot(b.cross(c)).abs() / 6.0;
+                volume += tetra_volume;
+                weighted += tetra_volume * (self.center + r + *p1 + *p2) / 4.0;
+            }
+        }
+        weighted / volume
+    }
+
+    fn surface_area(&self) -> Float {
+        self.faces.iter().map(|face| face.area).sum()
+    }
+
     fn new(data: &TriangulationData<ThreeD>, p: PointIndex) -> Self {
         let points = data.point_to_tetras_map[&p]
             .iter()
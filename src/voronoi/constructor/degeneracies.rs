@@ -0,0 +1,293 @@
+use derive_custom::subsweep_parameters;
+use log::warn;
+
+use super::super::Cell;
+use super::super::DCell;
+use super::super::DDimension;
+use super::super::Delaunay;
+use super::super::Triangulation;
+use super::super::TriangulationData;
+use crate::dimension::Point;
+use crate::domain::IntoKey;
+use crate::extent::Extent;
+use crate::hash_map::HashMap;
+use crate::hash_map::HashSet;
+use crate::prelude::ParticleId;
+use crate::sweep::grid::ParticleType;
+use crate::voronoi::DVector;
+
+/// Faces whose area is smaller than this fraction of the largest
+/// face of the same cell are considered to have zero area.
+const ZERO_AREA_RELATIVE_TOLERANCE: f64 = 1e-10;
+
+/// The distance by which coincident points are moved apart with
+/// [DuplicateHandling::Perturb], relative to the largest side length
+/// of the extent.
+const PERTURBATION_RELATIVE_DISTANCE: f64 = 1e-6;
+
+/// What to do with input points at the exact position of another
+/// input point, which the Delaunay construction cannot handle.
+#[derive(Copy, Debug, Default, PartialEq, Eq)]
+#[subsweep_parameters]
+pub enum DuplicateHandling {
+    /// Insert all points as they are. Unless the degeneracy check is
+    /// enabled (in which case the duplicates are merged), coincident
+    /// points will make the construction fail.
+    #[default]
+    Ignore,
+    /// Only insert the first of the coincident points. The others do
+    /// not get a cell. The merged pairs are available from
+    /// [Constructor::duplicates](super::Constructor::duplicates), so
+    /// that the caller can combine their mass.
+    Merge,
+    /// Move every duplicate by a small, deterministic distance
+    /// towards the center of the extent, so that all points get
+    /// their own (very thin) cell. Only the points passed to the
+    /// constructor are moved, so duplicates on different ranks are
+    /// not detected and halo copies keep their original position.
+    Perturb,
+}
+
+/// Degeneracies found during the construction of the grid. Only
+/// collected if requested on construction.
+#[derive(Debug, Default)]
+pub struct DegeneracyReport {
+    /// Pairs of input points at identical positions. Unless they are
+    /// perturbed, only the first point of each pair is inserted into
+    /// the triangulation, so the second one does not have a cell.
+    pub coincident_points: Vec<(ParticleId, ParticleId)>,
+    /// Faces of local cells with (numerically) zero area, given by
+    /// the cell and the neighbour on the other side of the face.
+    pub zero_area_faces: Vec<(ParticleId, ParticleType)>,
+    /// Faces between two local cells which only appear in one of the
+    /// two cells.
+    pub one_sided_faces: Vec<(ParticleId, ParticleId)>,
+}
+
+impl DegeneracyReport {
+    pub fn is_empty(&self) -> bool {
+        self.coincident_points.is_empty()
+            && self.zero_area_faces.is_empty()
+            && self.one_sided_faces.is_empty()
+    }
+
+    pub(super) fn warn(&self) {
+        if !self.coincident_points.is_empty() {
+            warn!(
+                "{} input points coincide with another point and were not inserted.",
+                self.coincident_points.len()
+            );
+        }
+        if !self.zero_area_faces.is_empty() {
+            warn!(
+                "Found {} voronoi faces with zero area.",
+                self.zero_area_faces.len()
+            );
+        }
+        if !self.one_sided_faces.is_empty() {
+            warn!(
+                "Found {} voronoi faces which are only part of one of the two adjacent cells.",
+                self.one_sided_faces.len()
+            );
+        }
+    }
+}
+
+/// Finds all points which are at the exact position of a previous
+/// point and returns the pairs of coincident points. To avoid
+/// comparing all pairs of points, the points are sorted by their
+/// peano hilbert key, so that coincident points end up next to each
+/// other.
+pub(super) fn find_coincident_points<D: DDimension>(
+    points: &[(ParticleId, Point<D>)],
+    extent: &Extent<Point<D>>,
+) -> Vec<(ParticleId, ParticleId)> {
+    let mut sorted: Vec<_> = points
+        .iter()
+        .map(|(id, p)| (p.into_key(extent), *id, *p))
+        .collect();
+    sorted.sort_by_key(|(key, id, _)| (*key, *id));
+    let mut coincident = vec![];
+    let mut group_start = 0;
+    for i in 1..sorted.len() {
+        if sorted[i].0 != sorted[group_start].0 {
+            group_start = i;
+            continue;
+        }
+        let (_, id, p) = sorted[i];
+        if let Some((_, first, _)) = sorted[group_start..i]
+            .iter()
+            .find(|(_, _, q)| p.distance_squared(*q) == 0.0)
+        {
+            coincident.push((*first, id));
+        }
+    }
+    coincident
+}
+
+/// Removes the second point of each pair of coincident points.
+pub(super) fn remove_coincident_points<D: DDimension>(
+    points: &mut Vec<(ParticleId, Point<D>)>,
+    coincident: &[(ParticleId, ParticleId)],
+) {
+    let removed: HashSet<_> = coincident.iter().map(|(_, id)| *id).collect();
+    points.retain(|(id, _)| !removed.contains(id));
+}
+
+/// Moves the second point of each pair of coincident points along
+/// the diagonal of the extent, towards its center. Multiple
+/// duplicates of the same point are moved by multiples of the
+/// perturbation distance, so that they do not coincide with each
+/// other afterwards.
+pub(super) fn perturb_coincident_points<D: DDimension>(
+    points: &mut [(ParticleId, Point<D>)],
+    coincident: &[(ParticleId, ParticleId)],
+    extent: &Extent<Point<D>>,
+) {
+    let step = extent.side_lengths().normalize()
+        * (extent.max_side_length() * PERTURBATION_RELATIVE_DISTANCE);
+    let mut num_duplicates: HashMap<ParticleId, usize> = HashMap::default();
+    let offsets: HashMap<ParticleId, f64> = coincident
+        .iter()
+        .map(|(first, id)| {
+            let num = num_duplicates.entry(*first).or_default();
+            *num += 1;
+            (*id, *num as f64)
+        })
+        .collect();
+    for (id, p) in points.iter_mut() {
+        if let Some(offset) = offsets.get(id) {
+            let towards_center = if (*p - extent.center()).dot(step) > 0.0 {
+                -*offset
+            } else {
+                *offset
+            };
+            *p = *p + step * towards_center;
+        }
+    }
+}
+
+/// Finds faces with zero area and faces which are only part of one
+/// of the two (local) cells that they separate.
+pub(super) fn check_faces<D: DDimension>(data: &TriangulationData<D>, report: &mut DegeneracyReport)
+where
+    Cell<D>: DCell<Dimension = D>,
+{
+    let mut connections = HashSet::default();
+    for cell in data.iter_voronoi_cells() {
+        let ParticleType::Local(id) = data.get_particle_type(cell.delaunay_point) else {
+            continue;
+        };
+        let max_area = cell.faces.iter().map(|face| face.area).fold(0.0, f64::max);
+        for face in cell.faces.iter() {
+            if face.area <= ZERO_AREA_RELATIVE_TOLERANCE * max_area {
+                report.zero_area_faces.push((id, face.connection));
+            }
+            if let ParticleType::Local(neighbour) = face.connection {
+                connections.insert((id, neighbour));
+            }
+        }
+    }
+    report.one_sided_faces = connections
+        .iter()
+        .filter(|(id, neighbour)| !connections.contains(&(*neighbour, *id)))
+        .copied()
+        .collect();
+    report.one_sided_faces.sort();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DuplicateHandling;
+    use crate::dimension::ActiveDimension;
+    use crate::dimension::Point;
+    use crate::hash_map::HashSet;
+    use crate::prelude::ParticleId;
+    use crate::sweep::grid::ParticleType;
+    use crate::test_utils::get_particles;
+    use crate::units::Volume;
+    use crate::voronoi::Constructor;
+
+    #[test]
+    fn coincident_points_are_reported() {
+        let mut points: Vec<_> = get_particles(4, 4)
+            .into_iter()
+            .map(|p| (p.id, p.pos.value_unchecked()))
+            .collect();
+        let (original, pos) = points[5];
+        let duplicate = ParticleId::test(1000);
+        points.push((duplicate, pos));
+        let cons = Constructor::<ActiveDimension>::new_with_degeneracy_check(points.into_iter());
+        let report = cons.degeneracies().unwrap();
+        assert_eq!(report.coincident_points, vec![(original, duplicate)]);
+    }
+
+    #[test]
+    fn regular_input_has_no_coincident_points_or_one_sided_faces() {
+        let points = get_particles(4, 4)
+            .into_iter()
+            .map(|p| (p.id, p.pos.value_unchecked()));
+        let cons = Constructor::<ActiveDimension>::new_with_degeneracy_check(points);
+        let report = cons.degeneracies().unwrap();
+        assert!(report.coincident_points.is_empty());
+        assert!(report.one_sided_faces.is_empty());
+    }
+
+    fn points_with_two_duplicates() -> Vec<(ParticleId, Point<ActiveDimension>)> {
+        let mut points: Vec<_> = get_particles(4, 4)
+            .into_iter()
+            .map(|p| (p.id, p.pos.value_unchecked()))
+            .collect();
+        let (_, pos) = points[5];
+        points.push((ParticleId::test(1000), pos));
+        points.push((ParticleId::test(1001), pos));
+        points
+    }
+
+    fn constructed_cells(
+        handling: DuplicateHandling,
+    ) -> (Constructor<ActiveDimension>, HashSet<ParticleId>) {
+        let points = points_with_two_duplicates();
+        let cons = Constructor::<ActiveDimension>::new_with_duplicate_handling(
+            points.into_iter(),
+            handling,
+        );
+        let mut ids = HashSet::default();
+        for (ptype, cell) in cons.sweep_grid(false) {
+            if let ParticleType::Local(id) = ptype {
+                assert!(cell.volume > Volume::zero());
+                assert!(cell.volume.value_unchecked().is_finite());
+                ids.insert(id);
+            }
+        }
+        (cons, ids)
+    }
+
+    #[test]
+    fn duplicates_are_merged() {
+        let points = points_with_two_duplicates();
+        let original = points[5].0;
+        let (cons, ids) = constructed_cells(DuplicateHandling::Merge);
+        assert_eq!(
+            cons.duplicates(),
+            &[
+                (original, ParticleId::test(1000)),
+                (original, ParticleId::test(1001))
+            ]
+        );
+        assert_eq!(ids.len(), points.len() - 2);
+        assert!(ids.contains(&original));
+        assert!(!ids.contains(&ParticleId::test(1000)));
+    }
+
+    #[test]
+    fn duplicates_are_perturbed() {
+        let points = points_with_two_duplicates();
+        let (cons, ids) = constructed_cells(DuplicateHandling::Perturb);
+        assert_eq!(cons.duplicates().len(), 2);
+        assert_eq!(ids.len(), points.len());
+        for (id, _) in points.iter() {
+            assert!(ids.contains(id));
+        }
+    }
+}
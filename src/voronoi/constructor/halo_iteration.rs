@@ -286,9 +286,11 @@ mod tests {
     use crate::prelude::ParticleId;
     use crate::voronoi::constructor::halo_cache::HaloCache;
     use crate::voronoi::constructor::Constructor;
+    use crate::voronoi::constructor::DuplicateHandling;
     use crate::voronoi::delaunay::dimension::DTetra;
     use crate::voronoi::delaunay::Delaunay;
     use crate::voronoi::delaunay::PointKind;
+    use crate::voronoi::delaunay::PointPrecision;
     use crate::voronoi::math::traits::DVector;
     use crate::voronoi::test_utils::TestDimension;
     use crate::voronoi::visualizer::Visualizable;
@@ -485,6 +487,9 @@ mod tests {
                 cache: HaloCache::default(),
             },
             None,
+            false,
+            PointPrecision::default(),
+            DuplicateHandling::default(),
         );
         let full_data = full_constructor.data;
         let sub_data = sub_constructor.data;
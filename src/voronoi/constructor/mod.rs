@@ -1,3 +1,4 @@
+mod degeneracies;
 mod halo_cache;
 mod halo_iteration;
 mod local;
@@ -7,11 +8,19 @@ use log::debug;
 use log::info;
 pub use parallel::ParallelVoronoiGridConstruction;
 
+use self::degeneracies::check_faces;
+use self::degeneracies::find_coincident_points;
+use self::degeneracies::perturb_coincident_points;
+use self::degeneracies::remove_coincident_points;
+pub use self::degeneracies::DegeneracyReport;
+pub use self::degeneracies::DuplicateHandling;
+
 use self::halo_iteration::HaloIteration;
 use self::halo_iteration::RadiusSearch;
 pub(super) use self::halo_iteration::SearchData;
 use self::local::Local;
 use super::delaunay::PointIndex;
+use super::delaunay::PointPrecision;
 use super::delaunay::TetraIndex;
 use super::primitives::Float;
 use super::visualizer::Visualizable;
@@ -39,6 +48,8 @@ use crate::voronoi::DDimension;
 
 pub struct Constructor<D: DDimension> {
     data: TriangulationData<D>,
+    degeneracies: Option<DegeneracyReport>,
+    duplicates: Vec<(ParticleId, ParticleId)>,
 }
 
 impl<D> Constructor<D>
@@ -51,23 +62,134 @@ where
 {
     pub fn construct_from_iter<'b, F>(
         iter: impl Iterator<Item = (ParticleId, Point<D>)> + 'b,
-        mut search: F,
+        search: F,
         characteristic_length: Option<Float>,
+        check_degeneracies: bool,
+        precision: PointPrecision,
+        duplicate_handling: DuplicateHandling,
+    ) -> Self
+    where
+        F: RadiusSearch<D>,
+    {
+        Self::construct_from_points(
+            iter.collect(),
+            search,
+            characteristic_length,
+            check_degeneracies,
+            false,
+            precision,
+            duplicate_handling,
+        )
+    }
+
+    /// Like [Constructor::construct_from_iter], but for points which
+    /// are already sorted along a space-filling curve, for example
+    /// because their ids were assigned in the order of their keys
+    /// during the domain decomposition. The ordering is trusted and
+    /// the points are not sorted again. Any spatially coherent
+    /// ordering results in the same triangulation, the sorting only
+    /// serves to make point location fast.
+    pub fn construct_from_sorted_iter<'b, F>(
+        iter: impl Iterator<Item = (ParticleId, Point<D>)> + 'b,
+        search: F,
+        characteristic_length: Option<Float>,
+        check_degeneracies: bool,
+        precision: PointPrecision,
+        duplicate_handling: DuplicateHandling,
+    ) -> Self
+    where
+        F: RadiusSearch<D>,
+    {
+        Self::construct_from_points(
+            iter.collect(),
+            search,
+            characteristic_length,
+            check_degeneracies,
+            true,
+            precision,
+            duplicate_handling,
+        )
+    }
+
+    fn construct_from_points<F>(
+        points: Vec<(ParticleId, Point<D>)>,
+        search: F,
+        characteristic_length: Option<Float>,
+        check_degeneracies: bool,
+        sorted: bool,
+        precision: PointPrecision,
+        duplicate_handling: DuplicateHandling,
     ) -> Self
     where
         F: RadiusSearch<D>,
     {
         info!("Beginning local Delaunay construction.");
-        let points: Vec<_> = iter.collect();
         let extent = search
             .determine_global_extent()
             .unwrap_or_else(|| Extent::from_points(points.iter().map(|p| p.1)).unwrap());
+        Self::construct(
+            points,
+            extent,
+            search,
+            characteristic_length,
+            check_degeneracies,
+            sorted,
+            precision,
+            duplicate_handling,
+        )
+    }
+
+    fn construct<F>(
+        mut points: Vec<(ParticleId, Point<D>)>,
+        extent: Extent<Point<D>>,
+        search: F,
+        characteristic_length: Option<Float>,
+        check_degeneracies: bool,
+        sorted: bool,
+        precision: PointPrecision,
+        duplicate_handling: DuplicateHandling,
+    ) -> Self
+    where
+        F: RadiusSearch<D>,
+    {
+        let duplicate_handling = match duplicate_handling {
+            DuplicateHandling::Ignore if check_degeneracies => DuplicateHandling::Merge,
+            handling => handling,
+        };
+        let duplicates = match duplicate_handling {
+            DuplicateHandling::Ignore => vec![],
+            DuplicateHandling::Merge => {
+                let duplicates = find_coincident_points::<D>(&points, &extent);
+                remove_coincident_points::<D>(&mut points, &duplicates);
+                duplicates
+            }
+            DuplicateHandling::Perturb => {
+                let duplicates = find_coincident_points::<D>(&points, &extent);
+                perturb_coincident_points::<D>(&mut points, &duplicates, &extent);
+                duplicates
+            }
+        };
+        let mut degeneracies = check_degeneracies.then(|| DegeneracyReport {
+            coincident_points: duplicates.clone(),
+            ..Default::default()
+        });
         let characteristic_length = characteristic_length.unwrap_or_else(|| {
             get_characteristic_length::<D>(extent.max_side_length(), search.num_points())
         });
         let extent = extent.including_periodic_images();
-        let (triangulation, map) =
-            Triangulation::<D>::construct_from_iter_custom_extent(points.into_iter(), &extent);
+        let (triangulation, map) = if sorted {
+            Triangulation::<D>::construct_from_sorted_iter_custom_extent(
+                points.into_iter(),
+                &extent,
+                precision,
+            )
+        } else {
+            Triangulation::<D>::construct_from_iter_custom_extent(
+                points.into_iter(),
+                &extent,
+                precision,
+            )
+        };
         let mut map: BiMap<_, _> = map
             .into_iter()
             .map(|(id, p)| (ParticleType::Local(id), p))
@@ -78,11 +200,63 @@ where
         map.extend(iteration.haloes);
         info!("Finished delaunay construction.",);
         let data = TriangulationData::from_triangulation_and_map(iteration.triangulation, map);
-        Self { data }
+        if let Some(ref mut degeneracies) = degeneracies {
+            check_faces(&data, degeneracies);
+            degeneracies.warn();
+        }
+        Self {
+            data,
+            degeneracies,
+            duplicates,
+        }
     }
 
     pub fn new(points: impl Iterator<Item = (ParticleId, Point<D>)>) -> Self {
-        Self::construct_from_iter(points, Local, None)
+        Self::new_with_duplicate_handling(points, DuplicateHandling::default())
+    }
+
+    /// Like [Constructor::new], but handles input points at
+    /// identical positions as specified. The pairs of coincident
+    /// points can be obtained with [Constructor::duplicates].
+    pub fn new_with_duplicate_handling(
+        points: impl Iterator<Item = (ParticleId, Point<D>)>,
+        duplicate_handling: DuplicateHandling,
+    ) -> Self {
+        Self::construct_from_iter(
+            points,
+            Local,
+            None,
+            false,
+            PointPrecision::default(),
+            duplicate_handling,
+        )
+    }
+
+    /// Like [Constructor::new], but also collects the degeneracies
+    /// of the resulting grid, which can be obtained with
+    /// [Constructor::degeneracies].
+    pub fn new_with_degeneracy_check(points: impl Iterator<Item = (ParticleId, Point<D>)>) -> Self {
+        Self::construct_from_iter(
+            points,
+            Local,
+            None,
+            true,
+            PointPrecision::default(),
+            DuplicateHandling::default(),
+        )
+    }
+
+    /// The degeneracies found during construction, if they were
+    /// requested.
+    pub fn degeneracies(&self) -> Option<&DegeneracyReport> {
+        self.degeneracies.as_ref()
+    }
+
+    /// The pairs of input points at identical positions which were
+    /// merged or perturbed, given as the first point and its
+    /// duplicate. Empty if duplicates are ignored.
+    pub fn duplicates(&self) -> &[(ParticleId, ParticleId)] {
+        &self.duplicates
     }
 
     pub fn only_delaunay<'a>(iter: impl Iterator<Item = &'a Point<D>> + 'a) -> Triangulation<D>
@@ -168,3 +342,59 @@ impl Constructor<ActiveDimension> {
             .collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Constructor;
+    use super::DuplicateHandling;
+    use super::Local;
+    use super::PointPrecision;
+    use crate::dimension::ActiveDimension;
+    use crate::domain::IntoKey;
+    use crate::extent::Extent;
+    use crate::prelude::ParticleId;
+    use crate::sweep::grid::ParticleType;
+    use crate::voronoi::test_utils::TestDimension;
+    use crate::voronoi::DCell;
+
+    #[test]
+    fn precomputed_keys_give_identical_triangulation() {
+        let points: Vec<_> = ActiveDimension::get_example_point_set_num(100, 0)
+            .into_iter()
+            .enumerate()
+            .map(|(i, p)| (ParticleId::test(i), p))
+            .collect();
+        let extent = Extent::from_points(points.iter().map(|(_, p)| *p)).unwrap();
+        let key_extent = extent.including_periodic_images();
+        let mut sorted: Vec<_> = points
+            .iter()
+            .map(|(id, p)| (p.into_key(&key_extent), (*id, *p)))
+            .collect();
+        sorted.sort_by_key(|(key, _)| *key);
+        let with_keys = Constructor::<ActiveDimension>::construct_from_sorted_iter(
+            sorted.into_iter().map(|(_, point)| point),
+            Local,
+            None,
+            false,
+            PointPrecision::default(),
+            DuplicateHandling::default(),
+        );
+        let sorting = Constructor::<ActiveDimension>::new(points.iter().cloned());
+        for (id, _) in points.iter() {
+            let cell = ParticleType::Local(*id);
+            assert_eq!(
+                with_keys.get_point_by_cell(cell),
+                sorting.get_point_by_cell(cell)
+            );
+        }
+        let volumes = |cons: &Constructor<ActiveDimension>| {
+            let mut volumes: Vec<_> = cons
+                .iter_voronoi_cells()
+                .map(|cell| (cell.delaunay_point.0, cell.volume()))
+                .collect();
+            volumes.sort_by_key(|(point, _)| *point);
+            volumes
+        };
+        assert_eq!(volumes(&with_keys), volumes(&sorting));
+    }
+}
@@ -5,18 +5,22 @@ use derive_custom::subsweep_parameters;
 use derive_custom::Named;
 use log::debug;
 use log::warn;
+use serde::de::Error;
+use serde::Deserialize;
+use serde::Deserializer;
 
 use super::super::Constructor;
 use super::ParallelSearch;
 use crate::communication::Rank;
+use crate::communication::WorldSize;
 use crate::components::Position;
 use crate::dimension::ActiveDimension;
 use crate::domain::DecompositionState;
-use crate::domain::IdEntityMap;
 use crate::domain::QuadTree;
 use crate::parameters::SimulationBox;
 use crate::parameters::SweepParameters;
 use crate::particle::HaloParticle;
+use crate::particle::ParticleIdMap;
 use crate::prelude::ParticleId;
 use crate::prelude::Particles;
 use crate::prelude::Simulation;
@@ -27,12 +31,70 @@ use crate::sweep::grid::ParticleType;
 use crate::units::Length;
 use crate::units::VecLength;
 use crate::voronoi::constructor::halo_cache::HaloCache;
+use crate::voronoi::constructor::DuplicateHandling;
+use crate::voronoi::delaunay::PointPrecision;
 use crate::voronoi::CellIndex;
 
-#[subsweep_parameters("grid")]
+#[subsweep_parameters("grid", validate_ranges)]
 pub struct GridParameters {
     /// The initial search radius for halo iteration during grid construction.
     pub initial_search_radius: Option<Length>,
+    /// Whether to check the grid for degeneracies (coincident input
+    /// points, faces with zero area and faces which are only part of
+    /// one of the two adjacent cells) and warn about them.
+    #[serde(default)]
+    pub check_degeneracies: bool,
+    /// The precision with which the positions are stored in the
+    /// triangulation. `single` roughly halves the memory of the
+    /// point array, but the grid is then constructed from the
+    /// positions rounded to single precision.
+    #[serde(default)]
+    pub point_precision: PointPrecision,
+    /// What to do with particles at identical positions. Either
+    /// `ignore` (the default) or `perturb`. `merge` is rejected when
+    /// the parameters are read, since merged particles would not get
+    /// a cell. `perturb` only works on a single rank, since only the
+    /// local points are perturbed, while the halo copies on other
+    /// ranks keep their original positions.
+    #[serde(default, deserialize_with = "deserialize_duplicate_handling")]
+    pub duplicate_handling: DuplicateHandling,
+}
+
+impl GridParameters {
+    fn range_warnings(&self) -> Vec<String> {
+        let mut warnings = vec![];
+        if let Some(radius) = self.initial_search_radius {
+            if radius <= Length::zero() {
+                warnings.push(format!(
+                    "initial_search_radius should be positive, but is {:?}",
+                    radius
+                ));
+            }
+        }
+        warnings
+    }
+}
+
+fn deserialize_duplicate_handling<'de, D>(deserializer: D) -> Result<DuplicateHandling, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let handling = DuplicateHandling::deserialize(deserializer)?;
+    if handling == DuplicateHandling::Merge {
+        Err(D::Error::custom(
+            "Merging duplicate particles is not supported during grid construction. Use `ignore` or `perturb`.",
+        ))
+    } else {
+        Ok(handling)
+    }
+}
+
+fn check_duplicate_handling(duplicate_handling: DuplicateHandling, num_ranks: usize) {
+    if duplicate_handling == DuplicateHandling::Perturb && num_ranks > 1 {
+        panic!(
+            "Perturbing duplicate particles is only supported on a single rank, but running on {num_ranks} ranks."
+        );
+    }
 }
 
 #[derive(Named)]
@@ -70,10 +132,12 @@ pub fn construct_grid_system(
     tree: Res<QuadTree>,
     decomposition: Res<DecompositionState>,
     box_: Res<SimulationBox>,
-    map: Res<IdEntityMap>,
+    map: Res<ParticleIdMap>,
     sweep_parameters: Res<SweepParameters>,
     grid_parameters: Res<GridParameters>,
+    world_size: Res<WorldSize>,
 ) {
+    check_duplicate_handling(grid_parameters.duplicate_handling, **world_size);
     let num_points_local = particles.iter().count();
     let search = ParallelSearch::new(
         &tree,
@@ -82,12 +146,23 @@ pub fn construct_grid_system(
         HaloCache::default(),
         num_points_local,
     );
-    let cons = Constructor::<ActiveDimension>::construct_from_iter(
-        particles.iter().map(|(_, i, p)| (*i, p.value_unchecked())),
+    let mut points: Vec<_> = particles
+        .iter()
+        .map(|(_, i, p)| (*i, p.value_unchecked()))
+        .collect();
+    // The particle ids are assigned in the order of the domain keys,
+    // so sorting by id orders the points along the space-filling
+    // curve without recomputing their keys.
+    points.sort_by_key(|(id, _)| *id);
+    let cons = Constructor::<ActiveDimension>::construct_from_sorted_iter(
+        points.into_iter(),
         search,
         grid_parameters
             .initial_search_radius
             .map(|r| r.value_unchecked()),
+        grid_parameters.check_degeneracies,
+        grid_parameters.point_precision,
+        grid_parameters.duplicate_handling,
     );
     let mut num_haloes = 0;
     let mut num_relevant_haloes = 0;
@@ -110,8 +185,8 @@ pub fn construct_grid_system(
         match cell_index {
             ParticleType::Local(id) => {
                 num_local_particles += 1;
-                let entity = map.get_by_left(&id).unwrap();
-                commands.entity(*entity).insert(cell);
+                let entity = map.entity_of(id).unwrap();
+                commands.entity(entity).insert(cell);
             }
             ParticleType::Remote(remote) => {
                 add_halo(&mut commands, cell_index, cell, remote.rank, remote.id);
@@ -125,3 +200,54 @@ pub fn construct_grid_system(
     }
     warn_if_halo_fraction_too_high(num_local_particles, num_haloes, num_relevant_haloes);
 }
+
+#[cfg(test)]
+mod tests {
+    use derive_traits::SubsweepParameters;
+
+    use super::check_duplicate_handling;
+    use super::GridParameters;
+    use crate::prelude::Simulation;
+    use crate::voronoi::constructor::DuplicateHandling;
+
+    fn read_grid_parameters(contents: &str) -> GridParameters {
+        let mut sim = Simulation::default();
+        sim.add_parameter_file_contents(contents.into());
+        sim.add_parameter_type_and_get_result::<GridParameters>()
+            .clone()
+    }
+
+    #[test]
+    fn perturb_duplicate_handling_is_accepted() {
+        let params = read_grid_parameters("grid:\n  duplicate_handling: perturb");
+        assert_eq!(params.duplicate_handling, DuplicateHandling::Perturb);
+    }
+
+    #[test]
+    #[should_panic]
+    fn merge_duplicate_handling_is_rejected() {
+        read_grid_parameters("grid:\n  duplicate_handling: merge");
+    }
+
+    #[test]
+    fn perturb_duplicate_handling_is_accepted_on_single_rank() {
+        check_duplicate_handling(DuplicateHandling::Perturb, 1);
+        check_duplicate_handling(DuplicateHandling::Ignore, 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "only supported on a single rank")]
+    fn perturb_duplicate_handling_is_rejected_on_multiple_ranks() {
+        check_duplicate_handling(DuplicateHandling::Perturb, 2);
+    }
+
+    #[test]
+    fn non_positive_initial_search_radius_produces_warning() {
+        let params = read_grid_parameters("grid:\n  initial_search_radius: 0 m");
+        let warnings = params.validate_ranges();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("initial_search_radius"));
+        let params = read_grid_parameters("grid:\n  initial_search_radius: 1 m");
+        assert!(params.validate_ranges().is_empty());
+    }
+}
@@ -15,6 +15,12 @@ pub trait DDimension: Dimension {
     type Tetra: Clone + DTetra<Dimension = Self>;
     type TetraData: DTetraData<Dimension = Self> + Clone + Visualizable;
     type VoronoiFaceData: std::fmt::Debug;
+    /// The point type used to store positions with
+    /// [PointPrecision::Single](super::PointPrecision::Single).
+    type SinglePrecisionPoint: Clone + Copy + std::fmt::Debug;
+
+    fn to_single_precision(point: Point<Self>) -> Self::SinglePrecisionPoint;
+    fn from_single_precision(point: Self::SinglePrecisionPoint) -> Point<Self>;
 
     fn estimate_num_faces(num_points: usize) -> usize;
     fn estimate_num_tetras(num_points: usize) -> usize;
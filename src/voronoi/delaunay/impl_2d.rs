@@ -29,6 +29,7 @@ impl DDimension for TwoD {
     type Tetra = Tetra;
     type TetraData = TetraData;
     type VoronoiFaceData = ();
+    type SinglePrecisionPoint = glam::Vec2;
 
     fn estimate_num_faces(num_points: usize) -> usize {
         (6.0 * num_points as f64) as usize
@@ -37,6 +38,14 @@ impl DDimension for TwoD {
     fn estimate_num_tetras(num_points: usize) -> usize {
         (3.0 * num_points as f64) as usize
     }
+
+    fn to_single_precision(point: Point2d) -> glam::Vec2 {
+        point.as_vec2()
+    }
+
+    fn from_single_precision(point: glam::Vec2) -> Point2d {
+        point.as_dvec2()
+    }
 }
 
 impl Triangulation<TwoD> {
@@ -31,6 +31,7 @@ impl DDimension for ThreeD {
     type Tetra = Tetra;
     type TetraData = TetraData;
     type VoronoiFaceData = Polygon3d;
+    type SinglePrecisionPoint = glam::Vec3;
 
     fn estimate_num_faces(num_points: usize) -> usize {
         // 15.54 is the expected number of neighbours of each cell in a 3D Voronoi grid (Meijering, J. L. (1953))
@@ -40,6 +41,14 @@ impl DDimension for ThreeD {
     fn estimate_num_tetras(num_points: usize) -> usize {
         Self::estimate_num_faces(num_points) / 2
     }
+
+    fn to_single_precision(point: DVec3) -> glam::Vec3 {
+        point.as_vec3()
+    }
+
+    fn from_single_precision(point: glam::Vec3) -> DVec3 {
+        point.as_dvec3()
+    }
 }
 
 impl Triangulation<ThreeD> {
@@ -2,6 +2,7 @@ pub(crate) mod dimension;
 pub(crate) mod face_info;
 mod impl_2d;
 mod impl_3d;
+mod point_list;
 mod point_location;
 
 use std::hash::Hash;
@@ -16,8 +17,9 @@ use self::dimension::DFace;
 use self::dimension::DTetra;
 use self::dimension::DTetraData;
 use self::face_info::ConnectionData;
+use self::point_list::PointList;
+pub use self::point_list::PointPrecision;
 use super::indexed_arena::IndexedArena;
-use super::indexed_arena::IndexedVec;
 use super::math::traits::DVector;
 use super::primitives::Float;
 use crate::communication::Rank;
@@ -44,7 +46,6 @@ pub type TetraData<D> = <D as DDimension>::TetraData;
 
 type TetraList<D> = IndexedArena<TetraIndex, Tetra<D>>;
 type FaceList<D> = IndexedArena<FaceIndex, Face<D>>;
-type PointList<D> = IndexedVec<PointIndex, Point<D>>;
 
 #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
 pub enum PointKind {
@@ -108,7 +109,7 @@ pub struct Triangulation<D: DDimension> {
 
 impl<D: DDimension> Triangulation<D> {
     pub fn get_original_point(&self, p: PointIndex) -> Point<D> {
-        self.points[p]
+        self.points.get(p)
     }
 }
 
@@ -125,9 +126,21 @@ where
     fn construct<T: Hash + Clone + Eq>(
         mut points: Vec<(T, Point<D>)>,
         extent: &Extent<Point<D>>,
+        precision: PointPrecision,
     ) -> (Self, BiMap<T, PointIndex>) {
         points.sort_by_key(|(_, p)| p.into_key(extent));
-        let mut triangulation = Self::all_encompassing(extent);
+        Self::construct_sorted(points, extent, precision)
+    }
+
+    /// Construct the triangulation by inserting the points in the
+    /// given order, which should follow the space-filling curve for
+    /// point location to be fast.
+    fn construct_sorted<T: Hash + Clone + Eq>(
+        points: Vec<(T, Point<D>)>,
+        extent: &Extent<Point<D>>,
+        precision: PointPrecision,
+    ) -> (Self, BiMap<T, PointIndex>) {
+        let mut triangulation = Self::all_encompassing(extent, precision);
         triangulation.reserve_capacity(points.len());
         let indices = points
             .iter()
@@ -139,9 +152,21 @@ where
     pub fn construct_from_iter_custom_extent<T: Hash + Clone + Eq>(
         iter: impl Iterator<Item = (T, Point<D>)>,
         extent: &Extent<Point<D>>,
+        precision: PointPrecision,
     ) -> (Self, BiMap<T, PointIndex>) {
         let points: Vec<_> = iter.collect();
-        Self::construct(points, extent)
+        Self::construct(points, extent, precision)
+    }
+
+    /// Like [Triangulation::construct_from_iter_custom_extent], but
+    /// trusts that the points are already sorted by their keys and
+    /// inserts them without sorting.
+    pub fn construct_from_sorted_iter_custom_extent<T: Hash + Clone + Eq>(
+        iter: impl Iterator<Item = (T, Point<D>)>,
+        extent: &Extent<Point<D>>,
+        precision: PointPrecision,
+    ) -> (Self, BiMap<T, PointIndex>) {
+        Self::construct_sorted(iter.collect(), extent, precision)
     }
 
     pub fn construct_from_iter<T: Hash + Clone + Eq>(
@@ -149,7 +174,7 @@ where
     ) -> (Self, BiMap<T, PointIndex>) {
         let points: Vec<_> = iter.collect();
         let extent = Extent::from_points(points.iter().map(|(_, p)| *p)).unwrap();
-        Self::construct(points, &extent)
+        Self::construct(points, &extent, PointPrecision::default())
     }
 
     pub fn construct_no_key<'a>(points: impl Iterator<Item = &'a Point<D>> + 'a) -> Self
@@ -160,17 +185,23 @@ where
         t
     }
 
-    fn all_encompassing(extent: &Extent<Point<D>>) -> Self {
+    fn all_encompassing(extent: &Extent<Point<D>>, precision: PointPrecision) -> Self {
         let initial_tetra_data = TetraData::<D>::all_encompassing(extent);
         let mut triangulation = Triangulation {
             tetras: TetraList::<D>::default(),
             faces: FaceList::<D>::default(),
-            points: PointList::<D>::default(),
+            points: PointList::<D>::new(precision),
             last_insertion_tetra: None,
             point_kinds: HashMap::default(),
             extent: initial_tetra_data.extent(),
         };
         triangulation.insert_basic_tetra(initial_tetra_data);
+        if precision == PointPrecision::Single {
+            // The points of the tetra have been rounded, so they
+            // might lie slightly outside the original extent.
+            triangulation.extent =
+                Extent::from_points(triangulation.points.iter().map(|(_, p)| p)).unwrap();
+        }
         triangulation
     }
 
@@ -200,13 +231,19 @@ where
     }
 
     pub(super) fn get_remapped_point(&self, point: PointIndex) -> Point<D> {
-        D::remap_point(self.points[point], &self.extent)
+        D::remap_point(self.points.get(point), &self.extent)
     }
 
     fn find_containing_tetra(&self, point: Point<D>) -> Option<TetraIndex> {
         point_location::find_containing_tetra(self, point)
     }
 
+    /// Return the non-boundary point which is closest to the given
+    /// point, or `None` if the triangulation does not contain any.
+    pub fn nearest_point(&self, point: Point<D>) -> Option<PointIndex> {
+        point_location::find_nearest_point(self, point)
+    }
+
     pub(super) fn get_original_tetra_circumcircle(&self, tetra: TetraIndex) -> Circumcircle<D> {
         let tetra = &self.tetras.get(tetra).unwrap();
         let tetra_data = self.get_original_tetra_data(tetra);
@@ -229,6 +266,17 @@ where
         })
     }
 
+    /// Iterate over the points which were inserted into the
+    /// triangulation (including halo points), along with their
+    /// positions. The points of the all-encompassing tetra are
+    /// only an artifact of the construction and never included, so
+    /// this should be used whenever the points are counted or
+    /// exported.
+    pub fn real_points(&self) -> impl Iterator<Item = (PointIndex, Point<D>)> + '_ {
+        self.iter_non_boundary_points()
+            .map(|p| (p, self.get_original_point(p)))
+    }
+
     fn insert_tetra(&mut self, tetra: Tetra<D>) -> TetraIndex {
         debug_assert!(tetra
             .points()
@@ -325,11 +373,68 @@ where
         tetra_data.circumcircle_contains(self.get_remapped_point(point))
     }
 
+    /// Iterate over all points of the triangulation, including the
+    /// points of the all-encompassing tetra. See
+    /// [Triangulation::real_points] for an iterator which only
+    /// contains the inserted points.
     pub fn iter_original_points(&self) -> impl Iterator<Item = (PointIndex, Point<D>)> + '_ {
         self.points
             .iter()
             .map(|(p, _)| (p, self.get_original_point(p)))
     }
+
+    /// Iterate over all tetras which do not contain any of the points
+    /// of the all-encompassing tetra.
+    fn iter_inner_tetras(&self) -> impl Iterator<Item = TetraIndex> + '_ {
+        self.tetras.iter().filter_map(|(index, tetra)| {
+            tetra
+                .points()
+                .all(|p| self.point_kinds[&p] != PointKind::Outer)
+                .then_some(index)
+        })
+    }
+
+    fn get_shortest_edge_length(&self, tetra: TetraIndex) -> Float {
+        let points: Vec<_> = self.tetras[tetra]
+            .points()
+            .map(|p| self.get_original_point(p))
+            .collect();
+        points
+            .iter()
+            .enumerate()
+            .flat_map(|(i, p1)| points[i + 1..].iter().map(|p2| p1.distance(*p2)))
+            .fold(Float::INFINITY, Float::min)
+    }
+
+    /// The minimum, maximum and mean circumradius of all tetras
+    /// (ignoring those connected to the all-encompassing tetra), or
+    /// `None` if there are no such tetras.
+    pub fn circumradius_stats(&self) -> Option<(Float, Float, Float)> {
+        let radii: Vec<_> = self
+            .iter_inner_tetras()
+            .map(|tetra| self.get_original_tetra_circumcircle(tetra).radius)
+            .collect();
+        if radii.is_empty() {
+            return None;
+        }
+        let min = radii.iter().copied().fold(Float::INFINITY, Float::min);
+        let max = radii.iter().copied().fold(0.0, Float::max);
+        let mean = radii.iter().sum::<Float>() / radii.len() as Float;
+        Some((min, max, mean))
+    }
+
+    /// The largest ratio of circumradius to shortest edge of all
+    /// tetras (ignoring those connected to the all-encompassing
+    /// tetra). Large values indicate badly shaped tetras, such as
+    /// slivers in 3D.
+    pub fn max_aspect_ratio(&self) -> Option<Float> {
+        self.iter_inner_tetras()
+            .map(|tetra| {
+                self.get_original_tetra_circumcircle(tetra).radius
+                    / self.get_shortest_edge_length(tetra)
+            })
+            .reduce(Float::max)
+    }
 }
 
 #[cfg(test)]
@@ -360,7 +465,7 @@ pub(super) mod tests {
     {
         let points = D::get_example_point_set_num(100, 0);
         let extent = Extent::from_points(points.iter().copied()).unwrap();
-        let mut triangulation = Triangulation::all_encompassing(&extent);
+        let mut triangulation = Triangulation::all_encompassing(&extent, PointPrecision::default());
         for (num_points_inserted, point) in points.iter().enumerate() {
             check(&triangulation, num_points_inserted);
             triangulation.insert(*point, PointKind::Inner);
@@ -368,6 +473,22 @@ pub(super) mod tests {
         check(&triangulation, points.len());
     }
 
+    #[test]
+    fn real_points_only_contain_inserted_points<D>()
+    where
+        D: DDimension + TestDimension,
+        Triangulation<D>: Delaunay<D>,
+    {
+        perform_triangulation_check_on_each_level_of_construction(
+            |triangulation, num_inserted_points| {
+                assert_eq!(triangulation.real_points().count(), num_inserted_points);
+                assert!(triangulation
+                    .real_points()
+                    .all(|(p, _)| triangulation.point_kinds[&p] == PointKind::Inner));
+            },
+        );
+    }
+
     #[test]
     fn correct_number_of_objects<D>()
     where
@@ -600,3 +721,205 @@ pub(super) mod tests {
         });
     }
 }
+
+#[cfg(test)]
+mod degeneracy_tests {
+    use super::dimension::DTetra;
+    use super::Triangulation;
+    use crate::dimension::TwoD;
+    use crate::voronoi::Point2d;
+
+    #[test]
+    fn cocircular_points() {
+        // Points on a circle with radius 5/32, chosen such that
+        // all coordinates are exactly representable and every
+        // circumcircle test between them is exactly degenerate.
+        let offsets = [
+            (5.0, 0.0),
+            (4.0, 3.0),
+            (3.0, 4.0),
+            (0.0, 5.0),
+            (-3.0, 4.0),
+            (-4.0, 3.0),
+            (-5.0, 0.0),
+            (-4.0, -3.0),
+            (-3.0, -4.0),
+            (0.0, -5.0),
+            (3.0, -4.0),
+            (4.0, -3.0),
+        ];
+        let points = offsets
+            .iter()
+            .enumerate()
+            .map(|(i, (x, y))| (i, Point2d::new(0.5 + x / 32.0, 0.5 + y / 32.0)));
+        let (triangulation, map) = Triangulation::<TwoD>::construct_from_iter(points);
+        assert_eq!(map.len(), offsets.len());
+        for (_, tetra) in triangulation.tetras.iter() {
+            for face in tetra.faces() {
+                if let Some(opp) = face.opposing {
+                    assert!(!triangulation.circumcircle_contains_point(tetra, opp.point));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod nearest_point_tests {
+    use super::Triangulation;
+    use crate::dimension::TwoD;
+    use crate::voronoi::Point2d;
+
+    #[test]
+    fn nearest_point_on_square_lattice() {
+        let n = 10;
+        let spacing = 0.1;
+        let points = (0..n).flat_map(|i| {
+            (0..n).map(move |j| ((i, j), Point2d::new(i as f64, j as f64) * spacing))
+        });
+        let (triangulation, map) = Triangulation::<TwoD>::construct_from_iter(points);
+        let check = |query: Point2d, expected: (usize, usize)| {
+            let nearest = triangulation.nearest_point(query).unwrap();
+            assert_eq!(*map.get_by_right(&nearest).unwrap(), expected);
+        };
+        check(Point2d::new(0.32, 0.46), (3, 5));
+        check(Point2d::new(0.04, 0.02), (0, 0));
+        check(Point2d::new(0.88, 0.13), (9, 1));
+        check(Point2d::new(0.51, 0.74), (5, 7));
+        // Outside of the lattice
+        check(Point2d::new(-0.3, 0.42), (0, 4));
+        check(Point2d::new(5.0, 5.0), (9, 9));
+    }
+}
+
+#[cfg(test)]
+mod quality_tests {
+    use super::Triangulation;
+    use crate::dimension::TwoD;
+    use crate::voronoi::Point2d;
+
+    /// Points on a triangular lattice in the shape of a parallelogram,
+    /// which is triangulated into congruent equilateral triangles.
+    fn triangular_lattice(n: usize, spacing: f64, offset: Point2d) -> Vec<Point2d> {
+        let half_height = 3.0f64.sqrt() / 2.0;
+        (0..n)
+            .flat_map(|i| {
+                (0..n).map(move |j| {
+                    offset
+                        + Point2d::new(i as f64 + 0.5 * j as f64, half_height * j as f64) * spacing
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn circumradius_stats_on_regular_lattice() {
+        let points = triangular_lattice(10, 0.1, Point2d::new(0.0, 0.0));
+        let triangulation = Triangulation::<TwoD>::construct_no_key(points.iter());
+        let (min, max, mean) = triangulation.circumradius_stats().unwrap();
+        let expected_radius = 0.1 / 3.0f64.sqrt();
+        assert!((min - expected_radius).abs() < 1e-10);
+        assert!((max - expected_radius).abs() < 1e-10);
+        assert!((mean - expected_radius).abs() < 1e-10);
+        let aspect_ratio = triangulation.max_aspect_ratio().unwrap();
+        assert!((aspect_ratio - 1.0 / 3.0f64.sqrt()).abs() < 1e-10);
+    }
+
+    #[test]
+    fn circumradius_stats_on_clustered_points() {
+        let mut points = triangular_lattice(10, 0.1, Point2d::new(0.0, 0.0));
+        points.extend(triangular_lattice(5, 0.001, Point2d::new(0.52, 0.43)));
+        let triangulation = Triangulation::<TwoD>::construct_no_key(points.iter());
+        let (min, max, mean) = triangulation.circumradius_stats().unwrap();
+        assert!(max > 10.0 * min);
+        assert!(min < mean && mean < max);
+        assert!(triangulation.max_aspect_ratio().unwrap() > 1.0 / 3.0f64.sqrt());
+    }
+}
+
+#[cfg(test)]
+mod precision_tests {
+    use std::collections::HashSet;
+
+    use rand::rngs::StdRng;
+    use rand::Rng;
+    use rand::SeedableRng;
+
+    use super::dimension::DTetra;
+    use super::DDimension;
+    use super::Delaunay;
+    use super::Point;
+    use super::PointPrecision;
+    use super::Triangulation;
+    use crate::dimension::ThreeD;
+    use crate::dimension::TwoD;
+    use crate::extent::Extent;
+    use crate::voronoi::Point2d;
+    use crate::voronoi::Point3d;
+
+    /// Lattice coordinates with a small random offset, so that they
+    /// are not exactly representable in single precision and the
+    /// lattice is not degenerate.
+    fn lattice_coords(n: usize) -> impl FnMut(usize) -> f64 {
+        let mut rng = StdRng::seed_from_u64(n as u64);
+        move |i| 0.1 * i as f64 + rng.gen_range(-0.01..0.01)
+    }
+
+    fn connectivity<D: DDimension>(
+        points: &[Point<D>],
+        precision: PointPrecision,
+    ) -> HashSet<Vec<usize>>
+    where
+        Triangulation<D>: Delaunay<D>,
+    {
+        let extent = Extent::from_points(points.iter().copied()).unwrap();
+        let (triangulation, _) = Triangulation::<D>::construct_from_iter_custom_extent(
+            points.iter().enumerate().map(|(i, p)| (i, *p)),
+            &extent,
+            precision,
+        );
+        triangulation
+            .tetras
+            .iter()
+            .map(|(_, tetra)| {
+                let mut points: Vec<_> = tetra.points().map(|p| p.0).collect();
+                points.sort();
+                points
+            })
+            .collect()
+    }
+
+    #[test]
+    fn single_precision_points_give_same_connectivity_2d() {
+        let n = 10;
+        let mut coord = lattice_coords(n);
+        let mut points = vec![];
+        for i in 0..n {
+            for j in 0..n {
+                points.push(Point2d::new(coord(i), coord(j)));
+            }
+        }
+        assert_eq!(
+            connectivity::<TwoD>(&points, PointPrecision::Single),
+            connectivity::<TwoD>(&points, PointPrecision::Double),
+        );
+    }
+
+    #[test]
+    fn single_precision_points_give_same_connectivity_3d() {
+        let n = 5;
+        let mut coord = lattice_coords(n);
+        let mut points = vec![];
+        for i in 0..n {
+            for j in 0..n {
+                for k in 0..n {
+                    points.push(Point3d::new(coord(i), coord(j), coord(k)));
+                }
+            }
+        }
+        assert_eq!(
+            connectivity::<ThreeD>(&points, PointPrecision::Single),
+            connectivity::<ThreeD>(&points, PointPrecision::Double),
+        );
+    }
+}
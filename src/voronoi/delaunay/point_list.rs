@@ -0,0 +1,76 @@
+use derive_custom::subsweep_parameters;
+
+use super::dimension::DDimension;
+use super::Point;
+use super::PointIndex;
+use crate::voronoi::indexed_arena::IndexedVec;
+
+type SinglePrecisionPoint<D> = <D as DDimension>::SinglePrecisionPoint;
+
+/// The precision with which the positions of the points are stored
+/// in the triangulation.
+#[derive(Copy, Debug, Default, PartialEq, Eq)]
+#[subsweep_parameters]
+pub enum PointPrecision {
+    #[default]
+    Double,
+    /// Round the positions to `f32` when they are inserted, which
+    /// halves the memory required for the positions. The geometric
+    /// predicates still operate on `f64` (and fall back to exact
+    /// arithmetic), but on the rounded positions, so the result is
+    /// the exact triangulation of the rounded points.
+    Single,
+}
+
+#[derive(Clone)]
+pub(super) enum PointList<D: DDimension> {
+    Double(IndexedVec<PointIndex, Point<D>>),
+    Single(IndexedVec<PointIndex, SinglePrecisionPoint<D>>),
+}
+
+impl<D: DDimension> Default for PointList<D> {
+    fn default() -> Self {
+        Self::new(PointPrecision::default())
+    }
+}
+
+impl<D: DDimension> PointList<D> {
+    pub fn new(precision: PointPrecision) -> Self {
+        match precision {
+            PointPrecision::Double => Self::Double(IndexedVec::default()),
+            PointPrecision::Single => Self::Single(IndexedVec::default()),
+        }
+    }
+
+    pub fn insert(&mut self, point: Point<D>) -> PointIndex {
+        match self {
+            Self::Double(points) => points.insert(point),
+            Self::Single(points) => points.insert(D::to_single_precision(point)),
+        }
+    }
+
+    pub fn get(&self, index: PointIndex) -> Point<D> {
+        match self {
+            Self::Double(points) => points[index],
+            Self::Single(points) => D::from_single_precision(points[index]),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Double(points) => points.len(),
+            Self::Single(points) => points.len(),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (PointIndex, Point<D>)> + '_ {
+        (0..self.len()).map(|i| (PointIndex(i), self.get(PointIndex(i))))
+    }
+
+    pub fn reserve(&mut self, cap: usize) {
+        match self {
+            Self::Double(points) => points.reserve(cap),
+            Self::Single(points) => points.reserve(cap),
+        }
+    }
+}
@@ -6,12 +6,18 @@ use ordered_float::OrderedFloat;
 use super::dimension::DTetraData;
 use super::Delaunay;
 use super::Point;
+use super::PointIndex;
+use super::PointKind;
 use super::Tetra;
 use super::TetraIndex;
 use super::Triangulation;
+use crate::extent::Extent;
 use crate::hash_map::HashSet;
+use crate::prelude::Float;
 use crate::voronoi::delaunay::dimension::DTetra;
 use crate::voronoi::DDimension;
+use crate::voronoi::DVector;
+use crate::voronoi::MinMax;
 
 #[derive(PartialEq, Eq)]
 struct CheckData {
@@ -101,3 +107,85 @@ where
             .map(|(index, _)| index)
     }
 }
+
+/// A lower bound for the distance between the point and any point
+/// within the tetra, given by the distance to its bounding box.
+fn distance_to_bounding_box<D>(t: &Triangulation<D>, tetra: &Tetra<D>, point: Point<D>) -> Float
+where
+    D: DDimension,
+    Triangulation<D>: Delaunay<D>,
+{
+    let extent = Extent::from_points(tetra.points().map(|p| t.get_original_point(p))).unwrap();
+    let closest = MinMax::min(MinMax::max(point, extent.min), extent.max);
+    point.distance(closest)
+}
+
+fn find_nearest_point_linear<D>(t: &Triangulation<D>, point: Point<D>) -> Option<PointIndex>
+where
+    D: DDimension,
+    Triangulation<D>: Delaunay<D>,
+{
+    t.iter_non_boundary_points()
+        .min_by_key(|p| OrderedFloat(point.distance(t.get_original_point(*p))))
+}
+
+/// Find the nearest (non-boundary) point by walking outward from the
+/// tetra containing the query point. Tetras are visited in order of
+/// the distance to their bounding box, and the search stops once this
+/// distance exceeds the distance to the nearest point found so far,
+/// so only the tetras in the vicinity of the query point are visited.
+/// Falls back to a linear search over all points if the query point
+/// is not contained in the triangulation.
+pub fn find_nearest_point<D>(t: &Triangulation<D>, point: Point<D>) -> Option<PointIndex>
+where
+    D: DDimension,
+    Triangulation<D>: Delaunay<D>,
+{
+    let clamped = MinMax::min(MinMax::max(point, t.extent.min), t.extent.max);
+    let start = if point.distance(clamped) == 0.0 {
+        find_containing_tetra(t, D::remap_point(point, &t.extent))
+    } else {
+        None
+    };
+    let Some(start) = start else {
+        return find_nearest_point_linear(t, point);
+    };
+    let mut nearest: Option<(PointIndex, Float)> = None;
+    let mut already_checked: HashSet<TetraIndex> = HashSet::default();
+    let mut to_check: BinaryHeap<CheckData> = BinaryHeap::default();
+    to_check.push(CheckData {
+        tetra: start,
+        heuristic_distance: OrderedFloat(0.0),
+    });
+    already_checked.insert(start);
+    while let Some(check) = to_check.pop() {
+        if let Some((_, nearest_distance)) = nearest {
+            if *check.heuristic_distance > nearest_distance {
+                break;
+            }
+        }
+        let tetra = &t.tetras[check.tetra];
+        for p in tetra.points() {
+            if t.point_kinds[&p] == PointKind::Outer {
+                continue;
+            }
+            let distance = point.distance(t.get_original_point(p));
+            if nearest.map(|(_, d)| distance < d).unwrap_or(true) {
+                nearest = Some((p, distance));
+            }
+        }
+        for face in tetra.faces() {
+            if let Some(opp) = face.opposing {
+                if already_checked.insert(opp.tetra) {
+                    let heuristic_distance =
+                        OrderedFloat(distance_to_bounding_box(t, &t.tetras[opp.tetra], point));
+                    to_check.push(CheckData {
+                        heuristic_distance,
+                        tetra: opp.tetra,
+                    });
+                }
+            }
+        }
+    }
+    nearest.map(|(p, _)| p)
+}
@@ -0,0 +1,250 @@
+//! Writes the topology of a [VoronoiGrid] to an HDF5 file, so that
+//! the mesh can be visualized or analyzed with external tools. The
+//! file contains the following datasets:
+//! - `cell_centers` (`num_cells x D`): The generating point of each cell.
+//! - `cell_volumes` (`num_cells`)
+//! - `cell_vertices` (`num_vertices x D`): The vertices of all cells,
+//!   the vertices of cell `i` are given by the rows
+//!   `cell_vertex_offsets[i]..cell_vertex_offsets[i + 1]`.
+//! - `cell_vertex_offsets` (`num_cells + 1`)
+//! - `face_cells` (`num_faces x 2`): The indices of the two cells which
+//!   share each face. Every face between two cells of the grid is only
+//!   contained once. For faces at the boundary or towards cells which
+//!   are not part of the grid (for example on other ranks), the second
+//!   index is -1.
+//! - `face_areas` (`num_faces`)
+//! - `face_normals` (`num_faces x D`): Pointing from the first to the
+//!   second cell.
+
+use std::path::Path;
+
+use hdf5::H5Type;
+use ndarray::Array2;
+
+use super::delaunay::dimension::DDimension;
+use super::primitives::Point2d;
+use super::primitives::Point3d;
+use super::Cell;
+use super::DCell;
+use super::VoronoiGrid;
+use crate::dimension::Point;
+use crate::hash_map::HashMap;
+use crate::prelude::Float;
+use crate::sweep::grid::ParticleType;
+
+pub trait Coordinates {
+    fn coordinates(&self) -> Vec<Float>;
+}
+
+impl Coordinates for Point2d {
+    fn coordinates(&self) -> Vec<Float> {
+        self.to_array().to_vec()
+    }
+}
+
+impl Coordinates for Point3d {
+    fn coordinates(&self) -> Vec<Float> {
+        self.to_array().to_vec()
+    }
+}
+
+fn to_array<P: Coordinates>(points: impl Iterator<Item = P>, num_dims: usize) -> Array2<Float> {
+    let flat: Vec<_> = points.flat_map(|p| p.coordinates()).collect();
+    Array2::from_shape_vec((flat.len() / num_dims, num_dims), flat).unwrap()
+}
+
+fn write_1d<T: H5Type>(file: &hdf5::File, name: &str, data: &[T]) -> hdf5::Result<()> {
+    file.new_dataset::<T>()
+        .shape(data.len())
+        .create(name)?
+        .write(data)
+}
+
+fn write_2d<T: H5Type>(file: &hdf5::File, name: &str, data: &Array2<T>) -> hdf5::Result<()> {
+    file.new_dataset::<T>()
+        .shape((data.nrows(), data.ncols()))
+        .create(name)?
+        .write(data)
+}
+
+impl<D: DDimension> VoronoiGrid<D>
+where
+    Cell<D>: DCell<Dimension = D>,
+    Point<D>: Coordinates,
+{
+    /// Writes the cells, faces and cell adjacency of the grid to an
+    /// HDF5 file. See the [module documentation](self) for the
+    /// format.
+    pub fn write_connectivity(&self, path: &Path) -> hdf5::Result<()> {
+        let num_dims = D::NUM as usize;
+        let cell_index: HashMap<ParticleType, i64> = self
+            .cells
+            .iter()
+            .enumerate()
+            .map(|(i, cell)| (cell.index, i as i64))
+            .collect();
+        let mut face_cells = vec![];
+        let mut face_areas = vec![];
+        let mut face_normals = vec![];
+        for (i, cell) in self.cells.iter().enumerate() {
+            let i = i as i64;
+            for face in cell.faces.iter() {
+                let other = match face.connection {
+                    ParticleType::Local(_) => cell_index.get(&face.connection).copied(),
+                    _ => None,
+                };
+                if other.map(|other| other < i).unwrap_or(false) {
+                    // Already added from the other side
+                    continue;
+                }
+                face_cells.extend([i, other.unwrap_or(-1)]);
+                face_areas.push(face.area);
+                face_normals.push(face.normal);
+            }
+        }
+        let mut vertex_offsets = vec![0u64];
+        for cell in self.cells.iter() {
+            vertex_offsets.push(vertex_offsets.last().unwrap() + cell.points.len() as u64);
+        }
+        let file = hdf5::File::create(path)?;
+        write_2d(
+            &file,
+            "cell_centers",
+            &to_array(self.cells.iter().map(|cell| cell.center), num_dims),
+        )?;
+        let volumes: Vec<_> = self.cells.iter().map(|cell| cell.volume()).collect();
+        write_1d(&file, "cell_volumes", &volumes)?;
+        write_2d(
+            &file,
+            "cell_vertices",
+            &to_array(
+                self.cells
+                    .iter()
+                    .flat_map(|cell| cell.points.iter().copied()),
+                num_dims,
+            ),
+        )?;
+        write_1d(&file, "cell_vertex_offsets", &vertex_offsets)?;
+        write_2d(
+            &file,
+            "face_cells",
+            &Array2::from_shape_vec((face_areas.len(), 2), face_cells).unwrap(),
+        )?;
+        write_1d(&file, "face_areas", &face_areas)?;
+        write_2d(
+            &file,
+            "face_normals",
+            &to_array(face_normals.into_iter(), num_dims),
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "2d"))]
+#[cfg(test)]
+mod tests {
+    use macro_utils::TempDir;
+
+    use crate::dimension::ThreeD;
+    use crate::prelude::ParticleId;
+    use crate::sweep::grid::ParticleType;
+    use crate::voronoi::primitives::Point3d;
+    use crate::voronoi::Constructor;
+    use crate::voronoi::VoronoiGrid;
+
+    #[test]
+    fn write_connectivity_three_d() {
+        let points = vec![
+            (ParticleId::test(0), Point3d::new(0.0, 0.0, 0.0)),
+            (ParticleId::test(1), Point3d::new(0.6, 0.1, 0.1)),
+            (ParticleId::test(2), Point3d::new(0.1, 0.5, 0.1)),
+            (ParticleId::test(3), Point3d::new(0.1, 0.1, 0.4)),
+            (ParticleId::test(4), Point3d::new(0.1, 0.1, 0.1)),
+        ];
+        let cons = Constructor::new(points.into_iter());
+        let central_point = cons
+            .get_point_by_cell(ParticleType::Local(ParticleId::test(4)))
+            .unwrap();
+        let grid: VoronoiGrid<ThreeD> = cons.voronoi();
+        let num_internal_faces: usize = grid
+            .cells
+            .iter()
+            .map(|cell| {
+                cell.faces
+                    .iter()
+                    .filter(|face| face.connection.is_local())
+                    .count()
+            })
+            .sum();
+        let num_boundary_faces: usize = grid
+            .cells
+            .iter()
+            .map(|cell| {
+                cell.faces
+                    .iter()
+                    .filter(|face| !face.connection.is_local())
+                    .count()
+            })
+            .sum();
+        let dir = TempDir::new();
+        let path = dir.join("voronoi_connectivity.hdf5");
+        grid.write_connectivity(&path).unwrap();
+        let file = hdf5::File::open(&path).unwrap();
+        let centers = file
+            .dataset("cell_centers")
+            .unwrap()
+            .read_2d::<f64>()
+            .unwrap();
+        let volumes = file
+            .dataset("cell_volumes")
+            .unwrap()
+            .read_raw::<f64>()
+            .unwrap();
+        let face_cells = file
+            .dataset("face_cells")
+            .unwrap()
+            .read_2d::<i64>()
+            .unwrap();
+        let face_normals = file
+            .dataset("face_normals")
+            .unwrap()
+            .read_2d::<f64>()
+            .unwrap();
+        let offsets = file
+            .dataset("cell_vertex_offsets")
+            .unwrap()
+            .read_raw::<u64>()
+            .unwrap();
+        let vertices = file
+            .dataset("cell_vertices")
+            .unwrap()
+            .read_2d::<f64>()
+            .unwrap();
+        assert_eq!(centers.shape(), [5, 3]);
+        assert_eq!(volumes.len(), 5);
+        assert_eq!(
+            face_cells.nrows(),
+            num_internal_faces / 2 + num_boundary_faces
+        );
+        assert_eq!(face_normals.shape(), [face_cells.nrows(), 3]);
+        assert_eq!(offsets.len(), 6);
+        assert_eq!(*offsets.last().unwrap() as usize, vertices.nrows());
+        // The central cell is a tetrahedron enclosed by the other four.
+        let central = grid
+            .cells
+            .iter()
+            .position(|cell| cell.delaunay_point == central_point)
+            .unwrap() as i64;
+        let central_faces = face_cells
+            .rows()
+            .into_iter()
+            .filter(|row| row[0] == central || row[1] == central)
+            .count();
+        assert_eq!(central_faces, 4);
+        assert!(face_cells
+            .rows()
+            .into_iter()
+            .filter(|row| row[0] == central || row[1] == central)
+            .all(|row| row[1] != -1));
+    }
+}
@@ -112,7 +112,6 @@ impl<Id: Into<usize> + From<usize>, T> IndexedVec<Id, T> {
         self.values.reserve(cap)
     }
 
-    #[cfg(test)]
     pub fn len(&self) -> usize {
         self.values.len()
     }
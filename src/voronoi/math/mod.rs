@@ -1,3 +1,4 @@
 pub mod precision_types;
+pub mod predicates;
 pub mod traits;
 pub mod utils;
@@ -26,9 +26,7 @@ impl PrecisionError {
     }
 }
 
-pub const TRIANGLE_CONTAINS_EPSILON: f64 = 1e-15;
 pub const TRIANGLE_INTERSECTION_TYPE_EPSILON: f64 = 1e-15;
-pub const TETRAHEDRON_POINTS_ON_SAME_SIDE_EPSILON: f64 = 1e-15;
 pub const DETERMINANT_3X3_EPSILON: f64 = 1e-15;
 pub const DETERMINANT_4X4_EPSILON: f64 = 1e-15;
 pub const DETERMINANT_5X5_EPSILON: f64 = 1e-15;
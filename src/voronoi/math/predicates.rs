@@ -0,0 +1,314 @@
+//! Robust geometric predicates, following Shewchuk (1997),
+//! "Adaptive Precision Floating-Point Arithmetic and Fast Robust
+//! Geometric Predicates". Each predicate is first evaluated with
+//! f64 arithmetic, along with an upper bound on the rounding error
+//! of the result. This is enough to decide the sign for almost all
+//! inputs. Only if the sign cannot be determined from this, the
+//! predicate is evaluated exactly with arbitrary precision arithmetic
+//! on the (exactly representable) input coordinates. Since this only
+//! happens for (nearly) degenerate input, the performance for
+//! generic input is close to that of the plain f64 computation.
+
+use super::precision_types::PrecisionFloat;
+use super::precision_types::PrecisionPoint2d;
+use super::precision_types::PrecisionPoint3d;
+use super::utils::determinant3x3;
+use super::utils::determinant4x4;
+use super::utils::Sign;
+use crate::voronoi::Point2d;
+use crate::voronoi::Point3d;
+
+/// Half the machine epsilon, i.e. the largest relative error of a
+/// single rounded f64 operation.
+const EPSILON: f64 = f64::EPSILON * 0.5;
+const ORIENT_2D_ERROR_BOUND: f64 = (3.0 + 16.0 * EPSILON) * EPSILON;
+const ORIENT_3D_ERROR_BOUND: f64 = (7.0 + 56.0 * EPSILON) * EPSILON;
+const INCIRCLE_ERROR_BOUND: f64 = (10.0 + 96.0 * EPSILON) * EPSILON;
+const INSPHERE_ERROR_BOUND: f64 = (16.0 + 224.0 * EPSILON) * EPSILON;
+
+fn sign_if_certain(det: f64, error_bound: f64) -> Option<Sign> {
+    if det > error_bound {
+        Some(Sign::Positive)
+    } else if -det > error_bound {
+        Some(Sign::Negative)
+    } else {
+        None
+    }
+}
+
+/// Positive if `a`, `b` and `c` are in counterclockwise order,
+/// negative if they are in clockwise order and zero if they are
+/// collinear.
+pub fn orient_2d(a: Point2d, b: Point2d, c: Point2d) -> Sign {
+    let det_left = (a.x - c.x) * (b.y - c.y);
+    let det_right = (a.y - c.y) * (b.x - c.x);
+    let det = det_left - det_right;
+    // If the two terms have different signs (or one of them is
+    // zero), there is no cancellation and the result is exact
+    // in sign.
+    if (det_left > 0.0 && det_right <= 0.0)
+        || (det_left < 0.0 && det_right >= 0.0)
+        || det_left == 0.0
+    {
+        return Sign::of(det);
+    }
+    let det_sum = (det_left + det_right).abs();
+    sign_if_certain(det, ORIENT_2D_ERROR_BOUND * det_sum).unwrap_or_else(|| {
+        let a = PrecisionPoint2d::new(a);
+        let b = PrecisionPoint2d::new(b);
+        let c = PrecisionPoint2d::new(c);
+        Sign::of((a.x - c.x.clone()) * (b.y - c.y.clone()) - (a.y - c.y) * (b.x - c.x))
+    })
+}
+
+/// Positive if `d` lies below the plane through `a`, `b` and `c`,
+/// where "below" is defined such that `a`, `b` and `c` appear in
+/// counterclockwise order when viewed from above the plane. Negative
+/// if `d` lies above the plane and zero if the points are coplanar.
+pub fn orient_3d(a: Point3d, b: Point3d, c: Point3d, d: Point3d) -> Sign {
+    let ad = a - d;
+    let bd = b - d;
+    let cd = c - d;
+
+    let bdx_cdy = bd.x * cd.y;
+    let cdx_bdy = cd.x * bd.y;
+    let cdx_ady = cd.x * ad.y;
+    let adx_cdy = ad.x * cd.y;
+    let adx_bdy = ad.x * bd.y;
+    let bdx_ady = bd.x * ad.y;
+
+    let det = ad.z * (bdx_cdy - cdx_bdy) + bd.z * (cdx_ady - adx_cdy) + cd.z * (adx_bdy - bdx_ady);
+    let permanent = (bdx_cdy.abs() + cdx_bdy.abs()) * ad.z.abs()
+        + (cdx_ady.abs() + adx_cdy.abs()) * bd.z.abs()
+        + (adx_bdy.abs() + bdx_ady.abs()) * cd.z.abs();
+    sign_if_certain(det, ORIENT_3D_ERROR_BOUND * permanent).unwrap_or_else(|| {
+        let d = PrecisionPoint3d::new(d);
+        let row = |p: Point3d| {
+            let p = PrecisionPoint3d::new(p) - d.clone();
+            [p.x, p.y, p.z]
+        };
+        Sign::of(determinant3x3([row(a), row(b), row(c)]))
+    })
+}
+
+/// Positive if `d` lies inside the circle through `a`, `b` and `c`,
+/// negative if it lies outside and zero if the four points are
+/// cocircular. The points `a`, `b` and `c` need to be in
+/// counterclockwise order, otherwise the sign of the result is
+/// reversed.
+pub fn incircle(a: Point2d, b: Point2d, c: Point2d, d: Point2d) -> Sign {
+    let ad = a - d;
+    let bd = b - d;
+    let cd = c - d;
+
+    let bdx_cdy = bd.x * cd.y;
+    let cdx_bdy = cd.x * bd.y;
+    let a_lift = ad.x * ad.x + ad.y * ad.y;
+
+    let cdx_ady = cd.x * ad.y;
+    let adx_cdy = ad.x * cd.y;
+    let b_lift = bd.x * bd.x + bd.y * bd.y;
+
+    let adx_bdy = ad.x * bd.y;
+    let bdx_ady = bd.x * ad.y;
+    let c_lift = cd.x * cd.x + cd.y * cd.y;
+
+    let det =
+        a_lift * (bdx_cdy - cdx_bdy) + b_lift * (cdx_ady - adx_cdy) + c_lift * (adx_bdy - bdx_ady);
+    let permanent = (bdx_cdy.abs() + cdx_bdy.abs()) * a_lift
+        + (cdx_ady.abs() + adx_cdy.abs()) * b_lift
+        + (adx_bdy.abs() + bdx_ady.abs()) * c_lift;
+    sign_if_certain(det, INCIRCLE_ERROR_BOUND * permanent).unwrap_or_else(|| {
+        let d = PrecisionPoint2d::new(d);
+        let row = |p: Point2d| {
+            let p = PrecisionPoint2d::new(p) - d.clone();
+            let lift = p.x.clone() * p.x.clone() + p.y.clone() * p.y.clone();
+            [p.x, p.y, lift]
+        };
+        Sign::of(determinant3x3([row(a), row(b), row(c)]))
+    })
+}
+
+/// Positive if `e` lies inside the sphere through `a`, `b`, `c` and
+/// `d`, negative if it lies outside and zero if the five points are
+/// cospherical. The points need to be oriented such that
+/// `orient_3d(a, b, c, d)` is positive, otherwise the sign of the
+/// result is reversed.
+pub fn insphere(a: Point3d, b: Point3d, c: Point3d, d: Point3d, e: Point3d) -> Sign {
+    let ae = a - e;
+    let be = b - e;
+    let ce = c - e;
+    let de = d - e;
+
+    let aex_bey = ae.x * be.y;
+    let bex_aey = be.x * ae.y;
+    let ab = aex_bey - bex_aey;
+    let bex_cey = be.x * ce.y;
+    let cex_bey = ce.x * be.y;
+    let bc = bex_cey - cex_bey;
+    let cex_dey = ce.x * de.y;
+    let dex_cey = de.x * ce.y;
+    let cd = cex_dey - dex_cey;
+    let dex_aey = de.x * ae.y;
+    let aex_dey = ae.x * de.y;
+    let da = dex_aey - aex_dey;
+    let aex_cey = ae.x * ce.y;
+    let cex_aey = ce.x * ae.y;
+    let ac = aex_cey - cex_aey;
+    let bex_dey = be.x * de.y;
+    let dex_bey = de.x * be.y;
+    let bd = bex_dey - dex_bey;
+
+    let abc = ae.z * bc - be.z * ac + ce.z * ab;
+    let bcd = be.z * cd - ce.z * bd + de.z * bc;
+    let cda = ce.z * da + de.z * ac + ae.z * cd;
+    let dab = de.z * ab + ae.z * bd + be.z * da;
+
+    let a_lift = ae.length_squared();
+    let b_lift = be.length_squared();
+    let c_lift = ce.length_squared();
+    let d_lift = de.length_squared();
+
+    let det = (d_lift * abc - c_lift * dab) + (b_lift * cda - a_lift * bcd);
+
+    let aez = ae.z.abs();
+    let bez = be.z.abs();
+    let cez = ce.z.abs();
+    let dez = de.z.abs();
+    let ab = aex_bey.abs() + bex_aey.abs();
+    let bc = bex_cey.abs() + cex_bey.abs();
+    let cd = cex_dey.abs() + dex_cey.abs();
+    let da = dex_aey.abs() + aex_dey.abs();
+    let ac = aex_cey.abs() + cex_aey.abs();
+    let bd = bex_dey.abs() + dex_bey.abs();
+    let permanent = (cd * bez + bd * cez + bc * dez) * a_lift
+        + (da * cez + ac * dez + cd * aez) * b_lift
+        + (ab * dez + bd * aez + da * bez) * c_lift
+        + (bc * aez + ac * bez + ab * cez) * d_lift;
+    sign_if_certain(det, INSPHERE_ERROR_BOUND * permanent).unwrap_or_else(|| {
+        let e = PrecisionPoint3d::new(e);
+        let row = |p: Point3d| {
+            let p = PrecisionPoint3d::new(p) - e.clone();
+            let lift: PrecisionFloat =
+                p.x.clone() * p.x.clone() + p.y.clone() * p.y.clone() + p.z.clone() * p.z.clone();
+            [p.x, p.y, p.z, lift]
+        };
+        Sign::of(determinant4x4([row(a), row(b), row(c), row(d)]))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::incircle;
+    use super::insphere;
+    use super::orient_2d;
+    use super::orient_3d;
+    use crate::voronoi::math::precision_types::PrecisionPoint2d;
+    use crate::voronoi::math::utils::Sign;
+    use crate::voronoi::Point2d;
+    use crate::voronoi::Point3d;
+
+    #[test]
+    fn orient_2d_simple() {
+        let a = Point2d::new(0.0, 0.0);
+        let b = Point2d::new(1.0, 0.0);
+        let c = Point2d::new(0.0, 1.0);
+        assert_eq!(orient_2d(a, b, c), Sign::Positive);
+        assert_eq!(orient_2d(a, c, b), Sign::Negative);
+        assert_eq!(orient_2d(a, b, Point2d::new(2.0, 0.0)), Sign::Zero);
+    }
+
+    #[test]
+    fn orient_2d_nearly_collinear_points_agree_with_exact_result() {
+        // The classic example from Kettner et al. (2008): Points very
+        // close to the line y = x, for which the naive f64 evaluation
+        // gives inconsistent results.
+        let b = Point2d::new(12.0, 12.0);
+        let c = Point2d::new(24.0, 24.0);
+        let exact = |a: Point2d| {
+            let a = PrecisionPoint2d::new(a);
+            let b = PrecisionPoint2d::new(b);
+            let c = PrecisionPoint2d::new(c);
+            Sign::of((a.x - c.x.clone()) * (b.y - c.y.clone()) - (a.y - c.y) * (b.x - c.x))
+        };
+        for i in 0..64 {
+            for j in 0..64 {
+                let a = Point2d::new(0.5 + i as f64 * f64::EPSILON, 0.5 + j as f64 * f64::EPSILON);
+                assert_eq!(orient_2d(a, b, c), exact(a));
+            }
+        }
+    }
+
+    #[test]
+    fn orient_3d_coplanar_points() {
+        let a = Point3d::new(0.1, 0.2, 0.3);
+        let b = Point3d::new(1.1, 0.2, 0.3);
+        let c = Point3d::new(0.1, 1.2, 0.3);
+        assert_eq!(orient_3d(a, b, c, Point3d::new(0.7, 0.9, 0.3)), Sign::Zero);
+        assert_eq!(
+            orient_3d(a, b, c, Point3d::new(0.7, 0.9, 0.0)),
+            Sign::Positive
+        );
+        assert_eq!(
+            orient_3d(a, b, c, Point3d::new(0.7, 0.9, 1.0)),
+            Sign::Negative
+        );
+    }
+
+    #[test]
+    fn incircle_cocircular_points() {
+        // Points on a circle with radius 5/32, chosen such that all
+        // coordinates are exactly representable.
+        let p = |x: f64, y: f64| Point2d::new(0.5 + x / 32.0, 0.5 + y / 32.0);
+        let a = p(5.0, 0.0);
+        let b = p(3.0, 4.0);
+        let c = p(-4.0, 3.0);
+        assert_eq!(incircle(a, b, c, p(0.0, -5.0)), Sign::Zero);
+        assert_eq!(incircle(a, b, c, p(-3.0, -4.0)), Sign::Zero);
+        assert_eq!(incircle(a, b, c, p(0.0, 0.0)), Sign::Positive);
+        assert_eq!(incircle(a, b, c, p(6.0, 0.0)), Sign::Negative);
+    }
+
+    #[test]
+    fn insphere_cospherical_points() {
+        // Points on a sphere with radius 3/32, chosen such that all
+        // coordinates are exactly representable.
+        let p =
+            |x: f64, y: f64, z: f64| Point3d::new(0.5 + x / 32.0, 0.5 + y / 32.0, 0.5 + z / 32.0);
+        let a = p(3.0, 0.0, 0.0);
+        let b = p(0.0, 3.0, 0.0);
+        let c = p(0.0, 0.0, 3.0);
+        let d = p(-1.0, -2.0, -2.0);
+        let (a, b) = if orient_3d(a, b, c, d) == Sign::Positive {
+            (a, b)
+        } else {
+            (b, a)
+        };
+        assert_eq!(insphere(a, b, c, d, p(2.0, -2.0, 1.0)), Sign::Zero);
+        assert_eq!(insphere(a, b, c, d, p(0.0, 0.0, 0.0)), Sign::Positive);
+        assert_eq!(insphere(a, b, c, d, p(4.0, 0.0, 0.0)), Sign::Negative);
+    }
+
+    #[test]
+    fn insphere_nearly_cospherical_points_are_consistent() {
+        let p =
+            |x: f64, y: f64, z: f64| Point3d::new(0.5 + x / 32.0, 0.5 + y / 32.0, 0.5 + z / 32.0);
+        let a = p(3.0, 0.0, 0.0);
+        let b = p(0.0, 3.0, 0.0);
+        let c = p(0.0, 0.0, 3.0);
+        let d = p(-1.0, -2.0, -2.0);
+        let (a, b) = if orient_3d(a, b, c, d) == Sign::Positive {
+            (a, b)
+        } else {
+            (b, a)
+        };
+        let e = p(2.0, -2.0, 1.0);
+        // Moving the point towards the center puts it inside the sphere,
+        // moving it away from the center puts it outside, no matter how
+        // small the displacement.
+        let inner = Point3d::new(e.x - f64::EPSILON * 0.5, e.y, e.z);
+        assert_eq!(insphere(a, b, c, d, inner), Sign::Positive);
+        let outer = Point3d::new(e.x + f64::EPSILON * 0.5, e.y, e.z);
+        assert_eq!(insphere(a, b, c, d, outer), Sign::Negative);
+    }
+}
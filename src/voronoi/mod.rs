@@ -1,6 +1,7 @@
 mod cell;
 pub mod constructor;
 pub mod delaunay;
+pub mod export;
 mod indexed_arena;
 pub mod math;
 mod primitives;
@@ -188,6 +189,90 @@ mod quantitative_tests {
         }
     }
 
+    fn assert_close(x: f64, y: f64) {
+        assert!((x - y).abs() < 1e-10, "{} {}", x, y);
+    }
+
+    /// Constructs the cell of a point whose neighbours lie along the
+    /// coordinate axes, which is a box with side lengths
+    /// `(neg_x + pos)/2, pos, pos, ...`.
+    #[cfg(feature = "2d")]
+    fn axis_aligned_cell(pos: f64, neg_x: f64) -> super::Cell<crate::dimension::TwoD> {
+        use super::primitives::Point2d;
+        let center = Point2d::new(0.5, 0.5);
+        let points = vec![
+            (ParticleId::test(0), center),
+            (ParticleId::test(1), center + Point2d::new(pos, 0.0)),
+            (ParticleId::test(2), center - Point2d::new(neg_x, 0.0)),
+            (ParticleId::test(3), center + Point2d::new(0.0, pos)),
+            (ParticleId::test(4), center - Point2d::new(0.0, pos)),
+        ];
+        let cons = Constructor::new(points.into_iter());
+        let index = cons
+            .get_point_by_cell(ParticleType::Local(ParticleId::test(0)))
+            .unwrap();
+        let grid: VoronoiGrid<crate::dimension::TwoD> = cons.voronoi();
+        grid.cells
+            .into_iter()
+            .find(|cell| cell.delaunay_point == index)
+            .unwrap()
+    }
+
+    #[cfg(feature = "2d")]
+    #[test]
+    fn centroid_and_surface_area_two_d() {
+        let cell = axis_aligned_cell(0.2, 0.2);
+        assert_close(cell.volume(), 0.2 * 0.2);
+        assert_close(cell.surface_area(), 4.0 * 0.2);
+        assert_close(cell.centroid().distance(cell.center), 0.0);
+        let cell = axis_aligned_cell(0.2, 0.4);
+        assert_close(cell.surface_area(), 2.0 * (0.3 + 0.2));
+        assert_close(cell.centroid().x, 0.5 - 0.05);
+        assert_close(cell.centroid().y, 0.5);
+    }
+
+    #[cfg(feature = "3d")]
+    fn axis_aligned_cell(pos: f64, neg_x: f64) -> super::Cell<crate::dimension::ThreeD> {
+        use crate::voronoi::primitives::Point3d;
+        let center = Point3d::new(0.5, 0.5, 0.5);
+        let points = vec![
+            (ParticleId::test(0), center),
+            (ParticleId::test(1), center + Point3d::new(pos, 0.0, 0.0)),
+            (ParticleId::test(2), center - Point3d::new(neg_x, 0.0, 0.0)),
+            (ParticleId::test(3), center + Point3d::new(0.0, pos, 0.0)),
+            (ParticleId::test(4), center - Point3d::new(0.0, pos, 0.0)),
+            (ParticleId::test(5), center + Point3d::new(0.0, 0.0, pos)),
+            (ParticleId::test(6), center - Point3d::new(0.0, 0.0, pos)),
+        ];
+        let cons = Constructor::new(points.into_iter());
+        let index = cons
+            .get_point_by_cell(ParticleType::Local(ParticleId::test(0)))
+            .unwrap();
+        let grid: VoronoiGrid<crate::dimension::ThreeD> = cons.voronoi();
+        grid.cells
+            .into_iter()
+            .find(|cell| cell.delaunay_point == index)
+            .unwrap()
+    }
+
+    #[cfg(feature = "3d")]
+    #[test]
+    fn centroid_and_surface_area_three_d() {
+        let side = 0.2;
+        let cell = axis_aligned_cell(side, side);
+        assert_close(cell.volume(), side.powi(3));
+        assert_close(cell.surface_area(), 6.0 * side.powi(2));
+        assert_close(cell.centroid().distance(cell.center), 0.0);
+        let cell = axis_aligned_cell(0.2, 0.4);
+        assert_close(
+            cell.surface_area(),
+            2.0 * (0.3 * 0.2 + 0.3 * 0.2 + 0.2 * 0.2),
+        );
+        assert_close(cell.centroid().x, 0.5 - 0.05);
+        assert_close(cell.centroid().y, 0.5);
+        assert_close(cell.centroid().z, 0.5);
+    }
+
     #[cfg(feature = "3d")]
     #[test]
     fn right_volume_and_face_areas_three_d() {
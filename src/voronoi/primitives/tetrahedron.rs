@@ -1,13 +1,10 @@
 use std::array::IntoIter;
-use std::ops::Sub;
 
 use num::FromPrimitive;
 use num::One;
 use num::ToPrimitive;
 
 use super::super::delaunay::face_info::FaceInfo;
-use super::super::math::traits::Dot;
-use super::super::math::traits::Vector3d;
 use super::triangle::TriangleData;
 use super::Float;
 use super::Point3d;
@@ -19,11 +16,9 @@ use crate::voronoi::delaunay::Point;
 use crate::voronoi::math::precision_types::PrecisionError;
 use crate::voronoi::math::precision_types::PrecisionFloat;
 use crate::voronoi::math::precision_types::PrecisionPoint3d;
-use crate::voronoi::math::precision_types::TETRAHEDRON_POINTS_ON_SAME_SIDE_EPSILON;
-use crate::voronoi::math::traits::Cross3d;
+use crate::voronoi::math::predicates::insphere;
+use crate::voronoi::math::predicates::orient_3d;
 use crate::voronoi::math::utils::determinant4x4;
-use crate::voronoi::math::utils::determinant5x5;
-use crate::voronoi::math::utils::lift_matrix;
 use crate::voronoi::math::utils::solve_3x4_system_of_equations_error;
 use crate::voronoi::math::utils::Sign;
 use crate::voronoi::PointIndex;
@@ -87,52 +82,9 @@ impl FromIterator<Point3d> for TetrahedronData {
 }
 
 impl TetrahedronData {
-    fn f64_contains(&self, point: Point3d) -> Result<bool, PrecisionError> {
-        Ok(
-            points_are_on_same_side_of_triangle(point, self.p1, (self.p2, self.p3, self.p4))?
-                && points_are_on_same_side_of_triangle(
-                    point,
-                    self.p2,
-                    (self.p1, self.p3, self.p4),
-                )?
-                && points_are_on_same_side_of_triangle(
-                    point,
-                    self.p3,
-                    (self.p1, self.p2, self.p4),
-                )?
-                && points_are_on_same_side_of_triangle(
-                    point,
-                    self.p4,
-                    (self.p1, self.p2, self.p3),
-                )?,
-        )
-    }
-
-    fn arbitrary_precision_contains(&self, point: Point3d) -> bool {
-        let p1 = PrecisionPoint3d::new(self.p1);
-        let p2 = PrecisionPoint3d::new(self.p2);
-        let p3 = PrecisionPoint3d::new(self.p3);
-        let p4 = PrecisionPoint3d::new(self.p4);
-        let point = PrecisionPoint3d::new(point);
-        points_are_on_same_side_of_triangle(
-            point.clone(),
-            p1.clone(),
-            (p2.clone(), p3.clone(), p4.clone()),
-        )
-        .unwrap()
-            && points_are_on_same_side_of_triangle(
-                point.clone(),
-                p2.clone(),
-                (p1.clone(), p3.clone(), p4.clone()),
-            )
-            .unwrap()
-            && points_are_on_same_side_of_triangle(
-                point.clone(),
-                p3.clone(),
-                (p1.clone(), p2.clone(), p4.clone()),
-            )
-            .unwrap()
-            && points_are_on_same_side_of_triangle(point, p4, (p1, p2, p3)).unwrap()
+    fn orientation(&self) -> Sign {
+        *orient_3d(self.p1, self.p2, self.p3, self.p4)
+            .panic_if_zero(|| "Zero volume tetrahedron encountered")
     }
 }
 
@@ -155,17 +107,31 @@ impl DTetraData for TetrahedronData {
         Extent::from_points([self.p1, self.p2, self.p3, self.p4].into_iter()).unwrap()
     }
 
-    #[rustfmt::skip]
     fn contains(&self, point: Point3d) -> bool {
-        self.f64_contains(point).unwrap_or_else(|_| self.arbitrary_precision_contains(point))
+        // Replacing any of the points of the tetrahedron by the point
+        // keeps the orientation if and only if the point is on the same
+        // side of the opposite face.
+        let orientation = self.orientation();
+        let signs = [
+            orient_3d(point, self.p2, self.p3, self.p4),
+            orient_3d(self.p1, point, self.p3, self.p4),
+            orient_3d(self.p1, self.p2, point, self.p4),
+            orient_3d(self.p1, self.p2, self.p3, point),
+        ]
+        .map(|sign| sign * orientation);
+        if signs.iter().any(|sign| sign.is_negative()) {
+            false
+        } else {
+            for sign in signs {
+                sign.panic_if_zero(|| "Degenerate case: point on face of tetrahedron.");
+            }
+            true
+        }
     }
 
     /// This only works if the point is outside of the tetrahedron
     fn distance_to_point(&self, p: Point3d) -> Float {
-        if self
-            .f64_contains(p)
-            .unwrap_or_else(|_| self.arbitrary_precision_contains(p))
-        {
+        if self.contains(p) {
             return 0.0;
         }
         let a1 = TriangleData {
@@ -196,28 +162,10 @@ impl DTetraData for TetrahedronData {
     }
 
     fn circumcircle_contains(&self, point: Point3d) -> bool {
-        self.circumcircle_contains_float(point).unwrap_or_else(|_| {
-            let a = self.p1;
-            let b = self.p2;
-            let c = self.p3;
-            let d = self.p4;
-            let e = point;
-            let matrix = [
-                [1.0, a.x, a.y, a.z, a.x.powi(2) + a.y.powi(2) + a.z.powi(2)],
-                [1.0, b.x, b.y, b.z, b.x.powi(2) + b.y.powi(2) + b.z.powi(2)],
-                [1.0, c.x, c.y, c.z, c.x.powi(2) + c.y.powi(2) + c.z.powi(2)],
-                [1.0, d.x, d.y, d.z, d.x.powi(2) + d.y.powi(2) + d.z.powi(2)],
-                [1.0, e.x, e.y, e.z, e.x.powi(2) + e.y.powi(2) + e.z.powi(2)],
-            ];
-            Sign::of(determinant5x5(lift_matrix(matrix)))
-                .panic_if_zero(|| {
-                    format!(
-                        "Degenerate case in circumcircle test of tetrahedron: {:?}. {:?}",
-                        self, matrix
-                    )
-                })
-                .is_negative()
-        })
+        // If the point lies exactly on the circumsphere, both possible
+        // tetrahedralizations are valid, so we report it as outside to
+        // avoid unnecessary flips.
+        (insphere(self.p1, self.p2, self.p3, self.p4, point) * self.orientation()).is_positive()
     }
 
     fn get_center_of_circumcircle(&self) -> Point3d {
@@ -268,78 +216,6 @@ impl DTetraData for TetrahedronData {
 }
 
 impl TetrahedronData {
-    fn circumcircle_contains_float(&self, point: Point3d) -> Result<bool, PrecisionError> {
-        // Taken from Arepo - InSphere_Errorbound
-        let ax = self.p1.x - point.x;
-        let ay = self.p1.y - point.y;
-        let az = self.p1.z - point.z;
-
-        let bx = self.p2.x - point.x;
-        let by = self.p2.y - point.y;
-        let bz = self.p2.z - point.z;
-
-        let cx = self.p3.x - point.x;
-        let cy = self.p3.y - point.y;
-        let cz = self.p3.z - point.z;
-
-        let dx = self.p4.x - point.x;
-        let dy = self.p4.y - point.y;
-        let dz = self.p4.z - point.z;
-
-        let axby = ax * by;
-        let bxay = bx * ay;
-        let bxcy = bx * cy;
-        let cxby = cx * by;
-        let cxdy = cx * dy;
-        let dxcy = dx * cy;
-        let dxay = dx * ay;
-        let axdy = ax * dy;
-        let axcy = ax * cy;
-        let cxay = cx * ay;
-        let bxdy = bx * dy;
-        let dxby = dx * by;
-
-        let ab = axby - bxay;
-        let bc = bxcy - cxby;
-        let cd = cxdy - dxcy;
-        let da = dxay - axdy;
-        let ac = axcy - cxay;
-        let bd = bxdy - dxby;
-
-        let abc = az * bc - bz * ac + cz * ab;
-        let bcd = bz * cd - cz * bd + dz * bc;
-        let cda = cz * da + dz * ac + az * cd;
-        let dab = dz * ab + az * bd + bz * da;
-
-        let a2 = ax * ax + ay * ay + az * az;
-        let b2 = bx * bx + by * by + bz * bz;
-        let c2 = cx * cx + cy * cy + cz * cz;
-        let d2 = dx * dx + dy * dy + dz * dz;
-
-        let x = (c2 * dab - d2 * abc) + (a2 * bcd - b2 * cda);
-
-        let ab = axby.abs() + bxay.abs();
-        let bc = bxcy.abs() + cxby.abs();
-        let cd = cxdy.abs() + dxcy.abs();
-        let da = dxay.abs() + axdy.abs();
-        let ac = axcy.abs() + cxay.abs();
-        let bd = bxdy.abs() + dxby.abs();
-
-        let az = az.abs();
-        let bz = bz.abs();
-        let cz = cz.abs();
-        let dz = dz.abs();
-
-        let abc = az * bc + bz * ac + cz * ab;
-        let bcd = bz * cd + cz * bd + dz * bc;
-        let cda = cz * da + dz * ac + az * cd;
-        let dab = dz * ab + az * bd + bz * da;
-
-        let size_limit = (c2 * dab + d2 * abc) + (a2 * bcd + b2 * cda);
-        let error_bound = 1.0e-14 * size_limit;
-        Ok(Sign::try_from_val(&x, error_bound)?.is_positive())
-    }
-
     #[rustfmt::skip]
     fn get_center_of_circumcircle_float(&self) -> Result<Point3d, PrecisionError> {
         let p0 = self.p1;
@@ -368,26 +244,6 @@ impl TetrahedronData {
     }
 }
 
-fn points_are_on_same_side_of_triangle<P: Vector3d + Cross3d + Sub<Output = P> + Dot + Clone>(
-    p1: P,
-    p2: P,
-    triangle: (P, P, P),
-) -> Result<bool, PrecisionError> {
-    let (p_a, p_b, p_c) = triangle;
-    let normal = (p_b - p_a.clone()).cross(&(p_c - p_a.clone()));
-    let dot_1_sign = Sign::try_from_val(
-        &(p1 - p_a.clone()).dot(normal.clone()),
-        TETRAHEDRON_POINTS_ON_SAME_SIDE_EPSILON,
-    )?;
-    let dot_2_sign = Sign::try_from_val(
-        &(p2 - p_a).dot(normal),
-        TETRAHEDRON_POINTS_ON_SAME_SIDE_EPSILON,
-    )?;
-    Ok((dot_1_sign * dot_2_sign)
-        .panic_if_zero(|| "Degenerate case: point on line of triangle.")
-        .is_positive())
-}
-
 #[cfg(test)]
 mod tests {
     use super::super::Point3d;
@@ -4,7 +4,6 @@ use std::ops::Sub;
 
 use num::One;
 
-use super::super::math::traits::Vector2d;
 use super::super::math::traits::Vector3d;
 use super::Float;
 use super::Point2d;
@@ -19,11 +18,10 @@ use crate::voronoi::delaunay::dimension::DTetraData;
 use crate::voronoi::delaunay::face_info::FaceInfo;
 use crate::voronoi::delaunay::Point;
 use crate::voronoi::math::precision_types::PrecisionError;
-use crate::voronoi::math::precision_types::PrecisionPoint2d;
 use crate::voronoi::math::precision_types::PrecisionPoint3d;
-use crate::voronoi::math::precision_types::TRIANGLE_CONTAINS_EPSILON;
 use crate::voronoi::math::precision_types::TRIANGLE_INTERSECTION_TYPE_EPSILON;
-use crate::voronoi::math::utils::determinant3x3_sign;
+use crate::voronoi::math::predicates::incircle;
+use crate::voronoi::math::predicates::orient_2d;
 use crate::voronoi::math::utils::solve_system_of_equations;
 use crate::voronoi::math::utils::Sign;
 use crate::voronoi::PointIndex;
@@ -148,57 +146,9 @@ impl FromIterator<Point2d> for TriangleData<Point2d> {
     }
 }
 
-impl<V: Vector2d + Clone + Sub<Output = V> + std::fmt::Debug> TriangleData<V> {
-    fn transform_point_to_canonical_coordinates(&self, point: V) -> (V::Float, V::Float) {
-        // We solve
-        // p = p1 + r (p2 - p1) + s (p3 - p1)
-        // where r and s are the coordinates of the point in the (two-dimensional) vector space
-        // spanned by the (linearly independent) vectors given by (p2 - p1) and (p3 - p1).
-        let a = self.p2.clone() - self.p1.clone();
-        let b = self.p3.clone() - self.p1.clone();
-        let c = point - self.p1.clone();
-        let [r, s] = solve_system_of_equations([[a.x(), b.x(), c.x()], [a.y(), b.y(), c.y()]]);
-        (r, s)
-    }
-
-    fn generic_contains(&self, p: V) -> Result<bool, PrecisionError> {
-        let (r, s) = self.transform_point_to_canonical_coordinates(p);
-        let values = [r.clone(), s.clone(), V::Float::one() - (r + s)];
-        let signs = || {
-            values
-                .iter()
-                .map(|value| Sign::try_from_val(value, TRIANGLE_CONTAINS_EPSILON))
-        };
-        let is_definitely_outside = signs().any(|sign| {
-            if let Ok(sign) = sign {
-                sign.is_negative()
-            } else {
-                false
-            }
-        });
-        if is_definitely_outside {
-            Ok(false)
-        } else {
-            for sign in signs() {
-                sign?.panic_if_zero(|| "Degenerate case of point on edge of triangle");
-            }
-            Ok(true)
-        }
-    }
-}
-
 impl TriangleData<Point2d> {
-    fn arbitrary_precision_contains(&self, p: Point2d) -> bool {
-        let lifted = TriangleData {
-            p1: PrecisionPoint2d::new(self.p1),
-            p2: PrecisionPoint2d::new(self.p2),
-            p3: PrecisionPoint2d::new(self.p3),
-        };
-        lifted.generic_contains(PrecisionPoint2d::new(p)).unwrap()
-    }
-
-    fn f64_contains(&self, p: Point2d) -> Result<bool, PrecisionError> {
-        self.generic_contains(p)
+    fn orientation(&self) -> Sign {
+        *orient_2d(self.p1, self.p2, self.p3).panic_if_zero(|| "Zero area triangle encountered")
     }
 }
 
@@ -224,8 +174,21 @@ impl DTetraData for TriangleData<Point2d> {
     }
 
     fn contains(&self, p: Point<Self::Dimension>) -> bool {
-        self.f64_contains(p)
-            .unwrap_or_else(|_| self.arbitrary_precision_contains(p))
+        let orientation = self.orientation();
+        let signs = [
+            orient_2d(self.p1, self.p2, p),
+            orient_2d(self.p2, self.p3, p),
+            orient_2d(self.p3, self.p1, p),
+        ]
+        .map(|sign| sign * orientation);
+        if signs.iter().any(|sign| sign.is_negative()) {
+            false
+        } else {
+            for sign in signs {
+                sign.panic_if_zero(|| "Degenerate case of point on edge of triangle");
+            }
+            true
+        }
     }
 
     fn distance_to_point(&self, p: Point2d) -> Float {
@@ -241,21 +204,11 @@ impl DTetraData for TriangleData<Point2d> {
         d1.max(d2).max(d3)
     }
 
-    #[rustfmt::skip]
     fn circumcircle_contains(&self, point: Point2d) -> bool {
-        // See for example Springel (2009), doi:10.1111/j.1365-2966.2009.15715.x
-        let a = self.p1;
-        let b = self.p2;
-        let c = self.p3;
-        let d = point;
-        let sign = determinant3x3_sign(
-            [
-                [b.x - a.x, b.y - a.y, (b.x - a.x).powi(2) + (b.y - a.y).powi(2)],
-                [c.x - a.x, c.y - a.y, (c.x - a.x).powi(2) + (c.y - a.y).powi(2)],
-                [d.x - a.x, d.y - a.y, (d.x - a.x).powi(2) + (d.y - a.y).powi(2)]
-            ]
-        );
-        sign.panic_if_zero(|| "Degenerate case in circumcircle test.").is_negative()
+        // If the point lies exactly on the circumcircle, both possible
+        // triangulations are valid, so we report it as outside to
+        // avoid unnecessary flips.
+        (incircle(self.p1, self.p2, self.p3, point) * self.orientation()).is_positive()
     }
 
     fn get_center_of_circumcircle(&self) -> Point2d {
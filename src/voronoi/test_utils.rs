@@ -4,8 +4,8 @@ use rand::SeedableRng;
 
 use super::delaunay::Point;
 use super::delaunay::TetraData;
-use super::math::utils::determinant3x3_sign;
-use super::math::utils::determinant4x4_sign;
+use super::math::predicates::orient_2d;
+use super::math::predicates::orient_3d;
 use super::DDimension;
 use super::Point3d;
 use crate::dimension::ThreeD;
@@ -91,16 +91,10 @@ impl TestDimension for TwoD {
         }))
     }
 
-    #[rustfmt::skip]
     fn tetra_is_positively_oriented(t: &TetraData<TwoD>) -> bool {
-        let sign = determinant3x3_sign(
-            [
-                [1.0, t.p1.x, t.p1.y],
-                [1.0, t.p2.x, t.p2.y],
-                [1.0, t.p3.x, t.p3.y],
-            ]
-        );
-        sign.panic_if_zero(|| "Zero volume tetra encountered").is_positive()
+        orient_2d(t.p1, t.p2, t.p3)
+            .panic_if_zero(|| "Zero volume tetra encountered")
+            .is_positive()
     }
 }
 
@@ -146,15 +140,11 @@ impl TestDimension for ThreeD {
         }))
     }
 
-    #[rustfmt::skip]
     fn tetra_is_positively_oriented(t: &TetraData<ThreeD>) -> bool {
-        determinant4x4_sign(
-            [
-                [1.0, t.p1.x, t.p1.y, t.p1.z],
-                [1.0, t.p2.x, t.p2.y, t.p2.z],
-                [1.0, t.p3.x, t.p3.y, t.p3.z],
-                [1.0, t.p4.x, t.p4.y, t.p4.z],
-            ]
-        ).panic_if_zero(|| "Zero volume tetra encountered").is_positive()
+        // Our convention for positive orientation is the opposite
+        // of the one used in orient_3d.
+        orient_3d(t.p1, t.p2, t.p3, t.p4)
+            .panic_if_zero(|| "Zero volume tetra encountered")
+            .is_negative()
     }
 }
@@ -56,18 +56,17 @@ where
 
     pub fn construct_voronoi(&self) -> VoronoiGrid<D> {
         VoronoiGrid {
-            cells: self
-                .triangulation
-                .iter_non_boundary_points()
-                .map(|p| Cell::<D>::new(self, p))
-                .collect(),
+            cells: self.iter_voronoi_cells().collect(),
         }
     }
 
+    /// The cells of all points of the triangulation, except for the
+    /// points of the all-encompassing tetra, which have no
+    /// corresponding particle.
     pub fn iter_voronoi_cells(&self) -> impl Iterator<Item = Cell<D>> + '_ {
         self.triangulation
-            .iter_non_boundary_points()
-            .map(|p| Cell::<D>::new(self, p))
+            .real_points()
+            .map(|(p, _)| Cell::<D>::new(self, p))
     }
 
     pub fn get_particle_type(&self, p: PointIndex) -> ParticleType {